@@ -1,11 +1,53 @@
-pub const ZERO: &str = "0";
-pub const ONE: &str = "1";
-pub const UNIT: &str = "()";
+pub use crate::constant::{Const, ONE, UNIT, ZERO};
+
+use crate::backend::AbtBackend;
+use crate::dot::{DotWriter, ToDot};
+use crate::error::{
+    BuildError, DecodeError, EvalError, Location, Malformed, ParseError, TypeError,
+};
+use crate::fuel::{Exhausted, Limits, Outcome, Progress};
+use crate::observer::{EvalObserver, NoopObserver};
+use crate::parse::parse_without_fix;
+use crate::prim::PrimOp;
+use crate::strategy::Strategy;
+use crate::term::{Binder, ReadBack, Shape, Term};
+use crate::types::{self, Type};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::rc::Rc;
+
+// Serialized programs start with MAGIC and the format version, then the number
+// of slots and every slot as a tag byte followed by its varint fields; a
+// constant's field is a kind byte followed by its value, a primitive's an op
+// byte followed by the number of operands and their slots, and an if's the
+// slots of its condition and branches; the pairs, splits, injections and cases
+// after that write their slots in the order their variants hold them
+const MAGIC: &[u8; 4] = b"ABTa";
+const VERSION: u64 = 2;
+const TAG_INVALID: u8 = 0;
+const TAG_BAS: u8 = 1;
+const TAG_PTR: u8 = 2;
+const TAG_LAM: u8 = 3;
+const TAG_APP: u8 = 4;
+const TAG_LET: u8 = 5;
+const TAG_PRIM: u8 = 6;
+const TAG_IF: u8 = 7;
+const TAG_PAIR: u8 = 8;
+const TAG_SPLIT: u8 = 9;
+const TAG_INL: u8 = 10;
+const TAG_INR: u8 = 11;
+const TAG_CASE: u8 = 12;
+const CONST_UNIT: u8 = 0;
+const CONST_INT: u8 = 1;
+const CONST_FALSE: u8 = 2;
+const CONST_TRUE: u8 = 3;
+const CONST_STR: u8 = 4;
 
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct ExprRef(usize);
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ArgRef(usize);
 #[derive(Debug)]
 pub struct ExprDest(usize);
@@ -13,26 +55,387 @@ pub struct ExprDest(usize);
 #[derive(Debug)]
 pub struct Program {
     exprs: Vec<Expr>,
+    // slots evaluation has overwritten with Invalid since the last collection
+    dead: usize,
+    gc_threshold: Option<f64>,
+    // names given to binders by make_named_lam, keyed by their arg slot
+    hints: HashMap<usize, Rc<str>>,
+    // types given to lambdas' binders by make_typed_lam, keyed by their arg slot
+    annotations: HashMap<usize, Type>,
+}
+
+/// The subterm rooted at one slot of a program, as evaluation observers see it.
+#[derive(Clone, Copy)]
+pub struct Node<'a> {
+    prg: &'a Program,
+    idx: usize,
+}
+
+impl Node<'_> {
+    pub fn slot(&self) -> usize {
+        self.idx
+    }
+    pub fn to_term(&self) -> Term {
+        self.prg.read_back(self.idx)
+    }
+}
+
+impl fmt::Display for Node<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_term())
+    }
+}
+
+impl ToDot for Node<'_> {
+    fn to_dot(&self) -> String {
+        let exprs = &self.prg.exprs;
+        let mut w = DotWriter::new();
+        // slots still to draw, and whether they were reached as a binder's cell
+        let mut todo = vec![(self.idx, false)];
+        w.id(self.idx);
+        let child = |w: &mut DotWriter, todo: &mut Vec<_>, idx, cell| {
+            let (id, fresh) = w.id(idx);
+            if fresh {
+                todo.push((idx, cell));
+            }
+            id
+        };
+        while let Some((idx, cell)) = todo.pop() {
+            let (id, _) = w.id(idx);
+            let hint = self.prg.hints.get(&idx).map_or("", |hint| hint);
+            match exprs[idx] {
+                Expr::Invalid if cell => w.cell(id, hint),
+                Expr::Invalid => w.node(id, "invalid"),
+                Expr::Bas(ref c) => w.node(id, &c.to_string()),
+                Expr::Ptr(target) => {
+                    w.node(id, "var");
+                    let target = child(&mut w, &mut todo, target, true);
+                    w.back_edge(id, target);
+                }
+                Expr::Lam(arg, body) => {
+                    let hint = self.prg.hints.get(&arg).map_or("", |hint| hint);
+                    w.node(id, &format!("λ{hint}"));
+                    let arg = child(&mut w, &mut todo, arg, true);
+                    w.edge(id, arg, "arg");
+                    let body = child(&mut w, &mut todo, body, false);
+                    w.edge(id, body, "body");
+                }
+                Expr::App(f, v) => {
+                    w.node(id, "@");
+                    let f = child(&mut w, &mut todo, f, false);
+                    w.edge(id, f, "fun");
+                    let v = child(&mut w, &mut todo, v, false);
+                    w.edge(id, v, "arg");
+                }
+                Expr::Let(arg, v, body) => {
+                    let hint = self.prg.hints.get(&arg).map_or("", |hint| hint);
+                    w.node(id, &format!("let {hint}"));
+                    let arg = child(&mut w, &mut todo, arg, true);
+                    w.edge(id, arg, "arg");
+                    let v = child(&mut w, &mut todo, v, false);
+                    w.edge(id, v, "value");
+                    let body = child(&mut w, &mut todo, body, false);
+                    w.edge(id, body, "body");
+                }
+                Expr::Prim(op, ref operands) => {
+                    w.node(id, op.name());
+                    for (i, &operand) in operands.iter().enumerate() {
+                        let operand = child(&mut w, &mut todo, operand, false);
+                        w.edge(id, operand, &i.to_string());
+                    }
+                }
+                Expr::If(c, t, e) => {
+                    w.node(id, "if");
+                    for (branch, label) in [(c, "cond"), (t, "then"), (e, "else")] {
+                        let branch = child(&mut w, &mut todo, branch, false);
+                        w.edge(id, branch, label);
+                    }
+                }
+                Expr::Pair(a, b) => {
+                    w.node(id, "pair");
+                    for (component, label) in [(a, "fst"), (b, "snd")] {
+                        let component = child(&mut w, &mut todo, component, false);
+                        w.edge(id, component, label);
+                    }
+                }
+                Expr::Split(x, y, v, body) => {
+                    let hint = |arg| self.prg.hints.get(&arg).map_or("", |hint| hint);
+                    w.node(id, &format!("let ({}, {})", hint(x), hint(y)));
+                    for (arg, label) in [(x, "fst"), (y, "snd")] {
+                        let arg = child(&mut w, &mut todo, arg, true);
+                        w.edge(id, arg, label);
+                    }
+                    let v = child(&mut w, &mut todo, v, false);
+                    w.edge(id, v, "value");
+                    let body = child(&mut w, &mut todo, body, false);
+                    w.edge(id, body, "body");
+                }
+                Expr::Inl(v) | Expr::Inr(v) => {
+                    let side = if matches!(exprs[idx], Expr::Inl(_)) {
+                        "inl"
+                    } else {
+                        "inr"
+                    };
+                    w.node(id, side);
+                    let v = child(&mut w, &mut todo, v, false);
+                    w.edge(id, v, "inj");
+                }
+                Expr::Case(v, x, l, y, r) => {
+                    w.node(id, "case");
+                    let v = child(&mut w, &mut todo, v, false);
+                    w.edge(id, v, "scrutinee");
+                    for (arg, branch, label) in [(x, l, "left"), (y, r, "right")] {
+                        let arg = child(&mut w, &mut todo, arg, true);
+                        w.edge(id, arg, &format!("{label} arg"));
+                        let branch = child(&mut w, &mut todo, branch, false);
+                        w.edge(id, branch, label);
+                    }
+                }
+            }
+        }
+        w.finish()
+    }
+}
+
+/// A bound binder's slot holds its value, so a variable's back-edge
+/// leads straight to the value it was redirected to.
+impl ToDot for Program {
+    fn to_dot(&self) -> String {
+        self.node(0).to_dot()
+    }
+}
+
+impl fmt::Debug for Node<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let node = |idx| Node { prg: self.prg, idx };
+        match self.prg.exprs[self.idx] {
+            Expr::Bas(ref c) => write!(f, "Bas({c:?})"),
+            Expr::Ptr(target) => write!(f, "Ptr(#{target})"),
+            Expr::Lam(arg, body) => write!(f, "Lam(#{arg}, {:?})", node(body)),
+            Expr::App(fun, v) => write!(f, "App({:?}, {:?})", node(fun), node(v)),
+            Expr::Let(arg, v, body) => {
+                write!(f, "Let(#{arg}, {:?}, {:?})", node(v), node(body))
+            }
+            Expr::Prim(op, ref operands) => {
+                let operands: Vec<_> = operands.iter().map(|&operand| node(operand)).collect();
+                write!(f, "Prim({op}, {operands:?})")
+            }
+            Expr::If(c, t, e) => {
+                write!(f, "If({:?}, {:?}, {:?})", node(c), node(t), node(e))
+            }
+            Expr::Pair(a, b) => write!(f, "Pair({:?}, {:?})", node(a), node(b)),
+            Expr::Split(x, y, v, body) => {
+                write!(f, "Split(#{x}, #{y}, {:?}, {:?})", node(v), node(body))
+            }
+            Expr::Inl(v) => write!(f, "Inl({:?})", node(v)),
+            Expr::Inr(v) => write!(f, "Inr({:?})", node(v)),
+            Expr::Case(v, x, l, y, r) => {
+                write!(
+                    f,
+                    "Case({:?}, #{x}, {:?}, #{y}, {:?})",
+                    node(v),
+                    node(l),
+                    node(r)
+                )
+            }
+            Expr::Invalid => write!(f, "Invalid"),
+        }
+    }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+impl TryFrom<&Term> for Program {
+    type Error = BuildError;
+    fn try_from(term: &Term) -> Result<Self, BuildError> {
+        Self::from_term(term)
+    }
+}
+
+impl From<&Program> for Term {
+    fn from(prg: &Program) -> Self {
+        prg.to_term()
+    }
+}
+
+/// Whether the roots of two programs are the same term up to renaming binders,
+/// comparing variables by the lambdas they point to rather than by slot.
+pub fn alpha_eq(a: &Program, b: &Program) -> bool {
+    a.to_term().alpha_eq(&b.to_term())
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_term())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
 enum Expr {
-    Bas(&'static str),
+    Bas(Const),
     Ptr(usize),
     Lam(usize, usize),
     App(usize, usize),
+    // the binder's slot, the value bound and the body
+    Let(usize, usize, usize),
+    Prim(PrimOp, Vec<usize>),
+    // the condition and the two branches
+    If(usize, usize, usize),
+    Pair(usize, usize),
+    // the binders' slots, the pair and the body
+    Split(usize, usize, usize, usize),
+    Inl(usize),
+    Inr(usize),
+    // the scrutinee, then the binder's slot and branch for either side
+    Case(usize, usize, usize, usize, usize),
     Invalid,
 }
 
+// The evaluation context around the slot being evaluated,
+// innermost application last
+#[derive(Debug)]
+struct Machine {
+    focus: usize,
+    stack: Vec<Frame>,
+    fuel: usize,
+    max_depth: usize,
+    strategy: Strategy,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Frame {
+    // evaluating the function of the application at this slot
+    Fun(usize),
+    // evaluating the argument of the application at this slot
+    Arg(usize),
+    // normalizing the body of the lambda at this slot
+    Body(usize),
+    // normalizing the function of the neutral application at this slot,
+    // one stuck on a variable that nothing binds
+    NeutralFun(usize),
+    // normalizing the argument of the neutral application at this slot
+    NeutralArg(usize),
+    // evaluating, in place, the argument the variable at this slot points to
+    Force(usize),
+    // evaluating the value of the let at this slot before binding it
+    Bound(usize),
+    // evaluating the operand at this index of the primitive at this slot
+    Operand(usize, usize),
+    // evaluating the condition of the if at this slot
+    Cond(usize),
+    // normalizing the then branch of the if at this slot, stuck on its condition
+    NeutralThen(usize),
+    // normalizing the else branch of the if at this slot
+    NeutralElse(usize),
+    // evaluating the first component of the pair at this slot
+    Fst(usize),
+    // evaluating the second component of the pair at this slot
+    Snd(usize),
+    // evaluating what the injection at this slot injects
+    Inj(usize),
+    // evaluating the pair of the split or the scrutinee of the case at this slot
+    Scrutinee(usize),
+    // normalizing the body of the split at this slot, stuck on its pair
+    NeutralSplit(usize),
+    // normalizing the left branch of the case at this slot, stuck on its scrutinee
+    NeutralLeft(usize),
+    // normalizing the right branch of the case at this slot
+    NeutralRight(usize),
+}
+
+impl Frame {
+    // the slot of the node this frame is evaluating part of
+    fn slot_mut(&mut self) -> &mut usize {
+        match self {
+            Frame::Fun(slot)
+            | Frame::Arg(slot)
+            | Frame::Body(slot)
+            | Frame::NeutralFun(slot)
+            | Frame::NeutralArg(slot)
+            | Frame::Force(slot)
+            | Frame::Bound(slot)
+            | Frame::Operand(slot, _)
+            | Frame::Cond(slot)
+            | Frame::NeutralThen(slot)
+            | Frame::NeutralElse(slot)
+            | Frame::Fst(slot)
+            | Frame::Snd(slot)
+            | Frame::Inj(slot)
+            | Frame::Scrutinee(slot)
+            | Frame::NeutralSplit(slot)
+            | Frame::NeutralLeft(slot)
+            | Frame::NeutralRight(slot) => slot,
+        }
+    }
+}
+
+impl Machine {
+    fn new(root: usize, limits: &Limits, strategy: Strategy) -> Self {
+        Self {
+            focus: root,
+            stack: Vec::new(),
+            fuel: limits.max_steps,
+            max_depth: limits.max_depth,
+            strategy,
+        }
+    }
+    // every slot the machine holds: its focus and the slot of each frame
+    fn slots_mut(&mut self) -> impl Iterator<Item = &mut usize> {
+        std::iter::once(&mut self.focus).chain(self.stack.iter_mut().map(Frame::slot_mut))
+    }
+    // whether a value here is part of the result, rather than a function about
+    // to be applied or an argument about to be bound, unless those get normalized too;
+    // operands, conditions, components and scrutinees are normalized whenever
+    // evaluation is strong, since what is stuck on a variable is part of the result
+    fn in_result(&self) -> bool {
+        match self.stack.last() {
+            None
+            | Some(
+                Frame::Body(_)
+                | Frame::NeutralFun(_)
+                | Frame::NeutralArg(_)
+                | Frame::NeutralThen(_)
+                | Frame::NeutralElse(_)
+                | Frame::NeutralSplit(_)
+                | Frame::NeutralLeft(_)
+                | Frame::NeutralRight(_),
+            ) => true,
+            Some(Frame::Arg(_) | Frame::Bound(_)) => self.strategy.normalizes_args(),
+            Some(
+                Frame::Operand(..)
+                | Frame::Cond(_)
+                | Frame::Fst(_)
+                | Frame::Snd(_)
+                | Frame::Inj(_)
+                | Frame::Scrutinee(_),
+            ) => self.strategy.strong(),
+            Some(Frame::Fun(_) | Frame::Force(_)) => false,
+        }
+    }
+    // whether the value returned is taken apart by the split or case on top of the
+    // stack, so a pair or an injection is only normalized first if arguments are
+    fn taken_apart(&self) -> bool {
+        matches!(self.stack.last(), Some(Frame::Scrutinee(_))) && !self.strategy.normalizes_args()
+    }
+    // spends the fuel for one reduction, if there is any left
+    fn burn(&mut self) -> bool {
+        let has_fuel = self.fuel > 0;
+        self.fuel = self.fuel.saturating_sub(1);
+        has_fuel
+    }
+}
+
 impl Program {
     pub fn build() -> (Self, ExprDest) {
         let mut p = Self {
             exprs: Vec::with_capacity(128),
+            dead: 0,
+            gc_threshold: None,
+            hints: HashMap::new(),
+            annotations: HashMap::new(),
         };
         p.exprs.push(Expr::Invalid);
         (p, ExprDest(0))
     }
-    pub fn make_const(&mut self, into: ExprDest, c: &'static str) -> ExprRef {
+    pub fn make_const(&mut self, into: ExprDest, c: Const) -> ExprRef {
         let const_ref = into.0;
         assert_eq!(self.exprs[const_ref], Expr::Invalid);
         self.exprs[const_ref] = Expr::Bas(c);
@@ -48,6 +451,24 @@ impl Program {
         self.exprs.push(Expr::Invalid);
         (ExprRef(lam_ref), ArgRef(arg_ref), ExprDest(body_ref))
     }
+    /// Like `make_lam`, with a name for the binder that printing uses.
+    pub fn make_named_lam(&mut self, into: ExprDest, hint: &str) -> (ExprRef, ArgRef, ExprDest) {
+        let (lam, arg, body) = self.make_lam(into);
+        self.hints.insert(arg.0, hint.into());
+        (lam, arg, body)
+    }
+    /// Like `make_named_lam`, with the binder annotated with its type for
+    /// [`Program::typecheck`].
+    pub fn make_typed_lam(
+        &mut self,
+        into: ExprDest,
+        hint: &str,
+        ty: Type,
+    ) -> (ExprRef, ArgRef, ExprDest) {
+        let (lam, arg, body) = self.make_named_lam(into, hint);
+        self.annotations.insert(arg.0, ty);
+        (lam, arg, body)
+    }
     pub fn make_app(&mut self, into: ExprDest) -> (ExprRef, ExprDest, ExprDest) {
         let app_ref = into.0;
         assert_eq!(self.exprs[app_ref], Expr::Invalid);
@@ -58,130 +479,2346 @@ impl Program {
         self.exprs.push(Expr::Invalid);
         (ExprRef(app_ref), ExprDest(f_ref), ExprDest(v_ref))
     }
+    /// Makes `let x = v in body` at `into`, returning x and where v and the body go.
+    /// It binds x like applying a lambda to v would, in one step rather than two.
+    pub fn make_let(&mut self, into: ExprDest) -> (ExprRef, ArgRef, ExprDest, ExprDest) {
+        let let_ref = into.0;
+        assert_eq!(self.exprs[let_ref], Expr::Invalid);
+        let (arg_ref, v_ref, body_ref) = (self.alloc(), self.alloc(), self.alloc());
+        self.exprs[let_ref] = Expr::Let(arg_ref, v_ref, body_ref);
+        (
+            ExprRef(let_ref),
+            ArgRef(arg_ref),
+            ExprDest(v_ref),
+            ExprDest(body_ref),
+        )
+    }
+    /// Like `make_let`, with a name for the binder that printing uses.
+    pub fn make_named_let(
+        &mut self,
+        into: ExprDest,
+        hint: &str,
+    ) -> (ExprRef, ArgRef, ExprDest, ExprDest) {
+        let (binding, arg, v, body) = self.make_let(into);
+        self.hints.insert(arg.0, hint.into());
+        (binding, arg, v, body)
+    }
+    /// Makes the primitive `op` at `into`, returning where each of its operands goes.
+    pub fn make_prim(&mut self, into: ExprDest, op: PrimOp) -> (ExprRef, Vec<ExprDest>) {
+        let prim_ref = into.0;
+        assert_eq!(self.exprs[prim_ref], Expr::Invalid);
+        let operands: Vec<usize> = (0..op.arity()).map(|_| self.alloc()).collect();
+        self.exprs[prim_ref] = Expr::Prim(op, operands.clone());
+        (
+            ExprRef(prim_ref),
+            operands.into_iter().map(ExprDest).collect(),
+        )
+    }
+    /// Makes `if c then t else e` at `into`, returning where c and the branches go.
+    pub fn make_if(&mut self, into: ExprDest) -> (ExprRef, ExprDest, ExprDest, ExprDest) {
+        let if_ref = into.0;
+        assert_eq!(self.exprs[if_ref], Expr::Invalid);
+        let (c_ref, t_ref, e_ref) = (self.alloc(), self.alloc(), self.alloc());
+        self.exprs[if_ref] = Expr::If(c_ref, t_ref, e_ref);
+        (
+            ExprRef(if_ref),
+            ExprDest(c_ref),
+            ExprDest(t_ref),
+            ExprDest(e_ref),
+        )
+    }
+    /// Makes the pair `(a, b)` at `into`, returning where a and b go.
+    pub fn make_pair(&mut self, into: ExprDest) -> (ExprRef, ExprDest, ExprDest) {
+        let pair_ref = into.0;
+        assert_eq!(self.exprs[pair_ref], Expr::Invalid);
+        let (a_ref, b_ref) = (self.alloc(), self.alloc());
+        self.exprs[pair_ref] = Expr::Pair(a_ref, b_ref);
+        (ExprRef(pair_ref), ExprDest(a_ref), ExprDest(b_ref))
+    }
+    /// Makes `let (x, y) = v in body` at `into`, returning x and y and where v and
+    /// the body go.
+    pub fn make_split(&mut self, into: ExprDest) -> (ExprRef, ArgRef, ArgRef, ExprDest, ExprDest) {
+        let split_ref = into.0;
+        assert_eq!(self.exprs[split_ref], Expr::Invalid);
+        let (x_ref, y_ref) = (self.alloc(), self.alloc());
+        let (v_ref, body_ref) = (self.alloc(), self.alloc());
+        self.exprs[split_ref] = Expr::Split(x_ref, y_ref, v_ref, body_ref);
+        (
+            ExprRef(split_ref),
+            ArgRef(x_ref),
+            ArgRef(y_ref),
+            ExprDest(v_ref),
+            ExprDest(body_ref),
+        )
+    }
+    /// Makes `inl v` at `into`, returning where v goes.
+    pub fn make_inl(&mut self, into: ExprDest) -> (ExprRef, ExprDest) {
+        let inl_ref = into.0;
+        assert_eq!(self.exprs[inl_ref], Expr::Invalid);
+        let v_ref = self.alloc();
+        self.exprs[inl_ref] = Expr::Inl(v_ref);
+        (ExprRef(inl_ref), ExprDest(v_ref))
+    }
+    /// Makes `inr v` at `into`, returning where v goes.
+    pub fn make_inr(&mut self, into: ExprDest) -> (ExprRef, ExprDest) {
+        let inr_ref = into.0;
+        assert_eq!(self.exprs[inr_ref], Expr::Invalid);
+        let v_ref = self.alloc();
+        self.exprs[inr_ref] = Expr::Inr(v_ref);
+        (ExprRef(inr_ref), ExprDest(v_ref))
+    }
+    /// Makes `case v of inl x. l | inr y. r` at `into`, returning where v goes, then
+    /// x and where l goes, then y and where r goes.
+    #[allow(clippy::type_complexity)]
+    pub fn make_case(
+        &mut self,
+        into: ExprDest,
+    ) -> (ExprRef, ExprDest, (ArgRef, ExprDest), (ArgRef, ExprDest)) {
+        let case_ref = into.0;
+        assert_eq!(self.exprs[case_ref], Expr::Invalid);
+        let v_ref = self.alloc();
+        let (x_ref, l_ref) = (self.alloc(), self.alloc());
+        let (y_ref, r_ref) = (self.alloc(), self.alloc());
+        self.exprs[case_ref] = Expr::Case(v_ref, x_ref, l_ref, y_ref, r_ref);
+        (
+            ExprRef(case_ref),
+            ExprDest(v_ref),
+            (ArgRef(x_ref), ExprDest(l_ref)),
+            (ArgRef(y_ref), ExprDest(r_ref)),
+        )
+    }
     pub fn make_deref(&mut self, into: ExprDest, arg_ref: ArgRef) -> ExprRef {
         let deref = into.0;
         assert_eq!(self.exprs[deref], Expr::Invalid);
         self.exprs[deref] = Expr::Ptr(arg_ref.0);
         ExprRef(deref)
     }
-    fn step(&mut self, expr_idx: usize) -> bool {
-        let expr = self.exprs[expr_idx];
-        match expr {
-            Expr::Invalid => unreachable!("Not fully initialized program!"),
-            Expr::Bas(_) | Expr::Lam(_, _) => false,
-            Expr::Ptr(target) => {
-                // deref this expr to self.exprs[target]
-                self.exprs[expr_idx] = self.exprs[target];
-
-                self.exprs[target] = Expr::Invalid;
-                true
-            }
-            Expr::App(f, v) => {
-                self.step(f) || self.step(v) || {
-                    let Expr::App(f, v) = self.exprs[expr_idx] else {
-                        unreachable!("we already know expr is app");
-                    };
-                    if let Expr::Lam(arg, body) = self.exprs[f] {
-                        // beta reduction: set arg to v, replace expr with body
-                        self.exprs[arg] = self.exprs[v];
-                        self.exprs[expr_idx] = self.exprs[body];
-
-                        self.exprs[v] = Expr::Invalid;
-                        self.exprs[body] = Expr::Invalid;
-                        true
-                    } else {
-                        panic!("stuck, f value is not a function {:?}", self.exprs[f]);
+    /// Fills `into` with `term`, each `Var` pointing at the arg of the lambda that
+    /// binds it. A variable whose binder is not in the term gets an arg slot
+    /// that no lambda binds, like a variable used after its lambda is gone.
+    /// Fails, leaving `into` unfilled, if the term has a `fix`.
+    pub fn make_term(&mut self, into: ExprDest, term: &Term) -> Result<ExprRef, BuildError> {
+        if let Some(at) = term.find_fix() {
+            return Err(BuildError::Fix(at));
+        }
+        Ok(self.build_term(into, term))
+    }
+    // make_term for a term without a fix
+    fn build_term(&mut self, into: ExprDest, term: &Term) -> ExprRef {
+        let root = into.0;
+        let mut args = HashMap::new();
+        let mut todo = vec![(term, into)];
+        while let Some((term, into)) = todo.pop() {
+            match term {
+                Term::Var(id) => {
+                    let arg = *args.entry(*id).or_insert_with(|| self.alloc());
+                    let _ = self.make_deref(into, ArgRef(arg));
+                }
+                Term::Const(c) => {
+                    let _ = self.make_const(into, c.clone());
+                }
+                Term::Lam(binder, body) => {
+                    let (_lam, arg, body_dest) = self.make_lam(into);
+                    if let Some(hint) = &binder.hint {
+                        self.hints.insert(arg.0, Rc::clone(hint));
+                    }
+                    if let Some(ty) = &binder.ty {
+                        self.annotations.insert(arg.0, ty.clone());
+                    }
+                    args.insert(binder.id, arg.0);
+                    todo.push((body, body_dest));
+                }
+                Term::App(f, v) => {
+                    let (_app, f_dest, v_dest) = self.make_app(into);
+                    todo.push((v, v_dest));
+                    todo.push((f, f_dest));
+                }
+                Term::Let(binder, v, body) => {
+                    let (_let, arg, v_dest, body_dest) = self.make_let(into);
+                    if let Some(hint) = &binder.hint {
+                        self.hints.insert(arg.0, Rc::clone(hint));
+                    }
+                    args.insert(binder.id, arg.0);
+                    todo.push((body, body_dest));
+                    todo.push((v, v_dest));
+                }
+                // built by hand, since the term may have any number of operands
+                Term::Prim(op, operands) => {
+                    assert_eq!(self.exprs[into.0], Expr::Invalid);
+                    let slots: Vec<usize> = operands.iter().map(|_| self.alloc()).collect();
+                    self.exprs[into.0] = Expr::Prim(*op, slots.clone());
+                    let dests = slots.into_iter().map(ExprDest);
+                    todo.extend(operands.iter().zip(dests).rev());
+                }
+                Term::If(c, t, e) => {
+                    let (_if, c_dest, t_dest, e_dest) = self.make_if(into);
+                    todo.extend([(&**e, e_dest), (&**t, t_dest), (&**c, c_dest)]);
+                }
+                Term::Pair(a, b) => {
+                    let (_pair, a_dest, b_dest) = self.make_pair(into);
+                    todo.extend([(&**b, b_dest), (&**a, a_dest)]);
+                }
+                Term::Split(x, y, v, body) => {
+                    let (_split, x_arg, y_arg, v_dest, body_dest) = self.make_split(into);
+                    for (binder, arg) in [(x, x_arg), (y, y_arg)] {
+                        if let Some(hint) = &binder.hint {
+                            self.hints.insert(arg.0, Rc::clone(hint));
+                        }
+                        args.insert(binder.id, arg.0);
+                    }
+                    todo.extend([(&**body, body_dest), (&**v, v_dest)]);
+                }
+                Term::Inl(v) => {
+                    let (_inl, v_dest) = self.make_inl(into);
+                    todo.push((v, v_dest));
+                }
+                Term::Inr(v) => {
+                    let (_inr, v_dest) = self.make_inr(into);
+                    todo.push((v, v_dest));
+                }
+                Term::Case(v, x, l, y, r) => {
+                    let (_case, v_dest, (x_arg, l_dest), (y_arg, r_dest)) = self.make_case(into);
+                    for (binder, arg) in [(x, x_arg), (y, y_arg)] {
+                        if let Some(hint) = &binder.hint {
+                            self.hints.insert(arg.0, Rc::clone(hint));
+                        }
+                        args.insert(binder.id, arg.0);
                     }
+                    todo.extend([(&**r, r_dest), (&**l, l_dest), (&**v, v_dest)]);
                 }
+                Term::Fix(..) => unreachable!("make_term rejects fix"),
+                Term::Invalid => {}
             }
         }
+        ExprRef(root)
     }
-    pub fn eval(&mut self) -> Option<&'static str> {
-        println!("eval {self:?}");
-        while self.step(0) {
-            println!("step {self:?}");
+    /// A program whose root is `term`, unless it has a `fix`.
+    pub fn from_term(term: &Term) -> Result<Self, BuildError> {
+        let (mut prg, start) = Self::build();
+        prg.make_term(start, term)?;
+        Ok(prg)
+    }
+    /// Parses `src` with [`parse`](crate::parse::parse) and fills `into` with it,
+    /// resolving every name to the `ArgRef` of the lambda binding it. A `fix` is a
+    /// parse error here.
+    pub fn make_parsed(&mut self, into: ExprDest, src: &str) -> Result<ExprRef, ParseError> {
+        let term = parse_without_fix(src)?;
+        Ok(self.build_term(into, &term))
+    }
+    /// Checks a program for slots reachable from the root more than once,
+    /// uninitialized slots reachable from the root, variables that do not point at
+    /// the argument of a lambda, let, split or case, and variables used outside of
+    /// the scope of the binder that binds them. A partially evaluated program passes
+    /// too: a variable may also point at an argument that evaluation already bound,
+    /// whose value is checked like the rest of the program, but not at one whose
+    /// binder is still there.
+    pub fn validate(&self) -> Result<(), Vec<Malformed>> {
+        // first find every binder, since a stray variable may come before its lambda,
+        // and make sure the walk is over a tree: a slot reached twice, through two
+        // parents or a cycle, is reported and stops the checks here
+        let mut binders = HashMap::new();
+        let mut bound = HashSet::new();
+        let mut problems = Vec::new();
+        let mut seen = HashSet::from([0]);
+        let mut variables = Vec::new();
+        let mut todo = vec![0];
+        loop {
+            let Some(idx) = todo.pop() else {
+                // arguments that evaluation bound are only reached through variables,
+                // once nothing else owns them
+                for target in variables.drain(..) {
+                    if self.exprs[target] != Expr::Invalid && seen.insert(target) {
+                        bound.insert(target);
+                        todo.push(target);
+                    }
+                }
+                if todo.is_empty() {
+                    break;
+                }
+                continue;
+            };
+            // the argument slots a node binds, then the children it owns, last first
+            let (args, children) = match self.exprs[idx] {
+                Expr::Lam(arg, body) => (vec![arg], vec![body]),
+                Expr::Let(arg, v, body) => (vec![arg], vec![body, v]),
+                Expr::App(f, v) => (vec![], vec![v, f]),
+                Expr::Prim(_, ref operands) => (vec![], operands.iter().rev().copied().collect()),
+                Expr::If(c, t, e) => (vec![], vec![e, t, c]),
+                Expr::Pair(a, b) => (vec![], vec![b, a]),
+                Expr::Split(x, y, v, body) => (vec![x, y], vec![body, v]),
+                Expr::Inl(v) | Expr::Inr(v) => (vec![], vec![v]),
+                Expr::Case(v, x, l, y, r) => (vec![x, y], vec![r, l, v]),
+                Expr::Ptr(target) => {
+                    variables.push(target);
+                    continue;
+                }
+                Expr::Invalid | Expr::Bas(_) => continue,
+            };
+            for &arg in &args {
+                binders.insert(arg, idx);
+                // its value would only be reached through variables, outside the tree
+                if self.exprs[arg] != Expr::Invalid {
+                    problems.push(Malformed::AlreadyBound { arg, binder: idx });
+                }
+            }
+            let owned = args.iter().map(|&arg| (arg, false));
+            for (slot, walk) in owned.chain(children.into_iter().map(|child| (child, true))) {
+                if !seen.insert(slot) {
+                    problems.push(Malformed::Shared { slot, parent: idx });
+                } else if walk {
+                    todo.push(slot);
+                }
+            }
+        }
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+        enum Visit {
+            Expr(usize),
+            // a binder coming into scope, once what comes before its scope is visited
+            Enter(usize),
+            Leave(usize),
+            // a bound argument whose value is checked
+            Checked(usize),
+        }
+        let mut in_scope = HashSet::new();
+        // bound arguments whose values are being checked, and those that have been
+        let (mut checking, mut checked) = (HashSet::new(), HashSet::new());
+        let mut todo = vec![Visit::Expr(0)];
+        while let Some(visit) = todo.pop() {
+            let idx = match visit {
+                Visit::Expr(idx) => idx,
+                Visit::Enter(arg) => {
+                    in_scope.insert(arg);
+                    continue;
+                }
+                Visit::Leave(arg) => {
+                    in_scope.remove(&arg);
+                    continue;
+                }
+                Visit::Checked(arg) => {
+                    checking.remove(&arg);
+                    continue;
+                }
+            };
+            match self.exprs[idx] {
+                Expr::Invalid => problems.push(Malformed::Uninitialized { slot: idx }),
+                Expr::Bas(_) => {}
+                Expr::Ptr(target) => match binders.get(&target) {
+                    // the value is checked in scope where the argument is first used,
+                    // which is inside the scope of the application that bound it
+                    None if bound.contains(&target) => {
+                        if checking.contains(&target) {
+                            problems.push(Malformed::Shared {
+                                slot: target,
+                                parent: idx,
+                            });
+                        } else if checked.insert(target) {
+                            checking.insert(target);
+                            todo.extend([Visit::Checked(target), Visit::Expr(target)]);
+                        }
+                    }
+                    None => problems.push(Malformed::NotABinder { ptr: idx, target }),
+                    Some(&lam) if !in_scope.contains(&target) => {
+                        problems.push(Malformed::OutOfScope { ptr: idx, lam })
+                    }
+                    Some(_) => {}
+                },
+                Expr::Lam(arg, body) => {
+                    in_scope.insert(arg);
+                    todo.push(Visit::Leave(arg));
+                    todo.push(Visit::Expr(body));
+                }
+                Expr::App(f, v) => todo.extend([Visit::Expr(v), Visit::Expr(f)]),
+                Expr::Let(arg, v, body) => todo.extend([
+                    Visit::Leave(arg),
+                    Visit::Expr(body),
+                    Visit::Enter(arg),
+                    Visit::Expr(v),
+                ]),
+                Expr::Prim(_, ref operands) => {
+                    todo.extend(operands.iter().rev().map(|&operand| Visit::Expr(operand)))
+                }
+                Expr::If(c, t, e) => todo.extend([Visit::Expr(e), Visit::Expr(t), Visit::Expr(c)]),
+                Expr::Pair(a, b) => todo.extend([Visit::Expr(b), Visit::Expr(a)]),
+                Expr::Split(x, y, v, body) => todo.extend([
+                    Visit::Leave(y),
+                    Visit::Leave(x),
+                    Visit::Expr(body),
+                    Visit::Enter(y),
+                    Visit::Enter(x),
+                    Visit::Expr(v),
+                ]),
+                Expr::Inl(v) | Expr::Inr(v) => todo.push(Visit::Expr(v)),
+                Expr::Case(v, x, l, y, r) => todo.extend([
+                    Visit::Leave(y),
+                    Visit::Expr(r),
+                    Visit::Enter(y),
+                    Visit::Leave(x),
+                    Visit::Expr(l),
+                    Visit::Enter(x),
+                    Visit::Expr(v),
+                ]),
+            }
         }
-        println!("result {self:?}");
-        if let Expr::Bas(result) = self.exprs[0] {
-            Some(result)
+        if problems.is_empty() {
+            Ok(())
         } else {
-            None
+            Err(problems)
         }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    trait ProgramExt {
-        fn make_const_fn(&mut self, e: ExprDest) -> ExprRef;
-        fn make_ident(&mut self, e: ExprDest) -> ExprRef;
-        fn make_lam_true(&mut self, e: ExprDest) -> ExprRef;
-        fn make_lam_false(&mut self, e: ExprDest) -> ExprRef;
+    // the slot at `idx` as observers see it
+    fn node(&self, idx: usize) -> Node<'_> {
+        Node { prg: self, idx }
     }
-    impl ProgramExt for Program {
-        fn make_const_fn(&mut self, e: ExprDest) -> ExprRef {
-            let (lam, _arg, body) = self.make_lam(e);
-            let _ = self.make_const(body, UNIT);
-            lam
+    /// Runs the machine up to and including the next reduction, and leaves it focused
+    /// on the reduced slot so the next call picks up from there.
+    fn step(
+        &mut self,
+        m: &mut Machine,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Result<Progress, EvalError> {
+        loop {
+            let expr_idx = m.focus;
+            match self.exprs[expr_idx] {
+                Expr::Invalid => return Err(EvalError::Uninitialized(Location::Slot(expr_idx))),
+                // when normalizing, a variable nothing binds is a value
+                Expr::Ptr(target) if m.strategy.strong() && self.exprs[target] == Expr::Invalid => {
+                    if let Some(progress) = self.ret(m, expr_idx, obs)? {
+                        return Ok(progress);
+                    }
+                }
+                Expr::Ptr(target) => {
+                    if self.exprs[target] == Expr::Invalid {
+                        return Err(EvalError::DanglingPointer(Location::Slot(expr_idx)));
+                    }
+                    // call-by-need evaluates the argument where it is bound, so every
+                    // use copies the value out
+                    if m.strategy.shares() && is_neutral(&self.exprs[target]) {
+                        if m.stack.len() >= m.max_depth {
+                            return Ok(Progress::Exhausted(Exhausted::Depth));
+                        }
+                        m.stack.push(Frame::Force(expr_idx));
+                        m.focus = target;
+                        continue;
+                    }
+                    return Ok(self.deref(m, expr_idx, obs));
+                }
+                Expr::App(f, _) => {
+                    if m.stack.len() >= m.max_depth {
+                        return Ok(Progress::Exhausted(Exhausted::Depth));
+                    }
+                    m.stack.push(Frame::Fun(expr_idx));
+                    m.focus = f;
+                }
+                Expr::Let(..) if m.strategy.by_name() => return Ok(self.bind_let(m, expr_idx, obs)),
+                Expr::Let(_, v, _) => {
+                    if m.stack.len() >= m.max_depth {
+                        return Ok(Progress::Exhausted(Exhausted::Depth));
+                    }
+                    m.stack.push(Frame::Bound(expr_idx));
+                    m.focus = v;
+                }
+                Expr::Prim(_, ref operands) => match operands.first() {
+                    Some(&first) => {
+                        if m.stack.len() >= m.max_depth {
+                            return Ok(Progress::Exhausted(Exhausted::Depth));
+                        }
+                        m.stack.push(Frame::Operand(expr_idx, 0));
+                        m.focus = first;
+                    }
+                    None => {
+                        let progress = self.apply_prim(m, expr_idx, obs)?;
+                        return Ok(progress.expect("no operands to be stuck on"));
+                    }
+                },
+                Expr::If(c, _, _) => {
+                    if m.stack.len() >= m.max_depth {
+                        return Ok(Progress::Exhausted(Exhausted::Depth));
+                    }
+                    m.stack.push(Frame::Cond(expr_idx));
+                    m.focus = c;
+                }
+                // unless arguments are bound unevaluated, a pair or an injection is
+                // only a value once what it holds is
+                Expr::Pair(a, _) if !m.strategy.by_name() => {
+                    if m.stack.len() >= m.max_depth {
+                        return Ok(Progress::Exhausted(Exhausted::Depth));
+                    }
+                    m.stack.push(Frame::Fst(expr_idx));
+                    m.focus = a;
+                }
+                Expr::Inl(v) | Expr::Inr(v) if !m.strategy.by_name() => {
+                    if m.stack.len() >= m.max_depth {
+                        return Ok(Progress::Exhausted(Exhausted::Depth));
+                    }
+                    m.stack.push(Frame::Inj(expr_idx));
+                    m.focus = v;
+                }
+                Expr::Split(_, _, v, _) | Expr::Case(v, ..) => {
+                    if m.stack.len() >= m.max_depth {
+                        return Ok(Progress::Exhausted(Exhausted::Depth));
+                    }
+                    m.stack.push(Frame::Scrutinee(expr_idx));
+                    m.focus = v;
+                }
+                Expr::Bas(_) | Expr::Lam(_, _) | Expr::Pair(..) | Expr::Inl(_) | Expr::Inr(_) => {
+                    if let Some(progress) = self.ret(m, expr_idx, obs)? {
+                        return Ok(progress);
+                    }
+                }
+            }
         }
-        fn make_ident(&mut self, e: ExprDest) -> ExprRef {
-            let (lam, arg, body) = self.make_lam(e);
-            let _ = self.make_deref(body, arg);
-            lam
+    }
+    // Hands the value at slot `val` to the frames below it, until there is a
+    // reduction to report or another subterm to evaluate, which is left in focus.
+    fn ret(
+        &mut self,
+        m: &mut Machine,
+        mut val: usize,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Result<Option<Progress>, EvalError> {
+        // whether val is already normalized, so it is not gone into again
+        let mut normal = false;
+        loop {
+            if m.strategy.strong() && !normal && m.in_result() {
+                if m.stack.len() >= m.max_depth {
+                    return Ok(Some(Progress::Exhausted(Exhausted::Depth)));
+                }
+                match self.exprs[val] {
+                    Expr::Lam(_, body) => {
+                        m.stack.push(Frame::Body(val));
+                        m.focus = body;
+                        return Ok(None);
+                    }
+                    // the function of a neutral application is already a value
+                    Expr::App(f, _) => {
+                        m.stack.push(Frame::NeutralFun(val));
+                        val = f;
+                        continue;
+                    }
+                    // the components of a pair and what an injection injects, unless
+                    // they were evaluated before it was a value or it is taken apart
+                    Expr::Pair(a, _) if !m.taken_apart() => {
+                        m.stack.push(Frame::Fst(val));
+                        m.focus = a;
+                        return Ok(None);
+                    }
+                    Expr::Inl(v) | Expr::Inr(v) if !m.taken_apart() => {
+                        m.stack.push(Frame::Inj(val));
+                        m.focus = v;
+                        return Ok(None);
+                    }
+                    // constants and variables that nothing binds
+                    _ => {}
+                }
+            }
+            let frame = m.stack.pop();
+            match frame {
+                None => return Ok(Some(Progress::Finished)),
+                Some(Frame::Fun(app) | Frame::Arg(app)) => {
+                    let Expr::App(f, v) = self.exprs[app] else {
+                        unreachable!("frames only point at apps");
+                    };
+                    let by_name = m.strategy.by_name();
+                    match self.exprs[f] {
+                        Expr::Lam(..) if by_name || matches!(frame, Some(Frame::Arg(_))) => {
+                            return Ok(Some(self.beta(m, app, obs)));
+                        }
+                        _ if !by_name && matches!(frame, Some(Frame::Fun(_))) => {
+                            m.stack.push(Frame::Arg(app));
+                            m.focus = v;
+                            return Ok(None);
+                        }
+                        ref f if m.strategy.strong() && is_neutral(f) => {
+                            (val, normal) = (app, false)
+                        }
+                        _ => return Err(EvalError::Stuck(Location::Slot(app))),
+                    }
+                }
+                Some(Frame::NeutralFun(app)) => {
+                    let Expr::App(_, v) = self.exprs[app] else {
+                        unreachable!("frames only point at apps");
+                    };
+                    m.stack.push(Frame::NeutralArg(app));
+                    m.focus = v;
+                    return Ok(None);
+                }
+                Some(
+                    Frame::Body(node)
+                    | Frame::NeutralArg(node)
+                    | Frame::NeutralElse(node)
+                    | Frame::Snd(node)
+                    | Frame::Inj(node)
+                    | Frame::NeutralSplit(node)
+                    | Frame::NeutralRight(node),
+                ) => (val, normal) = (node, true),
+                Some(Frame::Bound(binding)) => return Ok(Some(self.bind_let(m, binding, obs))),
+                Some(Frame::Force(var)) => {
+                    let progress = self.deref(m, var, obs);
+                    if matches!(progress, Progress::Exhausted(_)) {
+                        m.stack.push(Frame::Force(var));
+                    }
+                    return Ok(Some(progress));
+                }
+                Some(Frame::Operand(prim, i)) => {
+                    let Expr::Prim(_, ref operands) = self.exprs[prim] else {
+                        unreachable!("frames only point at primitives");
+                    };
+                    if let Some(&next) = operands.get(i + 1) {
+                        m.stack.push(Frame::Operand(prim, i + 1));
+                        m.focus = next;
+                        return Ok(None);
+                    }
+                    match self.apply_prim(m, prim, obs)? {
+                        Some(progress) => return Ok(Some(progress)),
+                        None => (val, normal) = (prim, true),
+                    }
+                }
+                Some(Frame::Cond(branch)) => {
+                    let Expr::If(c, t, _) = self.exprs[branch] else {
+                        unreachable!("frames only point at ifs");
+                    };
+                    match self.exprs[c] {
+                        Expr::Bas(Const::Bool(_)) => return Ok(Some(self.choose(m, branch, obs))),
+                        // when normalizing, an if stuck on its condition is a value
+                        // once both branches are normalized
+                        ref c if m.strategy.strong() && is_neutral(c) => {
+                            m.stack.push(Frame::NeutralThen(branch));
+                            m.focus = t;
+                            return Ok(None);
+                        }
+                        _ => return Err(EvalError::BadCondition(Location::Slot(branch))),
+                    }
+                }
+                Some(Frame::NeutralThen(branch)) => {
+                    let Expr::If(_, _, e) = self.exprs[branch] else {
+                        unreachable!("frames only point at ifs");
+                    };
+                    m.stack.push(Frame::NeutralElse(branch));
+                    m.focus = e;
+                    return Ok(None);
+                }
+                Some(Frame::Fst(pair)) => {
+                    let Expr::Pair(_, b) = self.exprs[pair] else {
+                        unreachable!("frames only point at pairs");
+                    };
+                    m.stack.push(Frame::Snd(pair));
+                    m.focus = b;
+                    return Ok(None);
+                }
+                Some(Frame::Scrutinee(elim)) => match self.exprs[elim] {
+                    Expr::Split(_, _, v, body) => match self.exprs[v] {
+                        Expr::Pair(..) => return Ok(Some(self.split(m, elim, obs))),
+                        // when normalizing, a split stuck on its pair is a value
+                        // once its body is normalized
+                        ref v if m.strategy.strong() && is_neutral(v) => {
+                            m.stack.push(Frame::NeutralSplit(elim));
+                            m.focus = body;
+                            return Ok(None);
+                        }
+                        _ => return Err(EvalError::BadScrutinee(Location::Slot(elim))),
+                    },
+                    Expr::Case(v, _, l, _, _) => match self.exprs[v] {
+                        Expr::Inl(_) | Expr::Inr(_) => return Ok(Some(self.pick(m, elim, obs))),
+                        // when normalizing, a case stuck on its scrutinee is a value
+                        // once both branches are normalized
+                        ref v if m.strategy.strong() && is_neutral(v) => {
+                            m.stack.push(Frame::NeutralLeft(elim));
+                            m.focus = l;
+                            return Ok(None);
+                        }
+                        _ => return Err(EvalError::BadScrutinee(Location::Slot(elim))),
+                    },
+                    _ => unreachable!("frames only point at splits and cases"),
+                },
+                Some(Frame::NeutralLeft(case)) => {
+                    let Expr::Case(_, _, _, _, r) = self.exprs[case] else {
+                        unreachable!("frames only point at cases");
+                    };
+                    m.stack.push(Frame::NeutralRight(case));
+                    m.focus = r;
+                    return Ok(None);
+                }
+            }
         }
-        fn make_lam_true(&mut self, e: ExprDest) -> ExprRef {
-            let (x_lam, x_arg, x_body) = self.make_lam(e);
-            let (_y_lam, _y_arg, y_body) = self.make_lam(x_body);
-            let _ = self.make_deref(y_body, x_arg);
-            x_lam
+    }
+    // Replaces the primitive at `prim`, whose operands are all evaluated, with its
+    // result. When normalizing, a primitive stuck on a variable is a value instead,
+    // and None is returned.
+    fn apply_prim(
+        &mut self,
+        m: &mut Machine,
+        prim: usize,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Result<Option<Progress>, EvalError> {
+        let Expr::Prim(op, ref operands) = self.exprs[prim] else {
+            unreachable!("only primitives are applied");
+        };
+        let constants: Option<Vec<&Const>> = operands
+            .iter()
+            .map(|&operand| match self.exprs[operand] {
+                Expr::Bas(ref c) => Some(c),
+                _ => None,
+            })
+            .collect();
+        let Some(constants) = constants else {
+            let neutral = |&operand: &usize| match self.exprs[operand] {
+                Expr::Bas(_) => true,
+                ref e => is_neutral(e),
+            };
+            if m.strategy.strong() && operands.iter().all(neutral) {
+                return Ok(None);
+            }
+            return Err(EvalError::BadPrim(Location::Slot(prim)));
+        };
+        let Some(result) = op.apply(&constants) else {
+            return Err(EvalError::BadPrim(Location::Slot(prim)));
+        };
+        // the operands are constants, so evaluating them again costs no fuel
+        if !m.burn() {
+            m.focus = prim;
+            return Ok(Some(Progress::Exhausted(Exhausted::Steps)));
         }
-        fn make_lam_false(&mut self, e: ExprDest) -> ExprRef {
-            let (x_lam, _x_arg, x_body) = self.make_lam(e);
-            let (_y_lam, y_arg, y_body) = self.make_lam(x_body);
-            let _ = self.make_deref(y_body, y_arg);
-            x_lam
+        obs.on_prim(&self.node(prim));
+        let Expr::Prim(_, operands) = std::mem::replace(&mut self.exprs[prim], Expr::Bas(result))
+        else {
+            unreachable!("checked above");
+        };
+        for &operand in &operands {
+            self.exprs[operand] = Expr::Invalid;
         }
+        self.dead += operands.len();
+        m.focus = prim;
+        Ok(Some(Progress::Reduced))
     }
-    #[test]
-    fn t0() {
-        let (mut prg, start) = Program::build();
-        let (_app, f, v) = prg.make_app(start);
-        let _ = prg.make_const_fn(f);
-        let _ = prg.make_const(v, ONE);
-        assert_eq!(Some(UNIT), prg.eval());
+    // Replaces the if at `branch`, whose condition is a boolean, with the branch it
+    // picks. The untaken branch is invalidated, binders and all, since nothing can
+    // reach it any more.
+    fn choose(
+        &mut self,
+        m: &mut Machine,
+        branch: usize,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Progress {
+        let Expr::If(c, t, e) = self.exprs[branch] else {
+            unreachable!("only ifs choose");
+        };
+        // the condition is a constant, so evaluating it again costs no fuel
+        if !m.burn() {
+            m.focus = branch;
+            return Progress::Exhausted(Exhausted::Steps);
+        }
+        obs.on_if(&self.node(branch));
+        let (picked, untaken) = match self.exprs[c] {
+            Expr::Bas(Const::Bool(true)) => (t, e),
+            _ => (e, t),
+        };
+        self.exprs[branch] = std::mem::replace(&mut self.exprs[picked], Expr::Invalid);
+        self.exprs[c] = Expr::Invalid;
+        self.dead += 2 + self.invalidate(untaken);
+        m.focus = branch;
+        Progress::Reduced
     }
-
-    #[test]
-    fn t1() {
-        let (mut prg, start) = Program::build();
-        let (_app, f, v) = prg.make_app(start);
-        let (_f, ff, fv) = prg.make_app(f);
-        let _ = prg.make_ident(ff);
-        let _ = prg.make_const_fn(fv);
-        let _ = prg.make_const(v, ONE);
-        assert_eq!(Some(UNIT), prg.eval());
+    // Replaces the split at `split`, whose pair is evaluated, with its body, binding
+    // its variables to the components like beta reduction does.
+    fn split(
+        &mut self,
+        m: &mut Machine,
+        split: usize,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Progress {
+        let Expr::Split(x, y, v, body) = self.exprs[split] else {
+            unreachable!("only splits split");
+        };
+        // the pair is a value, so evaluating it again costs no fuel
+        if !m.burn() {
+            m.focus = split;
+            return Progress::Exhausted(Exhausted::Steps);
+        }
+        obs.on_match(&self.node(split));
+        let Expr::Pair(a, b) = std::mem::replace(&mut self.exprs[v], Expr::Invalid) else {
+            unreachable!("checked by ret");
+        };
+        self.exprs[x] = std::mem::replace(&mut self.exprs[a], Expr::Invalid);
+        self.exprs[y] = std::mem::replace(&mut self.exprs[b], Expr::Invalid);
+        self.exprs[split] = std::mem::replace(&mut self.exprs[body], Expr::Invalid);
+        self.dead += 4;
+        m.focus = split;
+        Progress::Reduced
     }
-
-    #[test]
-    fn t2() {
-        let (mut prg, start) = Program::build();
-        let (_app, f, v) = prg.make_app(start);
-        let (_f, ff, fv) = prg.make_app(f);
-        let _ = prg.make_lam_true(ff);
-        let _ = prg.make_const(fv, ZERO);
-        let _ = prg.make_const(v, ONE);
-        assert_eq!(Some(ZERO), prg.eval());
+    // Replaces the case at `case`, whose scrutinee is an injection, with the branch
+    // that binds what it injects. The other branch is invalidated with its binder.
+    fn pick(
+        &mut self,
+        m: &mut Machine,
+        case: usize,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Progress {
+        let Expr::Case(v, x, l, y, r) = self.exprs[case] else {
+            unreachable!("only cases pick");
+        };
+        // the scrutinee is a value, so evaluating it again costs no fuel
+        if !m.burn() {
+            m.focus = case;
+            return Progress::Exhausted(Exhausted::Steps);
+        }
+        obs.on_match(&self.node(case));
+        let (inner, arg, picked, other_arg, untaken) =
+            match std::mem::replace(&mut self.exprs[v], Expr::Invalid) {
+                Expr::Inl(inner) => (inner, x, l, y, r),
+                Expr::Inr(inner) => (inner, y, r, x, l),
+                _ => unreachable!("checked by ret"),
+            };
+        self.exprs[arg] = std::mem::replace(&mut self.exprs[inner], Expr::Invalid);
+        self.exprs[case] = std::mem::replace(&mut self.exprs[picked], Expr::Invalid);
+        self.dead += 3 + self.invalidate(other_arg) + self.invalidate(untaken);
+        m.focus = case;
+        Progress::Reduced
+    }
+    // overwrites the subterm at `root` and the arg slots of its binders with Invalid,
+    // returning how many slots that was; binders outside it that its variables point
+    // to are left alone
+    fn invalidate(&mut self, root: usize) -> usize {
+        let mut count = 0;
+        let mut todo = vec![root];
+        while let Some(idx) = todo.pop() {
+            match std::mem::replace(&mut self.exprs[idx], Expr::Invalid) {
+                Expr::Bas(_) | Expr::Ptr(_) | Expr::Invalid => {}
+                Expr::Lam(a, b) | Expr::App(a, b) => todo.extend([a, b]),
+                Expr::Let(arg, v, body) => todo.extend([arg, v, body]),
+                Expr::Prim(_, operands) => todo.extend(operands),
+                Expr::If(c, t, e) => todo.extend([c, t, e]),
+                Expr::Pair(a, b) => todo.extend([a, b]),
+                Expr::Split(x, y, v, body) => todo.extend([x, y, v, body]),
+                Expr::Inl(v) | Expr::Inr(v) => todo.push(v),
+                Expr::Case(v, x, l, y, r) => todo.extend([v, x, l, y, r]),
+            }
+            count += 1;
+        }
+        count
     }
+    // beta-reduces the application at `app`, whose function is a lambda,
+    // putting its frame back if there is no fuel left
+    fn beta(
+        &mut self,
+        m: &mut Machine,
+        app: usize,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Progress {
+        let Expr::App(f, v) = self.exprs[app] else {
+            unreachable!("frames only point at apps");
+        };
+        let Expr::Lam(arg, body) = self.exprs[f] else {
+            unreachable!("only lambdas are applied");
+        };
+        if !m.burn() {
+            let frame = if m.strategy.by_name() {
+                Frame::Fun(app)
+            } else {
+                Frame::Arg(app)
+            };
+            m.stack.push(frame);
+            return Progress::Exhausted(Exhausted::Steps);
+        }
+        obs.on_beta(&self.node(f), &self.node(v));
+        // beta reduction: set arg to v, replace expr with body
+        self.exprs[arg] = std::mem::replace(&mut self.exprs[v], Expr::Invalid);
+        self.exprs[app] = std::mem::replace(&mut self.exprs[body], Expr::Invalid);
 
-    #[test]
-    fn t3() {
-        let (mut prg, start) = Program::build();
-        let (_app, f, v) = prg.make_app(start);
-        let (_f, ff, fv) = prg.make_app(f);
-        let _ = prg.make_lam_false(ff);
-        let _ = prg.make_const(fv, ZERO);
-        let _ = prg.make_const(v, ONE);
-        assert_eq!(Some(ONE), prg.eval());
+        self.exprs[f] = Expr::Invalid;
+        self.dead += 3;
+        m.focus = app;
+        Progress::Reduced
+    }
+    // binds the variable of the let at `binding` to its value, like beta reduction
+    // does, and replaces the let with its body
+    fn bind_let(
+        &mut self,
+        m: &mut Machine,
+        binding: usize,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Progress {
+        let Expr::Let(arg, v, body) = self.exprs[binding] else {
+            unreachable!("only lets are bound");
+        };
+        if !m.burn() {
+            if !m.strategy.by_name() {
+                m.stack.push(Frame::Bound(binding));
+            }
+            return Progress::Exhausted(Exhausted::Steps);
+        }
+        obs.on_let(&self.node(binding));
+        self.exprs[arg] = std::mem::replace(&mut self.exprs[v], Expr::Invalid);
+        self.exprs[binding] = std::mem::replace(&mut self.exprs[body], Expr::Invalid);
+        self.dead += 2;
+        m.focus = binding;
+        Progress::Reduced
+    }
+    // replaces the variable at `var` with the expression its binder was bound to
+    fn deref(
+        &mut self,
+        m: &mut Machine,
+        var: usize,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Progress {
+        let Expr::Ptr(target) = self.exprs[var] else {
+            unreachable!("only variables are dereferenced");
+        };
+        if !m.burn() {
+            return Progress::Exhausted(Exhausted::Steps);
+        }
+        obs.on_deref(&self.node(var));
+        // deref this expr to a copy of self.exprs[target]; the target stays
+        // put since other uses of the same variable may still read it
+        self.exprs[var] = self.copy_value(target);
+        m.focus = var;
+        Progress::Reduced
+    }
+    /// Copies the expression at `src` into fresh slots and returns the copied root.
+    /// Binders inside the copy get fresh arg slots, so beta-reducing the copy leaves
+    /// the original intact; pointers to binders outside the copy stay shared.
+    fn copy_value(&mut self, src: usize) -> Expr {
+        let mut renamed = HashMap::new();
+        let mut todo = Vec::new();
+        let root = self.copy_shallow(self.exprs[src].clone(), &mut renamed, &mut todo);
+        while let Some((old, new)) = todo.pop() {
+            let expr = self.exprs[old].clone();
+            self.exprs[new] = self.copy_shallow(expr, &mut renamed, &mut todo);
+        }
+        root
+    }
+    fn copy_shallow(
+        &mut self,
+        expr: Expr,
+        renamed: &mut HashMap<usize, usize>,
+        todo: &mut Vec<(usize, usize)>,
+    ) -> Expr {
+        match expr {
+            Expr::Bas(_) | Expr::Invalid => expr,
+            Expr::Ptr(target) => Expr::Ptr(renamed.get(&target).copied().unwrap_or(target)),
+            Expr::Lam(arg, body) => {
+                let (new_arg, new_body) = (self.copy_binder(arg, renamed), self.alloc());
+                todo.push((arg, new_arg));
+                todo.push((body, new_body));
+                Expr::Lam(new_arg, new_body)
+            }
+            Expr::App(f, v) => {
+                let (new_f, new_v) = (self.alloc(), self.alloc());
+                todo.push((f, new_f));
+                todo.push((v, new_v));
+                Expr::App(new_f, new_v)
+            }
+            Expr::Let(arg, v, body) => {
+                let new_arg = self.copy_binder(arg, renamed);
+                let (new_v, new_body) = (self.alloc(), self.alloc());
+                todo.push((arg, new_arg));
+                todo.push((v, new_v));
+                todo.push((body, new_body));
+                Expr::Let(new_arg, new_v, new_body)
+            }
+            Expr::Prim(op, operands) => {
+                let mut new_operands = Vec::with_capacity(operands.len());
+                for operand in operands {
+                    let new_operand = self.alloc();
+                    todo.push((operand, new_operand));
+                    new_operands.push(new_operand);
+                }
+                Expr::Prim(op, new_operands)
+            }
+            Expr::If(c, t, e) => {
+                let (new_c, new_t, new_e) = (self.alloc(), self.alloc(), self.alloc());
+                todo.extend([(c, new_c), (t, new_t), (e, new_e)]);
+                Expr::If(new_c, new_t, new_e)
+            }
+            Expr::Pair(a, b) => {
+                let (new_a, new_b) = (self.alloc(), self.alloc());
+                todo.extend([(a, new_a), (b, new_b)]);
+                Expr::Pair(new_a, new_b)
+            }
+            Expr::Split(x, y, v, body) => {
+                let (new_x, new_y) = (self.copy_binder(x, renamed), self.copy_binder(y, renamed));
+                let (new_v, new_body) = (self.alloc(), self.alloc());
+                todo.extend([(x, new_x), (y, new_y), (v, new_v), (body, new_body)]);
+                Expr::Split(new_x, new_y, new_v, new_body)
+            }
+            Expr::Inl(v) => {
+                let new_v = self.alloc();
+                todo.push((v, new_v));
+                Expr::Inl(new_v)
+            }
+            Expr::Inr(v) => {
+                let new_v = self.alloc();
+                todo.push((v, new_v));
+                Expr::Inr(new_v)
+            }
+            Expr::Case(v, x, l, y, r) => {
+                let new_v = self.alloc();
+                let (new_x, new_l) = (self.copy_binder(x, renamed), self.alloc());
+                let (new_y, new_r) = (self.copy_binder(y, renamed), self.alloc());
+                todo.extend([(v, new_v), (x, new_x), (l, new_l), (y, new_y), (r, new_r)]);
+                Expr::Case(new_v, new_x, new_l, new_y, new_r)
+            }
+        }
+    }
+    // a fresh arg slot for the copy of the binder at `arg`, with its name and type
+    fn copy_binder(&mut self, arg: usize, renamed: &mut HashMap<usize, usize>) -> usize {
+        let new_arg = self.alloc();
+        renamed.insert(arg, new_arg);
+        if let Some(hint) = self.hints.get(&arg) {
+            self.hints.insert(new_arg, Rc::clone(hint));
+        }
+        if let Some(ty) = self.annotations.get(&arg) {
+            self.annotations.insert(new_arg, ty.clone());
+        }
+        new_arg
+    }
+    /// The term rooted at the program's root, with substituted variables
+    /// replaced by their values.
+    pub fn to_term(&self) -> Term {
+        self.read_back(0)
+    }
+    /// The type of the term rooted at the program's root, or why it has none, as
+    /// [`types::typecheck`] finds it. A program that checks does not get stuck.
+    pub fn typecheck(&self) -> Result<Type, TypeError> {
+        types::typecheck(&self.to_term())
+    }
+    fn read_back(&self, root: usize) -> Term {
+        ReadBack::new().run(root, |rb, mut idx| {
+            // a variable that evaluation already bound reads back as its value
+            while let Expr::Ptr(target) = self.exprs[idx] {
+                if self.exprs[target] == Expr::Invalid {
+                    return Shape::Leaf(rb.var(target));
+                }
+                idx = target;
+            }
+            match self.exprs[idx] {
+                Expr::Bas(ref c) => Shape::Leaf(Term::Const(c.clone())),
+                Expr::Lam(arg, body) => {
+                    let binder = rb.binder(arg, self.hints.get(&arg).cloned());
+                    let ty = self.annotations.get(&arg).cloned();
+                    Shape::Lam(Binder { ty, ..binder }, body)
+                }
+                Expr::App(f, v) => Shape::App(f, v),
+                Expr::Let(arg, v, body) => {
+                    Shape::Let(rb.binder(arg, self.hints.get(&arg).cloned()), v, body)
+                }
+                Expr::Prim(op, ref operands) => Shape::Prim(op, operands.clone()),
+                Expr::If(c, t, e) => Shape::If(c, t, e),
+                Expr::Pair(a, b) => Shape::Pair(a, b),
+                Expr::Split(x, y, v, body) => {
+                    let x = rb.binder(x, self.hints.get(&x).cloned());
+                    let y = rb.binder(y, self.hints.get(&y).cloned());
+                    Shape::Split(x, y, v, body)
+                }
+                Expr::Inl(v) => Shape::Inl(v),
+                Expr::Inr(v) => Shape::Inr(v),
+                Expr::Case(v, x, l, y, r) => {
+                    let x = rb.binder(x, self.hints.get(&x).cloned());
+                    let y = rb.binder(y, self.hints.get(&y).cloned());
+                    Shape::Case(v, x, l, y, r)
+                }
+                Expr::Ptr(_) => unreachable!("followed above"),
+                Expr::Invalid => Shape::Leaf(Term::Invalid),
+            }
+        })
+    }
+    /// Writes the program in a compact binary format that `read_from` loads back.
+    /// Every slot is written, including those left `Invalid` by evaluation, so a
+    /// program paused by `eval_with_fuel` loads back and resumes where it stopped,
+    /// but binder names from `make_named_lam` are not.
+    pub fn write_to(&self, mut w: impl Write) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        write_varint(&mut w, VERSION)?;
+        write_varint(&mut w, self.exprs.len() as u64)?;
+        for expr in &self.exprs {
+            match *expr {
+                Expr::Invalid => w.write_all(&[TAG_INVALID])?,
+                Expr::Bas(ref c) => {
+                    w.write_all(&[TAG_BAS])?;
+                    write_constant(&mut w, c)?;
+                }
+                Expr::Ptr(target) => {
+                    w.write_all(&[TAG_PTR])?;
+                    write_varint(&mut w, target as u64)?;
+                }
+                Expr::Lam(arg, body) => {
+                    w.write_all(&[TAG_LAM])?;
+                    write_varint(&mut w, arg as u64)?;
+                    write_varint(&mut w, body as u64)?;
+                }
+                Expr::App(f, v) => {
+                    w.write_all(&[TAG_APP])?;
+                    write_varint(&mut w, f as u64)?;
+                    write_varint(&mut w, v as u64)?;
+                }
+                Expr::Let(arg, v, body) => {
+                    w.write_all(&[TAG_LET])?;
+                    write_varint(&mut w, arg as u64)?;
+                    write_varint(&mut w, v as u64)?;
+                    write_varint(&mut w, body as u64)?;
+                }
+                Expr::Prim(op, ref operands) => {
+                    let op = PrimOp::ALL.iter().position(|&o| o == op);
+                    w.write_all(&[TAG_PRIM, op.expect("every op is in ALL") as u8])?;
+                    write_varint(&mut w, operands.len() as u64)?;
+                    for &operand in operands {
+                        write_varint(&mut w, operand as u64)?;
+                    }
+                }
+                Expr::If(c, t, e) => {
+                    w.write_all(&[TAG_IF])?;
+                    write_varint(&mut w, c as u64)?;
+                    write_varint(&mut w, t as u64)?;
+                    write_varint(&mut w, e as u64)?;
+                }
+                Expr::Pair(a, b) => {
+                    w.write_all(&[TAG_PAIR])?;
+                    write_varint(&mut w, a as u64)?;
+                    write_varint(&mut w, b as u64)?;
+                }
+                Expr::Split(x, y, v, body) => {
+                    w.write_all(&[TAG_SPLIT])?;
+                    for slot in [x, y, v, body] {
+                        write_varint(&mut w, slot as u64)?;
+                    }
+                }
+                Expr::Inl(v) => {
+                    w.write_all(&[TAG_INL])?;
+                    write_varint(&mut w, v as u64)?;
+                }
+                Expr::Inr(v) => {
+                    w.write_all(&[TAG_INR])?;
+                    write_varint(&mut w, v as u64)?;
+                }
+                Expr::Case(v, x, l, y, r) => {
+                    w.write_all(&[TAG_CASE])?;
+                    for slot in [v, x, l, y, r] {
+                        write_varint(&mut w, slot as u64)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Loads a program written by `write_to`, checking that every index is in range
+    /// and that the result passes `validate`. Reads one byte at a time, so buffer `r`
+    /// if needed.
+    pub fn read_from(mut r: impl Read) -> Result<Self, DecodeError> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(DecodeError::BadMagic);
+        }
+        let version = read_varint(&mut r)?;
+        if version != VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let len = read_usize(&mut r)?;
+        if len == 0 {
+            return Err(DecodeError::Empty);
+        }
+        // the length is untrusted, so don't reserve more than a default build would
+        let mut exprs = Vec::with_capacity(len.min(128));
+        for slot in 0..len {
+            let mut tag = [0];
+            r.read_exact(&mut tag)?;
+            let mut index = || read_index(&mut r, slot, len);
+            exprs.push(match tag[0] {
+                TAG_INVALID => Expr::Invalid,
+                TAG_BAS => Expr::Bas(read_constant(&mut r, slot)?),
+                TAG_PTR => Expr::Ptr(index()?),
+                TAG_LAM => Expr::Lam(index()?, index()?),
+                TAG_APP => Expr::App(index()?, index()?),
+                TAG_LET => Expr::Let(index()?, index()?, index()?),
+                TAG_PRIM => read_prim(&mut r, slot, len)?,
+                TAG_IF => Expr::If(index()?, index()?, index()?),
+                TAG_PAIR => Expr::Pair(index()?, index()?),
+                TAG_SPLIT => Expr::Split(index()?, index()?, index()?, index()?),
+                TAG_INL => Expr::Inl(index()?),
+                TAG_INR => Expr::Inr(index()?),
+                TAG_CASE => Expr::Case(index()?, index()?, index()?, index()?, index()?),
+                tag => return Err(DecodeError::UnknownTag { slot, tag }),
+            });
+        }
+        let prg = Self {
+            exprs,
+            dead: 0,
+            gc_threshold: None,
+            hints: HashMap::new(),
+            annotations: HashMap::new(),
+        };
+        prg.validate().map_err(DecodeError::Malformed)?;
+        Ok(prg)
+    }
+    fn alloc(&mut self) -> usize {
+        self.exprs.push(Expr::Invalid);
+        self.exprs.len() - 1
+    }
+    // moves the term built at t into the root
+    fn set_root(&mut self, t: ExprRef) {
+        if t.0 != 0 {
+            self.exprs[0] = std::mem::replace(&mut self.exprs[t.0], Expr::Invalid);
+            self.dead += 1;
+        }
+    }
+    /// Collect garbage during `eval` whenever more than `ratio` of all slots
+    /// have been left `Invalid` by evaluation.
+    pub fn set_gc_threshold(&mut self, ratio: f64) {
+        self.gc_threshold = Some(ratio);
+    }
+    /// Mark-compact collection: keeps only the slots reachable from the root,
+    /// slides them down in their original order and rewrites every index.
+    /// Returns how many slots were reclaimed.
+    ///
+    /// Any `ExprRef`, `ArgRef` or `ExprDest` still held for this program is invalidated.
+    pub fn collect_garbage(&mut self) -> usize {
+        self.compact([])
+    }
+    // Compacts the program, forwarding `roots`: slots held outside of it, like the
+    // machine's, which are all reachable from the root so they need no marking.
+    fn compact<'a>(&mut self, roots: impl IntoIterator<Item = &'a mut usize>) -> usize {
+        let mut live = vec![false; self.exprs.len()];
+        let mut todo = vec![0];
+        while let Some(idx) = todo.pop() {
+            if std::mem::replace(&mut live[idx], true) {
+                continue;
+            }
+            match self.exprs[idx] {
+                Expr::Bas(_) | Expr::Invalid => {}
+                Expr::Ptr(target) => todo.push(target),
+                Expr::Lam(a, b) | Expr::App(a, b) => todo.extend([a, b]),
+                Expr::Let(arg, v, body) => todo.extend([arg, v, body]),
+                Expr::Prim(_, ref operands) => todo.extend(operands),
+                Expr::If(c, t, e) => todo.extend([c, t, e]),
+                Expr::Pair(a, b) => todo.extend([a, b]),
+                Expr::Split(x, y, v, body) => todo.extend([x, y, v, body]),
+                Expr::Inl(v) | Expr::Inr(v) => todo.push(v),
+                Expr::Case(v, x, l, y, r) => todo.extend([v, x, l, y, r]),
+            }
+        }
+        let mut forward = vec![usize::MAX; self.exprs.len()];
+        let live_slots = live.iter().enumerate().filter(|(_, live)| **live);
+        for (new_idx, (idx, _)) in live_slots.enumerate() {
+            forward[idx] = new_idx;
+        }
+        let before = self.exprs.len();
+        let mut idx = 0;
+        self.exprs.retain(|_| {
+            idx += 1;
+            live[idx - 1]
+        });
+        for expr in &mut self.exprs {
+            match expr {
+                Expr::Ptr(target) => *target = forward[*target],
+                Expr::Lam(arg, body) => (*arg, *body) = (forward[*arg], forward[*body]),
+                Expr::App(f, v) => (*f, *v) = (forward[*f], forward[*v]),
+                Expr::Let(arg, v, body) => {
+                    (*arg, *v, *body) = (forward[*arg], forward[*v], forward[*body]);
+                }
+                Expr::Prim(_, operands) => {
+                    for operand in operands {
+                        *operand = forward[*operand];
+                    }
+                }
+                Expr::If(c, t, e) => (*c, *t, *e) = (forward[*c], forward[*t], forward[*e]),
+                Expr::Pair(a, b) => (*a, *b) = (forward[*a], forward[*b]),
+                Expr::Split(x, y, v, body) => {
+                    (*x, *y) = (forward[*x], forward[*y]);
+                    (*v, *body) = (forward[*v], forward[*body]);
+                }
+                Expr::Inl(v) | Expr::Inr(v) => *v = forward[*v],
+                Expr::Case(v, x, l, y, r) => {
+                    *v = forward[*v];
+                    (*x, *l) = (forward[*x], forward[*l]);
+                    (*y, *r) = (forward[*y], forward[*r]);
+                }
+                Expr::Bas(_) | Expr::Invalid => {}
+            }
+        }
+        for root in roots {
+            *root = forward[*root];
+        }
+        self.hints = std::mem::take(&mut self.hints)
+            .into_iter()
+            .filter(|&(arg, _)| live[arg])
+            .map(|(arg, hint)| (forward[arg], hint))
+            .collect();
+        self.annotations = std::mem::take(&mut self.annotations)
+            .into_iter()
+            .filter(|&(arg, _)| live[arg])
+            .map(|(arg, ty)| (forward[arg], ty))
+            .collect();
+        self.dead = 0;
+        before - self.exprs.len()
+    }
+    fn should_collect(&self) -> bool {
+        self.gc_threshold
+            .is_some_and(|ratio| self.dead as f64 > ratio * self.exprs.len() as f64)
+    }
+    pub fn eval(&mut self) -> Result<Option<Const>, EvalError> {
+        self.eval_observed(&mut NoopObserver)
+    }
+    /// Evaluates like `eval`, reporting every reduction to `obs`.
+    pub fn eval_observed(
+        &mut self,
+        obs: &mut impl for<'a> EvalObserver<Node<'a>>,
+    ) -> Result<Option<Const>, EvalError> {
+        match self.eval_with(Strategy::default(), Limits::default(), obs)? {
+            Outcome::Value(result) => Ok(result),
+            Outcome::OutOfFuel { .. } => unreachable!("evaluation without limits ran out"),
+        }
+    }
+    /// Evaluates with at most `max_steps` reductions.
+    pub fn eval_with_fuel(
+        &mut self,
+        max_steps: usize,
+    ) -> Result<Outcome<Option<Const>>, EvalError> {
+        self.eval_with_limits(Limits::steps(max_steps))
+    }
+    /// Evaluates until the result or until one of `limits` is hit, in which case
+    /// the partially reduced term is left in the program for `eval` to resume.
+    pub fn eval_with_limits(
+        &mut self,
+        limits: Limits,
+    ) -> Result<Outcome<Option<Const>>, EvalError> {
+        self.eval_with(Strategy::default(), limits, &mut NoopObserver)
+    }
+    /// Evaluates like `eval_with_limits` in the order `strategy` gives, reporting
+    /// every reduction to `obs`. The strong strategies leave the normal form in the
+    /// program, to read back with [`Program::to_term`].
+    pub fn eval_with(
+        &mut self,
+        strategy: Strategy,
+        limits: Limits,
+        obs: &mut impl for<'a> EvalObserver<Node<'a>>,
+    ) -> Result<Outcome<Option<Const>>, EvalError> {
+        self.run(strategy, limits, obs)
+    }
+    /// Reduces the program to its normal form in normal order, also under lambdas:
+    /// variables that nothing binds are values there, and so are applications stuck
+    /// on them. Read the result back with [`Program::to_term`].
+    pub fn normalize(&mut self) -> Result<(), EvalError> {
+        match self.run(Strategy::NormalOrder, Limits::default(), &mut NoopObserver)? {
+            Outcome::Value(_) => Ok(()),
+            Outcome::OutOfFuel { .. } => unreachable!("evaluation without limits ran out"),
+        }
+    }
+    fn run(
+        &mut self,
+        strategy: Strategy,
+        limits: Limits,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Result<Outcome<Option<Const>>, EvalError> {
+        obs.on_start(&self.node(0));
+        let mut machine = Machine::new(0, &limits, strategy);
+        loop {
+            // slots evaluation left Invalid are garbage, not part of the term
+            let progress = if self.exprs.len() - self.dead > limits.max_nodes {
+                Progress::Exhausted(Exhausted::Nodes)
+            } else {
+                self.step(&mut machine, obs)
+                    .inspect_err(|err| obs.on_stuck(err))?
+            };
+            match progress {
+                Progress::Reduced => {}
+                Progress::Finished => break,
+                Progress::Exhausted(exhausted) => {
+                    obs.on_out_of_fuel(exhausted);
+                    return Ok(Outcome::OutOfFuel {
+                        exhausted,
+                        term: (),
+                    });
+                }
+            }
+            if self.should_collect() {
+                self.compact(machine.slots_mut());
+            }
+            if obs.observes_steps() {
+                obs.on_step(&self.node(0));
+            }
+        }
+        obs.on_finish(&self.node(0));
+        if let Expr::Bas(result) = &self.exprs[0] {
+            Ok(Outcome::Value(Some(result.clone())))
+        } else {
+            Ok(Outcome::Value(None))
+        }
+    }
+}
+
+/// An empty program, whose root is still to be filled in.
+impl Default for Program {
+    fn default() -> Self {
+        Self::build().0
+    }
+}
+
+/// Builds each term in slots of its own, moving the one evaluated into the root.
+impl AbtBackend for Program {
+    type Term = ExprRef;
+    type Var = ArgRef;
+    fn constant(&mut self, c: Const) -> ExprRef {
+        let slot = self.alloc();
+        self.make_const(ExprDest(slot), c)
+    }
+    fn lam(&mut self, body: impl FnOnce(&mut Self, &ArgRef) -> ExprRef) -> ExprRef {
+        let (lam, arg) = (self.alloc(), self.alloc());
+        let body = body(self, &ArgRef(arg));
+        self.exprs[lam] = Expr::Lam(arg, body.0);
+        ExprRef(lam)
+    }
+    fn typed_lam(&mut self, ty: Type, body: impl FnOnce(&mut Self, &ArgRef) -> ExprRef) -> ExprRef {
+        let lam = self.lam(body);
+        let Expr::Lam(arg, _) = self.exprs[lam.0] else {
+            unreachable!("lam makes a lambda");
+        };
+        self.annotations.insert(arg, ty);
+        lam
+    }
+    fn app(&mut self, f: ExprRef, v: ExprRef) -> ExprRef {
+        let slot = self.alloc();
+        self.exprs[slot] = Expr::App(f.0, v.0);
+        ExprRef(slot)
+    }
+    fn var(&mut self, x: &ArgRef) -> ExprRef {
+        let slot = self.alloc();
+        self.make_deref(ExprDest(slot), x.clone())
+    }
+    fn let_in(&mut self, v: ExprRef, body: impl FnOnce(&mut Self, &ArgRef) -> ExprRef) -> ExprRef {
+        let (binding, arg) = (self.alloc(), self.alloc());
+        let body = body(self, &ArgRef(arg));
+        self.exprs[binding] = Expr::Let(arg, v.0, body.0);
+        ExprRef(binding)
+    }
+    fn prim(&mut self, op: PrimOp, operands: Vec<ExprRef>) -> ExprRef {
+        let slot = self.alloc();
+        self.exprs[slot] = Expr::Prim(op, operands.into_iter().map(|o| o.0).collect());
+        ExprRef(slot)
+    }
+    fn if_then_else(&mut self, c: ExprRef, t: ExprRef, e: ExprRef) -> ExprRef {
+        let slot = self.alloc();
+        self.exprs[slot] = Expr::If(c.0, t.0, e.0);
+        ExprRef(slot)
+    }
+    fn pair(&mut self, a: ExprRef, b: ExprRef) -> ExprRef {
+        let slot = self.alloc();
+        self.exprs[slot] = Expr::Pair(a.0, b.0);
+        ExprRef(slot)
+    }
+    fn split(
+        &mut self,
+        v: ExprRef,
+        body: impl FnOnce(&mut Self, &ArgRef, &ArgRef) -> ExprRef,
+    ) -> ExprRef {
+        let (split, x, y) = (self.alloc(), self.alloc(), self.alloc());
+        let body = body(self, &ArgRef(x), &ArgRef(y));
+        self.exprs[split] = Expr::Split(x, y, v.0, body.0);
+        ExprRef(split)
+    }
+    fn inl(&mut self, v: ExprRef) -> ExprRef {
+        let slot = self.alloc();
+        self.exprs[slot] = Expr::Inl(v.0);
+        ExprRef(slot)
+    }
+    fn inr(&mut self, v: ExprRef) -> ExprRef {
+        let slot = self.alloc();
+        self.exprs[slot] = Expr::Inr(v.0);
+        ExprRef(slot)
+    }
+    fn case(
+        &mut self,
+        v: ExprRef,
+        l: impl FnOnce(&mut Self, &ArgRef) -> ExprRef,
+        r: impl FnOnce(&mut Self, &ArgRef) -> ExprRef,
+    ) -> ExprRef {
+        let (case, x, y) = (self.alloc(), self.alloc(), self.alloc());
+        let l = l(self, &ArgRef(x));
+        let r = r(self, &ArgRef(y));
+        self.exprs[case] = Expr::Case(v.0, x, l.0, y, r.0);
+        ExprRef(case)
+    }
+    fn typecheck_term(&mut self, t: &ExprRef) -> Result<Type, TypeError> {
+        types::typecheck(&self.read_back(t.0))
+    }
+    fn eval_term_with(
+        &mut self,
+        t: ExprRef,
+        strategy: Strategy,
+        limits: Limits,
+    ) -> Result<Outcome<Term>, EvalError> {
+        self.set_root(t);
+        Ok(match self.eval_with(strategy, limits, &mut NoopObserver)? {
+            Outcome::Value(_) => Outcome::Value(self.to_term()),
+            Outcome::OutOfFuel { exhausted, term } => Outcome::OutOfFuel { exhausted, term },
+        })
+    }
+}
+
+// whether an expression is a variable, an application, a let, a primitive, an if,
+// a split or a case: not evaluated yet, or if it is a value, neutral
+fn is_neutral(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Ptr(_)
+            | Expr::App(..)
+            | Expr::Let(..)
+            | Expr::Prim(..)
+            | Expr::If(..)
+            | Expr::Split(..)
+            | Expr::Case(..)
+    )
+}
+
+// LEB128: seven bits at a time, least significant first,
+// with the high bit set on every byte but the last
+fn write_varint(w: &mut impl Write, mut n: u64) -> io::Result<()> {
+    while n >= 0x80 {
+        w.write_all(&[n as u8 | 0x80])?;
+        n >>= 7;
+    }
+    w.write_all(&[n as u8])
+}
+
+fn read_varint(r: &mut impl Read) -> Result<u64, DecodeError> {
+    let mut n = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        r.read_exact(&mut byte)?;
+        let bits = u64::from(byte[0] & 0x7f);
+        if bits << shift >> shift != bits {
+            return Err(DecodeError::VarintOverflow);
+        }
+        n |= bits << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err(DecodeError::VarintOverflow)
+}
+
+fn read_usize(r: &mut impl Read) -> Result<usize, DecodeError> {
+    usize::try_from(read_varint(r)?).map_err(|_| DecodeError::VarintOverflow)
+}
+
+// an index read for the slot at `slot` of a program with `len` slots
+fn read_index(r: &mut impl Read, slot: usize, len: usize) -> Result<usize, DecodeError> {
+    let index = read_usize(r)?;
+    if index >= len {
+        return Err(DecodeError::IndexOutOfRange { slot, index });
+    }
+    Ok(index)
+}
+
+// a constant as its kind byte, then a zigzag varint for an integer or the
+// length and UTF-8 bytes of a string
+fn write_constant(w: &mut impl Write, c: &Const) -> io::Result<()> {
+    match c {
+        Const::Unit => w.write_all(&[CONST_UNIT]),
+        Const::Int(n) => {
+            w.write_all(&[CONST_INT])?;
+            write_varint(w, ((n << 1) ^ (n >> 63)) as u64)
+        }
+        Const::Bool(false) => w.write_all(&[CONST_FALSE]),
+        Const::Bool(true) => w.write_all(&[CONST_TRUE]),
+        Const::Str(s) => {
+            w.write_all(&[CONST_STR])?;
+            write_varint(w, s.len() as u64)?;
+            w.write_all(s.as_bytes())
+        }
+    }
+}
+
+// a constant written by write_constant for the slot at `slot`
+fn read_constant(r: &mut impl Read, slot: usize) -> Result<Const, DecodeError> {
+    let mut kind = [0];
+    r.read_exact(&mut kind)?;
+    Ok(match kind[0] {
+        CONST_UNIT => Const::Unit,
+        CONST_INT => {
+            let n = read_varint(r)?;
+            Const::Int((n >> 1) as i64 ^ -((n & 1) as i64))
+        }
+        CONST_FALSE => Const::Bool(false),
+        CONST_TRUE => Const::Bool(true),
+        CONST_STR => {
+            let len = read_usize(r)?;
+            let mut bytes = Vec::new();
+            r.take(len as u64).read_to_end(&mut bytes)?;
+            if bytes.len() < len {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            let s = String::from_utf8(bytes).map_err(|_| DecodeError::InvalidUtf8 { slot })?;
+            Const::Str(s.into())
+        }
+        kind => return Err(DecodeError::UnknownConstant { slot, kind }),
+    })
+}
+
+// a primitive written by write_to for the slot at `slot`, after its tag
+fn read_prim(r: &mut impl Read, slot: usize, len: usize) -> Result<Expr, DecodeError> {
+    let mut op = [0];
+    r.read_exact(&mut op)?;
+    let Some(&op) = PrimOp::ALL.get(usize::from(op[0])) else {
+        return Err(DecodeError::UnknownPrim { slot, op: op[0] });
+    };
+    let count = read_usize(r)?;
+    if count != op.arity() {
+        let arity = Malformed::Arity {
+            slot,
+            op,
+            found: count,
+        };
+        return Err(DecodeError::Malformed(vec![arity]));
+    }
+    let operands = (0..count)
+        .map(|_| read_index(r, slot, len))
+        .collect::<Result<_, _>>()?;
+    Ok(Expr::Prim(op, operands))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dot::DotDump;
+    use crate::observer::{Event, Recorder};
+    trait ProgramExt {
+        fn make_const_fn(&mut self, e: ExprDest) -> ExprRef;
+        fn make_ident(&mut self, e: ExprDest) -> ExprRef;
+        fn make_lam_true(&mut self, e: ExprDest) -> ExprRef;
+    }
+    impl ProgramExt for Program {
+        fn make_const_fn(&mut self, e: ExprDest) -> ExprRef {
+            let (lam, _arg, body) = self.make_lam(e);
+            let _ = self.make_const(body, UNIT);
+            lam
+        }
+        fn make_ident(&mut self, e: ExprDest) -> ExprRef {
+            let (lam, arg, body) = self.make_lam(e);
+            let _ = self.make_deref(body, arg);
+            lam
+        }
+        fn make_lam_true(&mut self, e: ExprDest) -> ExprRef {
+            let (x_lam, x_arg, x_body) = self.make_lam(e);
+            let (_y_lam, _y_arg, y_body) = self.make_lam(x_body);
+            let _ = self.make_deref(y_body, x_arg);
+            x_lam
+        }
+    }
+    #[test]
+    fn self_application() {
+        // ((\x. x x) (\y. y)) 1
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        let (_f, ff, fv) = prg.make_app(f);
+        let (_x_lam, x_arg, x_body) = prg.make_lam(ff);
+        let (_xx, x0, x1) = prg.make_app(x_body);
+        let _ = prg.make_deref(x0, x_arg.clone());
+        let _ = prg.make_deref(x1, x_arg);
+        let _ = prg.make_ident(fv);
+        let _ = prg.make_const(v, ONE);
+        assert_eq!(Ok(Some(ONE)), prg.eval());
+    }
+
+    #[test]
+    fn church_two() {
+        // (\f. \x. f (f x)) (\y. y) 0 applies the identity twice
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        let (_f, two, id) = prg.make_app(f);
+        let (_f_lam, f_arg, f_body) = prg.make_lam(two);
+        let (_x_lam, x_arg, x_body) = prg.make_lam(f_body);
+        let (_outer, outer_f, inner) = prg.make_app(x_body);
+        let _ = prg.make_deref(outer_f, f_arg.clone());
+        let (_inner, inner_f, inner_x) = prg.make_app(inner);
+        let _ = prg.make_deref(inner_f, f_arg);
+        let _ = prg.make_deref(inner_x, x_arg);
+        let _ = prg.make_ident(id);
+        let _ = prg.make_const(v, ZERO);
+        assert_eq!(Ok(Some(ZERO)), prg.eval());
+    }
+
+    #[test]
+    fn collect_after_eval() {
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        let (_f, ff, fv) = prg.make_app(f);
+        let _ = prg.make_ident(ff);
+        let _ = prg.make_const_fn(fv);
+        let _ = prg.make_const(v, ONE);
+        assert_eq!(Ok(Some(UNIT)), prg.eval());
+        // the unused argument 1 is unreachable too, not just the Invalid holes
+        assert_eq!(10, prg.collect_garbage());
+        assert_eq!(vec![Expr::Bas(UNIT)], prg.exprs);
+    }
+
+    #[test]
+    fn collect_keeps_unbound_args() {
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        let _ = prg.make_ident(f);
+        let _ = prg.make_const(v, ONE);
+        assert_eq!(0, prg.collect_garbage());
+        assert_eq!(Ok(Some(ONE)), prg.eval());
+    }
+
+    #[test]
+    fn collect_during_eval() {
+        // ((\x. x x) (\y. y)) 1, where every deref copies the identity
+        let build = |prg: &mut Program, start| {
+            let (_app, f, v) = prg.make_app(start);
+            let (_f, ff, fv) = prg.make_app(f);
+            let (_x_lam, x_arg, x_body) = prg.make_lam(ff);
+            let (_xx, x0, x1) = prg.make_app(x_body);
+            let _ = prg.make_deref(x0, x_arg.clone());
+            let _ = prg.make_deref(x1, x_arg);
+            let _ = prg.make_ident(fv);
+            let _ = prg.make_const(v, ONE);
+        };
+        let (mut plain, start) = Program::build();
+        build(&mut plain, start);
+        assert_eq!(Ok(Some(ONE)), plain.eval());
+
+        let (mut collected, start) = Program::build();
+        build(&mut collected, start);
+        collected.set_gc_threshold(0.25);
+        assert_eq!(Ok(Some(ONE)), collected.eval());
+        assert!(collected.exprs.len() < plain.exprs.len());
+    }
+
+    #[test]
+    fn stuck_on_constant() {
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        let _ = prg.make_const(f, ZERO);
+        let _ = prg.make_const(v, ONE);
+        assert_eq!(Err(EvalError::Stuck(Location::Slot(0))), prg.eval());
+    }
+
+    #[test]
+    fn uninitialized_dest() {
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        let _ = prg.make_ident(f);
+        assert_eq!(
+            Err(EvalError::Uninitialized(Location::Slot(v.0))),
+            prg.eval()
+        );
+    }
+
+    #[test]
+    fn dangling_escaped_arg() {
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        // (\x. ()) x, with x used outside of its binder
+        let (_lam, arg, body) = prg.make_lam(f);
+        let _ = prg.make_const(body, UNIT);
+        let _ = prg.make_deref(v, arg);
+        assert_eq!(
+            Err(EvalError::DanglingPointer(Location::Slot(2))),
+            prg.eval()
+        );
+    }
+
+    #[test]
+    fn validate_well_formed() {
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        let _ = prg.make_lam_true(f);
+        let _ = prg.make_const(v, ONE);
+        assert_eq!(Ok(()), prg.validate());
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        // (\x. _) (x 1), with the 1 then redirected at the lambda's body
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        let (_lam, arg, _body) = prg.make_lam(f);
+        let (_v, vf, vv) = prg.make_app(v);
+        let _ = prg.make_deref(vf, arg);
+        let _ = prg.make_const(vv, ONE);
+        prg.exprs[6] = Expr::Ptr(4);
+        assert_eq!(
+            Err(vec![
+                Malformed::Uninitialized { slot: 4 },
+                Malformed::OutOfScope { ptr: 5, lam: 1 },
+                Malformed::NotABinder { ptr: 6, target: 4 },
+            ]),
+            prg.validate()
+        );
+    }
+
+    #[test]
+    fn deep_left_nested_app() {
+        // (((\x. x) (\x. x)) (\x. x)) ... 1, deeper than the native stack could recurse
+        let (mut prg, start) = Program::build();
+        let (_app, mut dest, v) = prg.make_app(start);
+        let _ = prg.make_const(v, ONE);
+        for _ in 0..100_000 {
+            let (_app, f, v) = prg.make_app(dest);
+            let _ = prg.make_ident(v);
+            dest = f;
+        }
+        let _ = prg.make_ident(dest);
+        let mut machine = Machine::new(0, &Limits::default(), Strategy::default());
+        while let Progress::Reduced = prg.step(&mut machine, &mut NoopObserver).unwrap() {}
+        assert_eq!(Expr::Bas(ONE), prg.exprs[0]);
+    }
+
+    fn make_omega(prg: &mut Program, e: ExprDest, copies: usize) {
+        // (\x. x x ... x) (\x. x x ... x)
+        let (_app, f, v) = prg.make_app(e);
+        for dest in [f, v] {
+            let (_lam, arg, mut body) = prg.make_lam(dest);
+            for _ in 1..copies {
+                let (_xx, rest, x) = prg.make_app(body);
+                let _ = prg.make_deref(x, arg.clone());
+                body = rest;
+            }
+            let _ = prg.make_deref(body, arg);
+        }
+    }
+
+    #[test]
+    fn fuel_runs_out_and_resumes() {
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        let _ = prg.make_const_fn(f);
+        let (_v, vf, vv) = prg.make_app(v);
+        let _ = prg.make_ident(vf);
+        let _ = prg.make_const(vv, ONE);
+        let out_of_steps = Outcome::OutOfFuel {
+            exhausted: Exhausted::Steps,
+            term: (),
+        };
+        assert_eq!(Ok(out_of_steps), prg.eval_with_fuel(1));
+        assert_eq!(Ok(Outcome::Value(Some(UNIT))), prg.eval_with_fuel(2));
+    }
+
+    #[test]
+    fn fuel_bounds_omega() {
+        let (mut prg, start) = Program::build();
+        make_omega(&mut prg, start, 2);
+        let out_of_steps = Outcome::OutOfFuel {
+            exhausted: Exhausted::Steps,
+            term: (),
+        };
+        assert_eq!(Ok(out_of_steps), prg.eval_with_fuel(1000));
+    }
+
+    #[test]
+    fn node_limit_bounds_growing_term() {
+        let (mut prg, start) = Program::build();
+        make_omega(&mut prg, start, 3);
+        let limits = Limits {
+            max_nodes: 1000,
+            ..Limits::default()
+        };
+        let out_of_nodes = Outcome::OutOfFuel {
+            exhausted: Exhausted::Nodes,
+            term: (),
+        };
+        assert_eq!(Ok(out_of_nodes), prg.eval_with_limits(limits));
+        assert!(prg.exprs.len() - prg.dead < 1100);
+    }
+
+    #[test]
+    fn node_limit_counts_live_slots() {
+        // every use of f copies the lambda into fresh slots and every beta leaves
+        // three slots dead, so the term peaks at 16 live slots while the program
+        // grows to 19
+        let (mut prg, start) = Program::build();
+        let _ = prg
+            .make_parsed(start, r"(\f. f (f (f 0))) (\x. x)")
+            .unwrap();
+        let limits = Limits {
+            max_nodes: 16,
+            ..Limits::default()
+        };
+        assert_eq!(Ok(Outcome::Value(Some(ZERO))), prg.eval_with_limits(limits));
+        assert_eq!(19, prg.exprs.len());
+    }
+
+    #[test]
+    fn depth_limit() {
+        let (mut prg, start) = Program::build();
+        let (_app, mut dest, v) = prg.make_app(start);
+        let _ = prg.make_const(v, ONE);
+        for _ in 0..10 {
+            let (_app, f, v) = prg.make_app(dest);
+            let _ = prg.make_ident(v);
+            dest = f;
+        }
+        let _ = prg.make_ident(dest);
+        let limits = Limits {
+            max_depth: 5,
+            ..Limits::default()
+        };
+        let too_deep = Outcome::OutOfFuel {
+            exhausted: Exhausted::Depth,
+            term: (),
+        };
+        assert_eq!(Ok(too_deep), prg.eval_with_limits(limits));
+        assert_eq!(Ok(Some(ONE)), prg.eval());
+    }
+
+    #[test]
+    fn recorder_sees_every_reduction() {
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        let (_f, ff, fv) = prg.make_app(f);
+        let _ = prg.make_ident(ff);
+        let _ = prg.make_const_fn(fv);
+        let _ = prg.make_const(v, ONE);
+        let mut recorder = Recorder::default();
+        assert_eq!(Ok(Some(UNIT)), prg.eval_observed(&mut recorder));
+        assert_eq!(
+            recorder.events,
+            vec![
+                Event::Start(r"(\x0. x0) (\x1. ()) 1".to_string()),
+                Event::Beta {
+                    lam: r"\x0. x0".to_string(),
+                    arg: r"\x0. ()".to_string(),
+                },
+                Event::Deref(r"\x0. ()".to_string()),
+                Event::Beta {
+                    lam: r"\x0. ()".to_string(),
+                    arg: "1".to_string(),
+                },
+                Event::Finish("()".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn let_binds_in_one_traced_step() {
+        let (mut prg, start) = Program::build();
+        let (_let, x, v, body) = prg.make_named_let(start, "x");
+        let _ = prg.make_const(v, ONE);
+        let _ = prg.make_deref(body, x);
+        let mut recorder = Recorder::default();
+        assert_eq!(Ok(Some(ONE)), prg.eval_observed(&mut recorder));
+        assert_eq!(
+            recorder.events,
+            vec![
+                Event::Start("let x = 1 in x".to_string()),
+                Event::Let("let x = 1 in x".to_string()),
+                Event::Deref("1".to_string()),
+                Event::Finish("1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn prims_trace_and_round_trip() {
+        let (mut prg, start) = Program::build();
+        let _ = prg.make_parsed(start, r"(\x. add x 1) (mul 2 3)").unwrap();
+        let mut bytes = Vec::new();
+        prg.write_to(&mut bytes).unwrap();
+        let loaded = Program::read_from(&bytes[..]).unwrap();
+        assert_eq!(prg.exprs, loaded.exprs);
+        let mut recorder = Recorder::default();
+        assert_eq!(Ok(Some(Const::Int(7))), prg.eval_observed(&mut recorder));
+        assert_eq!(
+            recorder.events,
+            vec![
+                Event::Start(r"(\x. add x 1) (mul 2 3)".to_string()),
+                Event::Prim("mul 2 3".to_string()),
+                Event::Beta {
+                    lam: r"\x. add x 1".to_string(),
+                    arg: "6".to_string(),
+                },
+                Event::Deref("6".to_string()),
+                Event::Prim("add 6 1".to_string()),
+                Event::Finish("7".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn ifs_trace_and_round_trip() {
+        let (mut prg, start) = Program::build();
+        let _ = prg
+            .make_parsed(start, r"if lt 0 1 then 1 else (\x. x) 0")
+            .unwrap();
+        let mut bytes = Vec::new();
+        prg.write_to(&mut bytes).unwrap();
+        let loaded = Program::read_from(&bytes[..]).unwrap();
+        assert_eq!(prg.exprs, loaded.exprs);
+        let mut recorder = Recorder::default();
+        assert_eq!(Ok(Some(ONE)), prg.eval_observed(&mut recorder));
+        assert_eq!(
+            recorder.events,
+            vec![
+                Event::Start(r"if lt 0 1 then 1 else (\x. x) 0".to_string()),
+                Event::Prim("lt 0 1".to_string()),
+                Event::If(r"if true then 1 else (\x. x) 0".to_string()),
+                Event::Finish("1".to_string()),
+            ]
+        );
+        // the untaken branch is dead along with its binder, leaving only the root
+        assert_eq!(prg.exprs.len() - 1, prg.dead);
+        assert_eq!(prg.exprs.len() - 1, prg.collect_garbage());
+    }
+
+    #[test]
+    fn pairs_and_sums_trace_and_round_trip() {
+        let src = r"case inr (1, 0) of inl x. x | inr y. let (a, b) = y in (\z. z) a";
+        let (mut prg, start) = Program::build();
+        let _ = prg.make_parsed(start, src).unwrap();
+        let mut bytes = Vec::new();
+        prg.write_to(&mut bytes).unwrap();
+        let loaded = Program::read_from(&bytes[..]).unwrap();
+        assert_eq!(prg.exprs, loaded.exprs);
+        let mut recorder = Recorder::default();
+        assert_eq!(Ok(Some(ONE)), prg.eval_observed(&mut recorder));
+        assert_eq!(
+            recorder.events,
+            vec![
+                Event::Start(src.to_string()),
+                Event::Match(src.to_string()),
+                Event::Deref("(1, 0)".to_string()),
+                Event::Match(r"let (a, b) = (1, 0) in (\z. z) a".to_string()),
+                Event::Deref("1".to_string()),
+                Event::Beta {
+                    lam: r"\z. z".to_string(),
+                    arg: "1".to_string(),
+                },
+                Event::Deref("1".to_string()),
+                Event::Finish("1".to_string()),
+            ]
+        );
+        // the untaken branch, the pair and the arguments bound to it are unreachable
+        assert_eq!(prg.exprs.len() - 1, prg.collect_garbage());
+        assert_eq!(vec![Expr::Bas(ONE)], prg.exprs);
+    }
+
+    #[test]
+    fn write_then_read_round_trips() {
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        let (_f, ff, fv) = prg.make_app(f);
+        let (_let, x, lv, body) = prg.make_let(ff);
+        let _ = prg.make_lam_true(lv);
+        let _ = prg.make_deref(body, x);
+        let _ = prg.make_const(fv, ZERO);
+        let _ = prg.make_const(v, ONE);
+        let mut bytes = Vec::new();
+        prg.write_to(&mut bytes).unwrap();
+        let mut loaded = Program::read_from(&bytes[..]).unwrap();
+        assert_eq!(prg.exprs, loaded.exprs);
+        assert_eq!(Ok(Some(ZERO)), loaded.eval());
+    }
+
+    #[test]
+    fn partially_reduced_program_round_trips() {
+        // (\x. (\y. add x y) 2) 1, paused with x bound and still used in the body
+        let (mut prg, start) = Program::build();
+        let _ = prg.make_parsed(start, r"(\x. (\y. add x y) 2) 1").unwrap();
+        assert!(matches!(
+            prg.eval_with_fuel(1),
+            Ok(Outcome::OutOfFuel { .. })
+        ));
+        let mut bytes = Vec::new();
+        prg.write_to(&mut bytes).unwrap();
+        let mut loaded = Program::read_from(&bytes[..]).unwrap();
+        assert_eq!(prg.exprs, loaded.exprs);
+        assert_eq!(loaded.to_string(), r"(\x0. add 1 x0) 2");
+        // and so does the collector moving the bound argument
+        loaded.collect_garbage();
+        let mut bytes = Vec::new();
+        loaded.write_to(&mut bytes).unwrap();
+        let mut loaded = Program::read_from(&bytes[..]).unwrap();
+        assert_eq!(Ok(Some(Const::Int(3))), loaded.eval());
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        for n in [0, 1, 0x7f, 0x80, 300, u32::MAX as u64, u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, n).unwrap();
+            assert_eq!(n, read_varint(&mut &bytes[..]).unwrap());
+        }
+        let too_long = [0xff; 10];
+        assert!(matches!(
+            read_varint(&mut &too_long[..]),
+            Err(DecodeError::VarintOverflow)
+        ));
+    }
+
+    #[test]
+    fn every_kind_of_constant_round_trips() {
+        let (mut prg, start) = Program::build();
+        let src = r#"\f. f () -7 9223372036854775807 true false "" "λ\n\"""#;
+        let _ = prg.make_parsed(start, src).unwrap();
+        let mut bytes = Vec::new();
+        prg.write_to(&mut bytes).unwrap();
+        let loaded = Program::read_from(&bytes[..]).unwrap();
+        assert_eq!(prg.exprs, loaded.exprs);
+        assert_eq!(
+            loaded.to_string(),
+            r#"\x0. x0 () -7 9223372036854775807 true false "" "λ\n\"""#
+        );
+    }
+
+    #[test]
+    fn read_rejects_bad_input() {
+        let (mut prg, start) = Program::build();
+        let _ = prg.make_ident(start);
+        let mut bytes = Vec::new();
+        prg.write_to(&mut bytes).unwrap();
+        // magic, version, 3 slots, then Lam(1, 2), Invalid, Ptr(1)
+        assert_eq!(bytes, b"ABTa\x02\x03\x03\x01\x02\x00\x02\x01");
+        let read = |patch: &[(usize, u8)]| {
+            let mut bytes = bytes.clone();
+            for &(at, byte) in patch {
+                bytes[at] = byte;
+            }
+            Program::read_from(&bytes[..]).map(|_| ())
+        };
+        assert!(matches!(read(&[(0, b'X')]), Err(DecodeError::BadMagic)));
+        assert!(matches!(
+            read(&[(4, 3)]),
+            Err(DecodeError::UnsupportedVersion(3))
+        ));
+        assert!(matches!(
+            read(&[(10, 13)]),
+            Err(DecodeError::UnknownTag { slot: 2, tag: 13 })
+        ));
+        assert!(matches!(
+            read(&[(11, 3)]),
+            Err(DecodeError::IndexOutOfRange { slot: 2, index: 3 })
+        ));
+        assert!(matches!(read(&[(5, 4)]), Err(DecodeError::Io(_))));
+        assert!(matches!(
+            read(&[(11, 0)]),
+            Err(DecodeError::Malformed(problems))
+                if problems == vec![Malformed::NotABinder { ptr: 2, target: 0 }]
+        ));
+        let unknown = b"ABTa\x02\x01\x01\x09";
+        assert!(matches!(
+            Program::read_from(&unknown[..]),
+            Err(DecodeError::UnknownConstant { slot: 0, kind: 9 })
+        ));
+        let not_utf8 = b"ABTa\x02\x01\x01\x04\x01\xff";
+        assert!(matches!(
+            Program::read_from(&not_utf8[..]),
+            Err(DecodeError::InvalidUtf8 { slot: 0 })
+        ));
+        let unknown_prim = b"ABTa\x02\x01\x06\x09\x00";
+        assert!(matches!(
+            Program::read_from(&unknown_prim[..]),
+            Err(DecodeError::UnknownPrim { slot: 0, op: 9 })
+        ));
+        // add with a single operand
+        let unary_add = b"ABTa\x02\x02\x06\x00\x01\x01\x01\x01\x02";
+        assert!(matches!(
+            Program::read_from(&unary_add[..]),
+            Err(DecodeError::Malformed(problems)) if problems == vec![Malformed::Arity {
+                slot: 0,
+                op: PrimOp::Add,
+                found: 1,
+            }]
+        ));
+        // a root applying itself to itself is a cycle, not a tree, through both edges
+        let cyclic = b"ABTa\x02\x01\x04\x00\x00";
+        let back_edge = Malformed::Shared { slot: 0, parent: 0 };
+        assert!(matches!(
+            Program::read_from(&cyclic[..]),
+            Err(DecodeError::Malformed(problems)) if problems == vec![back_edge; 2]
+        ));
+        // a variable bound to itself is a cycle through the argument
+        let self_bound = b"ABTa\x02\x02\x02\x01\x02\x01";
+        assert!(matches!(
+            Program::read_from(&self_bound[..]),
+            Err(DecodeError::Malformed(problems))
+                if problems == vec![Malformed::Shared { slot: 1, parent: 1 }]
+        ));
+        // a lambda owned by nothing, reached through a variable, whose argument
+        // already holds a let leading back to the root
+        let bound_binder = b"ABTa\x02\x0d\x04\x05\x06\x00\x00\x03\x09\x0a\x00\x02\x03\x04\x07\x08\x02\x03\x01\x01\x00\x05\x06\x00\x02\x0b\x0b\x02\x09\x01\x01\x02";
+        assert!(matches!(
+            Program::read_from(&bound_binder[..]),
+            Err(DecodeError::Malformed(problems))
+                if problems == vec![Malformed::AlreadyBound { arg: 9, binder: 3 }]
+        ));
+        // and an app whose function and argument are one slot shares it
+        let shared = b"ABTa\x02\x02\x04\x01\x01\x01\x00";
+        assert!(matches!(
+            Program::read_from(&shared[..]),
+            Err(DecodeError::Malformed(problems))
+                if problems == vec![Malformed::Shared { slot: 1, parent: 0 }]
+        ));
+    }
+
+    #[test]
+    fn display_follows_substituted_variables() {
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        let (_f, ff, fv) = prg.make_app(f);
+        let (_x_lam, x_arg, x_body) = prg.make_named_lam(ff, "x");
+        let (_y_lam, _y_arg, y_body) = prg.make_named_lam(x_body, "y");
+        let _ = prg.make_deref(y_body, x_arg);
+        let _ = prg.make_const(fv, ZERO);
+        let _ = prg.make_const(v, ONE);
+        assert_eq!(prg.to_string(), r"(\x. \y. x) 0 1");
+        assert!(matches!(
+            prg.eval_with_fuel(1),
+            Ok(Outcome::OutOfFuel { .. })
+        ));
+        assert_eq!(prg.to_string(), r"(\y. 0) 1");
+        // hints survive the collector moving the binder
+        prg.collect_garbage();
+        assert_eq!(prg.to_string(), r"(\y. 0) 1");
+    }
+
+    #[test]
+    fn typecheck_rejects_what_would_get_stuck() {
+        let (mut prg, start) = Program::build();
+        let _ = prg.make_parsed(start, r"(\x. x 1) 0").unwrap();
+        assert_eq!(
+            prg.typecheck().unwrap_err().to_string(),
+            "type mismatch at root.arg: expected int -> ?0, found int"
+        );
+        assert_eq!(Err(EvalError::Stuck(Location::Slot(0))), prg.eval());
+        // an annotation survives the copies evaluation makes and the collector
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        let _ = prg.make_parsed(f, r"\f. (f, f)").unwrap();
+        let (_lam, x, body) = prg.make_typed_lam(v, "x", Type::Int);
+        let _ = prg.make_deref(body, x);
+        let int_fn = Type::fun(Type::Int, Type::Int);
+        let pair = Type::prod(int_fn.clone(), int_fn);
+        assert_eq!(Ok(pair.clone()), prg.typecheck());
+        assert_eq!(Ok(None), prg.eval());
+        prg.collect_garbage();
+        assert_eq!(prg.to_string(), r"(\(x: int). x, \(x: int). x)");
+        assert_eq!(Ok(pair), prg.typecheck());
+    }
+
+    #[test]
+    fn dot_draws_back_edges_to_binders() {
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        let (_lam, arg, body) = prg.make_named_lam(f, "x");
+        let _ = prg.make_deref(body, arg);
+        let _ = prg.make_const(v, ONE);
+        let before = [
+            "digraph {",
+            "    node [shape=plaintext];",
+            "    n0 [label=\"@\"];",
+            "    n0 -> n1 [label=\"fun\"];",
+            "    n0 -> n2 [label=\"arg\"];",
+            "    n2 [label=\"1\"];",
+            "    n1 [label=\"λx\"];",
+            "    n1 -> n3 [label=\"arg\"];",
+            "    n1 -> n4 [label=\"body\"];",
+            "    n4 [label=\"var\"];",
+            "    n4 -> n3 [style=dashed, constraint=false];",
+            "    n3 [label=\"x\", shape=circle];",
+            "}",
+            "",
+        ];
+        assert_eq!(prg.to_dot(), before.join("\n"));
+        // after the beta step the variable's back-edge leads to the argument
+        assert!(matches!(
+            prg.eval_with_fuel(1),
+            Ok(Outcome::OutOfFuel { .. })
+        ));
+        let after = [
+            "digraph {",
+            "    node [shape=plaintext];",
+            "    n0 [label=\"var\"];",
+            "    n0 -> n1 [style=dashed, constraint=false];",
+            "    n1 [label=\"1\"];",
+            "}",
+            "",
+        ];
+        assert_eq!(prg.to_dot(), after.join("\n"));
+    }
+
+    #[test]
+    fn dot_dump_writes_every_step() {
+        let dir = std::env::temp_dir().join(format!("aptree-dot-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (mut prg, start) = Program::build();
+        let (_app, f, v) = prg.make_app(start);
+        let (_f, ff, fv) = prg.make_app(f);
+        let _ = prg.make_ident(ff);
+        let _ = prg.make_const_fn(fv);
+        let _ = prg.make_const(v, ONE);
+        let mut dump = DotDump::new(&dir);
+        assert_eq!(Ok(Some(UNIT)), prg.eval_observed(&mut dump));
+        // the start, then two betas and a deref
+        assert_eq!(4, dump.finish().unwrap());
+        let last = std::fs::read_to_string(dir.join("step-0003.dot")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(last, prg.to_dot());
+    }
+
+    #[test]
+    fn make_parsed_resolves_names() {
+        let (mut prg, start) = Program::build();
+        let _ = prg.make_parsed(start, r"(\x y. x) ((\x. x) 0) 1").unwrap();
+        assert_eq!(Ok(()), prg.validate());
+        assert_eq!(prg.to_string(), r"(\x. \y. x) ((\x. x) 0) 1");
+        assert_eq!(Ok(Some(ZERO)), prg.eval());
+
+        let (mut prg, start) = Program::build();
+        let err = prg.make_parsed(start, r"\x. y").unwrap_err();
+        assert_eq!(err.to_string(), "1:5: unbound variable `y`");
+    }
+
+    #[test]
+    fn alpha_eq_across_programs() {
+        let (mut a, start) = Program::build();
+        let (_app, f, v) = a.make_app(start);
+        let _ = a.make_ident(f);
+        let _ = a.make_lam_true(v);
+        let (mut b, start) = Program::build();
+        let _ = b.make_lam_true(start);
+        assert!(!alpha_eq(&a, &b));
+        // the same slots in both, but the variable points at the other lambda
+        let (mut c, start) = Program::build();
+        let _ = c.make_parsed(start, r"\x. \y. y").unwrap();
+        assert!(!alpha_eq(&b, &c));
+        assert_eq!(Ok(None), a.eval());
+        assert!(alpha_eq(&a, &b));
+    }
+
+    #[test]
+    fn normalize_reduces_under_lambdas() {
+        let (mut prg, start) = Program::build();
+        let src = r"(\x f. f ((\y. y) x) (\z. (\w. w) z f)) 0";
+        let _ = prg.make_parsed(start, src).unwrap();
+        assert_eq!(Ok(None), prg.eval());
+        assert_eq!(prg.to_string(), r"\f. f ((\y. y) 0) (\z. (\w. w) z f)");
+        prg.normalize().unwrap();
+        assert_eq!(prg.to_string(), r"\f. f 0 (\z. z f)");
+        // a constant applied to something is stuck even under a lambda
+        let (mut prg, start) = Program::build();
+        let _ = prg.make_parsed(start, r"\x. 0 x").unwrap();
+        assert!(matches!(prg.normalize(), Err(EvalError::Stuck(_))));
+    }
+
+    #[test]
+    fn call_by_need_evaluates_arguments_once() {
+        let betas = |strategy| {
+            let (mut prg, start) = Program::build();
+            let _ = prg
+                .make_parsed(start, r"(\x. x x) ((\i. i) (\z. z))")
+                .unwrap();
+            let mut recorder = Recorder::default();
+            let outcome = prg.eval_with(strategy, Limits::default(), &mut recorder);
+            assert_eq!(Ok(Outcome::Value(None)), outcome);
+            assert_eq!(prg.to_string(), r"\z. z");
+            let beta = |event: &&Event| matches!(event, Event::Beta { .. });
+            recorder.events.iter().filter(beta).count()
+        };
+        assert_eq!(3, betas(Strategy::CallByValue));
+        // by name, each use of x applies \i. i again
+        assert_eq!(4, betas(Strategy::CallByName));
+        assert_eq!(3, betas(Strategy::CallByNeed));
     }
 }
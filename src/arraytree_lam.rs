@@ -1,6 +1,16 @@
-pub const UNIT: &str = "()";
-pub const ZERO: &str = "0";
-pub const ONE: &str = "1";
+use crate::backend::AbtBackend;
+use crate::error::{BuildError, EvalError, Location, Malformed, TypeError};
+use crate::fuel::{Exhausted, Limits, Outcome, Progress};
+use crate::observer::{EvalObserver, NoopObserver};
+use crate::prim::PrimOp;
+use crate::strategy::Strategy;
+use crate::term::{Binder, ReadBack, Shape, Term};
+use crate::types::{self, Type};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+pub use crate::constant::{Const, ONE, UNIT, ZERO};
 
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -10,24 +20,256 @@ pub struct ArgRef(usize);
 #[derive(Debug)]
 pub struct ExprDest(usize);
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// The subterm rooted at one slot of a program, as evaluation observers see it.
+#[derive(Clone, Copy)]
+pub struct Node<'a> {
+    prg: &'a Program,
+    idx: usize,
+}
+
+impl Node<'_> {
+    pub fn slot(&self) -> usize {
+        self.idx
+    }
+    pub fn to_term(&self) -> Term {
+        self.prg.read_back(self.idx)
+    }
+}
+
+impl fmt::Display for Node<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_term())
+    }
+}
+
+impl fmt::Debug for Node<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let node = |idx| Node { prg: self.prg, idx };
+        match self.prg.exprs[self.idx] {
+            Expr::Bas(ref c) => write!(f, "Bas({c:?})"),
+            Expr::Ptr(target) => write!(f, "Ptr(#{target})"),
+            Expr::Lam(arg, body) => write!(f, "Lam(#{arg}, {:?})", node(body)),
+            Expr::App(fun, v) => write!(f, "App({:?}, {:?})", node(fun), node(v)),
+            Expr::Let(arg, v, body) => {
+                write!(f, "Let(#{arg}, {:?}, {:?})", node(v), node(body))
+            }
+            Expr::Prim(op, ref operands) => {
+                let operands: Vec<_> = operands.iter().map(|&operand| node(operand)).collect();
+                write!(f, "Prim({op}, {operands:?})")
+            }
+            Expr::If(c, t, e) => {
+                write!(f, "If({:?}, {:?}, {:?})", node(c), node(t), node(e))
+            }
+            Expr::Pair(a, b) => write!(f, "Pair({:?}, {:?})", node(a), node(b)),
+            Expr::Split(x, y, v, body) => {
+                write!(f, "Split(#{x}, #{y}, {:?}, {:?})", node(v), node(body))
+            }
+            Expr::Inl(v) => write!(f, "Inl({:?})", node(v)),
+            Expr::Inr(v) => write!(f, "Inr({:?})", node(v)),
+            Expr::Case(v, x, l, y, r) => {
+                write!(
+                    f,
+                    "Case({:?}, #{x}, {:?}, #{y}, {:?})",
+                    node(v),
+                    node(l),
+                    node(r)
+                )
+            }
+            Expr::Invalid => write!(f, "Invalid"),
+            Expr::Free => write!(f, "Free"),
+        }
+    }
+}
+
+impl TryFrom<&Term> for Program {
+    type Error = BuildError;
+    fn try_from(term: &Term) -> Result<Self, BuildError> {
+        Self::from_term(term)
+    }
+}
+
+impl From<&Program> for Term {
+    fn from(prg: &Program) -> Self {
+        prg.to_term()
+    }
+}
+
+/// Whether the roots of two programs are the same term up to renaming binders,
+/// comparing variables by the lambdas they point to rather than by slot.
+pub fn alpha_eq(a: &Program, b: &Program) -> bool {
+    a.to_term().alpha_eq(&b.to_term())
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_term())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
 enum Expr {
-    Bas(&'static str),
+    Bas(Const),
     Ptr(usize),
     Lam(usize, usize),
     App(usize, usize),
+    // the binder's slot, the value bound and the body
+    Let(usize, usize, usize),
+    Prim(PrimOp, Vec<usize>),
+    // the condition and the two branches
+    If(usize, usize, usize),
+    Pair(usize, usize),
+    // the binders' slots, the pair and the body
+    Split(usize, usize, usize, usize),
+    Inl(usize),
+    Inr(usize),
+    // the scrutinee, then the binder's slot and branch for either side
+    Case(usize, usize, usize, usize, usize),
     Invalid,
+    // released by evaluation and waiting on the free list
+    Free,
 }
 
 #[derive(Debug)]
 pub struct Program {
     exprs: Vec<Expr>,
+    // dead slots left behind by evaluation, handed out again before growing exprs
+    free: Vec<usize>,
+    stats: AllocStats,
+    // names given to binders by make_named_lam, keyed by their arg slot
+    hints: HashMap<usize, Rc<str>>,
+    // types given to lambdas' binders by make_typed_lam, keyed by their arg slot
+    annotations: HashMap<usize, Type>,
+}
+
+/// How the slots of a [`Program`] were allocated so far.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct AllocStats {
+    /// Slots taken from the free list
+    pub reused: usize,
+    /// Slots pushed onto the end of the program
+    pub pushed: usize,
+}
+
+// The evaluation context around the slot being evaluated,
+// innermost application last
+#[derive(Debug)]
+struct Machine {
+    focus: usize,
+    stack: Vec<Frame>,
+    fuel: usize,
+    max_depth: usize,
+    strategy: Strategy,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Frame {
+    // evaluating the function of the application at this slot
+    Fun(usize),
+    // evaluating the argument of the application at this slot
+    Arg(usize),
+    // normalizing the body of the lambda at this slot
+    Body(usize),
+    // normalizing the function of the neutral application at this slot,
+    // one stuck on a variable that nothing binds
+    NeutralFun(usize),
+    // normalizing the argument of the neutral application at this slot
+    NeutralArg(usize),
+    // evaluating the value of the let at this slot before binding it
+    Bound(usize),
+    // evaluating the operand at this index of the primitive at this slot
+    Operand(usize, usize),
+    // evaluating the condition of the if at this slot
+    Cond(usize),
+    // normalizing the then branch of the if at this slot, stuck on its condition
+    NeutralThen(usize),
+    // normalizing the else branch of the if at this slot
+    NeutralElse(usize),
+    // evaluating the first component of the pair at this slot
+    Fst(usize),
+    // evaluating the second component of the pair at this slot
+    Snd(usize),
+    // evaluating what the injection at this slot injects
+    Inj(usize),
+    // evaluating the pair of the split or the scrutinee of the case at this slot
+    Scrutinee(usize),
+    // normalizing the body of the split at this slot, stuck on its pair
+    NeutralSplit(usize),
+    // normalizing the left branch of the case at this slot, stuck on its scrutinee
+    NeutralLeft(usize),
+    // normalizing the right branch of the case at this slot
+    NeutralRight(usize),
+}
+
+impl Machine {
+    fn new(root: usize, limits: &Limits, strategy: Strategy) -> Self {
+        Self {
+            focus: root,
+            stack: Vec::new(),
+            fuel: limits.max_steps,
+            max_depth: limits.max_depth,
+            strategy,
+        }
+    }
+    // pushes `frame` and focuses on `child`, unless the stack is already as deep
+    // as the limits allow
+    fn push(&mut self, frame: Frame, child: usize) -> Option<Progress> {
+        if self.stack.len() >= self.max_depth {
+            return Some(Progress::Exhausted(Exhausted::Depth));
+        }
+        self.stack.push(frame);
+        self.focus = child;
+        None
+    }
+    // whether a value here is part of the result, rather than a function about
+    // to be applied or an argument about to be bound, unless those get normalized too;
+    // operands, conditions, components and scrutinees are normalized whenever
+    // evaluation is strong, since what is stuck on a variable is part of the result
+    fn in_result(&self) -> bool {
+        match self.stack.last() {
+            None
+            | Some(
+                Frame::Body(_)
+                | Frame::NeutralFun(_)
+                | Frame::NeutralArg(_)
+                | Frame::NeutralThen(_)
+                | Frame::NeutralElse(_)
+                | Frame::NeutralSplit(_)
+                | Frame::NeutralLeft(_)
+                | Frame::NeutralRight(_),
+            ) => true,
+            Some(Frame::Arg(_) | Frame::Bound(_)) => self.strategy.normalizes_args(),
+            Some(
+                Frame::Operand(..)
+                | Frame::Cond(_)
+                | Frame::Fst(_)
+                | Frame::Snd(_)
+                | Frame::Inj(_)
+                | Frame::Scrutinee(_),
+            ) => self.strategy.strong(),
+            Some(Frame::Fun(_)) => false,
+        }
+    }
+    // whether the value returned is taken apart by the split or case on top of the
+    // stack, so a pair or an injection is only normalized first if arguments are
+    fn taken_apart(&self) -> bool {
+        matches!(self.stack.last(), Some(Frame::Scrutinee(_))) && !self.strategy.normalizes_args()
+    }
+    // spends the fuel for one reduction, if there is any left
+    fn burn(&mut self) -> bool {
+        let has_fuel = self.fuel > 0;
+        self.fuel = self.fuel.saturating_sub(1);
+        has_fuel
+    }
 }
 
 impl Program {
     pub fn build(fun: impl FnOnce(&mut Program, ExprDest) -> ExprRef) -> Self {
         let mut out = Program {
             exprs: Vec::with_capacity(128),
+            free: Vec::new(),
+            stats: AllocStats::default(),
+            hints: HashMap::new(),
+            annotations: HashMap::new(),
         };
         out.exprs.push(Expr::Invalid);
         fun(&mut out, ExprDest(0));
@@ -40,14 +282,38 @@ impl Program {
     ) -> ExprRef {
         let lam_ref = into.0;
         assert_eq!(self.exprs[lam_ref], Expr::Invalid);
-        self.exprs[lam_ref] = Expr::Lam(self.exprs.len(), self.exprs.len() + 1);
-        let arg_ref = self.exprs.len();
-        self.exprs.push(Expr::Invalid);
-        let body_ref = self.exprs.len();
-        self.exprs.push(Expr::Invalid);
+        let arg_ref = self.alloc();
+        let body_ref = self.alloc();
+        self.exprs[lam_ref] = Expr::Lam(arg_ref, body_ref);
         body(self, ArgRef(arg_ref), ExprDest(body_ref));
         ExprRef(lam_ref)
     }
+    /// Like `make_lam`, with a name for the binder that printing uses.
+    pub fn make_named_lam(
+        &mut self,
+        into: ExprDest,
+        hint: &str,
+        body: impl FnOnce(&mut Self, ArgRef, ExprDest) -> ExprRef,
+    ) -> ExprRef {
+        self.make_lam(into, |p, arg, e| {
+            p.hints.insert(arg.0, hint.into());
+            body(p, arg, e)
+        })
+    }
+    /// Like `make_named_lam`, with the binder annotated with its type for
+    /// [`Program::typecheck`].
+    pub fn make_typed_lam(
+        &mut self,
+        into: ExprDest,
+        hint: &str,
+        ty: Type,
+        body: impl FnOnce(&mut Self, ArgRef, ExprDest) -> ExprRef,
+    ) -> ExprRef {
+        self.make_named_lam(into, hint, |p, arg, e| {
+            p.annotations.insert(arg.0, ty);
+            body(p, arg, e)
+        })
+    }
     pub fn make_app(
         &mut self,
         into: ExprDest,
@@ -56,16 +322,161 @@ impl Program {
     ) -> ExprRef {
         let app_ref = into.0;
         assert_eq!(self.exprs[app_ref], Expr::Invalid);
-        self.exprs[app_ref] = Expr::App(self.exprs.len(), self.exprs.len() + 1);
-        let f_ref = self.exprs.len();
-        self.exprs.push(Expr::Invalid);
-        let v_ref = self.exprs.len();
-        self.exprs.push(Expr::Invalid);
+        let f_ref = self.alloc();
+        let v_ref = self.alloc();
+        self.exprs[app_ref] = Expr::App(f_ref, v_ref);
         fun(self, ExprDest(f_ref));
         val(self, ExprDest(v_ref));
         ExprRef(app_ref)
     }
-    pub fn make_const(&mut self, into: ExprDest, constant: &'static str) -> ExprRef {
+    /// Makes `let x = val in body` at `into`, which binds x like applying a lambda
+    /// to val would, in one step rather than two.
+    pub fn make_let(
+        &mut self,
+        into: ExprDest,
+        val: impl FnOnce(&mut Self, ExprDest) -> ExprRef,
+        body: impl FnOnce(&mut Self, ArgRef, ExprDest) -> ExprRef,
+    ) -> ExprRef {
+        let let_ref = into.0;
+        assert_eq!(self.exprs[let_ref], Expr::Invalid);
+        let arg_ref = self.alloc();
+        let v_ref = self.alloc();
+        let body_ref = self.alloc();
+        self.exprs[let_ref] = Expr::Let(arg_ref, v_ref, body_ref);
+        val(self, ExprDest(v_ref));
+        body(self, ArgRef(arg_ref), ExprDest(body_ref));
+        ExprRef(let_ref)
+    }
+    /// Like `make_let`, with a name for the binder that printing uses.
+    pub fn make_named_let(
+        &mut self,
+        into: ExprDest,
+        hint: &str,
+        val: impl FnOnce(&mut Self, ExprDest) -> ExprRef,
+        body: impl FnOnce(&mut Self, ArgRef, ExprDest) -> ExprRef,
+    ) -> ExprRef {
+        self.make_let(into, val, |p, arg, e| {
+            p.hints.insert(arg.0, hint.into());
+            body(p, arg, e)
+        })
+    }
+    /// Makes the primitive `op` at `into`, with `operands` filling in where each
+    /// of its operands goes.
+    pub fn make_prim(
+        &mut self,
+        into: ExprDest,
+        op: PrimOp,
+        operands: impl FnOnce(&mut Self, Vec<ExprDest>),
+    ) -> ExprRef {
+        let prim_ref = into.0;
+        assert_eq!(self.exprs[prim_ref], Expr::Invalid);
+        let slots: Vec<usize> = (0..op.arity()).map(|_| self.alloc()).collect();
+        self.exprs[prim_ref] = Expr::Prim(op, slots.clone());
+        operands(self, slots.into_iter().map(ExprDest).collect());
+        ExprRef(prim_ref)
+    }
+    /// Makes `if cond then then else else_` at `into`.
+    pub fn make_if(
+        &mut self,
+        into: ExprDest,
+        cond: impl FnOnce(&mut Self, ExprDest) -> ExprRef,
+        then: impl FnOnce(&mut Self, ExprDest) -> ExprRef,
+        else_: impl FnOnce(&mut Self, ExprDest) -> ExprRef,
+    ) -> ExprRef {
+        let if_ref = into.0;
+        assert_eq!(self.exprs[if_ref], Expr::Invalid);
+        let c_ref = self.alloc();
+        let t_ref = self.alloc();
+        let e_ref = self.alloc();
+        self.exprs[if_ref] = Expr::If(c_ref, t_ref, e_ref);
+        cond(self, ExprDest(c_ref));
+        then(self, ExprDest(t_ref));
+        else_(self, ExprDest(e_ref));
+        ExprRef(if_ref)
+    }
+    /// Makes the pair `(fst, snd)` at `into`.
+    pub fn make_pair(
+        &mut self,
+        into: ExprDest,
+        fst: impl FnOnce(&mut Self, ExprDest) -> ExprRef,
+        snd: impl FnOnce(&mut Self, ExprDest) -> ExprRef,
+    ) -> ExprRef {
+        let pair_ref = into.0;
+        assert_eq!(self.exprs[pair_ref], Expr::Invalid);
+        let a_ref = self.alloc();
+        let b_ref = self.alloc();
+        self.exprs[pair_ref] = Expr::Pair(a_ref, b_ref);
+        fst(self, ExprDest(a_ref));
+        snd(self, ExprDest(b_ref));
+        ExprRef(pair_ref)
+    }
+    /// Makes `let (x, y) = val in body` at `into`, which binds x and y to the
+    /// components of the pair val.
+    pub fn make_split(
+        &mut self,
+        into: ExprDest,
+        val: impl FnOnce(&mut Self, ExprDest) -> ExprRef,
+        body: impl FnOnce(&mut Self, ArgRef, ArgRef, ExprDest) -> ExprRef,
+    ) -> ExprRef {
+        let split_ref = into.0;
+        assert_eq!(self.exprs[split_ref], Expr::Invalid);
+        let x_ref = self.alloc();
+        let y_ref = self.alloc();
+        let v_ref = self.alloc();
+        let body_ref = self.alloc();
+        self.exprs[split_ref] = Expr::Split(x_ref, y_ref, v_ref, body_ref);
+        val(self, ExprDest(v_ref));
+        body(self, ArgRef(x_ref), ArgRef(y_ref), ExprDest(body_ref));
+        ExprRef(split_ref)
+    }
+    /// Makes `inl val` at `into`.
+    pub fn make_inl(
+        &mut self,
+        into: ExprDest,
+        val: impl FnOnce(&mut Self, ExprDest) -> ExprRef,
+    ) -> ExprRef {
+        let inl_ref = into.0;
+        assert_eq!(self.exprs[inl_ref], Expr::Invalid);
+        let v_ref = self.alloc();
+        self.exprs[inl_ref] = Expr::Inl(v_ref);
+        val(self, ExprDest(v_ref));
+        ExprRef(inl_ref)
+    }
+    /// Makes `inr val` at `into`.
+    pub fn make_inr(
+        &mut self,
+        into: ExprDest,
+        val: impl FnOnce(&mut Self, ExprDest) -> ExprRef,
+    ) -> ExprRef {
+        let inr_ref = into.0;
+        assert_eq!(self.exprs[inr_ref], Expr::Invalid);
+        let v_ref = self.alloc();
+        self.exprs[inr_ref] = Expr::Inr(v_ref);
+        val(self, ExprDest(v_ref));
+        ExprRef(inr_ref)
+    }
+    /// Makes `case val of inl x. left | inr y. right` at `into`.
+    pub fn make_case(
+        &mut self,
+        into: ExprDest,
+        val: impl FnOnce(&mut Self, ExprDest) -> ExprRef,
+        left: impl FnOnce(&mut Self, ArgRef, ExprDest) -> ExprRef,
+        right: impl FnOnce(&mut Self, ArgRef, ExprDest) -> ExprRef,
+    ) -> ExprRef {
+        let case_ref = into.0;
+        assert_eq!(self.exprs[case_ref], Expr::Invalid);
+        let v_ref = self.alloc();
+        let x_ref = self.alloc();
+        let l_ref = self.alloc();
+        let y_ref = self.alloc();
+        let r_ref = self.alloc();
+        self.exprs[case_ref] = Expr::Case(v_ref, x_ref, l_ref, y_ref, r_ref);
+        val(self, ExprDest(v_ref));
+        left(self, ArgRef(x_ref), ExprDest(l_ref));
+        right(self, ArgRef(y_ref), ExprDest(r_ref));
+        ExprRef(case_ref)
+    }
+    pub fn make_const(&mut self, into: ExprDest, constant: Const) -> ExprRef {
         let const_ref = into.0;
         assert_eq!(self.exprs[const_ref], Expr::Invalid);
         self.exprs[const_ref] = Expr::Bas(constant);
@@ -77,65 +488,995 @@ impl Program {
         self.exprs[deref] = Expr::Ptr(arg_ref.0);
         ExprRef(deref)
     }
-    fn step(&mut self, expr_idx: usize) -> bool {
-        let expr = self.exprs[expr_idx];
-        match expr {
-            Expr::Invalid => unreachable!("Not fully initialized program!"),
-            Expr::Bas(_) | Expr::Lam(_, _) => false,
-            Expr::Ptr(target) => {
-                // deref this expr to self.exprs[target]
-                self.exprs[expr_idx] = self.exprs[target];
-
-                self.exprs[target] = Expr::Invalid;
-                true
-            }
-            Expr::App(f, v) => {
-                self.step(f) || self.step(v) || {
-                    let Expr::App(f, v) = self.exprs[expr_idx] else {
-                        unreachable!("we already know expr is app");
-                    };
-                    if let Expr::Lam(arg, body) = self.exprs[f] {
-                        // beta reduction: set arg to v, replace expr with body
-                        self.exprs[arg] = self.exprs[v];
-                        self.exprs[expr_idx] = self.exprs[body];
+    /// Applies the current root expression, evaluated or not, to a new argument,
+    /// so a term can be built up step by step in between calls to `eval`.
+    pub fn apply(&mut self, val: impl FnOnce(&mut Self, ExprDest) -> ExprRef) -> ExprRef {
+        let f_ref = self.alloc();
+        self.exprs[f_ref] = std::mem::replace(&mut self.exprs[0], Expr::Invalid);
+        let v_ref = self.alloc();
+        self.exprs[0] = Expr::App(f_ref, v_ref);
+        val(self, ExprDest(v_ref));
+        ExprRef(0)
+    }
+    pub fn alloc_stats(&self) -> AllocStats {
+        self.stats
+    }
+    fn alloc(&mut self) -> usize {
+        if let Some(idx) = self.free.pop() {
+            self.stats.reused += 1;
+            self.exprs[idx] = Expr::Invalid;
+            idx
+        } else {
+            self.stats.pushed += 1;
+            self.exprs.push(Expr::Invalid);
+            self.exprs.len() - 1
+        }
+    }
+    // moves the term built at t into the root
+    fn set_root(&mut self, t: ExprRef) {
+        if t.0 != 0 {
+            self.exprs[0] = std::mem::replace(&mut self.exprs[t.0], Expr::Free);
+            self.release(t.0);
+        }
+    }
+    fn release(&mut self, idx: usize) {
+        self.hints.remove(&idx);
+        self.annotations.remove(&idx);
+        self.exprs[idx] = Expr::Free;
+        self.free.push(idx);
+    }
+    /// Fills `into` with `term`, each `Var` pointing at the arg of the lambda that
+    /// binds it. A variable whose binder is not in the term gets an arg slot
+    /// that no lambda binds, like a variable whose value was already moved out.
+    /// Fails, leaving `into` unfilled, if the term has a `fix`.
+    pub fn make_term(&mut self, into: ExprDest, term: &Term) -> Result<ExprRef, BuildError> {
+        if let Some(at) = term.find_fix() {
+            return Err(BuildError::Fix(at));
+        }
+        Ok(self.build_term(into, term))
+    }
+    // make_term for a term without a fix
+    fn build_term(&mut self, into: ExprDest, term: &Term) -> ExprRef {
+        let root = into.0;
+        let mut args = HashMap::new();
+        let mut todo = vec![(term, root)];
+        while let Some((term, slot)) = todo.pop() {
+            assert_eq!(self.exprs[slot], Expr::Invalid);
+            let expr = match term {
+                Term::Var(id) => Expr::Ptr(*args.entry(*id).or_insert_with(|| self.alloc())),
+                Term::Const(c) => Expr::Bas(c.clone()),
+                Term::Lam(binder, body) => {
+                    let (arg, body_slot) = (self.alloc(), self.alloc());
+                    if let Some(hint) = &binder.hint {
+                        self.hints.insert(arg, Rc::clone(hint));
+                    }
+                    if let Some(ty) = &binder.ty {
+                        self.annotations.insert(arg, ty.clone());
+                    }
+                    args.insert(binder.id, arg);
+                    todo.push((body, body_slot));
+                    Expr::Lam(arg, body_slot)
+                }
+                Term::App(f, v) => {
+                    let (f_slot, v_slot) = (self.alloc(), self.alloc());
+                    todo.push((v, v_slot));
+                    todo.push((f, f_slot));
+                    Expr::App(f_slot, v_slot)
+                }
+                Term::Let(binder, v, body) => {
+                    let (arg, v_slot, body_slot) = (self.alloc(), self.alloc(), self.alloc());
+                    if let Some(hint) = &binder.hint {
+                        self.hints.insert(arg, Rc::clone(hint));
+                    }
+                    args.insert(binder.id, arg);
+                    todo.push((body, body_slot));
+                    todo.push((v, v_slot));
+                    Expr::Let(arg, v_slot, body_slot)
+                }
+                Term::Prim(op, operands) => {
+                    let slots: Vec<usize> = operands.iter().map(|_| self.alloc()).collect();
+                    todo.extend(operands.iter().zip(slots.iter().copied()).rev());
+                    Expr::Prim(*op, slots)
+                }
+                Term::If(c, t, e) => {
+                    let (c_slot, t_slot, e_slot) = (self.alloc(), self.alloc(), self.alloc());
+                    todo.extend([(&**e, e_slot), (&**t, t_slot), (&**c, c_slot)]);
+                    Expr::If(c_slot, t_slot, e_slot)
+                }
+                Term::Pair(a, b) => {
+                    let (a_slot, b_slot) = (self.alloc(), self.alloc());
+                    todo.extend([(&**b, b_slot), (&**a, a_slot)]);
+                    Expr::Pair(a_slot, b_slot)
+                }
+                Term::Split(x, y, v, body) => {
+                    let (x_arg, y_arg) = (self.alloc(), self.alloc());
+                    let (v_slot, body_slot) = (self.alloc(), self.alloc());
+                    for (binder, arg) in [(x, x_arg), (y, y_arg)] {
+                        if let Some(hint) = &binder.hint {
+                            self.hints.insert(arg, Rc::clone(hint));
+                        }
+                        args.insert(binder.id, arg);
+                    }
+                    todo.extend([(&**body, body_slot), (&**v, v_slot)]);
+                    Expr::Split(x_arg, y_arg, v_slot, body_slot)
+                }
+                Term::Inl(v) => {
+                    let v_slot = self.alloc();
+                    todo.push((v, v_slot));
+                    Expr::Inl(v_slot)
+                }
+                Term::Inr(v) => {
+                    let v_slot = self.alloc();
+                    todo.push((v, v_slot));
+                    Expr::Inr(v_slot)
+                }
+                Term::Case(v, x, l, y, r) => {
+                    let v_slot = self.alloc();
+                    let (x_arg, l_slot) = (self.alloc(), self.alloc());
+                    let (y_arg, r_slot) = (self.alloc(), self.alloc());
+                    for (binder, arg) in [(x, x_arg), (y, y_arg)] {
+                        if let Some(hint) = &binder.hint {
+                            self.hints.insert(arg, Rc::clone(hint));
+                        }
+                        args.insert(binder.id, arg);
+                    }
+                    todo.extend([(&**r, r_slot), (&**l, l_slot), (&**v, v_slot)]);
+                    Expr::Case(v_slot, x_arg, l_slot, y_arg, r_slot)
+                }
+                Term::Fix(..) => unreachable!("make_term rejects fix"),
+                Term::Invalid => Expr::Invalid,
+            };
+            self.exprs[slot] = expr;
+        }
+        ExprRef(root)
+    }
+    /// A program whose root is `term`, unless it has a `fix`.
+    pub fn from_term(term: &Term) -> Result<Self, BuildError> {
+        if let Some(at) = term.find_fix() {
+            return Err(BuildError::Fix(at));
+        }
+        Ok(Self::build(|p, e| p.build_term(e, term)))
+    }
+    /// The term rooted at the program's root, with substituted variables
+    /// replaced by their values.
+    pub fn to_term(&self) -> Term {
+        self.read_back(0)
+    }
+    /// The type of the term rooted at the program's root, or why it has none, as
+    /// [`types::typecheck`] finds it. A program that checks does not get stuck,
+    /// though it can still use a variable twice.
+    pub fn typecheck(&self) -> Result<Type, TypeError> {
+        types::typecheck(&self.to_term())
+    }
+    fn read_back(&self, root: usize) -> Term {
+        ReadBack::new().run(root, |rb, mut idx| {
+            // a variable that evaluation already bound reads back as its value
+            while let Expr::Ptr(target) = self.exprs[idx] {
+                if let Expr::Invalid | Expr::Free = self.exprs[target] {
+                    return Shape::Leaf(rb.var(target));
+                }
+                idx = target;
+            }
+            match self.exprs[idx] {
+                Expr::Bas(ref c) => Shape::Leaf(Term::Const(c.clone())),
+                Expr::Lam(arg, body) => {
+                    let binder = rb.binder(arg, self.hints.get(&arg).cloned());
+                    let ty = self.annotations.get(&arg).cloned();
+                    Shape::Lam(Binder { ty, ..binder }, body)
+                }
+                Expr::App(f, v) => Shape::App(f, v),
+                Expr::Let(arg, v, body) => {
+                    Shape::Let(rb.binder(arg, self.hints.get(&arg).cloned()), v, body)
+                }
+                Expr::Prim(op, ref operands) => Shape::Prim(op, operands.clone()),
+                Expr::If(c, t, e) => Shape::If(c, t, e),
+                Expr::Pair(a, b) => Shape::Pair(a, b),
+                Expr::Split(x, y, v, body) => {
+                    let x = rb.binder(x, self.hints.get(&x).cloned());
+                    let y = rb.binder(y, self.hints.get(&y).cloned());
+                    Shape::Split(x, y, v, body)
+                }
+                Expr::Inl(v) => Shape::Inl(v),
+                Expr::Inr(v) => Shape::Inr(v),
+                Expr::Case(v, x, l, y, r) => {
+                    let x = rb.binder(x, self.hints.get(&x).cloned());
+                    let y = rb.binder(y, self.hints.get(&y).cloned());
+                    Shape::Case(v, x, l, y, r)
+                }
+                Expr::Ptr(_) => unreachable!("followed above"),
+                Expr::Invalid | Expr::Free => Shape::Leaf(Term::Invalid),
+            }
+        })
+    }
+    /// Checks a freshly built program for uninitialized slots reachable from the root,
+    /// variables that do not point at the argument of a lambda, let, split or case,
+    /// and variables used outside of the scope of the binder that binds them.
+    pub fn validate(&self) -> Result<(), Vec<Malformed>> {
+        // first find every binder, since a stray variable may come before its lambda
+        let mut binders = HashMap::new();
+        let mut todo = vec![0];
+        while let Some(idx) = todo.pop() {
+            match self.exprs[idx] {
+                Expr::Lam(arg, body) => {
+                    binders.insert(arg, idx);
+                    todo.push(body);
+                }
+                Expr::Let(arg, v, body) => {
+                    binders.insert(arg, idx);
+                    todo.extend([body, v]);
+                }
+                Expr::App(f, v) => todo.extend([v, f]),
+                Expr::Prim(_, ref operands) => todo.extend(operands.iter().rev()),
+                Expr::If(c, t, e) => todo.extend([e, t, c]),
+                Expr::Pair(a, b) => todo.extend([b, a]),
+                Expr::Split(x, y, v, body) => {
+                    binders.insert(x, idx);
+                    binders.insert(y, idx);
+                    todo.extend([body, v]);
+                }
+                Expr::Inl(v) | Expr::Inr(v) => todo.push(v),
+                Expr::Case(v, x, l, y, r) => {
+                    binders.insert(x, idx);
+                    binders.insert(y, idx);
+                    todo.extend([r, l, v]);
+                }
+                _ => {}
+            }
+        }
+        enum Visit {
+            Expr(usize),
+            // a binder coming into scope, once what comes before its scope is visited
+            Enter(usize),
+            Leave(usize),
+        }
+        let mut problems = Vec::new();
+        let mut in_scope = HashSet::new();
+        let mut todo = vec![Visit::Expr(0)];
+        while let Some(visit) = todo.pop() {
+            let idx = match visit {
+                Visit::Expr(idx) => idx,
+                Visit::Enter(arg) => {
+                    in_scope.insert(arg);
+                    continue;
+                }
+                Visit::Leave(arg) => {
+                    in_scope.remove(&arg);
+                    continue;
+                }
+            };
+            match self.exprs[idx] {
+                Expr::Invalid | Expr::Free => problems.push(Malformed::Uninitialized { slot: idx }),
+                Expr::Bas(_) => {}
+                Expr::Ptr(target) => match binders.get(&target) {
+                    None => problems.push(Malformed::NotABinder { ptr: idx, target }),
+                    Some(&lam) if !in_scope.contains(&target) => {
+                        problems.push(Malformed::OutOfScope { ptr: idx, lam })
+                    }
+                    Some(_) => {}
+                },
+                Expr::Lam(arg, body) => {
+                    in_scope.insert(arg);
+                    todo.push(Visit::Leave(arg));
+                    todo.push(Visit::Expr(body));
+                }
+                Expr::App(f, v) => todo.extend([Visit::Expr(v), Visit::Expr(f)]),
+                Expr::Let(arg, v, body) => todo.extend([
+                    Visit::Leave(arg),
+                    Visit::Expr(body),
+                    Visit::Enter(arg),
+                    Visit::Expr(v),
+                ]),
+                Expr::Prim(_, ref operands) => {
+                    todo.extend(operands.iter().rev().map(|&operand| Visit::Expr(operand)))
+                }
+                Expr::If(c, t, e) => todo.extend([Visit::Expr(e), Visit::Expr(t), Visit::Expr(c)]),
+                Expr::Pair(a, b) => todo.extend([Visit::Expr(b), Visit::Expr(a)]),
+                Expr::Split(x, y, v, body) => todo.extend([
+                    Visit::Leave(y),
+                    Visit::Leave(x),
+                    Visit::Expr(body),
+                    Visit::Enter(y),
+                    Visit::Enter(x),
+                    Visit::Expr(v),
+                ]),
+                Expr::Inl(v) | Expr::Inr(v) => todo.push(Visit::Expr(v)),
+                Expr::Case(v, x, l, y, r) => todo.extend([
+                    Visit::Leave(y),
+                    Visit::Expr(r),
+                    Visit::Enter(y),
+                    Visit::Leave(x),
+                    Visit::Expr(l),
+                    Visit::Enter(x),
+                    Visit::Expr(v),
+                ]),
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+    // the slot at `idx` as observers see it
+    fn node(&self, idx: usize) -> Node<'_> {
+        Node { prg: self, idx }
+    }
+    /// Runs the machine up to and including the next reduction, and leaves it focused
+    /// on the reduced slot so the next call picks up from there.
+    fn step(
+        &mut self,
+        m: &mut Machine,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Result<Progress, EvalError> {
+        loop {
+            let expr_idx = m.focus;
+            // the frame to push and the child to go into next
+            let (frame, child) = match self.exprs[expr_idx] {
+                Expr::Invalid | Expr::Free => {
+                    return Err(EvalError::Uninitialized(Location::Slot(expr_idx)))
+                }
+                // when normalizing, a variable nothing binds is a value
+                Expr::Ptr(target) if m.strategy.strong() && self.exprs[target] == Expr::Invalid => {
+                    if let Some(progress) = self.ret(m, expr_idx, obs)? {
+                        return Ok(progress);
+                    }
+                    continue;
+                }
+                Expr::Ptr(target) => match self.exprs[target] {
+                    Expr::Invalid => {
+                        return Err(EvalError::DanglingPointer(Location::Slot(expr_idx)))
+                    }
+                    Expr::Free => return Err(EvalError::DoubleDeref(Location::Slot(expr_idx))),
+                    _ => {
+                        if !m.burn() {
+                            return Ok(Progress::Exhausted(Exhausted::Steps));
+                        }
+                        obs.on_deref(&self.node(expr_idx));
+                        // deref this expr to self.exprs[target]
+                        self.exprs[expr_idx] =
+                            std::mem::replace(&mut self.exprs[target], Expr::Free);
 
-                        self.exprs[v] = Expr::Invalid;
-                        self.exprs[body] = Expr::Invalid;
-                        true
-                    } else {
-                        panic!("stuck, f value is not a function {:?}", self.exprs[f]);
+                        self.release(target);
+                        return Ok(Progress::Reduced);
+                    }
+                },
+                Expr::App(f, _) => (Frame::Fun(expr_idx), f),
+                Expr::Let(..) if m.strategy.by_name() => return Ok(self.bind_let(m, expr_idx, obs)),
+                Expr::Let(_, v, _) => (Frame::Bound(expr_idx), v),
+                Expr::Prim(_, ref operands) => match operands.first() {
+                    Some(&first) => (Frame::Operand(expr_idx, 0), first),
+                    None => {
+                        let progress = self.apply_prim(m, expr_idx, obs)?;
+                        return Ok(progress.expect("no operands to be stuck on"));
+                    }
+                },
+                Expr::If(c, _, _) => (Frame::Cond(expr_idx), c),
+                // unless arguments are bound unevaluated, a pair or an injection is
+                // only a value once what it holds is
+                Expr::Pair(a, _) if !m.strategy.by_name() => (Frame::Fst(expr_idx), a),
+                Expr::Inl(v) | Expr::Inr(v) if !m.strategy.by_name() => (Frame::Inj(expr_idx), v),
+                Expr::Split(_, _, v, _) | Expr::Case(v, ..) => (Frame::Scrutinee(expr_idx), v),
+                Expr::Bas(_) | Expr::Lam(_, _) | Expr::Pair(..) | Expr::Inl(_) | Expr::Inr(_) => {
+                    if let Some(progress) = self.ret(m, expr_idx, obs)? {
+                        return Ok(progress);
+                    }
+                    continue;
+                }
+            };
+            if let Some(progress) = m.push(frame, child) {
+                return Ok(progress);
+            }
+        }
+    }
+    // Hands the value at slot `val` to the frames below it, until there is a
+    // reduction to report or another subterm to evaluate, which is left in focus.
+    fn ret(
+        &mut self,
+        m: &mut Machine,
+        mut val: usize,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Result<Option<Progress>, EvalError> {
+        // whether val is already normalized, so it is not gone into again
+        let mut normal = false;
+        loop {
+            if m.strategy.strong() && !normal && m.in_result() {
+                if m.stack.len() >= m.max_depth {
+                    return Ok(Some(Progress::Exhausted(Exhausted::Depth)));
+                }
+                match self.exprs[val] {
+                    Expr::Lam(_, body) => {
+                        m.stack.push(Frame::Body(val));
+                        m.focus = body;
+                        return Ok(None);
+                    }
+                    // the function of a neutral application is already a value
+                    Expr::App(f, _) => {
+                        m.stack.push(Frame::NeutralFun(val));
+                        val = f;
+                        continue;
+                    }
+                    // the components of a pair and what an injection injects, unless
+                    // they were evaluated before it was a value or it is taken apart
+                    Expr::Pair(a, _) if !m.taken_apart() => {
+                        m.stack.push(Frame::Fst(val));
+                        m.focus = a;
+                        return Ok(None);
+                    }
+                    Expr::Inl(v) | Expr::Inr(v) if !m.taken_apart() => {
+                        m.stack.push(Frame::Inj(val));
+                        m.focus = v;
+                        return Ok(None);
+                    }
+                    // constants and variables that nothing binds
+                    _ => {}
+                }
+            }
+            let frame = m.stack.pop();
+            match frame {
+                None => return Ok(Some(Progress::Finished)),
+                Some(Frame::Fun(app) | Frame::Arg(app)) => {
+                    let Expr::App(f, v) = self.exprs[app] else {
+                        unreachable!("frames only point at apps");
+                    };
+                    let by_name = m.strategy.by_name();
+                    match self.exprs[f] {
+                        Expr::Lam(..) if by_name || matches!(frame, Some(Frame::Arg(_))) => {
+                            return Ok(Some(self.beta(m, app, obs)));
+                        }
+                        _ if !by_name && matches!(frame, Some(Frame::Fun(_))) => {
+                            m.stack.push(Frame::Arg(app));
+                            m.focus = v;
+                            return Ok(None);
+                        }
+                        ref f if m.strategy.strong() && is_neutral(f) => {
+                            (val, normal) = (app, false)
+                        }
+                        _ => return Err(EvalError::Stuck(Location::Slot(app))),
+                    }
+                }
+                Some(Frame::NeutralFun(app)) => {
+                    let Expr::App(_, v) = self.exprs[app] else {
+                        unreachable!("frames only point at apps");
+                    };
+                    m.stack.push(Frame::NeutralArg(app));
+                    m.focus = v;
+                    return Ok(None);
+                }
+                Some(
+                    Frame::Body(node)
+                    | Frame::NeutralArg(node)
+                    | Frame::NeutralElse(node)
+                    | Frame::Snd(node)
+                    | Frame::Inj(node)
+                    | Frame::NeutralSplit(node)
+                    | Frame::NeutralRight(node),
+                ) => (val, normal) = (node, true),
+                Some(Frame::Bound(binding)) => return Ok(Some(self.bind_let(m, binding, obs))),
+                Some(Frame::Operand(prim, i)) => {
+                    let Expr::Prim(_, ref operands) = self.exprs[prim] else {
+                        unreachable!("frames only point at primitives");
+                    };
+                    if let Some(&next) = operands.get(i + 1) {
+                        m.stack.push(Frame::Operand(prim, i + 1));
+                        m.focus = next;
+                        return Ok(None);
+                    }
+                    match self.apply_prim(m, prim, obs)? {
+                        Some(progress) => return Ok(Some(progress)),
+                        None => (val, normal) = (prim, true),
                     }
                 }
+                Some(Frame::Cond(branch)) => {
+                    let Expr::If(c, t, _) = self.exprs[branch] else {
+                        unreachable!("frames only point at ifs");
+                    };
+                    match self.exprs[c] {
+                        Expr::Bas(Const::Bool(_)) => return Ok(Some(self.choose(m, branch, obs))),
+                        // when normalizing, an if stuck on its condition is a value
+                        // once both branches are normalized
+                        ref c if m.strategy.strong() && is_neutral(c) => {
+                            m.stack.push(Frame::NeutralThen(branch));
+                            m.focus = t;
+                            return Ok(None);
+                        }
+                        _ => return Err(EvalError::BadCondition(Location::Slot(branch))),
+                    }
+                }
+                Some(Frame::NeutralThen(branch)) => {
+                    let Expr::If(_, _, e) = self.exprs[branch] else {
+                        unreachable!("frames only point at ifs");
+                    };
+                    m.stack.push(Frame::NeutralElse(branch));
+                    m.focus = e;
+                    return Ok(None);
+                }
+                Some(Frame::Fst(pair)) => {
+                    let Expr::Pair(_, b) = self.exprs[pair] else {
+                        unreachable!("frames only point at pairs");
+                    };
+                    m.stack.push(Frame::Snd(pair));
+                    m.focus = b;
+                    return Ok(None);
+                }
+                Some(Frame::Scrutinee(elim)) => match self.exprs[elim] {
+                    Expr::Split(_, _, v, body) => match self.exprs[v] {
+                        Expr::Pair(..) => return Ok(Some(self.split(m, elim, obs))),
+                        // when normalizing, a split stuck on its pair is a value
+                        // once its body is normalized
+                        ref v if m.strategy.strong() && is_neutral(v) => {
+                            m.stack.push(Frame::NeutralSplit(elim));
+                            m.focus = body;
+                            return Ok(None);
+                        }
+                        _ => return Err(EvalError::BadScrutinee(Location::Slot(elim))),
+                    },
+                    Expr::Case(v, _, l, _, _) => match self.exprs[v] {
+                        Expr::Inl(_) | Expr::Inr(_) => return Ok(Some(self.pick(m, elim, obs))),
+                        // when normalizing, a case stuck on its scrutinee is a value
+                        // once both branches are normalized
+                        ref v if m.strategy.strong() && is_neutral(v) => {
+                            m.stack.push(Frame::NeutralLeft(elim));
+                            m.focus = l;
+                            return Ok(None);
+                        }
+                        _ => return Err(EvalError::BadScrutinee(Location::Slot(elim))),
+                    },
+                    _ => unreachable!("frames only point at splits and cases"),
+                },
+                Some(Frame::NeutralLeft(case)) => {
+                    let Expr::Case(_, _, _, _, r) = self.exprs[case] else {
+                        unreachable!("frames only point at cases");
+                    };
+                    m.stack.push(Frame::NeutralRight(case));
+                    m.focus = r;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+    // Replaces the primitive at `prim`, whose operands are all evaluated, with its
+    // result and releases the operands. When normalizing, a primitive stuck on a
+    // variable is a value instead, and None is returned.
+    fn apply_prim(
+        &mut self,
+        m: &mut Machine,
+        prim: usize,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Result<Option<Progress>, EvalError> {
+        let Expr::Prim(op, ref operands) = self.exprs[prim] else {
+            unreachable!("only primitives are applied");
+        };
+        let constants: Option<Vec<&Const>> = operands
+            .iter()
+            .map(|&operand| match self.exprs[operand] {
+                Expr::Bas(ref c) => Some(c),
+                _ => None,
+            })
+            .collect();
+        let Some(constants) = constants else {
+            let neutral = |&operand: &usize| match self.exprs[operand] {
+                Expr::Bas(_) => true,
+                ref e => is_neutral(e),
+            };
+            if m.strategy.strong() && operands.iter().all(neutral) {
+                return Ok(None);
+            }
+            return Err(EvalError::BadPrim(Location::Slot(prim)));
+        };
+        let Some(result) = op.apply(&constants) else {
+            return Err(EvalError::BadPrim(Location::Slot(prim)));
+        };
+        // the operands are constants, so evaluating them again costs no fuel
+        if !m.burn() {
+            m.focus = prim;
+            return Ok(Some(Progress::Exhausted(Exhausted::Steps)));
+        }
+        obs.on_prim(&self.node(prim));
+        let Expr::Prim(_, operands) = std::mem::replace(&mut self.exprs[prim], Expr::Bas(result))
+        else {
+            unreachable!("checked above");
+        };
+        for operand in operands {
+            self.release(operand);
+        }
+        m.focus = prim;
+        Ok(Some(Progress::Reduced))
+    }
+    // Replaces the if at `branch`, whose condition is a boolean, with the branch it
+    // picks, and releases the condition and the untaken branch.
+    fn choose(
+        &mut self,
+        m: &mut Machine,
+        branch: usize,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Progress {
+        let Expr::If(c, t, e) = self.exprs[branch] else {
+            unreachable!("only ifs choose");
+        };
+        // the condition is a constant, so evaluating it again costs no fuel
+        if !m.burn() {
+            m.focus = branch;
+            return Progress::Exhausted(Exhausted::Steps);
+        }
+        obs.on_if(&self.node(branch));
+        let (picked, untaken) = match self.exprs[c] {
+            Expr::Bas(Const::Bool(true)) => (t, e),
+            _ => (e, t),
+        };
+        self.exprs[branch] = std::mem::replace(&mut self.exprs[picked], Expr::Free);
+
+        self.release(picked);
+        self.release(c);
+        self.release_tree(untaken);
+        m.focus = branch;
+        Progress::Reduced
+    }
+    // Replaces the split at `split`, whose pair is evaluated, with its body, moving
+    // the components into its binders like beta reduction does, and releases the pair.
+    fn split(
+        &mut self,
+        m: &mut Machine,
+        split: usize,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Progress {
+        let Expr::Split(x, y, v, body) = self.exprs[split] else {
+            unreachable!("only splits split");
+        };
+        // the pair is a value, so evaluating it again costs no fuel
+        if !m.burn() {
+            m.focus = split;
+            return Progress::Exhausted(Exhausted::Steps);
+        }
+        obs.on_match(&self.node(split));
+        let Expr::Pair(a, b) = self.exprs[v] else {
+            unreachable!("checked by ret");
+        };
+        self.exprs[x] = std::mem::replace(&mut self.exprs[a], Expr::Free);
+        self.exprs[y] = std::mem::replace(&mut self.exprs[b], Expr::Free);
+        self.exprs[split] = std::mem::replace(&mut self.exprs[body], Expr::Free);
+
+        self.release(a);
+        self.release(b);
+        self.release(v);
+        self.release(body);
+        m.focus = split;
+        Progress::Reduced
+    }
+    // Replaces the case at `case`, whose scrutinee is an injection, with the branch
+    // that binds what it injects, and releases the scrutinee and the other branch
+    // with its binder.
+    fn pick(
+        &mut self,
+        m: &mut Machine,
+        case: usize,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Progress {
+        let Expr::Case(v, x, l, y, r) = self.exprs[case] else {
+            unreachable!("only cases pick");
+        };
+        // the scrutinee is a value, so evaluating it again costs no fuel
+        if !m.burn() {
+            m.focus = case;
+            return Progress::Exhausted(Exhausted::Steps);
+        }
+        obs.on_match(&self.node(case));
+        let (inner, arg, picked, other_arg, untaken) = match self.exprs[v] {
+            Expr::Inl(inner) => (inner, x, l, y, r),
+            Expr::Inr(inner) => (inner, y, r, x, l),
+            _ => unreachable!("checked by ret"),
+        };
+        self.exprs[arg] = std::mem::replace(&mut self.exprs[inner], Expr::Free);
+        self.exprs[case] = std::mem::replace(&mut self.exprs[picked], Expr::Free);
+
+        self.release(inner);
+        self.release(v);
+        self.release(picked);
+        self.release(other_arg);
+        self.release_tree(untaken);
+        m.focus = case;
+        Progress::Reduced
+    }
+    // releases the subterm at `root` along with the arg slots of its binders; every
+    // variable is used once, so nothing else can point into it
+    fn release_tree(&mut self, root: usize) {
+        let mut todo = vec![root];
+        while let Some(idx) = todo.pop() {
+            match self.exprs[idx] {
+                Expr::Bas(_) | Expr::Ptr(_) | Expr::Invalid | Expr::Free => {}
+                Expr::Lam(a, b) | Expr::App(a, b) => todo.extend([a, b]),
+                Expr::Let(arg, v, body) => todo.extend([arg, v, body]),
+                Expr::Prim(_, ref operands) => todo.extend(operands),
+                Expr::If(c, t, e) => todo.extend([c, t, e]),
+                Expr::Pair(a, b) => todo.extend([a, b]),
+                Expr::Split(x, y, v, body) => todo.extend([x, y, v, body]),
+                Expr::Inl(v) | Expr::Inr(v) => todo.push(v),
+                Expr::Case(v, x, l, y, r) => todo.extend([v, x, l, y, r]),
             }
+            self.release(idx);
         }
     }
-    pub fn eval(&mut self) -> Option<&'static str> {
-        println!("eval {self:?}");
-        while self.step(0) {
-            println!("step {self:?}");
+    // beta-reduces the application at `app`, whose function is a lambda,
+    // putting its frame back if there is no fuel left
+    fn beta(
+        &mut self,
+        m: &mut Machine,
+        app: usize,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Progress {
+        let Expr::App(f, v) = self.exprs[app] else {
+            unreachable!("frames only point at apps");
+        };
+        let Expr::Lam(arg, body) = self.exprs[f] else {
+            unreachable!("only lambdas are applied");
+        };
+        if !m.burn() {
+            let frame = if m.strategy.by_name() {
+                Frame::Fun(app)
+            } else {
+                Frame::Arg(app)
+            };
+            m.stack.push(frame);
+            return Progress::Exhausted(Exhausted::Steps);
         }
-        println!("result {self:?}");
-        if let Expr::Bas(result) = self.exprs[0] {
-            Some(result)
+        obs.on_beta(&self.node(f), &self.node(v));
+        // beta reduction: set arg to v, replace expr with body
+        self.exprs[arg] = std::mem::replace(&mut self.exprs[v], Expr::Free);
+        self.exprs[app] = std::mem::replace(&mut self.exprs[body], Expr::Free);
+
+        self.release(f);
+        self.release(v);
+        self.release(body);
+        m.focus = app;
+        Progress::Reduced
+    }
+    // binds the variable of the let at `binding` to its value, like beta reduction
+    // does, and replaces the let with its body
+    fn bind_let(
+        &mut self,
+        m: &mut Machine,
+        binding: usize,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Progress {
+        let Expr::Let(arg, v, body) = self.exprs[binding] else {
+            unreachable!("only lets are bound");
+        };
+        if !m.burn() {
+            if !m.strategy.by_name() {
+                m.stack.push(Frame::Bound(binding));
+            }
+            return Progress::Exhausted(Exhausted::Steps);
+        }
+        obs.on_let(&self.node(binding));
+        self.exprs[arg] = std::mem::replace(&mut self.exprs[v], Expr::Free);
+        self.exprs[binding] = std::mem::replace(&mut self.exprs[body], Expr::Free);
+
+        self.release(v);
+        self.release(body);
+        m.focus = binding;
+        Progress::Reduced
+    }
+    pub fn eval(&mut self) -> Result<Option<Const>, EvalError> {
+        self.eval_observed(&mut NoopObserver)
+    }
+    /// Evaluates like `eval`, reporting every reduction to `obs`.
+    pub fn eval_observed(
+        &mut self,
+        obs: &mut impl for<'a> EvalObserver<Node<'a>>,
+    ) -> Result<Option<Const>, EvalError> {
+        match self.eval_with(Strategy::default(), Limits::default(), obs)? {
+            Outcome::Value(result) => Ok(result),
+            Outcome::OutOfFuel { .. } => unreachable!("evaluation without limits ran out"),
+        }
+    }
+    /// Evaluates with at most `max_steps` reductions.
+    pub fn eval_with_fuel(
+        &mut self,
+        max_steps: usize,
+    ) -> Result<Outcome<Option<Const>>, EvalError> {
+        self.eval_with_limits(Limits::steps(max_steps))
+    }
+    /// Evaluates until the result or until one of `limits` is hit, in which case
+    /// the partially reduced term is left in the program for `eval` to resume.
+    /// Nodes are the slots in use, not counting those on the free list.
+    pub fn eval_with_limits(
+        &mut self,
+        limits: Limits,
+    ) -> Result<Outcome<Option<Const>>, EvalError> {
+        self.eval_with(Strategy::default(), limits, &mut NoopObserver)
+    }
+    /// Evaluates like `eval_with_limits` in the order `strategy` gives, reporting
+    /// every reduction to `obs`. Every variable is used at most once, so
+    /// call-by-need is call-by-name here.
+    pub fn eval_with(
+        &mut self,
+        strategy: Strategy,
+        limits: Limits,
+        obs: &mut impl for<'a> EvalObserver<Node<'a>>,
+    ) -> Result<Outcome<Option<Const>>, EvalError> {
+        self.run(strategy, limits, obs)
+    }
+    /// Reduces the program to its normal form in normal order, also under lambdas:
+    /// variables that nothing binds are values there, and so are applications stuck
+    /// on them. Read the result back with [`Program::to_term`].
+    pub fn normalize(&mut self) -> Result<(), EvalError> {
+        match self.run(Strategy::NormalOrder, Limits::default(), &mut NoopObserver)? {
+            Outcome::Value(_) => Ok(()),
+            Outcome::OutOfFuel { .. } => unreachable!("evaluation without limits ran out"),
+        }
+    }
+    fn run(
+        &mut self,
+        strategy: Strategy,
+        limits: Limits,
+        obs: &mut dyn for<'a> EvalObserver<Node<'a>>,
+    ) -> Result<Outcome<Option<Const>>, EvalError> {
+        obs.on_start(&self.node(0));
+        let mut machine = Machine::new(0, &limits, strategy);
+        loop {
+            let progress = if self.exprs.len() - self.free.len() > limits.max_nodes {
+                Progress::Exhausted(Exhausted::Nodes)
+            } else {
+                self.step(&mut machine, obs)
+                    .inspect_err(|err| obs.on_stuck(err))?
+            };
+            match progress {
+                Progress::Reduced => {
+                    if obs.observes_steps() {
+                        obs.on_step(&self.node(0));
+                    }
+                }
+                Progress::Finished => break,
+                Progress::Exhausted(exhausted) => {
+                    obs.on_out_of_fuel(exhausted);
+                    return Ok(Outcome::OutOfFuel {
+                        exhausted,
+                        term: (),
+                    });
+                }
+            }
+        }
+        obs.on_finish(&self.node(0));
+        if let Expr::Bas(result) = &self.exprs[0] {
+            Ok(Outcome::Value(Some(result.clone())))
         } else {
-            None
+            Ok(Outcome::Value(None))
         }
     }
 }
 
+/// An empty program, whose root is still to be filled in.
+impl Default for Program {
+    fn default() -> Self {
+        Self::build(|_, into| ExprRef(into.0))
+    }
+}
+
+/// Builds each term in slots of its own, moving the one evaluated into the root.
+impl AbtBackend for Program {
+    type Term = ExprRef;
+    type Var = ArgRef;
+    fn constant(&mut self, c: Const) -> ExprRef {
+        let slot = self.alloc();
+        self.make_const(ExprDest(slot), c)
+    }
+    fn lam(&mut self, body: impl FnOnce(&mut Self, &ArgRef) -> ExprRef) -> ExprRef {
+        let (lam, arg) = (self.alloc(), self.alloc());
+        let body = body(self, &ArgRef(arg));
+        self.exprs[lam] = Expr::Lam(arg, body.0);
+        ExprRef(lam)
+    }
+    fn typed_lam(&mut self, ty: Type, body: impl FnOnce(&mut Self, &ArgRef) -> ExprRef) -> ExprRef {
+        let lam = self.lam(body);
+        let Expr::Lam(arg, _) = self.exprs[lam.0] else {
+            unreachable!("lam makes a lambda");
+        };
+        self.annotations.insert(arg, ty);
+        lam
+    }
+    fn app(&mut self, f: ExprRef, v: ExprRef) -> ExprRef {
+        let slot = self.alloc();
+        self.exprs[slot] = Expr::App(f.0, v.0);
+        ExprRef(slot)
+    }
+    fn var(&mut self, x: &ArgRef) -> ExprRef {
+        let slot = self.alloc();
+        self.make_varref(ExprDest(slot), ArgRef(x.0))
+    }
+    fn let_in(&mut self, v: ExprRef, body: impl FnOnce(&mut Self, &ArgRef) -> ExprRef) -> ExprRef {
+        let (binding, arg) = (self.alloc(), self.alloc());
+        let body = body(self, &ArgRef(arg));
+        self.exprs[binding] = Expr::Let(arg, v.0, body.0);
+        ExprRef(binding)
+    }
+    fn prim(&mut self, op: PrimOp, operands: Vec<ExprRef>) -> ExprRef {
+        let slot = self.alloc();
+        self.exprs[slot] = Expr::Prim(op, operands.into_iter().map(|o| o.0).collect());
+        ExprRef(slot)
+    }
+    fn if_then_else(&mut self, c: ExprRef, t: ExprRef, e: ExprRef) -> ExprRef {
+        let slot = self.alloc();
+        self.exprs[slot] = Expr::If(c.0, t.0, e.0);
+        ExprRef(slot)
+    }
+    fn pair(&mut self, a: ExprRef, b: ExprRef) -> ExprRef {
+        let slot = self.alloc();
+        self.exprs[slot] = Expr::Pair(a.0, b.0);
+        ExprRef(slot)
+    }
+    fn split(
+        &mut self,
+        v: ExprRef,
+        body: impl FnOnce(&mut Self, &ArgRef, &ArgRef) -> ExprRef,
+    ) -> ExprRef {
+        let (split, x, y) = (self.alloc(), self.alloc(), self.alloc());
+        let body = body(self, &ArgRef(x), &ArgRef(y));
+        self.exprs[split] = Expr::Split(x, y, v.0, body.0);
+        ExprRef(split)
+    }
+    fn inl(&mut self, v: ExprRef) -> ExprRef {
+        let slot = self.alloc();
+        self.exprs[slot] = Expr::Inl(v.0);
+        ExprRef(slot)
+    }
+    fn inr(&mut self, v: ExprRef) -> ExprRef {
+        let slot = self.alloc();
+        self.exprs[slot] = Expr::Inr(v.0);
+        ExprRef(slot)
+    }
+    fn case(
+        &mut self,
+        v: ExprRef,
+        l: impl FnOnce(&mut Self, &ArgRef) -> ExprRef,
+        r: impl FnOnce(&mut Self, &ArgRef) -> ExprRef,
+    ) -> ExprRef {
+        let (case, x, y) = (self.alloc(), self.alloc(), self.alloc());
+        let l = l(self, &ArgRef(x));
+        let r = r(self, &ArgRef(y));
+        self.exprs[case] = Expr::Case(v.0, x, l.0, y, r.0);
+        ExprRef(case)
+    }
+    fn typecheck_term(&mut self, t: &ExprRef) -> Result<Type, TypeError> {
+        types::typecheck(&self.read_back(t.0))
+    }
+    fn eval_term_with(
+        &mut self,
+        t: ExprRef,
+        strategy: Strategy,
+        limits: Limits,
+    ) -> Result<Outcome<Term>, EvalError> {
+        self.set_root(t);
+        Ok(match self.eval_with(strategy, limits, &mut NoopObserver)? {
+            Outcome::Value(_) => Outcome::Value(self.to_term()),
+            Outcome::OutOfFuel { exhausted, term } => Outcome::OutOfFuel { exhausted, term },
+        })
+    }
+}
+
+// whether an expression is a variable, an application, a let, a primitive, an if,
+// a split or a case: not evaluated yet, or if it is a value, neutral
+fn is_neutral(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Ptr(_)
+            | Expr::App(..)
+            | Expr::Let(..)
+            | Expr::Prim(..)
+            | Expr::If(..)
+            | Expr::Split(..)
+            | Expr::Case(..)
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::observer::{Event, Recorder};
     trait ProgramExt {
-        fn make_const_fn(&mut self, e: ExprDest) -> ExprRef;
         fn make_ident(&mut self, e: ExprDest) -> ExprRef;
         fn make_lam_true(&mut self, e: ExprDest) -> ExprRef;
         fn make_lam_false(&mut self, e: ExprDest) -> ExprRef;
     }
     impl ProgramExt for Program {
-        fn make_const_fn(&mut self, e: ExprDest) -> ExprRef {
-            self.make_lam(e, |p, _ptr, e| p.make_const(e, UNIT))
-        }
         fn make_ident(&mut self, e: ExprDest) -> ExprRef {
             self.make_lam(e, |p, ptr, e| p.make_varref(e, ptr))
         }
@@ -151,27 +1492,162 @@ mod tests {
         }
     }
     #[test]
-    fn t0() {
+    fn apply_reuses_dead_slots() {
+        let mut prg = Program::build(|p, e| {
+            p.make_app(e, |p, e| p.make_ident(e), |p, e| p.make_lam_false(e))
+        });
+        assert_eq!(Ok(None), prg.eval());
+        let grown = prg.exprs.len();
+        prg.apply(|p, e| p.make_const(e, ZERO));
+        assert_eq!(Ok(None), prg.eval());
+        prg.apply(|p, e| p.make_const(e, ONE));
+        assert_eq!(Ok(Some(ONE)), prg.eval());
+        assert_eq!(grown, prg.exprs.len());
+        assert_eq!(
+            AllocStats {
+                reused: 4,
+                pushed: 8
+            },
+            prg.alloc_stats()
+        );
+    }
+
+    #[test]
+    fn untaken_branch_is_released() {
+        // if true then 0 else (\x. x) 1, where only the root is left in use
+        let mut prg = Program::build(|p, e| {
+            p.make_if(
+                e,
+                |p, e| p.make_const(e, true.into()),
+                |p, e| p.make_const(e, ZERO),
+                |p, e| p.make_app(e, |p, e| p.make_ident(e), |p, e| p.make_const(e, ONE)),
+            )
+        });
+        assert_eq!(Ok(Some(ZERO)), prg.eval());
+        assert_eq!(prg.exprs.len() - 1, prg.free.len());
+    }
+
+    #[test]
+    fn untaken_case_branch_is_released() {
+        // case inl (0, 1) of inl x. let (a, b) = x in add a b | inr y. (\z. z) y, where
+        // only the root is left in use
+        let mut prg = Program::build(|p, e| {
+            p.make_case(
+                e,
+                |p, e| {
+                    p.make_inl(e, |p, e| {
+                        p.make_pair(e, |p, e| p.make_const(e, ZERO), |p, e| p.make_const(e, ONE))
+                    })
+                },
+                |p, x, e| {
+                    p.make_split(
+                        e,
+                        |p, e| p.make_varref(e, x),
+                        |p, a, b, e| {
+                            p.make_prim(e, PrimOp::Add, |p, operands| {
+                                for (operand, var) in operands.into_iter().zip([a, b]) {
+                                    p.make_varref(operand, var);
+                                }
+                            })
+                        },
+                    )
+                },
+                |p, y, e| p.make_app(e, |p, e| p.make_ident(e), |p, e| p.make_varref(e, y)),
+            )
+        });
+        assert_eq!(Ok(Some(ONE)), prg.eval());
+        assert_eq!(prg.exprs.len() - 1, prg.free.len());
+    }
+
+    #[test]
+    fn stuck_on_constant() {
         let mut app = Program::build(|p, e| {
-            p.make_app(e, |p, e| p.make_const_fn(e), |p, e| p.make_const(e, ZERO))
+            p.make_app(e, |p, e| p.make_const(e, ZERO), |p, e| p.make_const(e, ONE))
         });
-        assert_eq!(Some(UNIT), app.eval());
+        assert_eq!(Err(EvalError::Stuck(Location::Slot(0))), app.eval());
     }
 
     #[test]
-    fn t1() {
+    fn double_deref() {
+        // (\x. x 1) (\y. y), with the pointer to x copied over the 1 behind the builder's back
         let mut app = Program::build(|p, e| {
             p.make_app(
                 e,
-                |p, e| p.make_app(e, |p, e| p.make_ident(e), |p, e| p.make_const_fn(e)),
-                |p, e| p.make_const(e, ONE),
+                |p, e| {
+                    p.make_lam(e, |p, x, e| {
+                        p.make_app(e, |p, e| p.make_varref(e, x), |p, e| p.make_const(e, ONE))
+                    })
+                },
+                |p, e| p.make_ident(e),
+            )
+        });
+        let Expr::App(f, _) = app.exprs[0] else {
+            unreachable!()
+        };
+        let Expr::Lam(_, body) = app.exprs[f] else {
+            unreachable!()
+        };
+        let Expr::App(x0, x1) = app.exprs[body] else {
+            unreachable!()
+        };
+        app.exprs[x1] = app.exprs[x0].clone();
+        assert!(matches!(
+            app.eval(),
+            Err(EvalError::DoubleDeref(Location::Slot(_)))
+        ));
+    }
+
+    #[test]
+    fn validate_well_formed() {
+        let app = Program::build(|p, e| {
+            p.make_app(e, |p, e| p.make_lam_false(e), |p, e| p.make_const(e, ZERO))
+        });
+        assert_eq!(Ok(()), app.validate());
+    }
+
+    #[test]
+    fn validate_escaped_arg() {
+        // (\x. ()) x, with x smuggled out of the lambda's body
+        let escaped = std::cell::Cell::new(None);
+        let app = Program::build(|p, e| {
+            p.make_app(
+                e,
+                |p, e| {
+                    p.make_lam(e, |p, x, e| {
+                        escaped.set(Some(x));
+                        p.make_const(e, UNIT)
+                    })
+                },
+                |p, e| p.make_varref(e, escaped.take().unwrap()),
             )
         });
-        assert_eq!(Some(UNIT), app.eval());
+        assert_eq!(
+            Err(vec![Malformed::OutOfScope { ptr: 2, lam: 1 }]),
+            app.validate()
+        );
     }
 
     #[test]
-    fn t2() {
+    fn deep_right_nested_app() {
+        // (\x. x) ((\x. x) (... ((\x. x) 1))), deeper than the native stack could recurse
+        fn nest(p: &mut Program, e: ExprDest, depth: usize) -> ExprRef {
+            let mut dest = e;
+            for _ in 0..depth {
+                let (f, v) = (p.alloc(), p.alloc());
+                p.exprs[dest.0] = Expr::App(f, v);
+                p.make_ident(ExprDest(f));
+                dest = ExprDest(v);
+            }
+            p.make_const(dest, ONE)
+        }
+        let mut app = Program::build(|p, e| nest(p, e, 100_000));
+        let mut machine = Machine::new(0, &Limits::default(), Strategy::default());
+        while let Progress::Reduced = app.step(&mut machine, &mut NoopObserver).unwrap() {}
+        assert_eq!(Expr::Bas(ONE), app.exprs[0]);
+    }
+
+    #[test]
+    fn fuel_runs_out_and_resumes() {
         let mut app = Program::build(|p, e| {
             p.make_app(
                 e,
@@ -179,18 +1655,58 @@ mod tests {
                 |p, e| p.make_const(e, ONE),
             )
         });
-        assert_eq!(Some(ZERO), app.eval());
+        let out_of_steps = Outcome::OutOfFuel {
+            exhausted: Exhausted::Steps,
+            term: (),
+        };
+        assert_eq!(Ok(out_of_steps), app.eval_with_fuel(2));
+        assert_eq!(Ok(Outcome::Value(Some(ZERO))), app.eval_with_fuel(1));
     }
 
     #[test]
-    fn t3() {
+    fn node_limit() {
+        let mut app = Program::build(|p, e| {
+            p.make_app(e, |p, e| p.make_ident(e), |p, e| p.make_const(e, ONE))
+        });
+        let limits = Limits {
+            max_nodes: 4,
+            ..Limits::default()
+        };
+        let out_of_nodes = Outcome::OutOfFuel {
+            exhausted: Exhausted::Nodes,
+            term: (),
+        };
+        assert_eq!(Ok(out_of_nodes), app.eval_with_limits(limits));
+    }
+
+    #[test]
+    fn recorder_sees_stuck_application() {
+        let mut app = Program::build(|p, e| {
+            p.make_app(e, |p, e| p.make_const(e, ZERO), |p, e| p.make_const(e, ONE))
+        });
+        let mut recorder = Recorder::default();
+        let err = app.eval_observed(&mut recorder).unwrap_err();
+        assert_eq!(
+            recorder.events,
+            vec![Event::Start("0 1".to_string()), Event::Stuck(err),]
+        );
+    }
+
+    #[test]
+    fn display_names_binders() {
         let mut app = Program::build(|p, e| {
             p.make_app(
                 e,
-                |p, e| p.make_app(e, |p, e| p.make_lam_false(e), |p, e| p.make_const(e, ZERO)),
-                |p, e| p.make_const(e, ONE),
+                |p, e| {
+                    p.make_named_lam(e, "f", |p, f, e| {
+                        p.make_lam(e, |p, _x, e| p.make_varref(e, f))
+                    })
+                },
+                |p, e| p.make_ident(e),
             )
         });
-        assert_eq!(Some(ONE), app.eval());
+        assert_eq!(app.to_string(), r"(\f. \x1. f) (\x2. x2)");
+        assert_eq!(Ok(None), app.eval());
+        assert_eq!(app.to_string(), r"\x0. \x1. x1");
     }
 }
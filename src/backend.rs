@@ -0,0 +1,712 @@
+use crate::constant::Const;
+use crate::error::{EvalError, TypeError};
+use crate::fuel::{Limits, Outcome};
+use crate::prim::PrimOp;
+use crate::strategy::Strategy;
+use crate::term::Term;
+use crate::types::Type;
+
+/// What every backend can do: build a term bottom-up and evaluate it. Generic
+/// code written against this trait, like the conformance tests, runs the same
+/// on all of them.
+///
+/// Variables are handed to a lambda's body by reference, so the body can use
+/// one more than once with [`AbtBackend::var`]; the linear backends fail at
+/// evaluation time if it does.
+pub trait AbtBackend {
+    /// A term built so far
+    type Term;
+    /// The variable a lambda binds
+    type Var;
+    fn constant(&mut self, c: Const) -> Self::Term;
+    fn lam(&mut self, body: impl FnOnce(&mut Self, &Self::Var) -> Self::Term) -> Self::Term;
+    /// Like [`AbtBackend::lam`], with the binder annotated with its type
+    fn typed_lam(
+        &mut self,
+        ty: Type,
+        body: impl FnOnce(&mut Self, &Self::Var) -> Self::Term,
+    ) -> Self::Term;
+    fn app(&mut self, f: Self::Term, v: Self::Term) -> Self::Term;
+    fn var(&mut self, x: &Self::Var) -> Self::Term;
+    /// `let x = v in body`, which binds x like applying `\x. body` to v would
+    fn let_in(
+        &mut self,
+        v: Self::Term,
+        body: impl FnOnce(&mut Self, &Self::Var) -> Self::Term,
+    ) -> Self::Term;
+    /// The primitive `op` on `operands`, which it evaluates left to right before
+    /// applying `op`
+    fn prim(&mut self, op: PrimOp, operands: Vec<Self::Term>) -> Self::Term;
+    /// `if c then t else e`, which evaluates c and then only the branch it picks
+    fn if_then_else(&mut self, c: Self::Term, t: Self::Term, e: Self::Term) -> Self::Term;
+    /// The pair `(a, b)`
+    fn pair(&mut self, a: Self::Term, b: Self::Term) -> Self::Term;
+    /// `let (x, y) = v in body`, which binds x and y to the components of the pair v
+    fn split(
+        &mut self,
+        v: Self::Term,
+        body: impl FnOnce(&mut Self, &Self::Var, &Self::Var) -> Self::Term,
+    ) -> Self::Term;
+    /// `inl v`, injecting v into the left of a sum
+    fn inl(&mut self, v: Self::Term) -> Self::Term;
+    /// `inr v`, injecting v into the right of a sum
+    fn inr(&mut self, v: Self::Term) -> Self::Term;
+    /// `case v of inl x. l | inr y. r`, which binds x or y to what v injects and
+    /// evaluates only the branch that binds it
+    fn case(
+        &mut self,
+        v: Self::Term,
+        l: impl FnOnce(&mut Self, &Self::Var) -> Self::Term,
+        r: impl FnOnce(&mut Self, &Self::Var) -> Self::Term,
+    ) -> Self::Term;
+    /// The type of `t`, or why it has none, checked without evaluating it
+    fn typecheck_term(&mut self, t: &Self::Term) -> Result<Type, TypeError>;
+    /// Evaluates `t` in the order `strategy` gives until the result or until one of
+    /// `limits` is hit, and reads the result back.
+    fn eval_term_with(
+        &mut self,
+        t: Self::Term,
+        strategy: Strategy,
+        limits: Limits,
+    ) -> Result<Outcome<Term>, EvalError>;
+    /// Evaluates `t` to the constant it reduces to, or `None` if it reduces to a lambda.
+    fn eval_term(&mut self, t: Self::Term) -> Result<Option<Const>, EvalError> {
+        match self.eval_term_with(t, Strategy::default(), Limits::default())? {
+            Outcome::Value(Term::Const(c)) => Ok(Some(c)),
+            Outcome::Value(_) => Ok(None),
+            Outcome::OutOfFuel { .. } => unreachable!("evaluation without limits ran out"),
+        }
+    }
+    /// Reduces `t` to its normal form in normal order, also under lambdas, and reads it back.
+    fn normalize_term(&mut self, t: Self::Term) -> Result<Term, EvalError> {
+        match self.eval_term_with(t, Strategy::NormalOrder, Limits::default())? {
+            Outcome::Value(term) => Ok(term),
+            Outcome::OutOfFuel { .. } => unreachable!("evaluation without limits ran out"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::{ONE, UNIT, ZERO};
+    use crate::error::{Branch, Location};
+
+    fn ident<B: AbtBackend>(b: &mut B) -> B::Term {
+        b.lam(|b, x| b.var(x))
+    }
+    // \x. ()
+    fn const_fn<B: AbtBackend>(b: &mut B) -> B::Term {
+        b.lam(|b, _| b.constant(UNIT))
+    }
+    // \x. \y. x
+    fn lam_true<B: AbtBackend>(b: &mut B) -> B::Term {
+        b.lam(|b, x| b.lam(|b, _| b.var(x)))
+    }
+    // \x. \y. y
+    fn lam_false<B: AbtBackend>(b: &mut B) -> B::Term {
+        b.lam(|b, _| b.lam(|b, y| b.var(y)))
+    }
+
+    fn t0<B: AbtBackend>(mut b: B) {
+        let f = const_fn(&mut b);
+        let v = b.constant(ONE);
+        let app = b.app(f, v);
+        assert_eq!(Ok(Some(UNIT)), b.eval_term(app));
+    }
+
+    fn t1<B: AbtBackend>(mut b: B) {
+        let id = ident(&mut b);
+        let f = const_fn(&mut b);
+        let f = b.app(id, f);
+        let v = b.constant(ONE);
+        let app = b.app(f, v);
+        assert_eq!(Ok(Some(UNIT)), b.eval_term(app));
+    }
+
+    fn t2<B: AbtBackend>(mut b: B) {
+        let f = lam_true(&mut b);
+        let zero = b.constant(ZERO);
+        let f = b.app(f, zero);
+        let one = b.constant(ONE);
+        let app = b.app(f, one);
+        assert_eq!(Ok(Some(ZERO)), b.eval_term(app));
+    }
+
+    fn t3<B: AbtBackend>(mut b: B) {
+        let f = lam_false(&mut b);
+        let zero = b.constant(ZERO);
+        let f = b.app(f, zero);
+        let one = b.constant(ONE);
+        let app = b.app(f, one);
+        assert_eq!(Ok(Some(ONE)), b.eval_term(app));
+    }
+
+    fn constants_made_at_run_time<B: AbtBackend>(mut b: B) {
+        for c in [
+            Const::Int(-3),
+            Const::Int(i64::MAX),
+            Const::Bool(false),
+            Const::from(format!("built {}", 1 + 1)),
+        ] {
+            let id = ident(&mut b);
+            let v = b.constant(c.clone());
+            let t = b.app(id, v);
+            assert_eq!(Ok(Some(c)), b.eval_term(t));
+        }
+    }
+
+    fn lambda_is_a_value<B: AbtBackend>(mut b: B) {
+        let id = ident(&mut b);
+        let f = lam_false(&mut b);
+        let app = b.app(id, f);
+        assert_eq!(Ok(None), b.eval_term(app));
+    }
+
+    fn stuck_on_constant<B: AbtBackend>(mut b: B) {
+        let zero = b.constant(ZERO);
+        let one = b.constant(ONE);
+        let app = b.app(zero, one);
+        assert!(matches!(b.eval_term(app), Err(EvalError::Stuck(_))));
+    }
+
+    fn normalizes_under_lambdas<B: AbtBackend>(mut b: B) {
+        // \g. (\x. \y. y x) g, which is \g. \y. y g
+        let t = b.lam(|b, g| {
+            let f = b.lam(|b, x| {
+                b.lam(|b, y| {
+                    let (y, x) = (b.var(y), b.var(x));
+                    b.app(y, x)
+                })
+            });
+            let g = b.var(g);
+            b.app(f, g)
+        });
+        let normal = crate::parse::parse(r"\g y. y g").unwrap();
+        assert!(b.normalize_term(t).unwrap().alpha_eq(&normal));
+        // \f. f ((\x. x) (\z. z)), which is \f. f (\z. z)
+        let t = b.lam(|b, f| {
+            let f = b.var(f);
+            let (id, z) = (ident(b), ident(b));
+            let v = b.app(id, z);
+            b.app(f, v)
+        });
+        let normal = crate::parse::parse(r"\f. f (\z. z)").unwrap();
+        assert!(b.normalize_term(t).unwrap().alpha_eq(&normal));
+    }
+
+    // (\x. x x) (\x. x x), which never reaches a value; on the linear backends
+    // it is stuck instead, on the second use of x
+    fn omega<B: AbtBackend>(b: &mut B) -> B::Term {
+        let w = |b: &mut B| {
+            b.lam(|b, x| {
+                let (f, v) = (b.var(x), b.var(x));
+                b.app(f, v)
+            })
+        };
+        let (f, v) = (w(b), w(b));
+        b.app(f, v)
+    }
+    // what `t` evaluates to under `strategy` within a few steps, if anything
+    fn value_of<B: AbtBackend>(b: &mut B, t: B::Term, strategy: Strategy) -> Option<Term> {
+        match b.eval_term_with(t, strategy, Limits::steps(100)) {
+            Ok(Outcome::Value(term)) => Some(term),
+            _ => None,
+        }
+    }
+
+    fn strategies_on_unused_arguments<B: AbtBackend>(mut b: B) {
+        use Strategy::*;
+        let zero = Some(Term::Const(ZERO));
+        // (\x. 0) omega, where only the strategies that bind arguments unevaluated finish
+        for (strategy, expected) in [
+            (CallByValue, None),
+            (CallByName, zero.clone()),
+            (CallByNeed, zero.clone()),
+            (ApplicativeOrder, None),
+            (NormalOrder, zero.clone()),
+        ] {
+            let f = b.lam(|b, _| b.constant(ZERO));
+            let v = omega(&mut b);
+            let t = b.app(f, v);
+            assert_eq!(expected, value_of(&mut b, t, strategy), "{strategy:?}");
+        }
+        // (\x. 0) (\y. omega), where only applicative order goes into the argument
+        for (strategy, expected) in [
+            (CallByValue, zero.clone()),
+            (CallByName, zero.clone()),
+            (CallByNeed, zero.clone()),
+            (ApplicativeOrder, None),
+            (NormalOrder, zero.clone()),
+        ] {
+            let f = b.lam(|b, _| b.constant(ZERO));
+            let v = b.lam(|b, _| omega(b));
+            let t = b.app(f, v);
+            assert_eq!(expected, value_of(&mut b, t, strategy), "{strategy:?}");
+        }
+    }
+
+    fn strategies_under_lambdas<B: AbtBackend>(mut b: B) {
+        use Strategy::*;
+        // \y. (\x. x) y, which only the strong strategies reduce
+        for (strategy, expected) in [
+            (CallByValue, r"\y. (\x. x) y"),
+            (CallByName, r"\y. (\x. x) y"),
+            (CallByNeed, r"\y. (\x. x) y"),
+            (ApplicativeOrder, r"\y. y"),
+            (NormalOrder, r"\y. y"),
+        ] {
+            let t = b.lam(|b, y| {
+                let (id, y) = (ident(b), b.var(y));
+                b.app(id, y)
+            });
+            let term = value_of(&mut b, t, strategy).unwrap();
+            let expected = crate::parse::parse(expected).unwrap();
+            assert!(term.alpha_eq(&expected), "{strategy:?} gave {term}");
+        }
+    }
+
+    fn let_binds_like_application<B: AbtBackend>(mut b: B) {
+        use Strategy::*;
+        let zero = Some(Term::Const(ZERO));
+        // let x = (\y. y) 0 in x, which every strategy finishes
+        for strategy in [
+            CallByValue,
+            CallByName,
+            CallByNeed,
+            ApplicativeOrder,
+            NormalOrder,
+        ] {
+            let v = {
+                let id = ident(&mut b);
+                let zero = b.constant(ZERO);
+                b.app(id, zero)
+            };
+            let t = b.let_in(v, |b, x| b.var(x));
+            assert_eq!(zero, value_of(&mut b, t, strategy), "{strategy:?}");
+        }
+        // let x = omega in 0, which finishes exactly when (\x. 0) omega does
+        for (strategy, expected) in [
+            (CallByValue, None),
+            (CallByName, zero.clone()),
+            (CallByNeed, zero.clone()),
+            (ApplicativeOrder, None),
+            (NormalOrder, zero.clone()),
+        ] {
+            let v = omega(&mut b);
+            let t = b.let_in(v, |b, _| b.constant(ZERO));
+            assert_eq!(expected, value_of(&mut b, t, strategy), "{strategy:?}");
+        }
+        // \y. let x = y in x, where only the strong strategies bind x
+        for (strategy, expected) in [
+            (CallByValue, r"\y. let x = y in x"),
+            (NormalOrder, r"\y. y"),
+        ] {
+            let t = b.lam(|b, y| {
+                let y = b.var(y);
+                b.let_in(y, |b, x| b.var(x))
+            });
+            let term = value_of(&mut b, t, strategy).unwrap();
+            let expected = crate::parse::parse(expected).unwrap();
+            assert!(term.alpha_eq(&expected), "{strategy:?} gave {term}");
+        }
+    }
+
+    fn prims_on_constants<B: AbtBackend>(mut b: B) {
+        use crate::prim::PrimOp::*;
+        let (one, two) = (b.constant(ONE), b.constant(Const::Int(2)));
+        let t = b.prim(Add, vec![one, two]);
+        assert_eq!(Ok(Some(Const::Int(3))), b.eval_term(t));
+        // lt ((\x. x) 1) 2, where the operand is evaluated first
+        let v = {
+            let (id, one) = (ident(&mut b), b.constant(ONE));
+            b.app(id, one)
+        };
+        let two = b.constant(Const::Int(2));
+        let t = b.prim(Lt, vec![v, two]);
+        assert_eq!(Ok(Some(Const::Bool(true))), b.eval_term(t));
+        let (unit, also_unit) = (b.constant(UNIT), b.constant(UNIT));
+        let t = b.prim(Eq, vec![unit, also_unit]);
+        assert_eq!(Ok(Some(Const::Bool(true))), b.eval_term(t));
+        // add (\x. x) 1 and an overflowing add are not defined
+        let (id, one) = (ident(&mut b), b.constant(ONE));
+        let t = b.prim(Add, vec![id, one]);
+        assert!(matches!(b.eval_term(t), Err(EvalError::BadPrim(_))));
+        let (max, one) = (b.constant(Const::Int(i64::MAX)), b.constant(ONE));
+        let t = b.prim(Add, vec![max, one]);
+        assert!(matches!(b.eval_term(t), Err(EvalError::BadPrim(_))));
+    }
+
+    fn prims_under_lambdas<B: AbtBackend>(mut b: B) {
+        use crate::prim::PrimOp::*;
+        use Strategy::*;
+        // \x. mul (add 1 2) x, which is \x. mul 3 x once normalized
+        for (strategy, expected) in [
+            (CallByValue, r"\x. mul (add 1 2) x"),
+            (NormalOrder, r"\x. mul 3 x"),
+            (ApplicativeOrder, r"\x. mul 3 x"),
+        ] {
+            let t = b.lam(|b, x| {
+                let (one, two) = (b.constant(ONE), b.constant(Const::Int(2)));
+                let three = b.prim(Add, vec![one, two]);
+                let x = b.var(x);
+                b.prim(Mul, vec![three, x])
+            });
+            let term = value_of(&mut b, t, strategy).unwrap();
+            let expected = crate::parse::parse(expected).unwrap();
+            assert!(term.alpha_eq(&expected), "{strategy:?} gave {term}");
+        }
+        // \f. add (f 0) 1, where the operand is stuck on a variable too
+        let t = b.lam(|b, f| {
+            let (f, zero) = (b.var(f), b.constant(ZERO));
+            let v = b.app(f, zero);
+            let one = b.constant(ONE);
+            b.prim(Add, vec![v, one])
+        });
+        let normal = crate::parse::parse(r"\f. add (f 0) 1").unwrap();
+        assert!(b.normalize_term(t).unwrap().alpha_eq(&normal));
+    }
+
+    fn ifs_choose_a_branch<B: AbtBackend>(mut b: B) {
+        use Strategy::*;
+        let zero = Some(Term::Const(ZERO));
+        // if true then 0 else omega, where no strategy goes into the untaken branch
+        for strategy in [
+            CallByValue,
+            CallByName,
+            CallByNeed,
+            ApplicativeOrder,
+            NormalOrder,
+        ] {
+            let (c, zero_branch, omega) =
+                (b.constant(true.into()), b.constant(ZERO), omega(&mut b));
+            let t = b.if_then_else(c, zero_branch, omega);
+            assert_eq!(zero, value_of(&mut b, t, strategy), "{strategy:?}");
+        }
+        // if lt 1 0 then omega else 1, where the condition is evaluated first
+        let (one, zero_operand) = (b.constant(ONE), b.constant(ZERO));
+        let c = b.prim(crate::prim::PrimOp::Lt, vec![one, zero_operand]);
+        let (omega, one) = (omega(&mut b), b.constant(ONE));
+        let t = b.if_then_else(c, omega, one);
+        assert_eq!(Ok(Some(ONE)), b.eval_term(t));
+        // if 0 then 1 else 1 has no boolean to choose with
+        let (c, t, e) = (b.constant(ZERO), b.constant(ONE), b.constant(ONE));
+        let t = b.if_then_else(c, t, e);
+        assert!(matches!(b.eval_term(t), Err(EvalError::BadCondition(_))));
+    }
+
+    fn ifs_under_lambdas<B: AbtBackend>(mut b: B) {
+        use Strategy::*;
+        // \x. if x then (\y. y) 1 else 0, where only the strong strategies go into
+        // the branches of an if stuck on x
+        for (strategy, expected) in [
+            (CallByValue, r"\x. if x then (\y. y) 1 else 0"),
+            (NormalOrder, r"\x. if x then 1 else 0"),
+            (ApplicativeOrder, r"\x. if x then 1 else 0"),
+        ] {
+            let t = b.lam(|b, x| {
+                let x = b.var(x);
+                let (id, one) = (ident(b), b.constant(ONE));
+                let then = b.app(id, one);
+                let zero = b.constant(ZERO);
+                b.if_then_else(x, then, zero)
+            });
+            let term = value_of(&mut b, t, strategy).unwrap();
+            let expected = crate::parse::parse(expected).unwrap();
+            assert!(term.alpha_eq(&expected), "{strategy:?} gave {term}");
+        }
+    }
+
+    fn pairs_split<B: AbtBackend>(mut b: B) {
+        use crate::prim::PrimOp::*;
+        use Strategy::*;
+        let zero = Some(Term::Const(ZERO));
+        // let (x, y) = (1, 2) in add x y, which every strategy finishes
+        for strategy in [
+            CallByValue,
+            CallByName,
+            CallByNeed,
+            ApplicativeOrder,
+            NormalOrder,
+        ] {
+            let (one, two) = (b.constant(ONE), b.constant(Const::Int(2)));
+            let v = b.pair(one, two);
+            let t = b.split(v, |b, x, y| {
+                let (x, y) = (b.var(x), b.var(y));
+                b.prim(Add, vec![x, y])
+            });
+            let three = Some(Term::Const(Const::Int(3)));
+            assert_eq!(three, value_of(&mut b, t, strategy), "{strategy:?}");
+        }
+        // let (x, y) = (0, omega) in x, which finishes exactly when (\x. 0) omega does
+        for (strategy, expected) in [
+            (CallByValue, None),
+            (CallByName, zero.clone()),
+            (CallByNeed, zero.clone()),
+            (ApplicativeOrder, None),
+            (NormalOrder, zero.clone()),
+        ] {
+            let (zero_component, omega) = (b.constant(ZERO), omega(&mut b));
+            let v = b.pair(zero_component, omega);
+            let t = b.split(v, |b, x, _| b.var(x));
+            assert_eq!(expected, value_of(&mut b, t, strategy), "{strategy:?}");
+        }
+        // ((\x. x) 0, inl 1), whose components are only evaluated by the strategies
+        // that evaluate arguments
+        for (strategy, expected) in [
+            (CallByValue, "(0, inl 1)"),
+            (CallByName, r"((\x. x) 0, inl 1)"),
+            (ApplicativeOrder, "(0, inl 1)"),
+            (NormalOrder, "(0, inl 1)"),
+        ] {
+            let a = {
+                let (id, zero) = (ident(&mut b), b.constant(ZERO));
+                b.app(id, zero)
+            };
+            let one = b.constant(ONE);
+            let inl = b.inl(one);
+            let t = b.pair(a, inl);
+            let term = value_of(&mut b, t, strategy).unwrap();
+            let expected = crate::parse::parse(expected).unwrap();
+            assert!(term.alpha_eq(&expected), "{strategy:?} gave {term}");
+        }
+        // let (x, y) = inl 0 in x has no pair to split
+        let zero_component = b.constant(ZERO);
+        let v = b.inl(zero_component);
+        let t = b.split(v, |b, x, _| b.var(x));
+        assert!(matches!(b.eval_term(t), Err(EvalError::BadScrutinee(_))));
+    }
+
+    fn cases_pick_a_branch<B: AbtBackend>(mut b: B) {
+        use crate::prim::PrimOp::*;
+        use Strategy::*;
+        // case inl 0 of inl x. x | inr y. omega, where no strategy goes into the
+        // untaken branch
+        for strategy in [
+            CallByValue,
+            CallByName,
+            CallByNeed,
+            ApplicativeOrder,
+            NormalOrder,
+        ] {
+            let zero = b.constant(ZERO);
+            let v = b.inl(zero);
+            let t = b.case(v, |b, x| b.var(x), |b, _| omega(b));
+            assert_eq!(
+                Some(Term::Const(ZERO)),
+                value_of(&mut b, t, strategy),
+                "{strategy:?}"
+            );
+        }
+        // case inr ((\x. x) 1) of inl x. omega | inr y. add y 1
+        let v = {
+            let (id, one) = (ident(&mut b), b.constant(ONE));
+            let v = b.app(id, one);
+            b.inr(v)
+        };
+        let t = b.case(
+            v,
+            |b, _| omega(b),
+            |b, y| {
+                let (y, one) = (b.var(y), b.constant(ONE));
+                b.prim(Add, vec![y, one])
+            },
+        );
+        assert_eq!(Ok(Some(Const::Int(2))), b.eval_term(t));
+        // case 0 of inl x. x | inr y. y has no injection to pick with
+        let zero = b.constant(ZERO);
+        let t = b.case(zero, |b, x| b.var(x), |b, y| b.var(y));
+        assert!(matches!(b.eval_term(t), Err(EvalError::BadScrutinee(_))));
+    }
+
+    fn pairs_and_sums_under_lambdas<B: AbtBackend>(mut b: B) {
+        use Strategy::*;
+        // \z. case z of inl x. (\y. y) x | inr y. (y, 0), where only the strong
+        // strategies go into the branches of a case stuck on z
+        for (strategy, expected) in [
+            (
+                CallByValue,
+                r"\z. case z of inl x. (\y. y) x | inr y. (y, 0)",
+            ),
+            (NormalOrder, r"\z. case z of inl x. x | inr y. (y, 0)"),
+            (ApplicativeOrder, r"\z. case z of inl x. x | inr y. (y, 0)"),
+        ] {
+            let t = b.lam(|b, z| {
+                let z = b.var(z);
+                b.case(
+                    z,
+                    |b, x| {
+                        let (id, x) = (ident(b), b.var(x));
+                        b.app(id, x)
+                    },
+                    |b, y| {
+                        let (y, zero) = (b.var(y), b.constant(ZERO));
+                        b.pair(y, zero)
+                    },
+                )
+            });
+            let term = value_of(&mut b, t, strategy).unwrap();
+            let expected = crate::parse::parse(expected).unwrap();
+            assert!(term.alpha_eq(&expected), "{strategy:?} gave {term}");
+        }
+        // \z. let (x, y) = z 0 in (y, inl ((\w. w) x)), where the split is stuck
+        // on an application
+        let t = b.lam(|b, z| {
+            let (z, zero) = (b.var(z), b.constant(ZERO));
+            let v = b.app(z, zero);
+            b.split(v, |b, x, y| {
+                let y = b.var(y);
+                let (id, x) = (ident(b), b.var(x));
+                let x = b.app(id, x);
+                let inl = b.inl(x);
+                b.pair(y, inl)
+            })
+        });
+        let normal = crate::parse::parse(r"\z. let (x, y) = z 0 in (y, inl x)").unwrap();
+        assert!(b.normalize_term(t).unwrap().alpha_eq(&normal));
+    }
+
+    fn typecheck_before_evaluating<B: AbtBackend>(mut b: B) {
+        use crate::prim::PrimOp::*;
+        // (\(x: int). add x 1) 2 checks, and so reduces to a constant of its type
+        let t = {
+            let f = b.typed_lam(Type::Int, |b, x| {
+                let (x, one) = (b.var(x), b.constant(ONE));
+                b.prim(Add, vec![x, one])
+            });
+            let two = b.constant(Const::Int(2));
+            b.app(f, two)
+        };
+        assert_eq!(Ok(Type::Int), b.typecheck_term(&t));
+        assert_eq!(Ok(Some(Const::Int(3))), b.eval_term(t));
+        // (\x. x 1) 0 would get stuck applying 0, which the checker rejects
+        let t = {
+            let f = b.lam(|b, x| {
+                let (x, one) = (b.var(x), b.constant(ONE));
+                b.app(x, one)
+            });
+            let zero = b.constant(ZERO);
+            b.app(f, zero)
+        };
+        assert!(matches!(
+            b.typecheck_term(&t),
+            Err(TypeError::Mismatch {
+                found: Type::Int,
+                ..
+            })
+        ));
+        assert!(matches!(b.eval_term(t), Err(EvalError::Stuck(_))));
+        // (\(x: bool). x) 0 runs fine, but not at the type its binder declares
+        let t = {
+            let f = b.typed_lam(Type::Bool, |b, x| b.var(x));
+            let zero = b.constant(ZERO);
+            b.app(f, zero)
+        };
+        assert_eq!(
+            Err(TypeError::Mismatch {
+                at: Location::Path(vec![Branch::Arg]),
+                expected: Type::Bool,
+                found: Type::Int,
+            }),
+            b.typecheck_term(&t)
+        );
+        // annotations survive evaluation under lambdas
+        let t = b.typed_lam(Type::fun(Type::Int, Type::Int), |b, f| {
+            let (id, f) = (ident(b), b.var(f));
+            b.app(id, f)
+        });
+        let normal = crate::parse::parse(r"\(f: int -> int). f").unwrap();
+        assert!(b.normalize_term(t).unwrap().alpha_eq(&normal));
+    }
+
+    // one module per backend, each running the whole suite against a fresh backend
+    macro_rules! conformance {
+        ($($backend:ident => $new:expr;)*) => {$(
+            mod $backend {
+                #[test]
+                fn t0() {
+                    super::t0($new);
+                }
+                #[test]
+                fn t1() {
+                    super::t1($new);
+                }
+                #[test]
+                fn t2() {
+                    super::t2($new);
+                }
+                #[test]
+                fn t3() {
+                    super::t3($new);
+                }
+                #[test]
+                fn constants_made_at_run_time() {
+                    super::constants_made_at_run_time($new);
+                }
+                #[test]
+                fn lambda_is_a_value() {
+                    super::lambda_is_a_value($new);
+                }
+                #[test]
+                fn stuck_on_constant() {
+                    super::stuck_on_constant($new);
+                }
+                #[test]
+                fn normalizes_under_lambdas() {
+                    super::normalizes_under_lambdas($new);
+                }
+                #[test]
+                fn strategies_on_unused_arguments() {
+                    super::strategies_on_unused_arguments($new);
+                }
+                #[test]
+                fn strategies_under_lambdas() {
+                    super::strategies_under_lambdas($new);
+                }
+                #[test]
+                fn let_binds_like_application() {
+                    super::let_binds_like_application($new);
+                }
+                #[test]
+                fn prims_on_constants() {
+                    super::prims_on_constants($new);
+                }
+                #[test]
+                fn prims_under_lambdas() {
+                    super::prims_under_lambdas($new);
+                }
+                #[test]
+                fn ifs_choose_a_branch() {
+                    super::ifs_choose_a_branch($new);
+                }
+                #[test]
+                fn ifs_under_lambdas() {
+                    super::ifs_under_lambdas($new);
+                }
+                #[test]
+                fn pairs_split() {
+                    super::pairs_split($new);
+                }
+                #[test]
+                fn cases_pick_a_branch() {
+                    super::cases_pick_a_branch($new);
+                }
+                #[test]
+                fn pairs_and_sums_under_lambdas() {
+                    super::pairs_and_sums_under_lambdas($new);
+                }
+                #[test]
+                fn typecheck_before_evaluating() {
+                    super::typecheck_before_evaluating($new);
+                }
+            }
+        )*};
+    }
+
+    conformance! {
+        arraytree => crate::arraytree::Program::default();
+        arraytree_lam => crate::arraytree_lam::Program::default();
+        heaptree => crate::heaptree::Heap;
+        heaptree_norc => &crate::heaptree_norc::Args::with_capacity(64);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::types::Type;
+use std::fmt;
+use std::rc::Rc;
+
+pub const ZERO: Const = Const::Int(0);
+pub const ONE: Const = Const::Int(1);
+pub const UNIT: Const = Const::Unit;
+
+/// A base value: anything a term can hold that is not a lambda.
+///
+/// Constants are cheap to clone, strings being shared, so every backend copies
+/// them freely instead of pointing at them.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Const {
+    Unit,
+    Int(i64),
+    Bool(bool),
+    Str(Rc<str>),
+}
+
+impl Const {
+    /// The type every constant of this kind has
+    pub fn ty(&self) -> Type {
+        match self {
+            Const::Unit => Type::Unit,
+            Const::Int(_) => Type::Int,
+            Const::Bool(_) => Type::Bool,
+            Const::Str(_) => Type::Str,
+        }
+    }
+}
+
+/// Prints the constant the way `parse` reads it back: `()`, `-12`, `true`, or
+/// a string in double quotes with Rust's escapes.
+impl fmt::Display for Const {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Const::Unit => f.write_str("()"),
+            Const::Int(n) => write!(f, "{n}"),
+            Const::Bool(b) => write!(f, "{b}"),
+            Const::Str(s) => write!(f, "{s:?}"),
+        }
+    }
+}
+
+impl From<()> for Const {
+    fn from((): ()) -> Self {
+        Const::Unit
+    }
+}
+
+impl From<i64> for Const {
+    fn from(n: i64) -> Self {
+        Const::Int(n)
+    }
+}
+
+impl From<bool> for Const {
+    fn from(b: bool) -> Self {
+        Const::Bool(b)
+    }
+}
+
+impl From<&str> for Const {
+    fn from(s: &str) -> Self {
+        Const::Str(s.into())
+    }
+}
+
+impl From<String> for Const {
+    fn from(s: String) -> Self {
+        Const::Str(s.into())
+    }
+}
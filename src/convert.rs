@@ -0,0 +1,109 @@
+//! Conversions between the backends, all of which go through [`Term`] so binding
+//! structure and binder names carry over. Variables that evaluation already
+//! substituted arrive as their values. Only heaptree can build a `fix`, so
+//! converting into the other backends is a `TryFrom` that fails on one. Converting
+//! into `heaptree_norc` needs an arena, so that is
+//! [`heaptree_norc::Expr::from_term`] rather than a `TryFrom` impl.
+
+use crate::error::BuildError;
+use crate::term::Term;
+use crate::{arraytree, arraytree_lam, heaptree, heaptree_norc};
+
+macro_rules! via_term {
+    ($($from:ty => $($to:ty),+;)*) => {$($(
+        impl From<&$from> for $to {
+            fn from(e: &$from) -> Self {
+                Self::from(&Term::from(e))
+            }
+        }
+    )+)*};
+}
+
+macro_rules! try_via_term {
+    ($($from:ty => $($to:ty),+;)*) => {$($(
+        impl TryFrom<&$from> for $to {
+            type Error = BuildError;
+            fn try_from(e: &$from) -> Result<Self, BuildError> {
+                Self::try_from(&Term::from(e))
+            }
+        }
+    )+)*};
+}
+
+via_term! {
+    arraytree::Program => heaptree::Expr;
+    arraytree_lam::Program => heaptree::Expr;
+    heaptree_norc::Expr<'_> => heaptree::Expr;
+}
+
+try_via_term! {
+    heaptree::Expr => arraytree::Program, arraytree_lam::Program;
+    arraytree::Program => arraytree_lam::Program;
+    arraytree_lam::Program => arraytree::Program;
+    heaptree_norc::Expr<'_> => arraytree::Program, arraytree_lam::Program;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::ONE;
+    use crate::error::{Branch, Location, ParseErrorKind};
+    use crate::fuel::Outcome;
+
+    #[test]
+    fn every_backend_evaluates_a_converted_term_alike() {
+        // the inner x shadows the outer one, which must survive every conversion
+        let src = r"(\x. \x. x) ((\y. y) 0) 1";
+        let e = heaptree::parse(src).unwrap();
+        let mut a = arraytree::Program::try_from(&e).unwrap();
+        let mut l = arraytree_lam::Program::try_from(&a).unwrap();
+        let args = heaptree_norc::Args::with_capacity(16);
+        let n = heaptree_norc::Expr::from_term(&args, &l.to_term()).unwrap();
+        let back = heaptree::Expr::from(&n);
+        for printed in [
+            a.to_string(),
+            l.to_string(),
+            n.to_string(),
+            back.to_string(),
+        ] {
+            assert_eq!(r"(\x. \x'. x') ((\y. y) 0) 1", printed);
+        }
+        assert_eq!(Ok(Some(ONE)), a.eval());
+        assert_eq!(Ok(Some(ONE)), l.eval());
+        assert!(matches!(
+            heaptree_norc::eval(n),
+            Ok(heaptree_norc::Expr::Bas(c)) if c == ONE
+        ));
+        assert_eq!(Ok(heaptree::ONE), heaptree::eval(back));
+    }
+
+    #[test]
+    fn substituted_variables_convert_as_their_values() {
+        let (mut a, start) = arraytree::Program::build();
+        let _ = a.make_parsed(start, r"(\f. f (f 0)) (\x. x)").unwrap();
+        assert!(matches!(a.eval_with_fuel(1), Ok(Outcome::OutOfFuel { .. })));
+        let e = heaptree::Expr::from(&a);
+        assert_eq!(r"(\x. x) ((\x. x) 0)", e.to_string());
+        assert_eq!(Ok(heaptree::ZERO), heaptree::eval(e));
+    }
+
+    #[test]
+    fn only_heaptree_builds_a_fix() {
+        let src = r"(fix f. \x. x) 1";
+        let e = heaptree::parse(src).unwrap();
+        // the other backends reject it up front, pointing at the fix
+        let at = || BuildError::Fix(Location::Path(vec![Branch::Fun]));
+        assert!(matches!(arraytree::Program::try_from(&e), Err(err) if err == at()));
+        assert!(matches!(arraytree_lam::Program::try_from(&e), Err(err) if err == at()));
+        let args = heaptree_norc::Args::with_capacity(16);
+        let n = heaptree_norc::Expr::from_term(&args, &e.to_term());
+        assert!(matches!(n, Err(err) if err == at()));
+        let (mut a, start) = arraytree::Program::build();
+        let err = a.make_parsed(start, src).unwrap_err();
+        assert_eq!(
+            (1, 2, ParseErrorKind::UnsupportedFix),
+            (err.line, err.column, err.kind)
+        );
+        assert_eq!(Ok(heaptree::ONE), heaptree::eval(e));
+    }
+}
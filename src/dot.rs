@@ -0,0 +1,124 @@
+use crate::observer::EvalObserver;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io;
+use std::path::PathBuf;
+
+/// Graphviz DOT rendering of a term's pointer structure: solid tree edges from
+/// lambdas and applications to their children, and a dashed back-edge from every
+/// variable up to the cell of the binder it points to. A cell that evaluation has
+/// bound gets an edge to the value it now holds.
+pub trait ToDot {
+    fn to_dot(&self) -> String;
+}
+
+// Builds up a DOT graph, giving every node the backend identifies by some key
+// (a slot or an address) a small id of its own, in the order they are first seen
+pub(crate) struct DotWriter {
+    out: String,
+    ids: HashMap<usize, usize>,
+    next: usize,
+}
+
+impl DotWriter {
+    pub(crate) fn new() -> Self {
+        Self {
+            out: String::from("digraph {\n    node [shape=plaintext];\n"),
+            ids: HashMap::new(),
+            next: 0,
+        }
+    }
+    // the id for key, and whether this is the first time it was asked for
+    pub(crate) fn id(&mut self, key: usize) -> (usize, bool) {
+        match self.ids.get(&key) {
+            Some(&id) => (id, false),
+            None => {
+                let id = self.fresh();
+                self.ids.insert(key, id);
+                (id, true)
+            }
+        }
+    }
+    // an id for a node that has no key of its own
+    pub(crate) fn fresh(&mut self) -> usize {
+        self.next += 1;
+        self.next - 1
+    }
+    pub(crate) fn node(&mut self, id: usize, label: &str) {
+        let label = escape(label);
+        let _ = writeln!(self.out, "    n{id} [label=\"{label}\"];");
+    }
+    // a binder's cell, empty until evaluation binds it
+    pub(crate) fn cell(&mut self, id: usize, label: &str) {
+        let label = escape(label);
+        let _ = writeln!(self.out, "    n{id} [label=\"{label}\", shape=circle];");
+    }
+    pub(crate) fn edge(&mut self, from: usize, to: usize, label: &str) {
+        let _ = writeln!(self.out, "    n{from} -> n{to} [label=\"{label}\"];");
+    }
+    pub(crate) fn back_edge(&mut self, from: usize, to: usize) {
+        let _ = writeln!(
+            self.out,
+            "    n{from} -> n{to} [style=dashed, constraint=false];"
+        );
+    }
+    pub(crate) fn finish(mut self) -> String {
+        self.out.push_str("}\n");
+        self.out
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Writes the whole term as `step-0000.dot`, `step-0001.dot`, … into a directory,
+/// once before evaluation starts and once after every reduction.
+///
+/// Observers can't fail evaluation, so the first error writing a file stops
+/// the dump and is kept for [`DotDump::finish`].
+#[derive(Debug)]
+pub struct DotDump {
+    dir: PathBuf,
+    steps: usize,
+    error: Option<io::Error>,
+}
+
+impl DotDump {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            steps: 0,
+            error: None,
+        }
+    }
+    /// How many files were written, or the error that stopped the dump.
+    pub fn finish(self) -> io::Result<usize> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.steps),
+        }
+    }
+    fn dump(&mut self, term: &(impl ToDot + ?Sized)) {
+        if self.error.is_some() {
+            return;
+        }
+        let path = self.dir.join(format!("step-{:04}.dot", self.steps));
+        match std::fs::write(path, term.to_dot()) {
+            Ok(()) => self.steps += 1,
+            Err(err) => self.error = Some(err),
+        }
+    }
+}
+
+impl<T: ToDot + ?Sized> EvalObserver<T> for DotDump {
+    fn observes_steps(&self) -> bool {
+        true
+    }
+    fn on_start(&mut self, term: &T) {
+        self.dump(term);
+    }
+    fn on_step(&mut self, term: &T) {
+        self.dump(term);
+    }
+}
@@ -0,0 +1,441 @@
+use crate::prim::PrimOp;
+use crate::types::Type;
+use std::fmt;
+
+/// Which child of a heap-allocated node evaluation descended into.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Branch {
+    Fun,
+    Arg,
+    /// The expression stored in the cell a variable points to
+    Cell,
+    /// The body of a lambda, which only normalizing goes into
+    Body,
+    /// The value of a let, which call-by-value evaluates before binding it
+    Bound,
+    /// An operand of a primitive, counting from 0
+    Operand(usize),
+    /// The condition of an if
+    Cond,
+    /// A branch of an if stuck on its condition, which only normalizing goes into
+    Then,
+    Else,
+    /// A component of a pair, evaluated before the pair is a value unless
+    /// arguments are bound unevaluated
+    Fst,
+    Snd,
+    /// What an injection injects, evaluated like a component of a pair
+    Inj,
+    /// The pair a split or the injection a case takes apart
+    Scrutinee,
+    /// A branch of a case stuck on its scrutinee, which only normalizing goes into;
+    /// the body of a split stuck on its scrutinee is a `Body`
+    Left,
+    Right,
+}
+
+/// Where in a term evaluation went wrong.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Location {
+    /// Index of the offending slot in an index-based program
+    Slot(usize),
+    /// Path from the root of a heap-allocated term, or of a [`Term`](crate::term::Term)
+    /// being type checked or built, to the offending node
+    Path(Vec<Branch>),
+}
+
+impl Location {
+    pub fn root() -> Self {
+        Location::Path(Vec::new())
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Slot(idx) => write!(f, "slot {idx}"),
+            Location::Path(path) if path.is_empty() => write!(f, "root"),
+            Location::Path(path) => {
+                write!(f, "root")?;
+                for branch in path {
+                    match branch {
+                        Branch::Fun => write!(f, ".fun")?,
+                        Branch::Arg => write!(f, ".arg")?,
+                        Branch::Cell => write!(f, ".*")?,
+                        Branch::Body => write!(f, ".body")?,
+                        Branch::Bound => write!(f, ".bound")?,
+                        Branch::Operand(i) => write!(f, ".operand{i}")?,
+                        Branch::Cond => write!(f, ".cond")?,
+                        Branch::Then => write!(f, ".then")?,
+                        Branch::Else => write!(f, ".else")?,
+                        Branch::Fst => write!(f, ".fst")?,
+                        Branch::Snd => write!(f, ".snd")?,
+                        Branch::Inj => write!(f, ".inj")?,
+                        Branch::Scrutinee => write!(f, ".scrutinee")?,
+                        Branch::Left => write!(f, ".left")?,
+                        Branch::Right => write!(f, ".right")?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Why evaluation of a term could not go on.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum EvalError {
+    /// An application whose function part evaluated to something other than a lambda
+    Stuck(Location),
+    /// A part of the term that was never filled in
+    Uninitialized(Location),
+    /// A variable dereferenced before its binder was applied, or bound in a cell
+    /// that evaluation already freed
+    DanglingPointer(Location),
+    /// A variable dereferenced again after its value was moved out, or bound again
+    /// after it was bound
+    DoubleDeref(Location),
+    /// A primitive whose operands it is not defined on, like adding a lambda
+    /// or overflowing
+    BadPrim(Location),
+    /// An if whose condition evaluated to something other than a boolean
+    BadCondition(Location),
+    /// A split whose scrutinee evaluated to something other than a pair, or a case
+    /// whose scrutinee evaluated to something other than an injection
+    BadScrutinee(Location),
+}
+
+impl EvalError {
+    pub fn location(&self) -> &Location {
+        match self {
+            EvalError::Stuck(loc)
+            | EvalError::Uninitialized(loc)
+            | EvalError::DanglingPointer(loc)
+            | EvalError::DoubleDeref(loc)
+            | EvalError::BadPrim(loc)
+            | EvalError::BadCondition(loc)
+            | EvalError::BadScrutinee(loc) => loc,
+        }
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Stuck(loc) => write!(f, "stuck at {loc}: applied a non-function"),
+            EvalError::Uninitialized(loc) => write!(f, "uninitialized expression at {loc}"),
+            EvalError::DanglingPointer(loc) => {
+                write!(
+                    f,
+                    "dangling pointer at {loc}: variable used before it was bound"
+                )
+            }
+            EvalError::DoubleDeref(loc) => {
+                write!(
+                    f,
+                    "double deref at {loc}: variable value was already moved out"
+                )
+            }
+            EvalError::BadPrim(loc) => {
+                write!(f, "bad primitive at {loc}: not defined on its operands")
+            }
+            EvalError::BadCondition(loc) => {
+                write!(f, "bad condition at {loc}: not a boolean")
+            }
+            EvalError::BadScrutinee(loc) => {
+                write!(f, "bad scrutinee at {loc}: not what it is taken apart as")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// Why `typecheck` rejected a term, and where in it. Type variables are numbered
+/// from 0 afresh for each error.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TypeError {
+    /// A part whose type is not the one its place in the term needs, like applying
+    /// an integer or an if whose branches differ
+    Mismatch {
+        at: Location,
+        expected: Type,
+        found: Type,
+    },
+    /// A part whose type would have to contain itself, like the argument in `\x. x x`
+    Cyclic { at: Location, var: Type, ty: Type },
+    /// An operand of `eq` whose type is not a constant's
+    NotComparable { at: Location, ty: Type },
+    /// A primitive with the wrong number of operands
+    Arity {
+        at: Location,
+        op: PrimOp,
+        found: usize,
+    },
+    /// A binder annotated with a type that has a type variable in it, which only
+    /// `typecheck` itself makes
+    Annotation { at: Location, ty: Type },
+}
+
+impl TypeError {
+    pub fn location(&self) -> &Location {
+        match self {
+            TypeError::Mismatch { at, .. }
+            | TypeError::Cyclic { at, .. }
+            | TypeError::NotComparable { at, .. }
+            | TypeError::Arity { at, .. }
+            | TypeError::Annotation { at, .. } => at,
+        }
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeError::Mismatch {
+                at,
+                expected,
+                found,
+            } => write!(
+                f,
+                "type mismatch at {at}: expected {expected}, found {found}"
+            ),
+            TypeError::Cyclic { at, var, ty } => {
+                write!(f, "infinite type at {at}: {var} would have to be {ty}")
+            }
+            TypeError::NotComparable { at, ty } => {
+                write!(f, "bad comparison at {at}: {ty} is not a constant's type")
+            }
+            TypeError::Arity { at, op, found } => {
+                write!(
+                    f,
+                    "bad arity at {at}: {op} takes {} operands, found {found}",
+                    op.arity()
+                )
+            }
+            TypeError::Annotation { at, ty } => {
+                write!(f, "bad annotation at {at}: {ty} has a type variable in it")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+/// A defect `validate` found in an index-based program before evaluating it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Malformed {
+    /// A slot reachable from the root that was never filled in
+    Uninitialized { slot: usize },
+    /// A variable whose target slot is not the argument of any lambda, let, split
+    /// or case
+    NotABinder { ptr: usize, target: usize },
+    /// A variable used outside of the scope of the binder at `lam` that binds it
+    OutOfScope { ptr: usize, lam: usize },
+    /// A slot that the node at `parent` owns but that some other node, or a node
+    /// the slot itself leads to, already owns, so the program is not a tree
+    Shared { slot: usize, parent: usize },
+    /// A primitive with another number of operands than its op takes
+    Arity {
+        slot: usize,
+        op: PrimOp,
+        found: usize,
+    },
+    /// An argument of the binder at `binder` that already holds a value, though
+    /// binding it is what takes the binder apart
+    AlreadyBound { arg: usize, binder: usize },
+}
+
+impl fmt::Display for Malformed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Malformed::Uninitialized { slot } => write!(f, "slot {slot} is uninitialized"),
+            Malformed::NotABinder { ptr, target } => {
+                write!(
+                    f,
+                    "variable at slot {ptr} points at slot {target}, which no lambda binds"
+                )
+            }
+            Malformed::OutOfScope { ptr, lam } => {
+                write!(
+                    f,
+                    "variable at slot {ptr} is used outside of its lambda at slot {lam}"
+                )
+            }
+            Malformed::Shared { slot, parent } => {
+                write!(f, "slot {slot} is reached again from slot {parent}")
+            }
+            Malformed::Arity { slot, op, found } => write!(
+                f,
+                "primitive at slot {slot}: {op} takes {} operands, found {found}",
+                op.arity()
+            ),
+            Malformed::AlreadyBound { arg, binder } => write!(
+                f,
+                "argument at slot {arg} of the binder at slot {binder} already holds a value"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Malformed {}
+
+/// Why `read_from` rejected its input.
+#[derive(Debug)]
+pub enum DecodeError {
+    Io(std::io::Error),
+    /// The input does not start with the format's magic bytes
+    BadMagic,
+    UnsupportedVersion(u64),
+    /// A varint that does not fit in a `usize`
+    VarintOverflow,
+    /// A slot whose tag byte is not one the format defines
+    UnknownTag {
+        slot: usize,
+        tag: u8,
+    },
+    /// A slot that refers to a slot past the end of the program
+    IndexOutOfRange {
+        slot: usize,
+        index: usize,
+    },
+    /// A constant whose kind byte the format does not define
+    UnknownConstant {
+        slot: usize,
+        kind: u8,
+    },
+    /// A string constant that is not UTF-8
+    InvalidUtf8 {
+        slot: usize,
+    },
+    /// A primitive whose op byte the format does not define
+    UnknownPrim {
+        slot: usize,
+        op: u8,
+    },
+    /// A program without even a root slot
+    Empty,
+    /// A program that decoded fine but failed `validate`, or has a primitive
+    /// with the wrong number of operands
+    Malformed(Vec<Malformed>),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Io(err) => write!(f, "i/o error: {err}"),
+            DecodeError::BadMagic => write!(f, "not a serialized program"),
+            DecodeError::UnsupportedVersion(v) => write!(f, "unsupported format version {v}"),
+            DecodeError::VarintOverflow => write!(f, "varint does not fit in a usize"),
+            DecodeError::UnknownTag { slot, tag } => write!(f, "slot {slot} has unknown tag {tag}"),
+            DecodeError::IndexOutOfRange { slot, index } => {
+                write!(f, "slot {slot} refers to slot {index}, past the end")
+            }
+            DecodeError::UnknownConstant { slot, kind } => {
+                write!(f, "slot {slot} has unknown constant kind {kind}")
+            }
+            DecodeError::InvalidUtf8 { slot } => {
+                write!(f, "slot {slot} has a string that is not UTF-8")
+            }
+            DecodeError::UnknownPrim { slot, op } => {
+                write!(f, "slot {slot} has unknown primitive {op}")
+            }
+            DecodeError::Empty => write!(f, "program has no root slot"),
+            DecodeError::Malformed(problems) => {
+                write!(f, "malformed program")?;
+                for (i, problem) in problems.iter().enumerate() {
+                    let sep = if i == 0 { ": " } else { "; " };
+                    write!(f, "{sep}{problem}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DecodeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for DecodeError {
+    fn from(err: std::io::Error) -> Self {
+        DecodeError::Io(err)
+    }
+}
+
+/// Why `parse` rejected its input, and where: lines and columns count from 1,
+/// columns in characters.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParseErrorKind {
+    /// A character that can't start any token
+    UnexpectedChar(char),
+    Unexpected {
+        found: String,
+        expected: &'static str,
+    },
+    /// A parenthesis that is never closed, located at the opening one
+    Unclosed,
+    /// A name used outside of every lambda binding it
+    UnboundVariable(String),
+    /// A word starting with a digit that is not an integer in range
+    UnknownConstant(String),
+    /// A string literal that is never closed, located at its opening quote
+    UnclosedString,
+    /// A backslash in a string literal followed by this character, located at the backslash
+    UnknownEscape(char),
+    /// A `fix` parsed for a backend that cannot build it, located at the keyword
+    UnsupportedFix,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: ", self.line, self.column)?;
+        match &self.kind {
+            ParseErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {c:?}"),
+            ParseErrorKind::Unexpected { found, expected } => {
+                write!(f, "expected {expected}, found {found}")
+            }
+            ParseErrorKind::Unclosed => write!(f, "parenthesis is never closed"),
+            ParseErrorKind::UnboundVariable(name) => write!(f, "unbound variable `{name}`"),
+            ParseErrorKind::UnknownConstant(c) => write!(f, "unknown constant `{c}`"),
+            ParseErrorKind::UnclosedString => write!(f, "string is never closed"),
+            ParseErrorKind::UnknownEscape(c) => write!(f, "unknown escape `\\{c}`"),
+            ParseErrorKind::UnsupportedFix => write!(f, "this backend cannot build `fix`"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Why a backend other than heaptree could not build a [`Term`](crate::term::Term).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BuildError {
+    /// A `fix`, at this path in the term, which needs a cycle the backend has no
+    /// way to build
+    Fix(Location),
+    /// No cell left in the arena for a binder, counting those evaluation has freed
+    OutOfCells,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::Fix(at) => write!(f, "cannot build the fix at {at} without a cycle"),
+            BuildError::OutOfCells => f.write_str("the arena has no cell left for a binder"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
@@ -0,0 +1,55 @@
+/// Hard bounds on how much work a single evaluation may do.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Limits {
+    /// Reductions: beta steps, variable dereferences, lets, primitive ops and ifs
+    pub max_steps: usize,
+    /// Size of the term: slots for the index-based backends, nodes for the heap trees
+    pub max_nodes: usize,
+    /// Applications, and lambdas when normalizing, the evaluator may be nested inside of at once
+    pub max_depth: usize,
+}
+
+impl Limits {
+    pub fn steps(max_steps: usize) -> Self {
+        Self {
+            max_steps,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_steps: usize::MAX,
+            max_nodes: usize::MAX,
+            max_depth: usize::MAX,
+        }
+    }
+}
+
+/// The limit an evaluation ran into.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Exhausted {
+    Steps,
+    Nodes,
+    Depth,
+}
+
+/// How a limited evaluation ended.
+///
+/// The index-based backends keep the partially reduced term in their program,
+/// so their `term` is `()`. Evaluating the partially reduced term again
+/// resumes where the limited evaluation stopped.
+#[derive(PartialEq, Eq, Debug)]
+pub enum Outcome<V, T = ()> {
+    Value(V),
+    OutOfFuel { exhausted: Exhausted, term: T },
+}
+
+// What one run of an evaluator up to its next reduction came to
+pub(crate) enum Progress {
+    Reduced,
+    Finished,
+    Exhausted(Exhausted),
+}
@@ -1,81 +1,1695 @@
-use std::cell::OnceCell;
-use std::rc::Rc;
+use crate::backend::AbtBackend;
+use crate::constant::{self, Const};
+use crate::dot::{DotWriter, ToDot};
+use crate::error::{Branch, EvalError, Location, ParseError, TypeError};
+use crate::fuel::{Exhausted, Limits, Outcome, Progress};
+use crate::observer::{EvalObserver, NoopObserver};
+use crate::parse;
+use crate::prim::PrimOp;
+use crate::strategy::Strategy;
+use crate::term::{Binder, ReadBack, Shape, Term};
+use crate::types::{self, Type};
+use std::cell::{Ref, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::{Rc, Weak};
 
 // Ptr and Lam must be opaque
 #[derive(PartialEq, Eq, Debug)]
-pub struct Ptr(Rc<OnceCell<Box<Expr>>>);
+pub struct Ptr(Rc<RefCell<Slot>>);
 #[derive(PartialEq, Eq, Debug)]
-pub struct Lam(Ptr, Box<Expr>);
+pub struct Lam(Ptr, Box<Expr>, Option<Rc<str>>);
+// A variable of a fix inside the body its own cell holds, pointing back at the cell
+// without keeping it alive so the cycle between them doesn't leak. Two are equal
+// when they point at the same cell, since comparing contents would go around the
+// cycle forever.
+#[derive(Debug)]
+pub struct Rec(Weak<RefCell<Slot>>);
+impl PartialEq for Rec {
+    fn eq(&self, other: &Self) -> bool {
+        Weak::ptr_eq(&self.0, &other.0)
+    }
+}
+impl Eq for Rec {}
+
+// What a binder cell holds: nothing before beta reduction, an unevaluated
+// argument under call-by-need, a value that every use copies out, or the body
+// of a fix that unfolded, with the fix's name, which every use copies out too
+#[derive(PartialEq, Eq, Debug)]
+enum Slot {
+    Unbound,
+    Thunk(Box<Expr>),
+    Value(Box<Expr>),
+    Rec(Box<Expr>, Option<Rc<str>>),
+}
 
 // Exprs will only ever be evaluated once,
-// so Box is used instead of Rc
+// so Box is used instead of Rc; sharing happens through Ptr cells
 #[derive(PartialEq, Eq, Debug)]
 pub enum Expr {
     Ptr(Ptr),
-    Bas(&'static str),
-    Lam(Lam),
+    Bas(Const),
+    /// A lambda, and the type its binder is annotated with, if any
+    Lam(Lam, Option<Type>),
     App(Box<Expr>, Box<Expr>),
+    /// `let x = v in body`, with the value first and the binder and body as a lambda
+    Let(Box<Expr>, Lam),
+    Prim(PrimOp, Vec<Expr>),
+    /// `if c then t else e`
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+    Pair(Box<Expr>, Box<Expr>),
+    /// `let (x, y) = v in body`, with the pair first, then x's cell and name, and
+    /// y and the body as a lambda
+    Split(Box<Expr>, Ptr, Option<Rc<str>>, Lam),
+    Inl(Box<Expr>),
+    Inr(Box<Expr>),
+    /// `case v of inl x. l | inr y. r`, with the scrutinee first and either branch
+    /// as a lambda
+    Case(Box<Expr>, Lam, Lam),
+    /// `fix f. body`, with f and the body as a lambda. It unfolds to its body with
+    /// f's cell holding the body too, so every use of f unfolds it again.
+    Fix(Lam),
+    /// A use of the variable of a fix that unfolded, inside the body its cell holds
+    Rec(Rec),
     Invalid,
 }
-pub const ZERO: Expr = Expr::Bas("0");
-pub const ONE: Expr = Expr::Bas("1");
-pub const UNIT: Expr = Expr::Bas("()");
-
-fn step(e: &mut Box<Expr>) -> bool {
-    match std::mem::replace(e.as_mut(), Expr::Invalid) {
-        Expr::Invalid => {
-            unreachable!("Evaluating empty expr")
-        }
-        expr @ (Expr::Bas(_) | Expr::Lam(_)) => {
-            **e = expr;
-            false
-        }
-        Expr::Ptr(Ptr(rc)) => {
-            debug_assert_eq!(
-                Rc::strong_count(&rc),
-                1,
-                "at this time Rc should have just one referent"
-            );
-            debug_assert!(rc.get().is_some(), "invalid deref before beta reduction");
-            // we can take ownership of the value in the Rc since there are no other references by this point
-            let Some(deref) = Rc::into_inner(rc).and_then(OnceCell::into_inner) else {
-                unreachable!("Deref can't happen before beta reduction");
+impl Expr {
+    /// The term with every variable that evaluation already bound,
+    /// or that a call-by-need thunk stands for, replaced by its value.
+    pub fn to_term(&self) -> Term {
+        read_back(self)
+    }
+}
+
+impl Expr {
+    /// Builds `term`, with a fresh cell for each binder that all of its variables
+    /// share. A variable whose binder is not in the term gets an unbound cell of its own.
+    pub fn from_term(term: &Term) -> Expr {
+        enum Task<'a> {
+            Build(&'a Term),
+            Lam(Ptr, Option<Rc<str>>, Option<Type>),
+            App,
+            Let(Ptr, Option<Rc<str>>),
+            Prim(PrimOp, usize),
+            If,
+            Pair,
+            Split(Ptr, Option<Rc<str>>, Ptr, Option<Rc<str>>),
+            Inj(bool),
+            Case(Ptr, Option<Rc<str>>, Ptr, Option<Rc<str>>),
+            Fix(Ptr, Option<Rc<str>>),
+        }
+        let mut cells: HashMap<usize, Rc<RefCell<Slot>>> = HashMap::new();
+        let mut todo = vec![Task::Build(term)];
+        let mut done = Vec::new();
+        while let Some(task) = todo.pop() {
+            match task {
+                Task::Build(Term::Var(id)) => {
+                    let cell = cells
+                        .entry(*id)
+                        .or_insert_with(|| Rc::new(RefCell::new(Slot::Unbound)));
+                    done.push(Expr::Ptr(Ptr(Rc::clone(cell))));
+                }
+                Task::Build(Term::Const(c)) => done.push(Expr::Bas(c.clone())),
+                Task::Build(Term::Lam(binder, body)) => {
+                    let cell = Rc::new(RefCell::new(Slot::Unbound));
+                    cells.insert(binder.id, Rc::clone(&cell));
+                    todo.push(Task::Lam(Ptr(cell), binder.hint.clone(), binder.ty.clone()));
+                    todo.push(Task::Build(body));
+                }
+                Task::Build(Term::App(f, v)) => {
+                    todo.push(Task::App);
+                    todo.push(Task::Build(v));
+                    todo.push(Task::Build(f));
+                }
+                Task::Build(Term::Let(binder, v, body)) => {
+                    let cell = Rc::new(RefCell::new(Slot::Unbound));
+                    cells.insert(binder.id, Rc::clone(&cell));
+                    todo.push(Task::Let(Ptr(cell), binder.hint.clone()));
+                    todo.push(Task::Build(body));
+                    todo.push(Task::Build(v));
+                }
+                Task::Build(Term::Prim(op, operands)) => {
+                    todo.push(Task::Prim(*op, operands.len()));
+                    todo.extend(operands.iter().rev().map(Task::Build));
+                }
+                Task::Build(Term::If(c, t, e)) => {
+                    todo.push(Task::If);
+                    todo.extend([Task::Build(e), Task::Build(t), Task::Build(c)]);
+                }
+                Task::Build(Term::Pair(a, b)) => {
+                    todo.extend([Task::Pair, Task::Build(b), Task::Build(a)]);
+                }
+                Task::Build(Term::Split(x, y, v, body)) => {
+                    let (x_cell, y_cell) = (fresh_cell(), fresh_cell());
+                    cells.insert(x.id, Rc::clone(&x_cell));
+                    cells.insert(y.id, Rc::clone(&y_cell));
+                    let (x_hint, y_hint) = (x.hint.clone(), y.hint.clone());
+                    todo.push(Task::Split(Ptr(x_cell), x_hint, Ptr(y_cell), y_hint));
+                    todo.extend([Task::Build(body), Task::Build(v)]);
+                }
+                Task::Build(Term::Inl(v)) => todo.extend([Task::Inj(true), Task::Build(v)]),
+                Task::Build(Term::Inr(v)) => todo.extend([Task::Inj(false), Task::Build(v)]),
+                Task::Build(Term::Case(v, x, l, y, r)) => {
+                    let (x_cell, y_cell) = (fresh_cell(), fresh_cell());
+                    cells.insert(x.id, Rc::clone(&x_cell));
+                    cells.insert(y.id, Rc::clone(&y_cell));
+                    let (x_hint, y_hint) = (x.hint.clone(), y.hint.clone());
+                    todo.push(Task::Case(Ptr(x_cell), x_hint, Ptr(y_cell), y_hint));
+                    todo.extend([Task::Build(r), Task::Build(l), Task::Build(v)]);
+                }
+                Task::Build(Term::Fix(binder, body)) => {
+                    let cell = fresh_cell();
+                    cells.insert(binder.id, Rc::clone(&cell));
+                    todo.push(Task::Fix(Ptr(cell), binder.hint.clone()));
+                    todo.push(Task::Build(body));
+                }
+                Task::Build(Term::Invalid) => done.push(Expr::Invalid),
+                Task::Lam(ptr, hint, ty) => {
+                    let body = done.pop().expect("built body");
+                    done.push(Expr::Lam(Lam(ptr, Box::new(body), hint), ty));
+                }
+                Task::Fix(ptr, hint) => {
+                    let body = done.pop().expect("built body");
+                    done.push(Expr::Fix(Lam(ptr, Box::new(body), hint)));
+                }
+                Task::Let(ptr, hint) => {
+                    let body = done.pop().expect("built body");
+                    let v = done.pop().expect("built value");
+                    done.push(Expr::Let(Box::new(v), Lam(ptr, Box::new(body), hint)));
+                }
+                Task::App => {
+                    let v = done.pop().expect("built argument");
+                    let f = done.pop().expect("built function");
+                    done.push(make_app(f, v));
+                }
+                Task::Prim(op, n) => {
+                    let operands = done.split_off(done.len() - n);
+                    done.push(Expr::Prim(op, operands));
+                }
+                Task::If => {
+                    let e = done.pop().expect("built else branch");
+                    let t = done.pop().expect("built then branch");
+                    let c = done.pop().expect("built condition");
+                    done.push(make_if(c, t, e));
+                }
+                Task::Pair => {
+                    let b = done.pop().expect("built second component");
+                    let a = done.pop().expect("built first component");
+                    done.push(make_pair(a, b));
+                }
+                Task::Split(x, x_hint, y, y_hint) => {
+                    let body = done.pop().expect("built body");
+                    let v = done.pop().expect("built pair");
+                    let lam = Lam(y, Box::new(body), y_hint);
+                    done.push(Expr::Split(Box::new(v), x, x_hint, lam));
+                }
+                Task::Inj(left) => {
+                    let v = done.pop().expect("built injected value");
+                    done.push(if left { make_inl(v) } else { make_inr(v) });
+                }
+                Task::Case(x, x_hint, y, y_hint) => {
+                    let r = done.pop().expect("built right branch");
+                    let l = done.pop().expect("built left branch");
+                    let v = done.pop().expect("built scrutinee");
+                    let l = Lam(x, Box::new(l), x_hint);
+                    let r = Lam(y, Box::new(r), y_hint);
+                    done.push(Expr::Case(Box::new(v), l, r));
+                }
+            }
+        }
+        done.pop().expect("built term")
+    }
+}
+
+impl From<&Term> for Expr {
+    fn from(term: &Term) -> Self {
+        Expr::from_term(term)
+    }
+}
+
+impl From<&Expr> for Term {
+    fn from(e: &Expr) -> Self {
+        e.to_term()
+    }
+}
+
+/// Whether `a` and `b` are the same term up to renaming binders. The derived
+/// `PartialEq` compares cells by content, so it can't tell `\x. \y. x` from
+/// `\x. \y. y`; this compares variables by the lambdas they point to.
+pub fn alpha_eq(a: &Expr, b: &Expr) -> bool {
+    a.to_term().alpha_eq(&b.to_term())
+}
+
+/// Parses `src` with [`parse::parse`] into an expression whose variables
+/// share the cell of the lambda binding them.
+pub fn parse(src: &str) -> Result<Expr, ParseError> {
+    parse::parse(src).map(|term| Expr::from_term(&term))
+}
+
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_term())
+    }
+}
+
+/// Nodes and cells are keyed by address, so a cell shared by several variables
+/// is drawn once. Call-by-need thunks are drawn with their cell like values are.
+impl ToDot for Expr {
+    fn to_dot(&self) -> String {
+        let mut w = DotWriter::new();
+        let (id, _) = w.id(self as *const Expr as usize);
+        dot_walk(&mut w, self, id);
+        w.finish()
+    }
+}
+
+// draws e as node id, with the values in its cells hanging off them
+fn dot_walk(w: &mut DotWriter, e: &Expr, id: usize) {
+    let cells = Cells::from(e);
+    let slots = cells.borrow();
+    let mut todo = vec![(e, id)];
+    while let Some((e, id)) = todo.pop() {
+        match e {
+            Expr::Ptr(Ptr(rc)) => {
+                w.node(id, "var");
+                let cell = dot_cell(w, &slots, &mut todo, rc, "");
+                w.back_edge(id, cell);
+            }
+            Expr::Bas(c) => w.node(id, &c.to_string()),
+            Expr::Lam(Lam(Ptr(rc), body, hint), _) => {
+                let hint = hint.as_deref().unwrap_or("");
+                w.node(id, &format!("λ{hint}"));
+                let cell = dot_cell(w, &slots, &mut todo, rc, hint);
+                w.edge(id, cell, "arg");
+                let (body_id, _) = w.id(&**body as *const Expr as usize);
+                w.edge(id, body_id, "body");
+                todo.push((body, body_id));
+            }
+            Expr::App(f, v) => {
+                w.node(id, "@");
+                for (e, label) in [(&**f, "fun"), (&**v, "arg")] {
+                    let (child, _) = w.id(e as *const Expr as usize);
+                    w.edge(id, child, label);
+                    todo.push((e, child));
+                }
+            }
+            Expr::Let(v, Lam(Ptr(rc), body, hint)) => {
+                let hint = hint.as_deref().unwrap_or("");
+                w.node(id, &format!("let {hint}"));
+                let cell = dot_cell(w, &slots, &mut todo, rc, hint);
+                w.edge(id, cell, "arg");
+                for (e, label) in [(&**v, "value"), (&**body, "body")] {
+                    let (child, _) = w.id(e as *const Expr as usize);
+                    w.edge(id, child, label);
+                    todo.push((e, child));
+                }
+            }
+            Expr::Prim(op, operands) => {
+                w.node(id, op.name());
+                for (i, e) in operands.iter().enumerate() {
+                    let (child, _) = w.id(e as *const Expr as usize);
+                    w.edge(id, child, &i.to_string());
+                    todo.push((e, child));
+                }
+            }
+            Expr::If(c, t, e) => {
+                w.node(id, "if");
+                for (e, label) in [(&**c, "cond"), (&**t, "then"), (&**e, "else")] {
+                    let (child, _) = w.id(e as *const Expr as usize);
+                    w.edge(id, child, label);
+                    todo.push((e, child));
+                }
+            }
+            Expr::Pair(a, b) => {
+                w.node(id, "pair");
+                for (e, label) in [(&**a, "fst"), (&**b, "snd")] {
+                    let (child, _) = w.id(e as *const Expr as usize);
+                    w.edge(id, child, label);
+                    todo.push((e, child));
+                }
+            }
+            Expr::Split(v, Ptr(x), x_hint, Lam(Ptr(y), body, y_hint)) => {
+                let x_hint = x_hint.as_deref().unwrap_or("");
+                let y_hint = y_hint.as_deref().unwrap_or("");
+                w.node(id, &format!("let ({x_hint}, {y_hint})"));
+                for (rc, hint, label) in [(x, x_hint, "fst"), (y, y_hint, "snd")] {
+                    let cell = dot_cell(w, &slots, &mut todo, rc, hint);
+                    w.edge(id, cell, label);
+                }
+                for (e, label) in [(&**v, "value"), (&**body, "body")] {
+                    let (child, _) = w.id(e as *const Expr as usize);
+                    w.edge(id, child, label);
+                    todo.push((e, child));
+                }
+            }
+            Expr::Inl(v) | Expr::Inr(v) => {
+                w.node(
+                    id,
+                    if matches!(e, Expr::Inl(_)) {
+                        "inl"
+                    } else {
+                        "inr"
+                    },
+                );
+                let (child, _) = w.id(&**v as *const Expr as usize);
+                w.edge(id, child, "inj");
+                todo.push((v, child));
+            }
+            Expr::Case(v, Lam(Ptr(x), l, x_hint), Lam(Ptr(y), r, y_hint)) => {
+                w.node(id, "case");
+                let (child, _) = w.id(&**v as *const Expr as usize);
+                w.edge(id, child, "scrutinee");
+                todo.push((v, child));
+                for (rc, hint, e, label) in [(x, x_hint, l, "left"), (y, y_hint, r, "right")] {
+                    let cell = dot_cell(w, &slots, &mut todo, rc, hint.as_deref().unwrap_or(""));
+                    w.edge(id, cell, &format!("{label} arg"));
+                    let (child, _) = w.id(&**e as *const Expr as usize);
+                    w.edge(id, child, label);
+                    todo.push((e, child));
+                }
+            }
+            Expr::Fix(Lam(Ptr(rc), body, hint)) => {
+                let hint = hint.as_deref().unwrap_or("");
+                w.node(id, &format!("fix {hint}"));
+                let cell = dot_cell(w, &slots, &mut todo, rc, hint);
+                w.edge(id, cell, "arg");
+                let (body_id, _) = w.id(&**body as *const Expr as usize);
+                w.edge(id, body_id, "body");
+                todo.push((body, body_id));
+            }
+            // only ever inside the cell it points at, which is drawn already
+            Expr::Rec(Rec(weak)) => {
+                w.node(id, "var");
+                let (cell, _) = w.id(weak.as_ptr() as usize);
+                w.back_edge(id, cell);
+            }
+            Expr::Invalid => w.node(id, "invalid"),
+        }
+    }
+}
+
+// draws the cell once, with the value it holds as more of the walk in todo
+fn dot_cell<'a>(
+    w: &mut DotWriter,
+    slots: &'a Slots<'_>,
+    todo: &mut Vec<(&'a Expr, usize)>,
+    rc: &Rc<RefCell<Slot>>,
+    hint: &str,
+) -> usize {
+    let (id, fresh) = w.id(Rc::as_ptr(rc) as usize);
+    if fresh {
+        w.cell(id, hint);
+        if let Some(Slot::Thunk(e) | Slot::Value(e) | Slot::Rec(e, _)) =
+            slots.get(&Rc::as_ptr(rc)).map(|slot| &**slot)
+        {
+            let (value, _) = w.id(&**e as *const Expr as usize);
+            w.edge(id, value, "value");
+            todo.push((e, value));
+        }
+    }
+    id
+}
+
+// the slots of cells, borrowed all at once, by the cells' addresses
+type Slots<'a> = HashMap<*const RefCell<Slot>, Ref<'a, Slot>>;
+
+// The cells reachable from a term, through its variables and binders and the
+// values in other cells, so that their slots can all be borrowed at once and the
+// term walked with the values in place.
+struct Cells {
+    rcs: Vec<Rc<RefCell<Slot>>>,
+    // cells whose value leads back to the cell itself, which evaluation never
+    // makes but a hand-built term can
+    cyclic: HashSet<*const RefCell<Slot>>,
+}
+
+impl Cells {
+    fn from(e: &Expr) -> Self {
+        enum Visit {
+            Cell(Rc<RefCell<Slot>>),
+            // done with the value of this cell and every value it leads to
+            Leave(*const RefCell<Slot>),
+        }
+        let mut cells = Self {
+            rcs: Vec::new(),
+            cyclic: HashSet::new(),
+        };
+        let mut seen = HashSet::new();
+        // the cells whose values lead to the one being visited
+        let mut open = HashSet::new();
+        let mut todo = Vec::new();
+        cells_in(e, |rc| todo.push(Visit::Cell(rc.clone())));
+        while let Some(visit) = todo.pop() {
+            let rc = match visit {
+                Visit::Cell(rc) => rc,
+                Visit::Leave(key) => {
+                    open.remove(&key);
+                    continue;
+                }
+            };
+            let key = Rc::as_ptr(&rc);
+            if open.contains(&key) {
+                cells.cyclic.insert(key);
+                continue;
+            }
+            if !seen.insert(key) {
+                continue;
+            }
+            open.insert(key);
+            todo.push(Visit::Leave(key));
+            if let Slot::Thunk(e) | Slot::Value(e) | Slot::Rec(e, _) = &*rc.borrow() {
+                cells_in(e, |rc| todo.push(Visit::Cell(rc.clone())));
+            }
+            cells.rcs.push(rc);
+        }
+        cells
+    }
+    fn borrow(&self) -> Slots<'_> {
+        let slots = self.rcs.iter().map(|rc| (Rc::as_ptr(rc), rc.borrow()));
+        slots.collect()
+    }
+}
+
+// calls f with the cell of every variable and binder in e, without going into cells
+fn cells_in(e: &Expr, mut f: impl FnMut(&Rc<RefCell<Slot>>)) {
+    let mut todo = vec![e];
+    while let Some(e) = todo.pop() {
+        match e {
+            Expr::Ptr(Ptr(rc)) => f(rc),
+            Expr::Lam(Lam(Ptr(rc), body, _), _) | Expr::Fix(Lam(Ptr(rc), body, _)) => {
+                f(rc);
+                todo.push(body);
+            }
+            Expr::Let(v, Lam(Ptr(rc), body, _)) => {
+                f(rc);
+                todo.extend([&**v, &**body]);
+            }
+            Expr::Split(v, Ptr(x), _, Lam(Ptr(y), body, _)) => {
+                f(x);
+                f(y);
+                todo.extend([&**v, &**body]);
+            }
+            Expr::Case(v, Lam(Ptr(x), l, _), Lam(Ptr(y), r, _)) => {
+                f(x);
+                f(y);
+                todo.extend([&**v, &**l, &**r]);
+            }
+            Expr::App(a, b) | Expr::Pair(a, b) => todo.extend([&**a, &**b]),
+            Expr::Prim(_, operands) => todo.extend(operands),
+            Expr::If(c, t, e) => todo.extend([&**c, &**t, &**e]),
+            Expr::Inl(v) | Expr::Inr(v) => todo.push(v),
+            Expr::Rec(_) | Expr::Bas(_) | Expr::Invalid => {}
+        }
+    }
+}
+
+// reads e back with a variable that evaluation already bound, or that a
+// call-by-need thunk stands for, standing for its value
+fn read_back(e: &Expr) -> Term {
+    let cells = Cells::from(e);
+    let slots = cells.borrow();
+    ReadBack::new().run(e, |rb, mut e| {
+        while let Expr::Ptr(Ptr(rc)) = e {
+            let key = Rc::as_ptr(rc);
+            let slot = slots.get(&key).map(|slot| &**slot);
+            match slot {
+                _ if cells.cyclic.contains(&key) => return Shape::Leaf(rb.var(key)),
+                Some(Slot::Thunk(v) | Slot::Value(v)) => e = v,
+                // the variable of a fix that unfolded stands for the whole fix
+                Some(Slot::Rec(body, hint)) => {
+                    return Shape::Fix(rb.binder(key, hint.clone()), body)
+                }
+                Some(Slot::Unbound) | None => return Shape::Leaf(rb.var(key)),
+            }
+        }
+        match e {
+            Expr::Ptr(_) => unreachable!("followed above"),
+            Expr::Bas(c) => Shape::Leaf(Term::Const(c.clone())),
+            Expr::Lam(Lam(Ptr(rc), body, hint), ty) => {
+                let binder = rb.binder(Rc::as_ptr(rc), hint.clone());
+                let ty = ty.clone();
+                Shape::Lam(Binder { ty, ..binder }, body)
+            }
+            Expr::App(f, v) => Shape::App(f, v),
+            Expr::Let(v, Lam(Ptr(rc), body, hint)) => {
+                Shape::Let(rb.binder(Rc::as_ptr(rc), hint.clone()), v, body)
+            }
+            Expr::Prim(op, operands) => Shape::Prim(*op, operands.iter().collect()),
+            Expr::If(c, t, e) => Shape::If(c, t, e),
+            Expr::Pair(a, b) => Shape::Pair(a, b),
+            Expr::Split(v, Ptr(x), x_hint, Lam(Ptr(y), body, y_hint)) => {
+                let x = rb.binder(Rc::as_ptr(x), x_hint.clone());
+                let y = rb.binder(Rc::as_ptr(y), y_hint.clone());
+                Shape::Split(x, y, v, body)
+            }
+            Expr::Inl(v) => Shape::Inl(v),
+            Expr::Inr(v) => Shape::Inr(v),
+            Expr::Case(v, Lam(Ptr(x), l, x_hint), Lam(Ptr(y), r, y_hint)) => {
+                let x = rb.binder(Rc::as_ptr(x), x_hint.clone());
+                let y = rb.binder(Rc::as_ptr(y), y_hint.clone());
+                Shape::Case(v, x, l, y, r)
+            }
+            Expr::Fix(Lam(Ptr(rc), body, hint)) => {
+                Shape::Fix(rb.binder(Rc::as_ptr(rc), hint.clone()), body)
+            }
+            Expr::Rec(Rec(weak)) => Shape::Leaf(rb.var(weak.as_ptr())),
+            Expr::Invalid => Shape::Leaf(Term::Invalid),
+        }
+    })
+}
+
+pub const ZERO: Expr = Expr::Bas(constant::ZERO);
+pub const ONE: Expr = Expr::Bas(constant::ONE);
+pub const UNIT: Expr = Expr::Bas(constant::UNIT);
+
+// A zipper over the term being evaluated: the subterm in focus,
+// and the contexts it was taken out of with the innermost last
+struct Machine {
+    focus: Box<Expr>,
+    stack: Vec<Frame>,
+    strategy: Strategy,
+    fuel: usize,
+    max_depth: usize,
+    // nodes in the term, leaving out values bound in cells until a variable
+    // takes them out again
+    nodes: usize,
+}
+
+enum Frame {
+    // evaluating the function, the argument is parked here
+    Fun(Box<Expr>),
+    // evaluating the argument of this function value
+    Arg(Box<Expr>),
+    // forcing the thunk taken out of this variable's cell
+    Force(Rc<RefCell<Slot>>),
+    // normalizing the body of a lambda, whose binder and name are parked here
+    Body(Ptr, Option<Rc<str>>, Option<Type>),
+    // normalizing the function of a neutral application, one stuck on a variable
+    // that nothing binds, with the argument parked here
+    NeutralFun(Box<Expr>),
+    // normalizing the argument of a neutral application, with the function parked here
+    NeutralArg(Box<Expr>),
+    // evaluating the value of a let, whose binder and body are parked here
+    Bound(Lam),
+    // evaluating the operand at this index of a primitive, whose operands are
+    // parked here with Invalid in its place
+    Operand(PrimOp, Vec<Expr>, usize),
+    // evaluating the condition of an if, whose branches are parked here
+    Cond(Box<Expr>, Box<Expr>),
+    // normalizing the then branch of an if stuck on its condition, with the
+    // condition and the else branch parked here
+    NeutralThen(Box<Expr>, Box<Expr>),
+    // normalizing the else branch of an if stuck on its condition, with the
+    // condition and the then branch parked here
+    NeutralElse(Box<Expr>, Box<Expr>),
+    // evaluating the first component of a pair, with the second parked here
+    Fst(Box<Expr>),
+    // evaluating the second component of a pair, with the first parked here
+    Snd(Box<Expr>),
+    // evaluating what an injection injects, and whether it injects into the left
+    Inj(bool),
+    // evaluating the pair of a split, whose binders and body are parked here
+    Split(Ptr, Option<Rc<str>>, Lam),
+    // evaluating the scrutinee of a case, whose branches are parked here
+    Case(Lam, Lam),
+    // normalizing the body of a split stuck on its pair, with the pair and
+    // both binders parked here
+    NeutralSplit(Box<Expr>, Ptr, Option<Rc<str>>, Ptr, Option<Rc<str>>),
+    // normalizing the left branch of a case stuck on its scrutinee, with the
+    // scrutinee, the left binder and the right branch parked here
+    NeutralLeft(Box<Expr>, Ptr, Option<Rc<str>>, Lam),
+    // normalizing the right branch of a case stuck on its scrutinee, with the
+    // scrutinee, the left branch and the right binder parked here
+    NeutralRight(Box<Expr>, Lam, Ptr, Option<Rc<str>>),
+}
+
+impl Frame {
+    // whether the frame is one only normalizing pushes
+    fn normalizing(&self) -> bool {
+        matches!(
+            self,
+            Frame::Body(..)
+                | Frame::NeutralFun(_)
+                | Frame::NeutralArg(_)
+                | Frame::NeutralThen(..)
+                | Frame::NeutralElse(..)
+                | Frame::NeutralSplit(..)
+                | Frame::NeutralLeft(..)
+                | Frame::NeutralRight(..)
+        )
+    }
+}
+
+impl Machine {
+    fn new(e: Expr, strategy: Strategy, limits: &Limits) -> Self {
+        Self {
+            nodes: size(&e),
+            focus: Box::new(e),
+            stack: Vec::new(),
+            strategy,
+            fuel: limits.max_steps,
+            max_depth: limits.max_depth,
+        }
+    }
+    fn location(&self) -> Location {
+        let path = self.stack.iter().map(|frame| match frame {
+            Frame::Fun(_) | Frame::NeutralFun(_) => Branch::Fun,
+            Frame::Arg(_) | Frame::NeutralArg(_) => Branch::Arg,
+            Frame::Force(_) => Branch::Cell,
+            Frame::Body(..) => Branch::Body,
+            Frame::Bound(_) => Branch::Bound,
+            Frame::Operand(_, _, i) => Branch::Operand(*i),
+            Frame::Cond(..) => Branch::Cond,
+            Frame::NeutralThen(..) => Branch::Then,
+            Frame::NeutralElse(..) => Branch::Else,
+            Frame::Fst(_) => Branch::Fst,
+            Frame::Snd(_) => Branch::Snd,
+            Frame::Inj(_) => Branch::Inj,
+            Frame::Split(..) | Frame::Case(..) => Branch::Scrutinee,
+            Frame::NeutralSplit(..) => Branch::Body,
+            Frame::NeutralLeft(..) => Branch::Left,
+            Frame::NeutralRight(..) => Branch::Right,
+        });
+        Location::Path(path.collect())
+    }
+    // whether the value in focus is part of the result, rather than a function
+    // about to be applied, an argument about to be bound or a thunk being forced,
+    // unless arguments get normalized too; operands, components and scrutinees are
+    // normalized whenever evaluation is strong, since what is stuck on a variable
+    // is part of the result
+    fn in_result(&self) -> bool {
+        match self.stack.last() {
+            None => true,
+            Some(Frame::Arg(_) | Frame::Bound(_)) => self.strategy.normalizes_args(),
+            Some(
+                Frame::Operand(..)
+                | Frame::Cond(..)
+                | Frame::Fst(_)
+                | Frame::Snd(_)
+                | Frame::Inj(_)
+                | Frame::Split(..)
+                | Frame::Case(..),
+            ) => self.strategy.strong(),
+            Some(frame) => frame.normalizing(),
+        }
+    }
+    // whether the focus is taken apart by the split or case on top of the stack, so
+    // a pair or an injection is only normalized first if arguments are
+    fn taken_apart(&self) -> bool {
+        matches!(self.stack.last(), Some(Frame::Split(..) | Frame::Case(..)))
+            && !self.strategy.normalizes_args()
+    }
+    // puts the term back together around the focus, leaving the machine empty
+    fn plug(&mut self) -> Expr {
+        let mut e = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+        for frame in self.stack.drain(..).rev() {
+            e = Box::new(match frame {
+                Frame::Fun(v) => Expr::App(e, v),
+                Frame::Arg(f) => Expr::App(f, e),
+                Frame::Force(rc) => {
+                    *rc.borrow_mut() = Slot::Thunk(e);
+                    Expr::Ptr(Ptr(rc))
+                }
+                Frame::Body(ptr, hint, ty) => Expr::Lam(Lam(ptr, e, hint), ty),
+                Frame::NeutralFun(v) => Expr::App(e, v),
+                Frame::NeutralArg(f) => Expr::App(f, e),
+                Frame::Bound(lam) => Expr::Let(e, lam),
+                Frame::Operand(op, mut operands, i) => {
+                    operands[i] = *e;
+                    Expr::Prim(op, operands)
+                }
+                Frame::Cond(t, f) => Expr::If(e, t, f),
+                Frame::NeutralThen(c, f) => Expr::If(c, e, f),
+                Frame::NeutralElse(c, t) => Expr::If(c, t, e),
+                Frame::Fst(b) => Expr::Pair(e, b),
+                Frame::Snd(a) => Expr::Pair(a, e),
+                Frame::Inj(true) => Expr::Inl(e),
+                Frame::Inj(false) => Expr::Inr(e),
+                Frame::Split(x, x_hint, lam) => Expr::Split(e, x, x_hint, lam),
+                Frame::Case(l, r) => Expr::Case(e, l, r),
+                Frame::NeutralSplit(v, x, x_hint, y, y_hint) => {
+                    Expr::Split(v, x, x_hint, Lam(y, e, y_hint))
+                }
+                Frame::NeutralLeft(v, x, x_hint, r) => Expr::Case(v, Lam(x, e, x_hint), r),
+                Frame::NeutralRight(v, l, y, y_hint) => Expr::Case(v, l, Lam(y, e, y_hint)),
+            });
+        }
+        *e
+    }
+    // takes a term plugged by plug apart again along the path it was plugged up,
+    // with whether each frame on it was for normalizing
+    fn unplug(&mut self, e: Expr, path: &[Branch], normalizing: &[bool]) {
+        let mut e = Box::new(e);
+        for (depth, branch) in path.iter().enumerate() {
+            e = match (branch, *e) {
+                (Branch::Fun, Expr::App(f, v)) if normalizing[depth] => {
+                    self.stack.push(Frame::NeutralFun(v));
+                    f
+                }
+                (Branch::Arg, Expr::App(f, v)) if normalizing[depth] => {
+                    self.stack.push(Frame::NeutralArg(f));
+                    v
+                }
+                (Branch::Fun, Expr::App(f, v)) => {
+                    self.stack.push(Frame::Fun(v));
+                    f
+                }
+                (Branch::Arg, Expr::App(f, v)) => {
+                    self.stack.push(Frame::Arg(f));
+                    v
+                }
+                (Branch::Body, Expr::Lam(Lam(ptr, body, hint), ty)) => {
+                    self.stack.push(Frame::Body(ptr, hint, ty));
+                    body
+                }
+                (Branch::Bound, Expr::Let(v, lam)) => {
+                    self.stack.push(Frame::Bound(lam));
+                    v
+                }
+                (&Branch::Operand(i), Expr::Prim(op, mut operands)) => {
+                    let operand = std::mem::replace(&mut operands[i], Expr::Invalid);
+                    self.stack.push(Frame::Operand(op, operands, i));
+                    Box::new(operand)
+                }
+                (Branch::Cond, Expr::If(c, t, e)) => {
+                    self.stack.push(Frame::Cond(t, e));
+                    c
+                }
+                (Branch::Then, Expr::If(c, t, e)) => {
+                    self.stack.push(Frame::NeutralThen(c, e));
+                    t
+                }
+                (Branch::Else, Expr::If(c, t, e)) => {
+                    self.stack.push(Frame::NeutralElse(c, t));
+                    e
+                }
+                (Branch::Fst, Expr::Pair(a, b)) => {
+                    self.stack.push(Frame::Fst(b));
+                    a
+                }
+                (Branch::Snd, Expr::Pair(a, b)) => {
+                    self.stack.push(Frame::Snd(a));
+                    b
+                }
+                (Branch::Inj, Expr::Inl(v)) => {
+                    self.stack.push(Frame::Inj(true));
+                    v
+                }
+                (Branch::Inj, Expr::Inr(v)) => {
+                    self.stack.push(Frame::Inj(false));
+                    v
+                }
+                (Branch::Scrutinee, Expr::Split(v, x, x_hint, lam)) => {
+                    self.stack.push(Frame::Split(x, x_hint, lam));
+                    v
+                }
+                (Branch::Scrutinee, Expr::Case(v, l, r)) => {
+                    self.stack.push(Frame::Case(l, r));
+                    v
+                }
+                (Branch::Body, Expr::Split(v, x, x_hint, Lam(y, body, y_hint))) => {
+                    self.stack
+                        .push(Frame::NeutralSplit(v, x, x_hint, y, y_hint));
+                    body
+                }
+                (Branch::Left, Expr::Case(v, Lam(x, l, x_hint), r)) => {
+                    self.stack.push(Frame::NeutralLeft(v, x, x_hint, r));
+                    l
+                }
+                (Branch::Right, Expr::Case(v, l, Lam(y, r, y_hint))) => {
+                    self.stack.push(Frame::NeutralRight(v, l, y, y_hint));
+                    r
+                }
+                (Branch::Cell, Expr::Ptr(Ptr(rc))) => {
+                    let Slot::Thunk(thunk) =
+                        std::mem::replace(&mut *rc.borrow_mut(), Slot::Unbound)
+                    else {
+                        unreachable!("plug put the thunk back");
+                    };
+                    self.stack.push(Frame::Force(rc));
+                    thunk
+                }
+                _ => unreachable!("the term was plugged along this path"),
             };
-            **e = *deref;
-            true
         }
-        Expr::App(mut f, mut v) => {
-            if step(&mut f) || step(&mut v) {
-                **e = Expr::App(f, v);
-                true
-            } else if let Expr::Lam(Lam(arg, body)) = *f {
-                let _ = arg.0.set(v);
-                **e = *body;
-                true
-            } else {
-                // we're totally stuck, replace with the old app
-                **e = Expr::App(f, v);
-                false
+        self.focus = e;
+    }
+    // shows the observer the whole term, then goes back to where evaluation was
+    fn observe_step(&mut self, obs: &mut dyn EvalObserver<Expr>) {
+        let Location::Path(path) = self.location() else {
+            unreachable!("heap terms are located by path");
+        };
+        let normalizing: Vec<bool> = self.stack.iter().map(Frame::normalizing).collect();
+        let term = self.plug();
+        obs.on_step(&term);
+        self.unplug(term, &path, &normalizing);
+    }
+    // spends the fuel for one reduction, if there is any left
+    fn burn(&mut self) -> bool {
+        let has_fuel = self.fuel > 0;
+        self.fuel = self.fuel.saturating_sub(1);
+        has_fuel
+    }
+    fn push(&mut self, frame: Frame) -> Option<Progress> {
+        self.stack.push(frame);
+        (self.stack.len() > self.max_depth).then_some(Progress::Exhausted(Exhausted::Depth))
+    }
+    /// Runs up to and including the next reduction, staying focused on the reduced
+    /// subterm so the next call picks up from there.
+    fn step(&mut self, obs: &mut dyn EvalObserver<Expr>) -> Result<Progress, EvalError> {
+        let out_of_steps = Ok(Progress::Exhausted(Exhausted::Steps));
+        loop {
+            match std::mem::replace(self.focus.as_mut(), Expr::Invalid) {
+                Expr::Invalid => return Err(EvalError::Uninitialized(self.location())),
+                Expr::Ptr(Ptr(rc)) => {
+                    let slot = std::mem::replace(&mut *rc.borrow_mut(), Slot::Unbound);
+                    match slot {
+                        // when normalizing, a variable nothing binds is a value
+                        Slot::Unbound if self.strategy.strong() => {
+                            *self.focus = Expr::Ptr(Ptr(rc));
+                            if let Some(progress) = self.ret(obs)? {
+                                return Ok(progress);
+                            }
+                        }
+                        Slot::Unbound => return Err(EvalError::DanglingPointer(self.location())),
+                        Slot::Thunk(thunk) => {
+                            // force the shared argument, leaving its cell empty meanwhile
+                            self.nodes += size(&thunk);
+                            self.focus = thunk;
+                            if let Some(exhausted) = self.push(Frame::Force(rc)) {
+                                return Ok(exhausted);
+                            }
+                        }
+                        value @ (Slot::Value(_) | Slot::Rec(..)) => {
+                            *rc.borrow_mut() = value;
+                            if !self.burn() {
+                                *self.focus = Expr::Ptr(Ptr(rc));
+                                return out_of_steps;
+                            }
+                            *self.focus = self.deref(rc, obs);
+                            return Ok(Progress::Reduced);
+                        }
+                    }
+                }
+                Expr::App(f, v) => {
+                    self.focus = f;
+                    if let Some(exhausted) = self.push(Frame::Fun(v)) {
+                        return Ok(exhausted);
+                    }
+                }
+                Expr::Let(v, lam) if self.strategy.by_name() => {
+                    if !self.burn() {
+                        *self.focus = Expr::Let(v, lam);
+                        return out_of_steps;
+                    }
+                    self.bind_let(v, lam, obs);
+                    return Ok(Progress::Reduced);
+                }
+                Expr::Let(v, lam) => {
+                    self.focus = v;
+                    if let Some(exhausted) = self.push(Frame::Bound(lam)) {
+                        return Ok(exhausted);
+                    }
+                }
+                Expr::If(c, t, e) => {
+                    self.focus = c;
+                    if let Some(exhausted) = self.push(Frame::Cond(t, e)) {
+                        return Ok(exhausted);
+                    }
+                }
+                // unless arguments are bound unevaluated, a pair or an injection is
+                // only a value once what it holds is
+                Expr::Pair(a, b) if !self.strategy.by_name() => {
+                    self.focus = a;
+                    if let Some(exhausted) = self.push(Frame::Fst(b)) {
+                        return Ok(exhausted);
+                    }
+                }
+                Expr::Inl(v) if !self.strategy.by_name() => {
+                    self.focus = v;
+                    if let Some(exhausted) = self.push(Frame::Inj(true)) {
+                        return Ok(exhausted);
+                    }
+                }
+                Expr::Inr(v) if !self.strategy.by_name() => {
+                    self.focus = v;
+                    if let Some(exhausted) = self.push(Frame::Inj(false)) {
+                        return Ok(exhausted);
+                    }
+                }
+                Expr::Split(v, x, x_hint, lam) => {
+                    self.focus = v;
+                    if let Some(exhausted) = self.push(Frame::Split(x, x_hint, lam)) {
+                        return Ok(exhausted);
+                    }
+                }
+                Expr::Case(v, l, r) => {
+                    self.focus = v;
+                    if let Some(exhausted) = self.push(Frame::Case(l, r)) {
+                        return Ok(exhausted);
+                    }
+                }
+                Expr::Fix(lam) => {
+                    if !self.burn() {
+                        *self.focus = Expr::Fix(lam);
+                        return out_of_steps;
+                    }
+                    self.unfold(lam, obs);
+                    return Ok(Progress::Reduced);
+                }
+                Expr::Rec(_) => unreachable!("back pointers only live in the cell they point at"),
+                Expr::Prim(op, mut operands) => match operands.first_mut() {
+                    Some(first) => {
+                        *self.focus = std::mem::replace(first, Expr::Invalid);
+                        if let Some(exhausted) = self.push(Frame::Operand(op, operands, 0)) {
+                            return Ok(exhausted);
+                        }
+                    }
+                    None => {
+                        let progress = self.apply_prim(op, operands, obs)?;
+                        return Ok(progress.expect("no operands to be stuck on"));
+                    }
+                },
+                value @ (Expr::Bas(_)
+                | Expr::Lam(..)
+                | Expr::Pair(..)
+                | Expr::Inl(_)
+                | Expr::Inr(_)) => {
+                    *self.focus = value;
+                    if let Some(progress) = self.ret(obs)? {
+                        return Ok(progress);
+                    }
+                }
             }
         }
     }
+    // Hands the value in focus to the frames below it, until there is a
+    // reduction to report or another subterm to evaluate, which is left in focus.
+    fn ret(&mut self, obs: &mut dyn EvalObserver<Expr>) -> Result<Option<Progress>, EvalError> {
+        let out_of_steps = Ok(Some(Progress::Exhausted(Exhausted::Steps)));
+        // whether the focus is already normalized, so it is not gone into again
+        let mut normal = false;
+        loop {
+            if self.strategy.strong() && !normal && self.in_result() {
+                match std::mem::replace(self.focus.as_mut(), Expr::Invalid) {
+                    Expr::Lam(Lam(ptr, body, hint), ty) => {
+                        self.focus = body;
+                        return Ok(self.push(Frame::Body(ptr, hint, ty)));
+                    }
+                    // the function of a neutral application is already a value
+                    Expr::App(f, v) => {
+                        self.focus = f;
+                        if let Some(exhausted) = self.push(Frame::NeutralFun(v)) {
+                            return Ok(Some(exhausted));
+                        }
+                        continue;
+                    }
+                    // the components of a pair and what an injection injects, unless
+                    // they were evaluated before it was a value or it is taken apart
+                    Expr::Pair(a, b) if !self.taken_apart() => {
+                        self.focus = a;
+                        return Ok(self.push(Frame::Fst(b)));
+                    }
+                    Expr::Inl(v) if !self.taken_apart() => {
+                        self.focus = v;
+                        return Ok(self.push(Frame::Inj(true)));
+                    }
+                    Expr::Inr(v) if !self.taken_apart() => {
+                        self.focus = v;
+                        return Ok(self.push(Frame::Inj(false)));
+                    }
+                    // constants and variables that nothing binds
+                    value => *self.focus = value,
+                }
+            }
+            match self.stack.pop() {
+                None => return Ok(Some(Progress::Finished)),
+                Some(Frame::Fun(v))
+                    if self.strategy.by_name()
+                        && self.strategy.strong()
+                        && is_neutral(&self.focus) =>
+                {
+                    let f = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    (*self.focus, normal) = (Expr::App(f, v), false);
+                }
+                Some(Frame::Fun(v)) if self.strategy.by_name() => {
+                    if !self.burn() {
+                        self.stack.push(Frame::Fun(v));
+                        return out_of_steps;
+                    }
+                    let f = std::mem::replace(&mut self.focus, v);
+                    return self.beta(*f, obs).map(Some);
+                }
+                Some(Frame::Fun(v)) => {
+                    let f = std::mem::replace(&mut self.focus, v);
+                    self.stack.push(Frame::Arg(f));
+                    return Ok(None);
+                }
+                Some(Frame::Arg(f)) if self.strategy.strong() && is_neutral(&f) => {
+                    let v = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    (*self.focus, normal) = (Expr::App(f, v), false);
+                }
+                Some(Frame::Arg(f)) => {
+                    if !self.burn() {
+                        self.stack.push(Frame::Arg(f));
+                        return out_of_steps;
+                    }
+                    return self.beta(*f, obs).map(Some);
+                }
+                Some(Frame::Force(rc)) => {
+                    if !self.burn() {
+                        self.stack.push(Frame::Force(rc));
+                        return out_of_steps;
+                    }
+                    let value = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    self.nodes -= size(&value);
+                    *rc.borrow_mut() = Slot::Value(value);
+                    *self.focus = self.deref(rc, obs);
+                    return Ok(Some(Progress::Reduced));
+                }
+                Some(Frame::Body(ptr, hint, ty)) => {
+                    let body = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    (*self.focus, normal) = (Expr::Lam(Lam(ptr, body, hint), ty), true);
+                }
+                Some(Frame::NeutralFun(v)) => {
+                    let f = std::mem::replace(&mut self.focus, v);
+                    self.stack.push(Frame::NeutralArg(f));
+                    return Ok(None);
+                }
+                Some(Frame::NeutralArg(f)) => {
+                    let v = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    (*self.focus, normal) = (Expr::App(f, v), true);
+                }
+                Some(Frame::Bound(lam)) => {
+                    if !self.burn() {
+                        self.stack.push(Frame::Bound(lam));
+                        return out_of_steps;
+                    }
+                    let v = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    self.bind_let(v, lam, obs);
+                    return Ok(Some(Progress::Reduced));
+                }
+                Some(Frame::Operand(op, mut operands, i)) => {
+                    operands[i] = std::mem::replace(self.focus.as_mut(), Expr::Invalid);
+                    if let Some(next) = operands.get_mut(i + 1) {
+                        *self.focus = std::mem::replace(next, Expr::Invalid);
+                        self.stack.push(Frame::Operand(op, operands, i + 1));
+                        return Ok(None);
+                    }
+                    if let Some(progress) = self.apply_prim(op, operands, obs)? {
+                        return Ok(Some(progress));
+                    }
+                    normal = true;
+                }
+                Some(Frame::Cond(t, e)) if matches!(*self.focus, Expr::Bas(Const::Bool(_))) => {
+                    if !self.burn() {
+                        self.stack.push(Frame::Cond(t, e));
+                        return Ok(Some(Progress::Exhausted(Exhausted::Steps)));
+                    }
+                    self.choose(t, e, obs);
+                    return Ok(Some(Progress::Reduced));
+                }
+                // when normalizing, an if stuck on its condition is a value
+                // once both branches are normalized
+                Some(Frame::Cond(t, e)) if self.strategy.strong() && is_neutral(&self.focus) => {
+                    let c = std::mem::replace(&mut self.focus, t);
+                    self.stack.push(Frame::NeutralThen(c, e));
+                    return Ok(None);
+                }
+                Some(Frame::Cond(..)) => return Err(EvalError::BadCondition(self.location())),
+                Some(Frame::NeutralThen(c, e)) => {
+                    let t = std::mem::replace(&mut self.focus, e);
+                    self.stack.push(Frame::NeutralElse(c, t));
+                    return Ok(None);
+                }
+                Some(Frame::NeutralElse(c, t)) => {
+                    let e = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    (*self.focus, normal) = (Expr::If(c, t, e), true);
+                }
+                Some(Frame::Fst(b)) => {
+                    let a = std::mem::replace(&mut self.focus, b);
+                    self.stack.push(Frame::Snd(a));
+                    return Ok(None);
+                }
+                Some(Frame::Snd(a)) => {
+                    let b = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    (*self.focus, normal) = (Expr::Pair(a, b), true);
+                }
+                Some(Frame::Inj(left)) => {
+                    let v = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    let inj = if left { Expr::Inl(v) } else { Expr::Inr(v) };
+                    (*self.focus, normal) = (inj, true);
+                }
+                Some(Frame::Split(x, x_hint, lam)) if matches!(*self.focus, Expr::Pair(..)) => {
+                    if !self.burn() {
+                        self.stack.push(Frame::Split(x, x_hint, lam));
+                        return out_of_steps;
+                    }
+                    self.split(x, x_hint, lam, obs);
+                    return Ok(Some(Progress::Reduced));
+                }
+                // when normalizing, a split stuck on its pair is a value once its
+                // body is normalized
+                Some(Frame::Split(x, x_hint, Lam(y, body, y_hint)))
+                    if self.strategy.strong() && is_neutral(&self.focus) =>
+                {
+                    let v = std::mem::replace(&mut self.focus, body);
+                    self.stack
+                        .push(Frame::NeutralSplit(v, x, x_hint, y, y_hint));
+                    return Ok(None);
+                }
+                Some(Frame::Case(l, r)) if matches!(*self.focus, Expr::Inl(_) | Expr::Inr(_)) => {
+                    if !self.burn() {
+                        self.stack.push(Frame::Case(l, r));
+                        return out_of_steps;
+                    }
+                    self.pick(l, r, obs);
+                    return Ok(Some(Progress::Reduced));
+                }
+                // when normalizing, a case stuck on its scrutinee is a value once
+                // both branches are normalized
+                Some(Frame::Case(Lam(x, l, x_hint), r))
+                    if self.strategy.strong() && is_neutral(&self.focus) =>
+                {
+                    let v = std::mem::replace(&mut self.focus, l);
+                    self.stack.push(Frame::NeutralLeft(v, x, x_hint, r));
+                    return Ok(None);
+                }
+                Some(Frame::Split(..) | Frame::Case(..)) => {
+                    return Err(EvalError::BadScrutinee(self.location()))
+                }
+                Some(Frame::NeutralSplit(v, x, x_hint, y, y_hint)) => {
+                    let body = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    let split = Expr::Split(v, x, x_hint, Lam(y, body, y_hint));
+                    (*self.focus, normal) = (split, true);
+                }
+                Some(Frame::NeutralLeft(v, x, x_hint, Lam(y, r, y_hint))) => {
+                    let l = std::mem::replace(&mut self.focus, r);
+                    let l = Lam(x, l, x_hint);
+                    self.stack.push(Frame::NeutralRight(v, l, y, y_hint));
+                    return Ok(None);
+                }
+                Some(Frame::NeutralRight(v, l, y, y_hint)) => {
+                    let r = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    (*self.focus, normal) = (Expr::Case(v, l, Lam(y, r, y_hint)), true);
+                }
+            }
+        }
+    }
+    // replaces the split, whose pair is in focus, with its body, binding its
+    // variables to the components
+    fn split(
+        &mut self,
+        x: Ptr,
+        x_hint: Option<Rc<str>>,
+        lam: Lam,
+        obs: &mut dyn EvalObserver<Expr>,
+    ) {
+        let v = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+        let split = Expr::Split(v, x, x_hint, lam);
+        obs.on_match(&split);
+        let Expr::Split(v, x, _, Lam(y, body, _)) = split else {
+            unreachable!("just built");
+        };
+        let Expr::Pair(a, b) = *v else {
+            unreachable!("checked by ret");
+        };
+        self.bind(x, a);
+        self.bind(y, b);
+        self.focus = body;
+        // the split and the pair are gone
+        self.nodes -= 2;
+    }
+    // replaces the case, whose scrutinee in focus is an injection, with the
+    // branch it picks, binding that branch's variable to what it injects
+    fn pick(&mut self, l: Lam, r: Lam, obs: &mut dyn EvalObserver<Expr>) {
+        let v = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+        let case = Expr::Case(v, l, r);
+        obs.on_match(&case);
+        let Expr::Case(v, l, r) = case else {
+            unreachable!("just built");
+        };
+        let (v, Lam(arg, picked, _), Lam(_, untaken, _)) = match *v {
+            Expr::Inl(v) => (v, l, r),
+            Expr::Inr(v) => (v, r, l),
+            _ => unreachable!("checked by ret"),
+        };
+        self.bind(arg, v);
+        // the case, the injection and the untaken branch are gone
+        self.nodes -= 2 + size(&untaken);
+        self.focus = picked;
+    }
+    // replaces the if, whose condition in focus is a boolean, with the branch it picks
+    fn choose(&mut self, t: Box<Expr>, e: Box<Expr>, obs: &mut dyn EvalObserver<Expr>) {
+        let c = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+        let branch = Expr::If(c, t, e);
+        obs.on_if(&branch);
+        let Expr::If(c, t, e) = branch else {
+            unreachable!("just built");
+        };
+        let (picked, untaken) = match *c {
+            Expr::Bas(Const::Bool(true)) => (t, e),
+            _ => (e, t),
+        };
+        // the if, its condition and the untaken branch are gone
+        self.nodes -= 2 + size(&untaken);
+        self.focus = picked;
+    }
+    // Replaces the primitive, whose operands are all evaluated, with its result.
+    // When normalizing, a primitive stuck on a variable is left in focus as a
+    // value instead, and None returned.
+    fn apply_prim(
+        &mut self,
+        op: PrimOp,
+        operands: Vec<Expr>,
+        obs: &mut dyn EvalObserver<Expr>,
+    ) -> Result<Option<Progress>, EvalError> {
+        let constants: Option<Vec<&Const>> = operands
+            .iter()
+            .map(|e| match e {
+                Expr::Bas(c) => Some(c),
+                _ => None,
+            })
+            .collect();
+        let Some(constants) = constants else {
+            let neutral = |e: &Expr| matches!(e, Expr::Bas(_)) || is_neutral(e);
+            if self.strategy.strong() && operands.iter().all(neutral) {
+                *self.focus = Expr::Prim(op, operands);
+                return Ok(None);
+            }
+            return Err(EvalError::BadPrim(self.location()));
+        };
+        let Some(result) = op.apply(&constants) else {
+            return Err(EvalError::BadPrim(self.location()));
+        };
+        let prim = Expr::Prim(op, operands);
+        // the operands are constants, so evaluating them again costs no fuel
+        if !self.burn() {
+            *self.focus = prim;
+            return Ok(Some(Progress::Exhausted(Exhausted::Steps)));
+        }
+        obs.on_prim(&prim);
+        let Expr::Prim(_, operands) = prim else {
+            unreachable!("just built");
+        };
+        *self.focus = Expr::Bas(result);
+        self.nodes -= operands.len();
+        Ok(Some(Progress::Reduced))
+    }
+    // applies f to the argument in focus
+    fn beta(&mut self, f: Expr, obs: &mut dyn EvalObserver<Expr>) -> Result<Progress, EvalError> {
+        if !matches!(f, Expr::Lam(..)) {
+            return Err(EvalError::Stuck(self.location()));
+        }
+        obs.on_beta(&f, &self.focus);
+        let Expr::Lam(Lam(arg, body, _), _) = f else {
+            unreachable!("checked above");
+        };
+        let v = std::mem::replace(&mut self.focus, body);
+        self.bind(arg, v);
+        // the application and the lambda are gone
+        self.nodes -= 2;
+        Ok(Progress::Reduced)
+    }
+    // binds the variable of the let to v and focuses on its body
+    fn bind_let(&mut self, v: Box<Expr>, lam: Lam, obs: &mut dyn EvalObserver<Expr>) {
+        let binding = Expr::Let(v, lam);
+        obs.on_let(&binding);
+        let Expr::Let(v, Lam(arg, body, _)) = binding else {
+            unreachable!("just built");
+        };
+        self.bind(arg, v);
+        self.focus = body;
+        // the let is gone
+        self.nodes -= 1;
+    }
+    fn bind(&mut self, arg: Ptr, v: Box<Expr>) {
+        self.nodes -= size(&v);
+        *arg.0.borrow_mut() = if self.strategy.shares() {
+            Slot::Thunk(v)
+        } else {
+            Slot::Value(v)
+        };
+    }
+    // Replaces the fix with its body and binds its variable to the body too, with
+    // the uses of the variable in the bound body pointing back at the cell weakly:
+    // the cell lives as long as a use outside of it does, then goes with its body.
+    fn unfold(&mut self, lam: Lam, obs: &mut dyn EvalObserver<Expr>) {
+        let fix = Expr::Fix(lam);
+        obs.on_unfold(&fix);
+        let Expr::Fix(Lam(Ptr(rc), mut body, hint)) = fix else {
+            unreachable!("just built");
+        };
+        refer_back(&mut body, &rc);
+        *self.focus = copy(&body);
+        // the fix is gone, and its body is both in focus and in the cell
+        self.nodes += size(&body) - 1;
+        *rc.borrow_mut() = Slot::Rec(body, hint);
+    }
+    // The last use of a variable takes the value out of its cell,
+    // every other use gets its own copy
+    fn deref(&mut self, rc: Rc<RefCell<Slot>>, obs: &mut dyn EvalObserver<Expr>) -> Expr {
+        let ptr = Expr::Ptr(Ptr(rc));
+        obs.on_deref(&ptr);
+        let Expr::Ptr(Ptr(rc)) = ptr else {
+            unreachable!("just built");
+        };
+        self.nodes -= 1;
+        // the body of a fix is never taken out, since what it refers back to is
+        // the cell; its copy points at the cell strongly, keeping it alive
+        if let Slot::Rec(body, _) = &*rc.borrow() {
+            self.nodes += size(body);
+            return copy(body);
+        }
+        match Rc::try_unwrap(rc) {
+            Ok(cell) => {
+                let Slot::Value(value) = cell.into_inner() else {
+                    unreachable!("Deref can't happen before beta reduction");
+                };
+                self.nodes += size(&value);
+                *value
+            }
+            Err(rc) => {
+                let Slot::Value(value) = &*rc.borrow() else {
+                    unreachable!("Deref can't happen before beta reduction");
+                };
+                self.nodes += size(value);
+                copy(value)
+            }
+        }
+    }
+}
+
+// Whether a value is stuck on a variable nothing binds, which only normalizing allows
+fn is_neutral(e: &Expr) -> bool {
+    matches!(
+        e,
+        Expr::Ptr(_)
+            | Expr::App(..)
+            | Expr::Prim(..)
+            | Expr::If(..)
+            | Expr::Split(..)
+            | Expr::Case(..)
+    )
 }
 
-pub fn eval(e: Expr) -> Expr {
-    let mut e = Box::new(e);
-    println!("eval {e:?}");
-    while step(&mut e) {
-        println!("step {e:?}");
+fn size(e: &Expr) -> usize {
+    let mut nodes = 0;
+    let mut todo = vec![e];
+    while let Some(e) = todo.pop() {
+        nodes += 1;
+        match e {
+            Expr::Lam(Lam(_, body, _), _) | Expr::Fix(Lam(_, body, _)) => todo.push(body),
+            Expr::App(f, v) | Expr::Let(f, Lam(_, v, _)) => todo.extend([&**f, &**v]),
+            Expr::Prim(_, operands) => todo.extend(operands),
+            Expr::If(c, t, e) => todo.extend([&**c, &**t, &**e]),
+            Expr::Pair(a, b) | Expr::Split(a, _, _, Lam(_, b, _)) => todo.extend([&**a, &**b]),
+            Expr::Inl(v) | Expr::Inr(v) => todo.push(v),
+            Expr::Case(v, Lam(_, l, _), Lam(_, r, _)) => todo.extend([&**v, &**l, &**r]),
+            Expr::Ptr(_) | Expr::Rec(_) | Expr::Bas(_) | Expr::Invalid => {}
+        }
     }
-    println!("Result: {e:?}");
-    *e
+    nodes
+}
+
+// Turns the uses of the variable whose cell is `cell` in `e` into back pointers
+fn refer_back(e: &mut Expr, cell: &Rc<RefCell<Slot>>) {
+    let mut todo = vec![e];
+    while let Some(e) = todo.pop() {
+        match e {
+            Expr::Ptr(Ptr(rc)) if Rc::ptr_eq(rc, cell) => {
+                *e = Expr::Rec(Rec(Rc::downgrade(cell)));
+            }
+            Expr::Lam(Lam(_, body, _), _) | Expr::Fix(Lam(_, body, _)) => todo.push(body),
+            Expr::App(f, v) | Expr::Let(f, Lam(_, v, _)) => todo.extend([&mut **f, &mut **v]),
+            Expr::Prim(_, operands) => todo.extend(operands),
+            Expr::If(c, t, e) => todo.extend([&mut **c, &mut **t, &mut **e]),
+            Expr::Pair(a, b) | Expr::Split(a, _, _, Lam(_, b, _)) => {
+                todo.extend([&mut **a, &mut **b])
+            }
+            Expr::Inl(v) | Expr::Inr(v) => todo.push(v),
+            Expr::Case(v, Lam(_, l, _), Lam(_, r, _)) => {
+                todo.extend([&mut **v, &mut **l, &mut **r])
+            }
+            Expr::Ptr(_) | Expr::Rec(_) | Expr::Bas(_) | Expr::Invalid => {}
+        }
+    }
+}
+
+// Binders inside the copied expression get fresh cells,
+// pointers to binders outside of it keep sharing the same cell,
+// and back pointers become pointers that keep their cell alive
+fn copy(e: &Expr) -> Expr {
+    enum Task<'a> {
+        Copy(&'a Expr),
+        App,
+        Lam(Ptr, Option<Rc<str>>, Option<Type>),
+        Let(Ptr, Option<Rc<str>>),
+        Prim(PrimOp, usize),
+        If,
+        Pair,
+        Split(Ptr, Option<Rc<str>>, Ptr, Option<Rc<str>>),
+        Inj(bool),
+        Case(Ptr, Option<Rc<str>>, Ptr, Option<Rc<str>>),
+        Fix(Ptr, Option<Rc<str>>),
+    }
+    let mut renamed: HashMap<*const RefCell<Slot>, Rc<RefCell<Slot>>> = HashMap::new();
+    let mut todo = vec![Task::Copy(e)];
+    let mut done = Vec::new();
+    while let Some(task) = todo.pop() {
+        match task {
+            Task::Copy(Expr::Ptr(Ptr(rc))) => {
+                let target = renamed.get(&Rc::as_ptr(rc)).unwrap_or(rc);
+                done.push(Expr::Ptr(Ptr(Rc::clone(target))));
+            }
+            Task::Copy(Expr::Bas(c)) => done.push(Expr::Bas(c.clone())),
+            Task::Copy(Expr::Lam(Lam(Ptr(rc), body, hint), ty)) => {
+                let fresh = Rc::new(RefCell::new(Slot::Unbound));
+                renamed.insert(Rc::as_ptr(rc), Rc::clone(&fresh));
+                todo.push(Task::Lam(Ptr(fresh), hint.clone(), ty.clone()));
+                todo.push(Task::Copy(body));
+            }
+            Task::Copy(Expr::App(f, v)) => {
+                todo.push(Task::App);
+                todo.push(Task::Copy(v));
+                todo.push(Task::Copy(f));
+            }
+            Task::Copy(Expr::Let(v, Lam(Ptr(rc), body, hint))) => {
+                let fresh = Rc::new(RefCell::new(Slot::Unbound));
+                renamed.insert(Rc::as_ptr(rc), Rc::clone(&fresh));
+                todo.push(Task::Let(Ptr(fresh), hint.clone()));
+                todo.push(Task::Copy(body));
+                todo.push(Task::Copy(v));
+            }
+            Task::Copy(Expr::Prim(op, operands)) => {
+                todo.push(Task::Prim(*op, operands.len()));
+                todo.extend(operands.iter().rev().map(Task::Copy));
+            }
+            Task::Copy(Expr::If(c, t, e)) => {
+                todo.push(Task::If);
+                todo.extend([Task::Copy(e), Task::Copy(t), Task::Copy(c)]);
+            }
+            Task::Copy(Expr::Pair(a, b)) => {
+                todo.extend([Task::Pair, Task::Copy(b), Task::Copy(a)]);
+            }
+            Task::Copy(Expr::Split(v, Ptr(x), x_hint, Lam(Ptr(y), body, y_hint))) => {
+                let (x_fresh, y_fresh) = (fresh_cell(), fresh_cell());
+                renamed.insert(Rc::as_ptr(x), Rc::clone(&x_fresh));
+                renamed.insert(Rc::as_ptr(y), Rc::clone(&y_fresh));
+                todo.push(Task::Split(
+                    Ptr(x_fresh),
+                    x_hint.clone(),
+                    Ptr(y_fresh),
+                    y_hint.clone(),
+                ));
+                todo.extend([Task::Copy(body), Task::Copy(v)]);
+            }
+            Task::Copy(Expr::Inl(v)) => todo.extend([Task::Inj(true), Task::Copy(v)]),
+            Task::Copy(Expr::Inr(v)) => todo.extend([Task::Inj(false), Task::Copy(v)]),
+            Task::Copy(Expr::Case(v, Lam(Ptr(x), l, x_hint), Lam(Ptr(y), r, y_hint))) => {
+                let (x_fresh, y_fresh) = (fresh_cell(), fresh_cell());
+                renamed.insert(Rc::as_ptr(x), Rc::clone(&x_fresh));
+                renamed.insert(Rc::as_ptr(y), Rc::clone(&y_fresh));
+                todo.push(Task::Case(
+                    Ptr(x_fresh),
+                    x_hint.clone(),
+                    Ptr(y_fresh),
+                    y_hint.clone(),
+                ));
+                todo.extend([Task::Copy(r), Task::Copy(l), Task::Copy(v)]);
+            }
+            Task::Copy(Expr::Fix(Lam(Ptr(rc), body, hint))) => {
+                let fresh = fresh_cell();
+                renamed.insert(Rc::as_ptr(rc), Rc::clone(&fresh));
+                todo.push(Task::Fix(Ptr(fresh), hint.clone()));
+                todo.push(Task::Copy(body));
+            }
+            Task::Copy(Expr::Rec(Rec(weak))) => {
+                let rc = weak.upgrade().expect("a cell outlives the body it holds");
+                done.push(Expr::Ptr(Ptr(rc)));
+            }
+            Task::Copy(Expr::Invalid) => done.push(Expr::Invalid),
+            Task::App => {
+                let v = done.pop().expect("copied argument");
+                let f = done.pop().expect("copied function");
+                done.push(make_app(f, v));
+            }
+            Task::Lam(ptr, hint, ty) => {
+                let body = done.pop().expect("copied body");
+                done.push(Expr::Lam(Lam(ptr, Box::new(body), hint), ty));
+            }
+            Task::Let(ptr, hint) => {
+                let body = done.pop().expect("copied body");
+                let v = done.pop().expect("copied value");
+                done.push(Expr::Let(Box::new(v), Lam(ptr, Box::new(body), hint)));
+            }
+            Task::Prim(op, n) => {
+                let operands = done.split_off(done.len() - n);
+                done.push(Expr::Prim(op, operands));
+            }
+            Task::If => {
+                let e = done.pop().expect("copied else branch");
+                let t = done.pop().expect("copied then branch");
+                let c = done.pop().expect("copied condition");
+                done.push(make_if(c, t, e));
+            }
+            Task::Pair => {
+                let b = done.pop().expect("copied second component");
+                let a = done.pop().expect("copied first component");
+                done.push(make_pair(a, b));
+            }
+            Task::Split(x, x_hint, y, y_hint) => {
+                let body = done.pop().expect("copied body");
+                let v = done.pop().expect("copied pair");
+                let lam = Lam(y, Box::new(body), y_hint);
+                done.push(Expr::Split(Box::new(v), x, x_hint, lam));
+            }
+            Task::Inj(left) => {
+                let v = done.pop().expect("copied injected value");
+                done.push(if left { make_inl(v) } else { make_inr(v) });
+            }
+            Task::Case(x, x_hint, y, y_hint) => {
+                let r = done.pop().expect("copied right branch");
+                let l = done.pop().expect("copied left branch");
+                let v = done.pop().expect("copied scrutinee");
+                let l = Lam(x, Box::new(l), x_hint);
+                let r = Lam(y, Box::new(r), y_hint);
+                done.push(Expr::Case(Box::new(v), l, r));
+            }
+            Task::Fix(ptr, hint) => {
+                let body = done.pop().expect("copied body");
+                done.push(Expr::Fix(Lam(ptr, Box::new(body), hint)));
+            }
+        }
+    }
+    done.pop().expect("copied expression")
+}
+
+fn fresh_cell() -> Rc<RefCell<Slot>> {
+    Rc::new(RefCell::new(Slot::Unbound))
+}
+
+pub fn eval(e: Expr) -> Result<Expr, EvalError> {
+    eval_observed(e, &mut NoopObserver)
+}
+
+/// Evaluates `e` like `eval`, reporting every reduction to `obs`.
+pub fn eval_observed(e: Expr, obs: &mut impl EvalObserver<Expr>) -> Result<Expr, EvalError> {
+    finish(run(e, Strategy::default(), Limits::default(), obs))
+}
+
+/// Evaluates `e` call-by-need: arguments are bound unevaluated, and the first
+/// use of a variable forces its cell and writes the value back for the other uses.
+pub fn eval_lazy(e: Expr) -> Result<Expr, EvalError> {
+    finish(run(
+        e,
+        Strategy::CallByNeed,
+        Limits::default(),
+        &mut NoopObserver,
+    ))
+}
+
+/// Evaluates `e` with at most `max_steps` reductions.
+pub fn eval_with_fuel(e: Expr, max_steps: usize) -> Result<Outcome<Expr, Expr>, EvalError> {
+    eval_with_limits(e, Limits::steps(max_steps))
+}
+
+/// Evaluates `e` until the result or until one of `limits` is hit, in which case
+/// the partially reduced term comes back to be resumed later.
+pub fn eval_with_limits(e: Expr, limits: Limits) -> Result<Outcome<Expr, Expr>, EvalError> {
+    eval_with(e, Strategy::default(), limits, &mut NoopObserver)
+}
+
+/// Evaluates `e` like `eval_with_limits` in the order `strategy` gives,
+/// reporting every reduction to `obs`.
+pub fn eval_with(
+    e: Expr,
+    strategy: Strategy,
+    limits: Limits,
+    obs: &mut impl EvalObserver<Expr>,
+) -> Result<Outcome<Expr, Expr>, EvalError> {
+    run(e, strategy, limits, obs)
+}
+
+/// Reduces `e` to its normal form in normal order, also under lambdas: variables
+/// that nothing binds are values there, and so are applications stuck on them.
+pub fn normalize(e: Expr) -> Result<Expr, EvalError> {
+    finish(run(
+        e,
+        Strategy::NormalOrder,
+        Limits::default(),
+        &mut NoopObserver,
+    ))
+}
+
+/// The type of `e`, or why it has none, as [`types::typecheck`] finds it. A term
+/// that checks does not get stuck, whichever strategy evaluates it.
+pub fn typecheck(e: &Expr) -> Result<Type, TypeError> {
+    types::typecheck(&e.to_term())
+}
+
+fn finish(outcome: Result<Outcome<Expr, Expr>, EvalError>) -> Result<Expr, EvalError> {
+    match outcome? {
+        Outcome::Value(e) => Ok(e),
+        Outcome::OutOfFuel { .. } => unreachable!("evaluation without limits ran out"),
+    }
+}
+
+fn run(
+    e: Expr,
+    strategy: Strategy,
+    limits: Limits,
+    obs: &mut dyn EvalObserver<Expr>,
+) -> Result<Outcome<Expr, Expr>, EvalError> {
+    obs.on_start(&e);
+    let mut machine = Machine::new(e, strategy, &limits);
+    loop {
+        let exhausted = if machine.nodes > limits.max_nodes {
+            Exhausted::Nodes
+        } else {
+            match machine.step(obs).inspect_err(|err| obs.on_stuck(err))? {
+                Progress::Reduced => {
+                    if obs.observes_steps() {
+                        machine.observe_step(obs);
+                    }
+                    continue;
+                }
+                Progress::Finished => break,
+                Progress::Exhausted(exhausted) => exhausted,
+            }
+        };
+        obs.on_out_of_fuel(exhausted);
+        let term = machine.plug();
+        return Ok(Outcome::OutOfFuel { exhausted, term });
+    }
+    obs.on_finish(&machine.focus);
+    Ok(Outcome::Value(*machine.focus))
 }
 
 /// ```compile_fail,E0373,E0505
-/// use aptree::heaptree::{Expr,make_lam,make_app};
+/// use aptree::heaptree::{Expr,make_lam,make_app,ZERO};
 /// fn make_lam_cheat() -> Expr {
 ///     make_lam(|x| {
-///         let mut cheat = Expr::Bas("0");
+///         let mut cheat = ZERO;
 ///         let lam = make_lam(|y| {
 ///             cheat = y;
 ///             x
@@ -88,22 +1702,234 @@ pub fn make_lam<F>(init: F) -> Expr
 where
     F: FnOnce(Expr) -> Expr + 'static,
 {
-    let ptr = Ptr(Rc::new(OnceCell::new()));
+    let ptr = Ptr(Rc::new(RefCell::new(Slot::Unbound)));
     let body_ptr = Ptr(Rc::clone(&ptr.0));
-    Expr::Lam(Lam(ptr, Box::new(init(Expr::Ptr(body_ptr)))))
+    Expr::Lam(Lam(ptr, Box::new(init(Expr::Ptr(body_ptr))), None), None)
+}
+
+/// Like [`make_lam`], with a name for the binder that printing uses.
+pub fn make_named_lam<F>(hint: &str, init: F) -> Expr
+where
+    F: FnOnce(Expr) -> Expr + 'static,
+{
+    let Expr::Lam(Lam(ptr, body, _), ty) = make_lam(init) else {
+        unreachable!("make_lam makes a lambda");
+    };
+    Expr::Lam(Lam(ptr, body, Some(hint.into())), ty)
+}
+
+/// Like [`make_named_lam`], with the binder annotated with its type for [`typecheck`].
+pub fn make_typed_lam<F>(hint: &str, ty: Type, init: F) -> Expr
+where
+    F: FnOnce(Expr) -> Expr + 'static,
+{
+    let Expr::Lam(lam, _) = make_named_lam(hint, init) else {
+        unreachable!("make_named_lam makes a lambda");
+    };
+    Expr::Lam(lam, Some(ty))
+}
+
+/// Makes `fix f. body`, with `init` building the body from f, which stands for
+/// the whole fix: `make_fix(|f| make_lam(move |n| …f…))` is a recursive function.
+pub fn make_fix<F>(init: F) -> Expr
+where
+    F: FnOnce(Expr) -> Expr + 'static,
+{
+    let Expr::Lam(lam, _) = make_lam(init) else {
+        unreachable!("make_lam makes a lambda");
+    };
+    Expr::Fix(lam)
+}
+
+/// Like [`make_fix`], with a name for the binder that printing uses.
+pub fn make_named_fix<F>(hint: &str, init: F) -> Expr
+where
+    F: FnOnce(Expr) -> Expr + 'static,
+{
+    let Expr::Lam(lam, _) = make_named_lam(hint, init) else {
+        unreachable!("make_named_lam makes a lambda");
+    };
+    Expr::Fix(lam)
+}
+
+/// Makes another use of a variable handed out by [`make_lam`], or None if `var`
+/// is not a variable. All uses share the variable's cell.
+pub fn share_var(var: &Expr) -> Option<Expr> {
+    match var {
+        Expr::Ptr(Ptr(rc)) => Some(Expr::Ptr(Ptr(Rc::clone(rc)))),
+        _ => None,
+    }
+}
+
+/// Makes `let x = v in body`, with `init` building the body from x. It binds x
+/// like applying a lambda to v would, in one step rather than two.
+pub fn make_let<F>(v: Expr, init: F) -> Expr
+where
+    F: FnOnce(Expr) -> Expr + 'static,
+{
+    let Expr::Lam(lam, _) = make_lam(init) else {
+        unreachable!("make_lam makes a lambda");
+    };
+    Expr::Let(Box::new(v), lam)
+}
+
+/// Like [`make_let`], with a name for the binder that printing uses.
+pub fn make_named_let<F>(hint: &str, v: Expr, init: F) -> Expr
+where
+    F: FnOnce(Expr) -> Expr + 'static,
+{
+    let Expr::Lam(lam, _) = make_named_lam(hint, init) else {
+        unreachable!("make_named_lam makes a lambda");
+    };
+    Expr::Let(Box::new(v), lam)
 }
 
 pub fn make_app(f: Expr, v: Expr) -> Expr {
     Expr::App(Box::new(f), Box::new(v))
 }
 
-pub fn make_bas(c: &'static str) -> Expr {
+pub fn make_bas(c: Const) -> Expr {
     Expr::Bas(c)
 }
 
+/// Makes the primitive `op` on `operands`.
+pub fn make_prim(op: PrimOp, operands: Vec<Expr>) -> Expr {
+    Expr::Prim(op, operands)
+}
+
+/// Makes `if c then t else e`.
+pub fn make_if(c: Expr, t: Expr, e: Expr) -> Expr {
+    Expr::If(Box::new(c), Box::new(t), Box::new(e))
+}
+
+/// Makes the pair `(a, b)`.
+pub fn make_pair(a: Expr, b: Expr) -> Expr {
+    Expr::Pair(Box::new(a), Box::new(b))
+}
+
+/// Makes `let (x, y) = v in body`, with `init` building the body from x and y.
+pub fn make_split<F>(v: Expr, init: F) -> Expr
+where
+    F: FnOnce(Expr, Expr) -> Expr + 'static,
+{
+    let (x, y) = (fresh_cell(), fresh_cell());
+    let body = init(Expr::Ptr(Ptr(Rc::clone(&x))), Expr::Ptr(Ptr(Rc::clone(&y))));
+    Expr::Split(Box::new(v), Ptr(x), None, Lam(Ptr(y), Box::new(body), None))
+}
+
+/// Makes `inl v`.
+pub fn make_inl(v: Expr) -> Expr {
+    Expr::Inl(Box::new(v))
+}
+
+/// Makes `inr v`.
+pub fn make_inr(v: Expr) -> Expr {
+    Expr::Inr(Box::new(v))
+}
+
+/// Makes `case v of inl x. l | inr y. r`, with `left` building l from x and
+/// `right` building r from y.
+pub fn make_case<F, G>(v: Expr, left: F, right: G) -> Expr
+where
+    F: FnOnce(Expr) -> Expr + 'static,
+    G: FnOnce(Expr) -> Expr + 'static,
+{
+    let (Expr::Lam(l, _), Expr::Lam(r, _)) = (make_lam(left), make_lam(right)) else {
+        unreachable!("make_lam makes a lambda");
+    };
+    Expr::Case(Box::new(v), l, r)
+}
+
+/// The heaptree backend for generic code: terms are plain [`Expr`]s, so it
+/// holds no state of its own.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Heap;
+
+impl AbtBackend for Heap {
+    type Term = Expr;
+    type Var = Expr;
+    fn constant(&mut self, c: Const) -> Expr {
+        make_bas(c)
+    }
+    fn lam(&mut self, body: impl FnOnce(&mut Self, &Expr) -> Expr) -> Expr {
+        let ptr = Ptr(Rc::new(RefCell::new(Slot::Unbound)));
+        let body = body(self, &Expr::Ptr(Ptr(Rc::clone(&ptr.0))));
+        Expr::Lam(Lam(ptr, Box::new(body), None), None)
+    }
+    fn typed_lam(&mut self, ty: Type, body: impl FnOnce(&mut Self, &Expr) -> Expr) -> Expr {
+        let Expr::Lam(lam, _) = self.lam(body) else {
+            unreachable!("lam makes a lambda");
+        };
+        Expr::Lam(lam, Some(ty))
+    }
+    fn app(&mut self, f: Expr, v: Expr) -> Expr {
+        make_app(f, v)
+    }
+    fn var(&mut self, x: &Expr) -> Expr {
+        share_var(x).expect("lam hands out variables")
+    }
+    fn let_in(&mut self, v: Expr, body: impl FnOnce(&mut Self, &Expr) -> Expr) -> Expr {
+        let Expr::Lam(lam, _) = self.lam(body) else {
+            unreachable!("lam makes a lambda");
+        };
+        Expr::Let(Box::new(v), lam)
+    }
+    fn prim(&mut self, op: PrimOp, operands: Vec<Expr>) -> Expr {
+        make_prim(op, operands)
+    }
+    fn if_then_else(&mut self, c: Expr, t: Expr, e: Expr) -> Expr {
+        make_if(c, t, e)
+    }
+    fn pair(&mut self, a: Expr, b: Expr) -> Expr {
+        make_pair(a, b)
+    }
+    fn split(&mut self, v: Expr, body: impl FnOnce(&mut Self, &Expr, &Expr) -> Expr) -> Expr {
+        let (x, y) = (fresh_cell(), fresh_cell());
+        let (x_var, y_var) = (Expr::Ptr(Ptr(Rc::clone(&x))), Expr::Ptr(Ptr(Rc::clone(&y))));
+        let body = body(self, &x_var, &y_var);
+        Expr::Split(Box::new(v), Ptr(x), None, Lam(Ptr(y), Box::new(body), None))
+    }
+    fn inl(&mut self, v: Expr) -> Expr {
+        make_inl(v)
+    }
+    fn inr(&mut self, v: Expr) -> Expr {
+        make_inr(v)
+    }
+    fn case(
+        &mut self,
+        v: Expr,
+        l: impl FnOnce(&mut Self, &Expr) -> Expr,
+        r: impl FnOnce(&mut Self, &Expr) -> Expr,
+    ) -> Expr {
+        let (Expr::Lam(l, _), Expr::Lam(r, _)) = (self.lam(l), self.lam(r)) else {
+            unreachable!("lam makes a lambda");
+        };
+        Expr::Case(Box::new(v), l, r)
+    }
+    fn typecheck_term(&mut self, t: &Expr) -> Result<Type, TypeError> {
+        typecheck(t)
+    }
+    fn eval_term_with(
+        &mut self,
+        t: Expr,
+        strategy: Strategy,
+        limits: Limits,
+    ) -> Result<Outcome<Term>, EvalError> {
+        Ok(match eval_with(t, strategy, limits, &mut NoopObserver)? {
+            Outcome::Value(e) => Outcome::Value(e.to_term()),
+            Outcome::OutOfFuel { exhausted, .. } => Outcome::OutOfFuel {
+                exhausted,
+                term: (),
+            },
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dot::ToDot;
+    use crate::observer::{Event, Recorder};
 
     fn make_ident() -> Expr {
         make_lam(|ptr| ptr)
@@ -114,36 +1940,448 @@ mod tests {
     fn make_lam_true() -> Expr {
         make_lam(|x| make_lam(|_y| x))
     }
-    fn make_lam_false() -> Expr {
-        make_lam(|_x| make_lam(|y| y))
+    fn make_self_app() -> Expr {
+        make_lam(|x| {
+            let x2 = share_var(&x).unwrap();
+            make_app(x, x2)
+        })
+    }
+    fn make_omega() -> Expr {
+        make_app(make_self_app(), make_self_app())
     }
 
     #[test]
-    fn t0() {
-        let lam_const = make_const_fn();
-        let app = make_app(lam_const, ONE);
-        assert_eq!(UNIT, eval(app));
+    fn shared_var_cbv() {
+        let app = make_app(make_app(make_self_app(), make_ident()), ONE);
+        assert_eq!(Ok(ONE), eval(app));
     }
 
     #[test]
-    fn t1() {
-        let lam_id = make_ident();
-        let lam_const = make_const_fn();
-        let app = make_app(make_app(lam_id, lam_const), ONE);
-        assert_eq!(UNIT, eval(app));
+    fn only_a_variable_is_shared() {
+        assert_eq!(None, share_var(&ONE));
+        assert_eq!(None, share_var(&make_ident()));
+    }
+
+    #[test]
+    fn lazy_skips_unused_argument() {
+        let app = make_app(make_const_fn(), make_omega());
+        assert_eq!(Ok(UNIT), eval_lazy(app));
+    }
+
+    #[test]
+    fn lazy_forces_shared_thunk_once() {
+        // (\x. x x) ((\i. i) (\z. z)):
+        // bind x, force (\i. i) (\z. z) in two steps, copy it out for the first use,
+        // apply it to the second use and take the already-forced value out twice
+        let app = make_app(make_self_app(), make_app(make_ident(), make_ident()));
+        let mut machine = Machine::new(app, Strategy::CallByNeed, &Limits::default());
+        let mut steps = 0;
+        while let Progress::Reduced = machine.step(&mut NoopObserver).unwrap() {
+            steps += 1;
+        }
+        assert_eq!(7, steps);
+        assert_eq!(make_ident(), *machine.focus);
+    }
+
+    #[test]
+    fn stuck_on_constant() {
+        let app = make_app(make_app(make_ident(), ZERO), ONE);
+        assert_eq!(Err(EvalError::Stuck(Location::root())), eval(app));
+    }
+
+    #[test]
+    fn uninitialized_in_arg() {
+        let app = make_app(make_ident(), make_app(make_ident(), Expr::Invalid));
+        assert_eq!(
+            Err(EvalError::Uninitialized(Location::Path(vec![
+                Branch::Arg,
+                Branch::Arg
+            ]))),
+            eval(app)
+        );
+    }
+
+    #[test]
+    fn dangling_inside_thunk() {
+        let unbound = Expr::Ptr(Ptr(Rc::new(RefCell::new(Slot::Unbound))));
+        // x is bound to the thunk (\i. i) unbound, which binds i to the thunk unbound
+        let app = make_app(make_self_app(), make_app(make_ident(), unbound));
+        assert_eq!(
+            Err(EvalError::DanglingPointer(Location::Path(vec![
+                Branch::Fun,
+                Branch::Cell,
+                Branch::Cell
+            ]))),
+            eval_lazy(app)
+        );
+    }
+
+    #[test]
+    fn deep_left_nested_app() {
+        // (((\x. x) (\x. x)) (\x. x)) ... 1, deeper than the native stack could recurse
+        let mut f = make_ident();
+        for _ in 0..100_000 {
+            f = make_app(f, make_ident());
+        }
+        let mut machine = Machine::new(make_app(f, ONE), Strategy::default(), &Limits::default());
+        while let Progress::Reduced = machine.step(&mut NoopObserver).unwrap() {}
+        assert_eq!(ONE, *machine.focus);
+    }
+
+    #[test]
+    fn fuel_runs_out_and_resumes() {
+        let app = make_app(make_app(make_ident(), make_const_fn()), ONE);
+        let Ok(Outcome::OutOfFuel { exhausted, term }) = eval_with_fuel(app, 1) else {
+            panic!("one step is not enough");
+        };
+        assert_eq!(Exhausted::Steps, exhausted);
+        // the identity's variable is bound but not yet dereferenced
+        assert!(matches!(&term, Expr::App(f, _) if matches!(**f, Expr::Ptr(_))));
+        assert_eq!(Ok(UNIT), eval(term));
+    }
+
+    #[test]
+    fn resuming_counts_the_values_in_cells() {
+        let e = parse(r"(\x. x 0) (\y. y)").unwrap();
+        let Ok(Outcome::OutOfFuel { term, .. }) = eval_with_fuel(e, 1) else {
+            panic!("one step is not enough");
+        };
+        // the identity is in x's cell, out of the term the resumed machine is sized by
+        assert_eq!(term.to_string(), r"(\y. y) 0");
+        assert_eq!(Ok(ZERO), eval(term));
+    }
+
+    #[test]
+    fn fuel_runs_out_at_a_prim() {
+        let e = parse(r"add ((\x. x) 1) 2").unwrap();
+        let Ok(Outcome::OutOfFuel { term, .. }) = eval_with_fuel(e, 2) else {
+            panic!("two steps are not enough");
+        };
+        // the beta and the deref are done, the addition is not
+        assert_eq!(term.to_string(), "add 1 2");
+        assert_eq!(Ok(make_bas(Const::Int(3))), eval(term));
+    }
+
+    #[test]
+    fn fuel_runs_out_at_an_if() {
+        let e = parse(r"if lt 0 1 then 1 else (\x. x) 0").unwrap();
+        let Ok(Outcome::OutOfFuel { term, .. }) = eval_with_fuel(e, 1) else {
+            panic!("one step is not enough");
+        };
+        // the condition is evaluated, the choice is not
+        assert_eq!(term.to_string(), r"if true then 1 else (\x. x) 0");
+        assert_eq!(Ok(make_bas(Const::Int(1))), eval(term));
+    }
+
+    #[test]
+    fn fuel_runs_out_at_a_case() {
+        let e =
+            parse(r"case inl ((\x. x) 1) of inl y. let (a, b) = (y, 0) in a | inr z. z").unwrap();
+        let Ok(Outcome::OutOfFuel { term, .. }) = eval_with_fuel(e, 1) else {
+            panic!("one step is not enough");
+        };
+        // the injection is evaluated, the match is not
+        assert_eq!(
+            term.to_string(),
+            "case inl 1 of inl y. let (a, b) = (y, 0) in a | inr z. z"
+        );
+        assert_eq!(Ok(make_bas(Const::Int(1))), eval(term));
+    }
+
+    #[test]
+    fn fuel_bounds_omega() {
+        let Ok(Outcome::OutOfFuel { exhausted, term }) = eval_with_fuel(make_omega(), 1000) else {
+            panic!("omega never finishes");
+        };
+        assert_eq!(Exhausted::Steps, exhausted);
+        assert!(matches!(
+            eval_with_fuel(term, 1000),
+            Ok(Outcome::OutOfFuel { .. })
+        ));
+    }
+
+    #[test]
+    fn node_limit_bounds_growing_term() {
+        let make_triple = || {
+            make_lam(|x| {
+                let (x2, x3) = (share_var(&x).unwrap(), share_var(&x).unwrap());
+                make_app(make_app(x, x2), x3)
+            })
+        };
+        let limits = Limits {
+            max_nodes: 1000,
+            ..Limits::default()
+        };
+        let Ok(Outcome::OutOfFuel { exhausted, term }) =
+            eval_with_limits(make_app(make_triple(), make_triple()), limits)
+        else {
+            panic!("the term keeps growing");
+        };
+        assert_eq!(Exhausted::Nodes, exhausted);
+        assert!(size(&term) < 1100);
+    }
+
+    #[test]
+    fn depth_limit() {
+        let mut f = make_ident();
+        for _ in 0..10 {
+            f = make_app(f, make_ident());
+        }
+        let limits = Limits {
+            max_depth: 5,
+            ..Limits::default()
+        };
+        let Ok(Outcome::OutOfFuel { exhausted, term }) = eval_with_limits(make_app(f, ONE), limits)
+        else {
+            panic!("the term is nested deeper than the limit");
+        };
+        assert_eq!(Exhausted::Depth, exhausted);
+        assert_eq!(Ok(ONE), eval(term));
+    }
+
+    #[test]
+    fn recorder_sees_every_reduction() {
+        let app = make_app(make_app(make_ident(), make_const_fn()), ONE);
+        let mut recorder = Recorder::default();
+        assert_eq!(Ok(UNIT), eval_observed(app, &mut recorder));
+        assert_eq!(
+            recorder.events,
+            vec![
+                Event::Start(r"(\x0. x0) (\x1. ()) 1".to_string()),
+                Event::Beta {
+                    lam: r"\x0. x0".to_string(),
+                    arg: r"\x0. ()".to_string(),
+                },
+                Event::Deref(r"\x0. ()".to_string()),
+                Event::Beta {
+                    lam: r"\x0. ()".to_string(),
+                    arg: "1".to_string(),
+                },
+                Event::Finish("()".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn display_is_stable_across_copies() {
+        // alpha-equivalent terms built separately print the same
+        assert_eq!(make_lam_true().to_string(), make_lam_true().to_string());
+        assert_eq!(make_lam_true().to_string(), r"\x0. \x1. x0");
+        let named = make_named_lam("x", |x| make_app(x, make_named_lam("x", |x| x)));
+        assert_eq!(named.to_string(), r"\x. x (\x'. x')");
+        // a call-by-need thunk prints as the argument it stands for
+        let app = make_app(make_self_app(), make_app(make_ident(), ONE));
+        let Ok(Outcome::OutOfFuel { term, .. }) = run(
+            app,
+            Strategy::CallByNeed,
+            Limits::steps(1),
+            &mut NoopObserver,
+        ) else {
+            panic!("expected to run out of fuel");
+        };
+        assert_eq!(term.to_string(), r"(\x0. x0) 1 ((\x1. x1) 1)");
+    }
+
+    #[test]
+    fn dot_draws_a_shared_cell_once() {
+        let dot = make_self_app().to_dot();
+        assert_eq!(1, dot.matches("shape=circle").count());
+        assert_eq!(2, dot.matches("style=dashed").count());
+    }
+
+    #[test]
+    fn reads_back_a_long_chain_of_bound_cells() {
+        // x0 bound to inl x1, x1 to inl x2, and so on, deeper than the native
+        // stack could recurse
+        let n = 100_000;
+        let cells: Vec<_> = (0..=n)
+            .map(|_| Rc::new(RefCell::new(Slot::Unbound)))
+            .collect();
+        for (cell, next) in cells.iter().zip(&cells[1..]) {
+            let value = make_inl(Expr::Ptr(Ptr(next.clone())));
+            *cell.borrow_mut() = Slot::Value(Box::new(value));
+        }
+        *cells[n].borrow_mut() = Slot::Value(Box::new(ONE));
+        let chain = Expr::Ptr(Ptr(cells[0].clone()));
+        let mut term = chain.to_term();
+        for _ in 0..n {
+            let Term::Inl(v) = term else {
+                panic!("every bound cell reads back as its value");
+            };
+            term = *v;
+        }
+        assert_eq!(term, Term::Const(constant::ONE));
+        assert!(chain.to_dot().contains("[label=\"1\"]"));
+        // unlink the cells, which dropping the first would otherwise do recursively
+        for cell in &cells {
+            *cell.borrow_mut() = Slot::Unbound;
+        }
+    }
+
+    // every term on_step sees, printed
+    #[derive(Default)]
+    struct Steps(Vec<String>);
+    impl EvalObserver<Expr> for Steps {
+        fn observes_steps(&self) -> bool {
+            true
+        }
+        fn on_step(&mut self, term: &Expr) {
+            self.0.push(term.to_string());
+        }
+    }
+
+    #[test]
+    fn step_observer_sees_the_whole_term() {
+        // the same term as lazy_forces_shared_thunk_once, which plugs a
+        // thunk being forced back into its cell for every look at the term
+        let app = make_app(make_self_app(), make_app(make_ident(), make_ident()));
+        let mut steps = Steps::default();
+        let Ok(Outcome::Value(result)) =
+            run(app, Strategy::CallByNeed, Limits::default(), &mut steps)
+        else {
+            panic!("expected a value");
+        };
+        assert_eq!(make_ident(), result);
+        assert_eq!(
+            steps.0,
+            [
+                // both uses of x print the thunk they share
+                r"(\x0. x0) (\x1. x1) ((\x2. x2) (\x3. x3))",
+                r"(\x0. x0) (\x1. x1)",
+                r"(\x0. x0) (\x1. x1)",
+                r"(\x0. x0) (\x1. x1)",
+                r"\x0. x0",
+                r"\x0. x0",
+                r"\x0. x0",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_shares_cells_between_uses() {
+        let omega = parse(r"(\x. x x) (\x. x x)").unwrap();
+        assert_eq!(omega.to_string(), r"(\x. x x) (\x. x x)");
+        assert!(matches!(
+            eval_with_fuel(omega, 10),
+            Ok(Outcome::OutOfFuel { .. })
+        ));
+        let app = parse(r"(\x y. y) ((\x. x x) (\x. x x)) 1").unwrap();
+        assert_eq!(Ok(ONE), eval_lazy(app));
+    }
+
+    #[test]
+    fn alpha_eq_compares_binding_structure() {
+        let (t, f) = (make_lam_true(), make_lam(|_x| make_lam(|y| y)));
+        assert_eq!(t, f);
+        assert!(!alpha_eq(&t, &f));
+        assert!(alpha_eq(&t, &parse(r"\a b. a").unwrap()));
+        let app = make_app(make_ident(), make_lam_true());
+        assert!(alpha_eq(&eval(app).unwrap(), &t));
+    }
+
+    #[test]
+    fn normalize_reduces_under_lambdas() {
+        let e = parse(r"(\x f. f ((\y. y) x) (\z. (\w. w) z f)) 0").unwrap();
+        let mut steps = Steps::default();
+        let Ok(Outcome::Value(result)) =
+            eval_with(e, Strategy::NormalOrder, Limits::default(), &mut steps)
+        else {
+            panic!("expected a value");
+        };
+        assert_eq!(result.to_string(), r"\f. f 0 (\z. z f)");
+        assert_eq!(
+            steps.0,
+            [
+                // arguments are bound unevaluated, so y is bound to x itself
+                // and x is only dereferenced after y
+                r"\f. f ((\y. y) 0) (\z. (\w. w) z f)",
+                r"\f. f 0 (\z. (\w. w) z f)",
+                r"\f. f 0 (\z. (\w. w) z f)",
+                r"\f. f 0 (\z. (\w. w) z f)",
+                r"\f. f 0 (\z. z f)",
+                r"\f. f 0 (\z. z f)",
+            ]
+        );
+        // variables nothing binds are only values when normalizing
+        let free = make_lam(|x| make_app(x, make_app(make_ident(), ONE)));
+        let Expr::Lam(Lam(_, body, _), _) = normalize(free).unwrap() else {
+            panic!("expected a lambda");
+        };
+        assert!(matches!(eval(*body), Err(EvalError::DanglingPointer(_))));
+    }
+
+    // fix sum. \n. if eq n 0 then 0 else add n (sum (sub n 1)), and a weak handle
+    // on its binder's cell
+    fn make_sum() -> (Expr, Weak<RefCell<Slot>>) {
+        let sum = parse(r"fix sum. \n. if eq n 0 then 0 else add n (sum (sub n 1))").unwrap();
+        let Expr::Fix(Lam(Ptr(rc), ..)) = &sum else {
+            panic!("expected a fix");
+        };
+        let cell = Rc::downgrade(rc);
+        (sum, cell)
+    }
+
+    #[test]
+    fn fix_recurses_under_every_strategy() {
+        for strategy in [
+            Strategy::CallByValue,
+            Strategy::CallByName,
+            Strategy::CallByNeed,
+            Strategy::ApplicativeOrder,
+            Strategy::NormalOrder,
+        ] {
+            let (sum, cell) = make_sum();
+            let e = make_app(sum, make_bas(Const::Int(10)));
+            let Ok(Outcome::Value(result)) =
+                eval_with(e, strategy, Limits::default(), &mut NoopObserver)
+            else {
+                panic!("{strategy:?} gave no value");
+            };
+            assert_eq!(make_bas(Const::Int(55)), result, "{strategy:?}");
+            // the cell and the body it refers back to are gone with the last use
+            assert!(cell.upgrade().is_none(), "{strategy:?} leaked the fix");
+        }
     }
 
     #[test]
-    fn t2() {
-        let lam_true = make_lam_true();
-        let app = make_app(make_app(lam_true, ZERO), ONE);
-        assert_eq!(ZERO, eval(app));
+    fn recursive_value_outlives_its_fix() {
+        let (sum, cell) = make_sum();
+        let mut recorder = Recorder::default();
+        let f = eval_observed(sum, &mut recorder).unwrap();
+        assert_eq!(
+            recorder.events[1],
+            Event::Unfold(r"fix sum. \n. if eq n 0 then 0 else add n (sum (sub n 1))".to_string())
+        );
+        // the use of sum in the value keeps the cell alive, and reads back as the fix
+        assert!(cell.upgrade().is_some());
+        assert_eq!(
+            f.to_string(),
+            r"\n. if eq n 0 then 0 else add n ((fix sum. \n'. if eq n' 0 then 0 else add n' (sum (sub n' 1))) (sub n 1))"
+        );
+        let copy = copy(&f);
+        assert_eq!(
+            Ok(make_bas(Const::Int(6))),
+            eval(make_app(f, make_bas(Const::Int(3))))
+        );
+        assert!(cell.upgrade().is_some());
+        drop(copy);
+        assert!(cell.upgrade().is_none());
     }
 
     #[test]
-    fn t3() {
-        let lam_false = make_lam_false();
-        let app = make_app(make_app(lam_false, ZERO), ONE);
-        assert_eq!(ONE, eval(app));
+    fn fuel_bounds_fix() {
+        // fix f. f, which unfolds to itself forever
+        let e = make_named_fix("f", |f| f);
+        let Expr::Fix(Lam(Ptr(rc), ..)) = &e else {
+            panic!("expected a fix");
+        };
+        let cell = Rc::downgrade(rc);
+        let Ok(Outcome::OutOfFuel { exhausted, term }) = eval_with_fuel(e, 1000) else {
+            panic!("fix f. f never finishes");
+        };
+        assert_eq!(Exhausted::Steps, exhausted);
+        assert_eq!(term.to_string(), "fix f. f");
+        drop(term);
+        assert!(cell.upgrade().is_none());
     }
 }
@@ -1,79 +1,1460 @@
-use std::cell::Cell;
+use crate::backend::AbtBackend;
+use crate::dot::{DotWriter, ToDot};
+use crate::error::{Branch, BuildError, EvalError, Location, TypeError};
+use crate::fuel::{Exhausted, Limits, Outcome, Progress};
+use crate::observer::{EvalObserver, NoopObserver};
+use crate::prim::PrimOp;
+use crate::strategy::Strategy;
+use crate::term::{Binder, ReadBack, Shape, Term};
+use crate::types::{self, Type};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+pub use crate::constant::{Const, ONE, UNIT, ZERO};
 
 pub struct Ptr<'prg>(&'prg Cell<Expr<'prg>>);
-pub struct Lam<'prg>(Ptr<'prg>, Box<Expr<'prg>>);
+pub struct Lam<'prg>(Ptr<'prg>, Box<Expr<'prg>>, Option<Rc<str>>);
 
 pub enum Expr<'prg> {
     Ptr(Ptr<'prg>),
-    Bas(&'static str),
-    Lam(Lam<'prg>),
+    Bas(Const),
+    /// A lambda, and the type its binder is annotated with, if any
+    Lam(Lam<'prg>, Option<Type>),
     App(Box<Expr<'prg>>, Box<Expr<'prg>>),
+    /// `let x = v in body`, with the value first and the binder and body as a lambda
+    Let(Box<Expr<'prg>>, Lam<'prg>),
+    Prim(PrimOp, Vec<Expr<'prg>>),
+    /// `if c then t else e`
+    If(Box<Expr<'prg>>, Box<Expr<'prg>>, Box<Expr<'prg>>),
+    Pair(Box<Expr<'prg>>, Box<Expr<'prg>>),
+    /// `let (x, y) = v in body`, with the pair first, then x's cell and name, and
+    /// y and the body as a lambda
+    Split(Box<Expr<'prg>>, Ptr<'prg>, Option<Rc<str>>, Lam<'prg>),
+    Inl(Box<Expr<'prg>>),
+    Inr(Box<Expr<'prg>>),
+    /// `case v of inl x. l | inr y. r`, with the scrutinee first and either branch
+    /// as a lambda
+    Case(Box<Expr<'prg>>, Lam<'prg>, Lam<'prg>),
     Invalid,
+    /// Left behind in an argument cell once its value has been dereferenced
+    Moved,
+    /// Left in the cell of a binder in the untaken branch of an if or a case, for
+    /// `Args` to hand out again
+    Free,
+}
+
+impl<'prg> Expr<'prg> {
+    /// The term with every variable that evaluation already bound replaced by its value.
+    pub fn to_term(&self) -> Term {
+        read_back(self)
+    }
+}
+
+impl<'prg> Expr<'prg> {
+    /// Builds `term` with a cell from `args` for each binder, which all of its
+    /// variables point to. A variable whose binder is not in the term gets a cell
+    /// of its own that nothing binds. Fails if the term has a `fix`, or if `args`
+    /// runs out of cells, counting those evaluation has freed.
+    pub fn from_term(args: &'prg Args<'prg>, term: &Term) -> Result<Self, BuildError> {
+        if let Some(at) = term.find_fix() {
+            return Err(BuildError::Fix(at));
+        }
+        enum Task<'a, 'prg> {
+            Build(&'a Term),
+            Lam(Ptr<'prg>, Option<Rc<str>>, Option<Type>),
+            App,
+            Let(Ptr<'prg>, Option<Rc<str>>),
+            Prim(PrimOp, usize),
+            If,
+            Pair,
+            Split(Ptr<'prg>, Option<Rc<str>>, Ptr<'prg>, Option<Rc<str>>),
+            Inj(bool),
+            Case(Ptr<'prg>, Option<Rc<str>>, Ptr<'prg>, Option<Rc<str>>),
+        }
+        let next_cell = || args.next_cell().ok_or(BuildError::OutOfCells);
+        let mut cells = HashMap::new();
+        let mut todo = vec![Task::Build(term)];
+        let mut done = Vec::new();
+        while let Some(task) = todo.pop() {
+            match task {
+                Task::Build(Term::Var(id)) => {
+                    let cell = match cells.get(id) {
+                        Some(&cell) => cell,
+                        None => {
+                            let cell = next_cell()?;
+                            cells.insert(*id, cell);
+                            cell
+                        }
+                    };
+                    done.push(Expr::Ptr(Ptr(cell)));
+                }
+                Task::Build(Term::Const(c)) => done.push(Expr::Bas(c.clone())),
+                Task::Build(Term::Lam(binder, body)) => {
+                    let cell = next_cell()?;
+                    cells.insert(binder.id, cell);
+                    todo.push(Task::Lam(Ptr(cell), binder.hint.clone(), binder.ty.clone()));
+                    todo.push(Task::Build(body));
+                }
+                Task::Build(Term::App(f, v)) => {
+                    todo.push(Task::App);
+                    todo.push(Task::Build(v));
+                    todo.push(Task::Build(f));
+                }
+                Task::Build(Term::Let(binder, v, body)) => {
+                    let cell = next_cell()?;
+                    cells.insert(binder.id, cell);
+                    todo.push(Task::Let(Ptr(cell), binder.hint.clone()));
+                    todo.push(Task::Build(body));
+                    todo.push(Task::Build(v));
+                }
+                Task::Build(Term::Prim(op, operands)) => {
+                    todo.push(Task::Prim(*op, operands.len()));
+                    todo.extend(operands.iter().rev().map(Task::Build));
+                }
+                Task::Build(Term::If(c, t, e)) => {
+                    todo.push(Task::If);
+                    todo.extend([Task::Build(e), Task::Build(t), Task::Build(c)]);
+                }
+                Task::Build(Term::Pair(a, b)) => {
+                    todo.extend([Task::Pair, Task::Build(b), Task::Build(a)]);
+                }
+                Task::Build(Term::Split(x, y, v, body)) => {
+                    let (x_cell, y_cell) = (next_cell()?, next_cell()?);
+                    cells.insert(x.id, x_cell);
+                    cells.insert(y.id, y_cell);
+                    let (x_hint, y_hint) = (x.hint.clone(), y.hint.clone());
+                    todo.push(Task::Split(Ptr(x_cell), x_hint, Ptr(y_cell), y_hint));
+                    todo.extend([Task::Build(body), Task::Build(v)]);
+                }
+                Task::Build(Term::Inl(v)) => todo.extend([Task::Inj(true), Task::Build(v)]),
+                Task::Build(Term::Inr(v)) => todo.extend([Task::Inj(false), Task::Build(v)]),
+                Task::Build(Term::Case(v, x, l, y, r)) => {
+                    let (x_cell, y_cell) = (next_cell()?, next_cell()?);
+                    cells.insert(x.id, x_cell);
+                    cells.insert(y.id, y_cell);
+                    let (x_hint, y_hint) = (x.hint.clone(), y.hint.clone());
+                    todo.push(Task::Case(Ptr(x_cell), x_hint, Ptr(y_cell), y_hint));
+                    todo.extend([Task::Build(r), Task::Build(l), Task::Build(v)]);
+                }
+                Task::Build(Term::Fix(..)) => unreachable!("rejected above"),
+                Task::Build(Term::Invalid) => done.push(Expr::Invalid),
+                Task::Lam(ptr, hint, ty) => {
+                    let body = done.pop().expect("built body");
+                    done.push(Expr::Lam(Lam(ptr, Box::new(body), hint), ty));
+                }
+                Task::Let(ptr, hint) => {
+                    let body = done.pop().expect("built body");
+                    let v = done.pop().expect("built value");
+                    done.push(Expr::Let(Box::new(v), Lam(ptr, Box::new(body), hint)));
+                }
+                Task::App => {
+                    let v = done.pop().expect("built argument");
+                    let f = done.pop().expect("built function");
+                    done.push(make_app(f, v));
+                }
+                Task::Prim(op, n) => {
+                    let operands = done.split_off(done.len() - n);
+                    done.push(Expr::Prim(op, operands));
+                }
+                Task::If => {
+                    let e = done.pop().expect("built else branch");
+                    let t = done.pop().expect("built then branch");
+                    let c = done.pop().expect("built condition");
+                    done.push(make_if(c, t, e));
+                }
+                Task::Pair => {
+                    let b = done.pop().expect("built second component");
+                    let a = done.pop().expect("built first component");
+                    done.push(make_pair(a, b));
+                }
+                Task::Split(x, x_hint, y, y_hint) => {
+                    let body = done.pop().expect("built body");
+                    let v = done.pop().expect("built pair");
+                    let lam = Lam(y, Box::new(body), y_hint);
+                    done.push(Expr::Split(Box::new(v), x, x_hint, lam));
+                }
+                Task::Inj(left) => {
+                    let v = done.pop().expect("built injected value");
+                    done.push(if left { make_inl(v) } else { make_inr(v) });
+                }
+                Task::Case(x, x_hint, y, y_hint) => {
+                    let r = done.pop().expect("built right branch");
+                    let l = done.pop().expect("built left branch");
+                    let v = done.pop().expect("built scrutinee");
+                    let l = Lam(x, Box::new(l), x_hint);
+                    let r = Lam(y, Box::new(r), y_hint);
+                    done.push(Expr::Case(Box::new(v), l, r));
+                }
+            }
+        }
+        Ok(done.pop().expect("built term"))
+    }
+}
+
+impl<'prg> From<&Expr<'prg>> for Term {
+    fn from(e: &Expr<'prg>) -> Self {
+        e.to_term()
+    }
+}
+
+/// Whether `a` and `b` are the same term up to renaming binders, comparing
+/// variables by the lambdas they point to, so the two may live in different arenas.
+pub fn alpha_eq(a: &Expr<'_>, b: &Expr<'_>) -> bool {
+    a.to_term().alpha_eq(&b.to_term())
 }
 
 impl<'prg> std::fmt::Display for Expr<'prg> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Expr::Ptr(Ptr(ptr)) => write!(f, "@{:p}", *ptr),
-            Expr::Bas(b) => write!(f, "{b}"),
-            Expr::Lam(Lam(Ptr(ptr), body)) => write!(f, "(\\{:p} -> {})", *ptr, *body),
-            Expr::App(fun, val) => write!(f, "({} {})", **fun, **val),
-            Expr::Invalid => write!(f, "NUL"),
+        write!(f, "{}", self.to_term())
+    }
+}
+
+/// Nodes and cells are keyed by address; a value still waiting in its cell
+/// is drawn hanging off the cell.
+impl<'prg> ToDot for Expr<'prg> {
+    fn to_dot(&self) -> String {
+        let mut w = DotWriter::new();
+        let (id, _) = w.id(self as *const Expr as usize);
+        dot_walk(&mut w, self, id);
+        w.finish()
+    }
+}
+
+// draws e as node id, with the values in its cells hanging off them
+fn dot_walk<'prg>(w: &mut DotWriter, e: &Expr<'prg>, id: usize) {
+    let taken = Taken::from(e);
+    let mut todo = vec![(e, id)];
+    while let Some((e, id)) = todo.pop() {
+        match e {
+            Expr::Ptr(Ptr(cell)) => {
+                w.node(id, "var");
+                let cell = dot_cell(w, &taken, &mut todo, cell, "");
+                w.back_edge(id, cell);
+            }
+            Expr::Bas(c) => w.node(id, &c.to_string()),
+            Expr::Lam(Lam(Ptr(cell), body, hint), _) => {
+                let hint = hint.as_deref().unwrap_or("");
+                w.node(id, &format!("λ{hint}"));
+                let cell = dot_cell(w, &taken, &mut todo, cell, hint);
+                w.edge(id, cell, "arg");
+                let (body_id, _) = w.id(&**body as *const Expr as usize);
+                w.edge(id, body_id, "body");
+                todo.push((body, body_id));
+            }
+            Expr::App(f, v) => {
+                w.node(id, "@");
+                for (e, label) in [(&**f, "fun"), (&**v, "arg")] {
+                    let (child, _) = w.id(e as *const Expr as usize);
+                    w.edge(id, child, label);
+                    todo.push((e, child));
+                }
+            }
+            Expr::Let(v, Lam(Ptr(cell), body, hint)) => {
+                let hint = hint.as_deref().unwrap_or("");
+                w.node(id, &format!("let {hint}"));
+                let cell = dot_cell(w, &taken, &mut todo, cell, hint);
+                w.edge(id, cell, "arg");
+                for (e, label) in [(&**v, "value"), (&**body, "body")] {
+                    let (child, _) = w.id(e as *const Expr as usize);
+                    w.edge(id, child, label);
+                    todo.push((e, child));
+                }
+            }
+            Expr::Prim(op, operands) => {
+                w.node(id, op.name());
+                for (i, e) in operands.iter().enumerate() {
+                    let (child, _) = w.id(e as *const Expr as usize);
+                    w.edge(id, child, &i.to_string());
+                    todo.push((e, child));
+                }
+            }
+            Expr::If(c, t, e) => {
+                w.node(id, "if");
+                for (e, label) in [(&**c, "cond"), (&**t, "then"), (&**e, "else")] {
+                    let (child, _) = w.id(e as *const Expr as usize);
+                    w.edge(id, child, label);
+                    todo.push((e, child));
+                }
+            }
+            Expr::Pair(a, b) => {
+                w.node(id, "pair");
+                for (e, label) in [(&**a, "fst"), (&**b, "snd")] {
+                    let (child, _) = w.id(e as *const Expr as usize);
+                    w.edge(id, child, label);
+                    todo.push((e, child));
+                }
+            }
+            Expr::Split(v, Ptr(x), x_hint, Lam(Ptr(y), body, y_hint)) => {
+                let x_hint = x_hint.as_deref().unwrap_or("");
+                let y_hint = y_hint.as_deref().unwrap_or("");
+                w.node(id, &format!("let ({x_hint}, {y_hint})"));
+                for (cell, hint, label) in [(x, x_hint, "fst"), (y, y_hint, "snd")] {
+                    let cell = dot_cell(w, &taken, &mut todo, cell, hint);
+                    w.edge(id, cell, label);
+                }
+                for (e, label) in [(&**v, "value"), (&**body, "body")] {
+                    let (child, _) = w.id(e as *const Expr as usize);
+                    w.edge(id, child, label);
+                    todo.push((e, child));
+                }
+            }
+            Expr::Inl(v) | Expr::Inr(v) => {
+                w.node(
+                    id,
+                    if matches!(e, Expr::Inl(_)) {
+                        "inl"
+                    } else {
+                        "inr"
+                    },
+                );
+                let (child, _) = w.id(&**v as *const Expr as usize);
+                w.edge(id, child, "inj");
+                todo.push((v, child));
+            }
+            Expr::Case(v, Lam(Ptr(x), l, x_hint), Lam(Ptr(y), r, y_hint)) => {
+                w.node(id, "case");
+                let (child, _) = w.id(&**v as *const Expr as usize);
+                w.edge(id, child, "scrutinee");
+                todo.push((v, child));
+                for (cell, hint, e, label) in [(x, x_hint, l, "left"), (y, y_hint, r, "right")] {
+                    let cell = dot_cell(w, &taken, &mut todo, cell, hint.as_deref().unwrap_or(""));
+                    w.edge(id, cell, &format!("{label} arg"));
+                    let (child, _) = w.id(&**e as *const Expr as usize);
+                    w.edge(id, child, label);
+                    todo.push((e, child));
+                }
+            }
+            Expr::Invalid => w.node(id, "invalid"),
+            Expr::Moved => w.node(id, "moved"),
+            Expr::Free => w.node(id, "free"),
         }
     }
 }
 
-pub const ZERO: &str = "0";
-pub const ONE: &str = "1";
-pub const UNIT: &str = "()";
+// draws the cell once, with the value it holds as more of the walk in todo
+fn dot_cell<'a, 'prg>(
+    w: &mut DotWriter,
+    taken: &'a Taken<'prg>,
+    todo: &mut Vec<(&'a Expr<'prg>, usize)>,
+    cell: &Cell<Expr<'prg>>,
+    hint: &str,
+) -> usize {
+    let (id, fresh) = w.id(cell as *const _ as usize);
+    if fresh {
+        w.cell(id, hint);
+        if let Some(value) = taken.value(cell) {
+            let (value_id, _) = w.id(value as *const Expr as usize);
+            w.edge(id, value_id, "value");
+            todo.push((value, value_id));
+        }
+    }
+    id
+}
+
+// The values bound in the cells reachable from a term, through its variables and
+// binders and the values in other cells. They are taken out of their cells, which
+// is the only way to look inside a Cell holding something that isn't Copy, so the
+// term can be walked with them in place, and go back in once this is dropped.
+struct Taken<'prg> {
+    values: HashMap<*const Cell<Expr<'prg>>, (&'prg Cell<Expr<'prg>>, Expr<'prg>)>,
+    // cells whose value leads back to the cell itself, which evaluation never
+    // makes but a hand-built term can
+    cyclic: HashSet<*const Cell<Expr<'prg>>>,
+}
+
+impl<'prg> Taken<'prg> {
+    fn from(e: &Expr<'prg>) -> Self {
+        enum Visit<'prg> {
+            Cell(&'prg Cell<Expr<'prg>>),
+            // done with the value of this cell and every value it leads to
+            Leave(*const Cell<Expr<'prg>>),
+        }
+        let mut taken = Self {
+            values: HashMap::new(),
+            cyclic: HashSet::new(),
+        };
+        // the cells whose values lead to the one being visited
+        let mut open = HashSet::new();
+        let mut todo = Vec::new();
+        cells_in(e, |cell| todo.push(Visit::Cell(cell)));
+        while let Some(visit) = todo.pop() {
+            let cell = match visit {
+                Visit::Cell(cell) => cell,
+                Visit::Leave(key) => {
+                    open.remove(&key);
+                    continue;
+                }
+            };
+            let key = cell as *const _;
+            if open.contains(&key) {
+                taken.cyclic.insert(key);
+                continue;
+            }
+            if taken.values.contains_key(&key) {
+                continue;
+            }
+            match cell.replace(Expr::Invalid) {
+                empty @ (Expr::Invalid | Expr::Moved | Expr::Free) => cell.set(empty),
+                value => {
+                    open.insert(key);
+                    todo.push(Visit::Leave(key));
+                    cells_in(&value, |cell| todo.push(Visit::Cell(cell)));
+                    taken.values.insert(key, (cell, value));
+                }
+            }
+        }
+        taken
+    }
+    // the value the cell holds, if any
+    fn value(&self, cell: &Cell<Expr<'prg>>) -> Option<&Expr<'prg>> {
+        let key = cell as *const _;
+        self.values.get(&key).map(|(_, value)| value)
+    }
+    // the value a variable pointing at the cell stands for, unless the value
+    // leads back to it
+    fn substitute(&self, cell: &Cell<Expr<'prg>>) -> Option<&Expr<'prg>> {
+        let key = cell as *const _;
+        if self.cyclic.contains(&key) {
+            return None;
+        }
+        self.value(cell)
+    }
+}
 
-fn step(e: &mut Box<Expr<'_>>) -> bool {
-    match std::mem::replace(e.as_mut(), Expr::Invalid) {
-        Expr::Invalid => {
-            unreachable!("Evaluating empty expr")
+impl Drop for Taken<'_> {
+    fn drop(&mut self) {
+        for (_, (cell, value)) in self.values.drain() {
+            cell.set(value);
         }
-        expr @ (Expr::Bas(_) | Expr::Lam(_)) => {
-            **e = expr;
-            false
+    }
+}
+
+// calls f with the cell of every variable and binder in e, without going into cells
+fn cells_in<'prg>(e: &Expr<'prg>, mut f: impl FnMut(&'prg Cell<Expr<'prg>>)) {
+    let mut todo = vec![e];
+    while let Some(e) = todo.pop() {
+        match e {
+            Expr::Ptr(Ptr(cell)) => f(cell),
+            Expr::Lam(Lam(Ptr(cell), body, _), _) => {
+                f(cell);
+                todo.push(body);
+            }
+            Expr::Let(v, Lam(Ptr(cell), body, _)) => {
+                f(cell);
+                todo.extend([&**v, &**body]);
+            }
+            Expr::Split(v, Ptr(x), _, Lam(Ptr(y), body, _)) => {
+                f(x);
+                f(y);
+                todo.extend([&**v, &**body]);
+            }
+            Expr::Case(v, Lam(Ptr(x), l, _), Lam(Ptr(y), r, _)) => {
+                f(x);
+                f(y);
+                todo.extend([&**v, &**l, &**r]);
+            }
+            Expr::App(a, b) | Expr::Pair(a, b) => todo.extend([&**a, &**b]),
+            Expr::Prim(_, operands) => todo.extend(operands),
+            Expr::If(c, t, e) => todo.extend([&**c, &**t, &**e]),
+            Expr::Inl(v) | Expr::Inr(v) => todo.push(v),
+            Expr::Bas(_) | Expr::Invalid | Expr::Moved | Expr::Free => {}
         }
-        Expr::Ptr(Ptr(cell)) => {
-            let deref = cell.replace(Expr::Invalid);
-            debug_assert!(!matches!(deref, Expr::Invalid));
-            **e = deref;
-            true
+    }
+}
+
+// reads e back with a variable that evaluation already bound standing for its value
+fn read_back(e: &Expr<'_>) -> Term {
+    let taken = Taken::from(e);
+    ReadBack::new().run(e, |rb, mut e| {
+        while let Expr::Ptr(Ptr(cell)) = e {
+            match taken.substitute(cell) {
+                Some(value) => e = value,
+                None => return Shape::Leaf(rb.var(*cell as *const _)),
+            }
         }
-        Expr::App(mut f, mut v) => {
-            if step(&mut f) || step(&mut v) {
-                **e = Expr::App(f, v);
-                true
-            } else if let Expr::Lam(Lam(Ptr(cell), body)) = *f {
-                let _old = cell.replace(*v);
-                debug_assert!(matches!(_old, Expr::Invalid));
-                **e = *body;
-                true
-            } else {
-                // we're totally stuck, replace with the old app
-                **e = Expr::App(f, v);
-                false
+        match e {
+            Expr::Ptr(_) => unreachable!("followed above"),
+            Expr::Bas(c) => Shape::Leaf(Term::Const(c.clone())),
+            Expr::Lam(Lam(Ptr(cell), body, hint), ty) => {
+                let binder = rb.binder(*cell as *const _, hint.clone());
+                let ty = ty.clone();
+                Shape::Lam(Binder { ty, ..binder }, body)
+            }
+            Expr::App(f, v) => Shape::App(f, v),
+            Expr::Let(v, Lam(Ptr(cell), body, hint)) => {
+                Shape::Let(rb.binder(*cell as *const _, hint.clone()), v, body)
+            }
+            Expr::Prim(op, operands) => Shape::Prim(*op, operands.iter().collect()),
+            Expr::If(c, t, e) => Shape::If(c, t, e),
+            Expr::Pair(a, b) => Shape::Pair(a, b),
+            Expr::Split(v, Ptr(x), x_hint, Lam(Ptr(y), body, y_hint)) => {
+                let x = rb.binder(*x as *const _, x_hint.clone());
+                let y = rb.binder(*y as *const _, y_hint.clone());
+                Shape::Split(x, y, v, body)
             }
+            Expr::Inl(v) => Shape::Inl(v),
+            Expr::Inr(v) => Shape::Inr(v),
+            Expr::Case(v, Lam(Ptr(x), l, x_hint), Lam(Ptr(y), r, y_hint)) => {
+                let x = rb.binder(*x as *const _, x_hint.clone());
+                let y = rb.binder(*y as *const _, y_hint.clone());
+                Shape::Case(v, x, l, y, r)
+            }
+            Expr::Invalid | Expr::Moved | Expr::Free => Shape::Leaf(Term::Invalid),
         }
+    })
+}
+
+impl<'prg> std::fmt::Debug for Expr<'prg> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+// A zipper over the term being evaluated: the subterm in focus,
+// and the contexts it was taken out of with the innermost last
+struct Machine<'prg> {
+    focus: Box<Expr<'prg>>,
+    stack: Vec<Frame<'prg>>,
+    fuel: usize,
+    max_depth: usize,
+    // nodes in the term, leaving out values bound in cells until a variable
+    // moves them out again
+    nodes: usize,
+    strategy: Strategy,
+}
+
+enum Frame<'prg> {
+    // evaluating the function, the argument is parked here
+    Fun(Box<Expr<'prg>>),
+    // evaluating the argument of this function value
+    Arg(Box<Expr<'prg>>),
+    // normalizing the body of a lambda, whose binder and name are parked here
+    Body(Ptr<'prg>, Option<Rc<str>>, Option<Type>),
+    // normalizing the function of a neutral application, one stuck on a variable
+    // that nothing binds, with the argument parked here
+    NeutralFun(Box<Expr<'prg>>),
+    // normalizing the argument of a neutral application, with the function parked here
+    NeutralArg(Box<Expr<'prg>>),
+    // evaluating the value of a let, whose binder and body are parked here
+    Bound(Lam<'prg>),
+    // evaluating the operand at this index of a primitive, whose operands are
+    // parked here with Invalid in its place
+    Operand(PrimOp, Vec<Expr<'prg>>, usize),
+    // evaluating the condition of an if, whose branches are parked here
+    Cond(Box<Expr<'prg>>, Box<Expr<'prg>>),
+    // normalizing the then branch of an if stuck on its condition, with the
+    // condition and the else branch parked here
+    NeutralThen(Box<Expr<'prg>>, Box<Expr<'prg>>),
+    // normalizing the else branch of an if stuck on its condition, with the
+    // condition and the then branch parked here
+    NeutralElse(Box<Expr<'prg>>, Box<Expr<'prg>>),
+    // evaluating the first component of a pair, with the second parked here
+    Fst(Box<Expr<'prg>>),
+    // evaluating the second component of a pair, with the first parked here
+    Snd(Box<Expr<'prg>>),
+    // evaluating what an injection injects, and whether it injects into the left
+    Inj(bool),
+    // evaluating the pair of a split, whose binders and body are parked here
+    Split(Ptr<'prg>, Option<Rc<str>>, Lam<'prg>),
+    // evaluating the scrutinee of a case, whose branches are parked here
+    Case(Lam<'prg>, Lam<'prg>),
+    // normalizing the body of a split stuck on its pair, with the pair and
+    // both binders parked here
+    NeutralSplit(
+        Box<Expr<'prg>>,
+        Ptr<'prg>,
+        Option<Rc<str>>,
+        Ptr<'prg>,
+        Option<Rc<str>>,
+    ),
+    // normalizing the left branch of a case stuck on its scrutinee, with the
+    // scrutinee, the left binder and the right branch parked here
+    NeutralLeft(Box<Expr<'prg>>, Ptr<'prg>, Option<Rc<str>>, Lam<'prg>),
+    // normalizing the right branch of a case stuck on its scrutinee, with the
+    // scrutinee, the left branch and the right binder parked here
+    NeutralRight(Box<Expr<'prg>>, Lam<'prg>, Ptr<'prg>, Option<Rc<str>>),
+}
+
+impl Frame<'_> {
+    // whether the frame is one only normalizing pushes
+    fn normalizing(&self) -> bool {
+        matches!(
+            self,
+            Frame::Body(..)
+                | Frame::NeutralFun(_)
+                | Frame::NeutralArg(_)
+                | Frame::NeutralThen(..)
+                | Frame::NeutralElse(..)
+                | Frame::NeutralSplit(..)
+                | Frame::NeutralLeft(..)
+                | Frame::NeutralRight(..)
+        )
+    }
+}
+
+impl<'prg> Machine<'prg> {
+    fn new(e: Expr<'prg>, strategy: Strategy, limits: &Limits) -> Self {
+        Self {
+            nodes: size(&e),
+            focus: Box::new(e),
+            stack: Vec::new(),
+            fuel: limits.max_steps,
+            max_depth: limits.max_depth,
+            strategy,
+        }
+    }
+    fn location(&self) -> Location {
+        let path = self.stack.iter().map(|frame| match frame {
+            Frame::Fun(_) | Frame::NeutralFun(_) => Branch::Fun,
+            Frame::Arg(_) | Frame::NeutralArg(_) => Branch::Arg,
+            Frame::Body(..) => Branch::Body,
+            Frame::Bound(_) => Branch::Bound,
+            Frame::Operand(_, _, i) => Branch::Operand(*i),
+            Frame::Cond(..) => Branch::Cond,
+            Frame::NeutralThen(..) => Branch::Then,
+            Frame::NeutralElse(..) => Branch::Else,
+            Frame::Fst(_) => Branch::Fst,
+            Frame::Snd(_) => Branch::Snd,
+            Frame::Inj(_) => Branch::Inj,
+            Frame::Split(..) | Frame::Case(..) => Branch::Scrutinee,
+            Frame::NeutralSplit(..) => Branch::Body,
+            Frame::NeutralLeft(..) => Branch::Left,
+            Frame::NeutralRight(..) => Branch::Right,
+        });
+        Location::Path(path.collect())
+    }
+    // whether the value in focus is part of the result, rather than a function about
+    // to be applied or an argument about to be bound, unless those get normalized too;
+    // operands, components and scrutinees are normalized whenever evaluation is
+    // strong, since what is stuck on a variable is part of the result
+    fn in_result(&self) -> bool {
+        match self.stack.last() {
+            None => true,
+            Some(Frame::Arg(_) | Frame::Bound(_)) => self.strategy.normalizes_args(),
+            Some(
+                Frame::Operand(..)
+                | Frame::Cond(..)
+                | Frame::Fst(_)
+                | Frame::Snd(_)
+                | Frame::Inj(_)
+                | Frame::Split(..)
+                | Frame::Case(..),
+            ) => self.strategy.strong(),
+            Some(frame) => frame.normalizing(),
+        }
+    }
+    fn push(&mut self, frame: Frame<'prg>) -> Option<Progress> {
+        self.stack.push(frame);
+        (self.stack.len() > self.max_depth).then_some(Progress::Exhausted(Exhausted::Depth))
+    }
+    // whether the focus is taken apart by the split or case on top of the stack, so
+    // a pair or an injection is only normalized first if arguments are
+    fn taken_apart(&self) -> bool {
+        matches!(self.stack.last(), Some(Frame::Split(..) | Frame::Case(..)))
+            && !self.strategy.normalizes_args()
+    }
+    // puts the term back together around the focus, leaving the machine empty
+    fn plug(&mut self) -> Expr<'prg> {
+        let mut e = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+        for frame in self.stack.drain(..).rev() {
+            e = Box::new(match frame {
+                Frame::Fun(v) | Frame::NeutralFun(v) => Expr::App(e, v),
+                Frame::Arg(f) | Frame::NeutralArg(f) => Expr::App(f, e),
+                Frame::Body(ptr, hint, ty) => Expr::Lam(Lam(ptr, e, hint), ty),
+                Frame::Bound(lam) => Expr::Let(e, lam),
+                Frame::Operand(op, mut operands, i) => {
+                    operands[i] = *e;
+                    Expr::Prim(op, operands)
+                }
+                Frame::Cond(t, f) => Expr::If(e, t, f),
+                Frame::NeutralThen(c, f) => Expr::If(c, e, f),
+                Frame::NeutralElse(c, t) => Expr::If(c, t, e),
+                Frame::Fst(b) => Expr::Pair(e, b),
+                Frame::Snd(a) => Expr::Pair(a, e),
+                Frame::Inj(true) => Expr::Inl(e),
+                Frame::Inj(false) => Expr::Inr(e),
+                Frame::Split(x, x_hint, lam) => Expr::Split(e, x, x_hint, lam),
+                Frame::Case(l, r) => Expr::Case(e, l, r),
+                Frame::NeutralSplit(v, x, x_hint, y, y_hint) => {
+                    Expr::Split(v, x, x_hint, Lam(y, e, y_hint))
+                }
+                Frame::NeutralLeft(v, x, x_hint, r) => Expr::Case(v, Lam(x, e, x_hint), r),
+                Frame::NeutralRight(v, l, y, y_hint) => Expr::Case(v, l, Lam(y, e, y_hint)),
+            });
+        }
+        *e
+    }
+    // takes a term plugged by plug apart again along the path it was plugged up,
+    // with whether each frame on it was for normalizing
+    fn unplug(&mut self, e: Expr<'prg>, path: &[Branch], normalizing: &[bool]) {
+        let mut e = Box::new(e);
+        for (depth, branch) in path.iter().enumerate() {
+            e = match (branch, *e) {
+                (Branch::Fun, Expr::App(f, v)) if normalizing[depth] => {
+                    self.stack.push(Frame::NeutralFun(v));
+                    f
+                }
+                (Branch::Arg, Expr::App(f, v)) if normalizing[depth] => {
+                    self.stack.push(Frame::NeutralArg(f));
+                    v
+                }
+                (Branch::Body, Expr::Lam(Lam(ptr, body, hint), ty)) => {
+                    self.stack.push(Frame::Body(ptr, hint, ty));
+                    body
+                }
+                (Branch::Bound, Expr::Let(v, lam)) => {
+                    self.stack.push(Frame::Bound(lam));
+                    v
+                }
+                (&Branch::Operand(i), Expr::Prim(op, mut operands)) => {
+                    let operand = std::mem::replace(&mut operands[i], Expr::Invalid);
+                    self.stack.push(Frame::Operand(op, operands, i));
+                    Box::new(operand)
+                }
+                (Branch::Cond, Expr::If(c, t, e)) => {
+                    self.stack.push(Frame::Cond(t, e));
+                    c
+                }
+                (Branch::Then, Expr::If(c, t, e)) => {
+                    self.stack.push(Frame::NeutralThen(c, e));
+                    t
+                }
+                (Branch::Else, Expr::If(c, t, e)) => {
+                    self.stack.push(Frame::NeutralElse(c, t));
+                    e
+                }
+                (Branch::Fst, Expr::Pair(a, b)) => {
+                    self.stack.push(Frame::Fst(b));
+                    a
+                }
+                (Branch::Snd, Expr::Pair(a, b)) => {
+                    self.stack.push(Frame::Snd(a));
+                    b
+                }
+                (Branch::Inj, Expr::Inl(v)) => {
+                    self.stack.push(Frame::Inj(true));
+                    v
+                }
+                (Branch::Inj, Expr::Inr(v)) => {
+                    self.stack.push(Frame::Inj(false));
+                    v
+                }
+                (Branch::Scrutinee, Expr::Split(v, x, x_hint, lam)) => {
+                    self.stack.push(Frame::Split(x, x_hint, lam));
+                    v
+                }
+                (Branch::Scrutinee, Expr::Case(v, l, r)) => {
+                    self.stack.push(Frame::Case(l, r));
+                    v
+                }
+                (Branch::Body, Expr::Split(v, x, x_hint, Lam(y, body, y_hint))) => {
+                    self.stack
+                        .push(Frame::NeutralSplit(v, x, x_hint, y, y_hint));
+                    body
+                }
+                (Branch::Left, Expr::Case(v, Lam(x, l, x_hint), r)) => {
+                    self.stack.push(Frame::NeutralLeft(v, x, x_hint, r));
+                    l
+                }
+                (Branch::Right, Expr::Case(v, l, Lam(y, r, y_hint))) => {
+                    self.stack.push(Frame::NeutralRight(v, l, y, y_hint));
+                    r
+                }
+                (Branch::Fun, Expr::App(f, v)) => {
+                    self.stack.push(Frame::Fun(v));
+                    f
+                }
+                (Branch::Arg, Expr::App(f, v)) => {
+                    self.stack.push(Frame::Arg(f));
+                    v
+                }
+                _ => unreachable!("the term was plugged along this path"),
+            };
+        }
+        self.focus = e;
+    }
+    // shows the observer the whole term, then goes back to where evaluation was
+    fn observe_step(&mut self, obs: &mut dyn EvalObserver<Expr<'prg>>) {
+        let Location::Path(path) = self.location() else {
+            unreachable!("heap terms are located by path");
+        };
+        let normalizing: Vec<bool> = self.stack.iter().map(Frame::normalizing).collect();
+        let term = self.plug();
+        obs.on_step(&term);
+        self.unplug(term, &path, &normalizing);
+    }
+    // spends the fuel for one reduction, if there is any left
+    fn burn(&mut self) -> bool {
+        let has_fuel = self.fuel > 0;
+        self.fuel = self.fuel.saturating_sub(1);
+        has_fuel
+    }
+    /// Runs up to and including the next reduction, staying focused on the reduced
+    /// subterm so the next call picks up from there.
+    fn step(&mut self, obs: &mut dyn EvalObserver<Expr<'prg>>) -> Result<Progress, EvalError> {
+        let out_of_steps = Ok(Progress::Exhausted(Exhausted::Steps));
+        loop {
+            match std::mem::replace(self.focus.as_mut(), Expr::Invalid) {
+                Expr::Invalid | Expr::Moved | Expr::Free => {
+                    return Err(EvalError::Uninitialized(self.location()))
+                }
+                Expr::Ptr(Ptr(cell)) => match cell.replace(Expr::Moved) {
+                    // when normalizing, a variable nothing binds is a value
+                    Expr::Invalid if self.strategy.strong() => {
+                        cell.set(Expr::Invalid);
+                        *self.focus = Expr::Ptr(Ptr(cell));
+                        if let Some(progress) = self.ret(obs)? {
+                            return Ok(progress);
+                        }
+                    }
+                    empty @ (Expr::Invalid | Expr::Free) => {
+                        cell.set(empty);
+                        *self.focus = Expr::Ptr(Ptr(cell));
+                        return Err(EvalError::DanglingPointer(self.location()));
+                    }
+                    Expr::Moved => return Err(EvalError::DoubleDeref(self.location())),
+                    deref if !self.burn() => {
+                        cell.set(deref);
+                        *self.focus = Expr::Ptr(Ptr(cell));
+                        return out_of_steps;
+                    }
+                    deref => {
+                        // the observer sees the variable with its value still in place
+                        cell.set(deref);
+                        obs.on_deref(&Expr::Ptr(Ptr(cell)));
+                        *self.focus = cell.replace(Expr::Moved);
+                        self.nodes = self.nodes + size(&self.focus) - 1;
+                        return Ok(Progress::Reduced);
+                    }
+                },
+                Expr::App(f, v) => {
+                    self.focus = f;
+                    if let Some(exhausted) = self.push(Frame::Fun(v)) {
+                        return Ok(exhausted);
+                    }
+                }
+                Expr::Let(v, lam) if self.strategy.by_name() => {
+                    if !self.burn() {
+                        *self.focus = Expr::Let(v, lam);
+                        return out_of_steps;
+                    }
+                    self.bind_let(v, lam, obs)?;
+                    return Ok(Progress::Reduced);
+                }
+                Expr::Let(v, lam) => {
+                    self.focus = v;
+                    if let Some(exhausted) = self.push(Frame::Bound(lam)) {
+                        return Ok(exhausted);
+                    }
+                }
+                Expr::If(c, t, e) => {
+                    self.focus = c;
+                    if let Some(exhausted) = self.push(Frame::Cond(t, e)) {
+                        return Ok(exhausted);
+                    }
+                }
+                // unless arguments are bound unevaluated, a pair or an injection is
+                // only a value once what it holds is
+                Expr::Pair(a, b) if !self.strategy.by_name() => {
+                    self.focus = a;
+                    if let Some(exhausted) = self.push(Frame::Fst(b)) {
+                        return Ok(exhausted);
+                    }
+                }
+                Expr::Inl(v) if !self.strategy.by_name() => {
+                    self.focus = v;
+                    if let Some(exhausted) = self.push(Frame::Inj(true)) {
+                        return Ok(exhausted);
+                    }
+                }
+                Expr::Inr(v) if !self.strategy.by_name() => {
+                    self.focus = v;
+                    if let Some(exhausted) = self.push(Frame::Inj(false)) {
+                        return Ok(exhausted);
+                    }
+                }
+                Expr::Split(v, x, x_hint, lam) => {
+                    self.focus = v;
+                    if let Some(exhausted) = self.push(Frame::Split(x, x_hint, lam)) {
+                        return Ok(exhausted);
+                    }
+                }
+                Expr::Case(v, l, r) => {
+                    self.focus = v;
+                    if let Some(exhausted) = self.push(Frame::Case(l, r)) {
+                        return Ok(exhausted);
+                    }
+                }
+                Expr::Prim(op, mut operands) => match operands.first_mut() {
+                    Some(first) => {
+                        *self.focus = std::mem::replace(first, Expr::Invalid);
+                        if let Some(exhausted) = self.push(Frame::Operand(op, operands, 0)) {
+                            return Ok(exhausted);
+                        }
+                    }
+                    None => {
+                        let progress = self.apply_prim(op, operands, obs)?;
+                        return Ok(progress.expect("no operands to be stuck on"));
+                    }
+                },
+                value @ (Expr::Bas(_)
+                | Expr::Lam(..)
+                | Expr::Pair(..)
+                | Expr::Inl(_)
+                | Expr::Inr(_)) => {
+                    *self.focus = value;
+                    if let Some(progress) = self.ret(obs)? {
+                        return Ok(progress);
+                    }
+                }
+            }
+        }
+    }
+    // Hands the value in focus to the frames below it, until there is a
+    // reduction to report or another subterm to evaluate, which is left in focus.
+    fn ret(
+        &mut self,
+        obs: &mut dyn EvalObserver<Expr<'prg>>,
+    ) -> Result<Option<Progress>, EvalError> {
+        // whether the focus is already normalized, so it is not gone into again
+        let mut normal = false;
+        loop {
+            if self.strategy.strong() && !normal && self.in_result() {
+                match std::mem::replace(self.focus.as_mut(), Expr::Invalid) {
+                    Expr::Lam(Lam(ptr, body, hint), ty) => {
+                        self.focus = body;
+                        return Ok(self.push(Frame::Body(ptr, hint, ty)));
+                    }
+                    // the function of a neutral application is already a value
+                    Expr::App(f, v) => {
+                        self.focus = f;
+                        if let Some(exhausted) = self.push(Frame::NeutralFun(v)) {
+                            return Ok(Some(exhausted));
+                        }
+                        continue;
+                    }
+                    // the components of a pair and what an injection injects, unless
+                    // they were evaluated before it was a value or it is taken apart
+                    Expr::Pair(a, b) if !self.taken_apart() => {
+                        self.focus = a;
+                        return Ok(self.push(Frame::Fst(b)));
+                    }
+                    Expr::Inl(v) if !self.taken_apart() => {
+                        self.focus = v;
+                        return Ok(self.push(Frame::Inj(true)));
+                    }
+                    Expr::Inr(v) if !self.taken_apart() => {
+                        self.focus = v;
+                        return Ok(self.push(Frame::Inj(false)));
+                    }
+                    // constants and variables that nothing binds
+                    value => *self.focus = value,
+                }
+            }
+            match self.stack.pop() {
+                None => return Ok(Some(Progress::Finished)),
+                Some(Frame::Fun(v))
+                    if self.strategy.by_name()
+                        && self.strategy.strong()
+                        && is_neutral(&self.focus) =>
+                {
+                    let f = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    (*self.focus, normal) = (Expr::App(f, v), false);
+                }
+                Some(Frame::Fun(v)) if self.strategy.by_name() => {
+                    if !self.burn() {
+                        self.stack.push(Frame::Fun(v));
+                        return Ok(Some(Progress::Exhausted(Exhausted::Steps)));
+                    }
+                    let f = std::mem::replace(&mut self.focus, v);
+                    return self.beta(*f, obs).map(Some);
+                }
+                Some(Frame::Fun(v)) => {
+                    let f = std::mem::replace(&mut self.focus, v);
+                    self.stack.push(Frame::Arg(f));
+                    return Ok(None);
+                }
+                Some(Frame::Arg(f)) if self.strategy.strong() && is_neutral(&f) => {
+                    let v = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    (*self.focus, normal) = (Expr::App(f, v), false);
+                }
+                Some(Frame::Arg(f)) if !self.burn() => {
+                    self.stack.push(Frame::Arg(f));
+                    return Ok(Some(Progress::Exhausted(Exhausted::Steps)));
+                }
+                Some(Frame::Arg(f)) => return self.beta(*f, obs).map(Some),
+                Some(Frame::Body(ptr, hint, ty)) => {
+                    let body = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    (*self.focus, normal) = (Expr::Lam(Lam(ptr, body, hint), ty), true);
+                }
+                Some(Frame::NeutralFun(v)) => {
+                    let f = std::mem::replace(&mut self.focus, v);
+                    self.stack.push(Frame::NeutralArg(f));
+                    return Ok(None);
+                }
+                Some(Frame::NeutralArg(f)) => {
+                    let v = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    (*self.focus, normal) = (Expr::App(f, v), true);
+                }
+                Some(Frame::Bound(lam)) => {
+                    if !self.burn() {
+                        self.stack.push(Frame::Bound(lam));
+                        return Ok(Some(Progress::Exhausted(Exhausted::Steps)));
+                    }
+                    let v = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    self.bind_let(v, lam, obs)?;
+                    return Ok(Some(Progress::Reduced));
+                }
+                Some(Frame::Operand(op, mut operands, i)) => {
+                    operands[i] = std::mem::replace(self.focus.as_mut(), Expr::Invalid);
+                    if let Some(next) = operands.get_mut(i + 1) {
+                        *self.focus = std::mem::replace(next, Expr::Invalid);
+                        self.stack.push(Frame::Operand(op, operands, i + 1));
+                        return Ok(None);
+                    }
+                    if let Some(progress) = self.apply_prim(op, operands, obs)? {
+                        return Ok(Some(progress));
+                    }
+                    normal = true;
+                }
+                Some(Frame::Cond(t, e)) if matches!(*self.focus, Expr::Bas(Const::Bool(_))) => {
+                    if !self.burn() {
+                        self.stack.push(Frame::Cond(t, e));
+                        return Ok(Some(Progress::Exhausted(Exhausted::Steps)));
+                    }
+                    self.choose(t, e, obs);
+                    return Ok(Some(Progress::Reduced));
+                }
+                // when normalizing, an if stuck on its condition is a value
+                // once both branches are normalized
+                Some(Frame::Cond(t, e)) if self.strategy.strong() && is_neutral(&self.focus) => {
+                    let c = std::mem::replace(&mut self.focus, t);
+                    self.stack.push(Frame::NeutralThen(c, e));
+                    return Ok(None);
+                }
+                Some(Frame::Cond(..)) => return Err(EvalError::BadCondition(self.location())),
+                Some(Frame::NeutralThen(c, e)) => {
+                    let t = std::mem::replace(&mut self.focus, e);
+                    self.stack.push(Frame::NeutralElse(c, t));
+                    return Ok(None);
+                }
+                Some(Frame::NeutralElse(c, t)) => {
+                    let e = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    (*self.focus, normal) = (Expr::If(c, t, e), true);
+                }
+                Some(Frame::Fst(b)) => {
+                    let a = std::mem::replace(&mut self.focus, b);
+                    self.stack.push(Frame::Snd(a));
+                    return Ok(None);
+                }
+                Some(Frame::Snd(a)) => {
+                    let b = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    (*self.focus, normal) = (Expr::Pair(a, b), true);
+                }
+                Some(Frame::Inj(left)) => {
+                    let v = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    let inj = if left { Expr::Inl(v) } else { Expr::Inr(v) };
+                    (*self.focus, normal) = (inj, true);
+                }
+                Some(Frame::Split(x, x_hint, lam)) if matches!(*self.focus, Expr::Pair(..)) => {
+                    if !self.burn() {
+                        self.stack.push(Frame::Split(x, x_hint, lam));
+                        return Ok(Some(Progress::Exhausted(Exhausted::Steps)));
+                    }
+                    self.split(x, x_hint, lam, obs)?;
+                    return Ok(Some(Progress::Reduced));
+                }
+                // when normalizing, a split stuck on its pair is a value once its
+                // body is normalized
+                Some(Frame::Split(x, x_hint, Lam(y, body, y_hint)))
+                    if self.strategy.strong() && is_neutral(&self.focus) =>
+                {
+                    let v = std::mem::replace(&mut self.focus, body);
+                    self.stack
+                        .push(Frame::NeutralSplit(v, x, x_hint, y, y_hint));
+                    return Ok(None);
+                }
+                Some(Frame::Case(l, r)) if matches!(*self.focus, Expr::Inl(_) | Expr::Inr(_)) => {
+                    if !self.burn() {
+                        self.stack.push(Frame::Case(l, r));
+                        return Ok(Some(Progress::Exhausted(Exhausted::Steps)));
+                    }
+                    self.pick(l, r, obs)?;
+                    return Ok(Some(Progress::Reduced));
+                }
+                // when normalizing, a case stuck on its scrutinee is a value once
+                // both branches are normalized
+                Some(Frame::Case(Lam(x, l, x_hint), r))
+                    if self.strategy.strong() && is_neutral(&self.focus) =>
+                {
+                    let v = std::mem::replace(&mut self.focus, l);
+                    self.stack.push(Frame::NeutralLeft(v, x, x_hint, r));
+                    return Ok(None);
+                }
+                Some(Frame::Split(..) | Frame::Case(..)) => {
+                    return Err(EvalError::BadScrutinee(self.location()))
+                }
+                Some(Frame::NeutralSplit(v, x, x_hint, y, y_hint)) => {
+                    let body = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    let split = Expr::Split(v, x, x_hint, Lam(y, body, y_hint));
+                    (*self.focus, normal) = (split, true);
+                }
+                Some(Frame::NeutralLeft(v, x, x_hint, Lam(y, r, y_hint))) => {
+                    let l = std::mem::replace(&mut self.focus, r);
+                    let l = Lam(x, l, x_hint);
+                    self.stack.push(Frame::NeutralRight(v, l, y, y_hint));
+                    return Ok(None);
+                }
+                Some(Frame::NeutralRight(v, l, y, y_hint)) => {
+                    let r = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+                    (*self.focus, normal) = (Expr::Case(v, l, Lam(y, r, y_hint)), true);
+                }
+            }
+        }
+    }
+    // replaces the split, whose pair is in focus, with its body, binding its
+    // variables to the components
+    fn split(
+        &mut self,
+        x: Ptr<'prg>,
+        x_hint: Option<Rc<str>>,
+        lam: Lam<'prg>,
+        obs: &mut dyn EvalObserver<Expr<'prg>>,
+    ) -> Result<(), EvalError> {
+        let v = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+        let split = Expr::Split(v, x, x_hint, lam);
+        obs.on_match(&split);
+        let Expr::Split(v, Ptr(x), _, Lam(Ptr(y), body, _)) = split else {
+            unreachable!("just built");
+        };
+        let Expr::Pair(a, b) = *v else {
+            unreachable!("checked by ret");
+        };
+        self.bind(x, *a)?;
+        self.bind(y, *b)?;
+        self.focus = body;
+        // the split and the pair are gone
+        self.nodes -= 2;
+        Ok(())
+    }
+    // replaces the case, whose scrutinee in focus is an injection, with the
+    // branch it picks, binding that branch's variable to what it injects
+    fn pick(
+        &mut self,
+        l: Lam<'prg>,
+        r: Lam<'prg>,
+        obs: &mut dyn EvalObserver<Expr<'prg>>,
+    ) -> Result<(), EvalError> {
+        let v = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+        let case = Expr::Case(v, l, r);
+        obs.on_match(&case);
+        let Expr::Case(v, l, r) = case else {
+            unreachable!("just built");
+        };
+        let (v, Lam(Ptr(cell), picked, _), Lam(Ptr(untaken_cell), untaken, _)) = match *v {
+            Expr::Inl(v) => (v, l, r),
+            Expr::Inr(v) => (v, r, l),
+            _ => unreachable!("checked by ret"),
+        };
+        self.bind(cell, *v)?;
+        // the case, the injection and the untaken branch are gone
+        self.nodes -= 2 + size(&untaken);
+        untaken_cell.set(Expr::Free);
+        free_binders(&untaken);
+        self.focus = picked;
+        Ok(())
+    }
+    // replaces the if, whose condition in focus is a boolean, with the branch it picks
+    fn choose(
+        &mut self,
+        t: Box<Expr<'prg>>,
+        e: Box<Expr<'prg>>,
+        obs: &mut dyn EvalObserver<Expr<'prg>>,
+    ) {
+        let c = std::mem::replace(&mut self.focus, Box::new(Expr::Invalid));
+        let branch = Expr::If(c, t, e);
+        obs.on_if(&branch);
+        let Expr::If(c, t, e) = branch else {
+            unreachable!("just built");
+        };
+        let (picked, untaken) = match *c {
+            Expr::Bas(Const::Bool(true)) => (t, e),
+            _ => (e, t),
+        };
+        // the if, its condition and the untaken branch are gone
+        self.nodes -= 2 + size(&untaken);
+        free_binders(&untaken);
+        self.focus = picked;
+    }
+    // Replaces the primitive, whose operands are all evaluated, with its result.
+    // When normalizing, a primitive stuck on a variable is left in focus as a
+    // value instead, and None returned.
+    fn apply_prim(
+        &mut self,
+        op: PrimOp,
+        operands: Vec<Expr<'prg>>,
+        obs: &mut dyn EvalObserver<Expr<'prg>>,
+    ) -> Result<Option<Progress>, EvalError> {
+        let constants: Option<Vec<&Const>> = operands
+            .iter()
+            .map(|e| match e {
+                Expr::Bas(c) => Some(c),
+                _ => None,
+            })
+            .collect();
+        let Some(constants) = constants else {
+            let neutral = |e: &Expr| matches!(e, Expr::Bas(_)) || is_neutral(e);
+            if self.strategy.strong() && operands.iter().all(neutral) {
+                *self.focus = Expr::Prim(op, operands);
+                return Ok(None);
+            }
+            return Err(EvalError::BadPrim(self.location()));
+        };
+        let Some(result) = op.apply(&constants) else {
+            return Err(EvalError::BadPrim(self.location()));
+        };
+        let prim = Expr::Prim(op, operands);
+        // the operands are constants, so evaluating them again costs no fuel
+        if !self.burn() {
+            *self.focus = prim;
+            return Ok(Some(Progress::Exhausted(Exhausted::Steps)));
+        }
+        obs.on_prim(&prim);
+        let Expr::Prim(_, operands) = prim else {
+            unreachable!("just built");
+        };
+        *self.focus = Expr::Bas(result);
+        self.nodes -= operands.len();
+        Ok(Some(Progress::Reduced))
+    }
+    // applies f to the argument in focus
+    fn beta(
+        &mut self,
+        f: Expr<'prg>,
+        obs: &mut dyn EvalObserver<Expr<'prg>>,
+    ) -> Result<Progress, EvalError> {
+        if !matches!(f, Expr::Lam(..)) {
+            return Err(EvalError::Stuck(self.location()));
+        }
+        obs.on_beta(&f, &self.focus);
+        let Expr::Lam(Lam(Ptr(cell), body, _), _) = f else {
+            unreachable!("checked above");
+        };
+        let v = std::mem::replace(&mut self.focus, body);
+        self.bind(cell, *v)?;
+        // the application and the lambda are gone
+        self.nodes -= 2;
+        Ok(Progress::Reduced)
+    }
+    // binds the variable of the let to v and focuses on its body
+    fn bind_let(
+        &mut self,
+        v: Box<Expr<'prg>>,
+        lam: Lam<'prg>,
+        obs: &mut dyn EvalObserver<Expr<'prg>>,
+    ) -> Result<(), EvalError> {
+        let binding = Expr::Let(v, lam);
+        obs.on_let(&binding);
+        let Expr::Let(v, Lam(Ptr(cell), body, _)) = binding else {
+            unreachable!("just built");
+        };
+        self.bind(cell, *v)?;
+        self.focus = body;
+        // the let is gone
+        self.nodes -= 1;
+        Ok(())
+    }
+    // binds the variable whose cell is `cell` to v, which only a cell that no
+    // binder has used yet can take: one that is bound, moved out of or freed
+    // already belongs to another binder
+    fn bind(&mut self, cell: &'prg Cell<Expr<'prg>>, v: Expr<'prg>) -> Result<(), EvalError> {
+        let nodes = size(&v);
+        match cell.replace(v) {
+            Expr::Invalid => {
+                self.nodes -= nodes;
+                Ok(())
+            }
+            old => {
+                let freed = matches!(old, Expr::Free);
+                cell.set(old);
+                Err(if freed {
+                    EvalError::DanglingPointer(self.location())
+                } else {
+                    EvalError::DoubleDeref(self.location())
+                })
+            }
+        }
+    }
+}
+
+// Whether a value is stuck on a variable nothing binds, which only normalizing allows
+fn is_neutral(e: &Expr<'_>) -> bool {
+    matches!(
+        e,
+        Expr::Ptr(_)
+            | Expr::App(..)
+            | Expr::Prim(..)
+            | Expr::If(..)
+            | Expr::Split(..)
+            | Expr::Case(..)
+    )
+}
+
+fn size(e: &Expr<'_>) -> usize {
+    let mut nodes = 0;
+    let mut todo = vec![e];
+    while let Some(e) = todo.pop() {
+        nodes += 1;
+        match e {
+            Expr::Lam(Lam(_, body, _), _) => todo.push(body),
+            Expr::App(f, v) | Expr::Let(f, Lam(_, v, _)) => todo.extend([&**f, &**v]),
+            Expr::Prim(_, operands) => todo.extend(operands),
+            Expr::If(c, t, e) => todo.extend([&**c, &**t, &**e]),
+            Expr::Pair(a, b) | Expr::Split(a, _, _, Lam(_, b, _)) => todo.extend([&**a, &**b]),
+            Expr::Inl(v) | Expr::Inr(v) => todo.push(v),
+            Expr::Case(v, Lam(_, l, _), Lam(_, r, _)) => todo.extend([&**v, &**l, &**r]),
+            Expr::Ptr(_) | Expr::Bas(_) | Expr::Invalid | Expr::Moved | Expr::Free => {}
+        }
+    }
+    nodes
+}
+
+// Frees the cells of the binders in e, which is dropped without being evaluated.
+// The cells its variables point to are left alone, since binders outside of e
+// may still have other uses.
+fn free_binders(e: &Expr<'_>) {
+    let mut todo = vec![e];
+    while let Some(e) = todo.pop() {
+        match e {
+            Expr::Lam(Lam(Ptr(cell), body, _), _) => {
+                cell.set(Expr::Free);
+                todo.push(body);
+            }
+            Expr::Let(v, Lam(Ptr(cell), body, _)) => {
+                cell.set(Expr::Free);
+                todo.extend([&**v, &**body]);
+            }
+            Expr::App(f, v) => todo.extend([&**f, &**v]),
+            Expr::Prim(_, operands) => todo.extend(operands),
+            Expr::If(c, t, e) => todo.extend([&**c, &**t, &**e]),
+            Expr::Split(v, Ptr(x), _, Lam(Ptr(y), body, _)) => {
+                x.set(Expr::Free);
+                y.set(Expr::Free);
+                todo.extend([&**v, &**body]);
+            }
+            Expr::Case(v, Lam(Ptr(x), l, _), Lam(Ptr(y), r, _)) => {
+                x.set(Expr::Free);
+                y.set(Expr::Free);
+                todo.extend([&**v, &**l, &**r]);
+            }
+            Expr::Pair(a, b) => todo.extend([&**a, &**b]),
+            Expr::Inl(v) | Expr::Inr(v) => todo.push(v),
+            Expr::Ptr(_) | Expr::Bas(_) | Expr::Invalid | Expr::Moved | Expr::Free => {}
+        }
+    }
+}
+
+pub fn eval(e: Expr<'_>) -> Result<Expr<'_>, EvalError> {
+    eval_observed(e, &mut NoopObserver)
+}
+
+/// Evaluates `e` like `eval`, reporting every reduction to `obs`.
+pub fn eval_observed<'prg>(
+    e: Expr<'prg>,
+    obs: &mut impl EvalObserver<Expr<'prg>>,
+) -> Result<Expr<'prg>, EvalError> {
+    match eval_with(e, Strategy::default(), Limits::default(), obs)? {
+        Outcome::Value(e) => Ok(e),
+        Outcome::OutOfFuel { .. } => unreachable!("evaluation without limits ran out"),
+    }
+}
+
+/// Evaluates `e` with at most `max_steps` reductions.
+pub fn eval_with_fuel(
+    e: Expr<'_>,
+    max_steps: usize,
+) -> Result<Outcome<Expr<'_>, Expr<'_>>, EvalError> {
+    eval_with_limits(e, Limits::steps(max_steps))
+}
+
+/// Evaluates `e` until the result or until one of `limits` is hit, in which case
+/// the partially reduced term comes back to be resumed later.
+pub fn eval_with_limits(
+    e: Expr<'_>,
+    limits: Limits,
+) -> Result<Outcome<Expr<'_>, Expr<'_>>, EvalError> {
+    eval_with(e, Strategy::default(), limits, &mut NoopObserver)
+}
+
+/// Evaluates `e` like `eval_with_limits` in the order `strategy` gives, reporting
+/// every reduction to `obs`. Every variable is used at most once, so call-by-need
+/// is call-by-name here.
+pub fn eval_with<'prg>(
+    e: Expr<'prg>,
+    strategy: Strategy,
+    limits: Limits,
+    obs: &mut impl EvalObserver<Expr<'prg>>,
+) -> Result<Outcome<Expr<'prg>, Expr<'prg>>, EvalError> {
+    run(e, strategy, limits, obs)
+}
+
+/// Reduces `e` to its normal form in normal order, also under lambdas: variables
+/// that nothing binds are values there, and so are applications stuck on them.
+pub fn normalize(e: Expr<'_>) -> Result<Expr<'_>, EvalError> {
+    match run(
+        e,
+        Strategy::NormalOrder,
+        Limits::default(),
+        &mut NoopObserver,
+    )? {
+        Outcome::Value(e) => Ok(e),
+        Outcome::OutOfFuel { .. } => unreachable!("evaluation without limits ran out"),
     }
 }
 
-pub fn eval(e: Expr<'_>) -> Expr<'_> {
-    let mut e = Box::new(e);
-    println!("eval {e}");
-    while step(&mut e) {
-        println!("step {e}");
+/// The type of `e`, or why it has none, as [`types::typecheck`] finds it. A term
+/// that checks does not get stuck, whichever strategy evaluates it.
+pub fn typecheck(e: &Expr<'_>) -> Result<Type, TypeError> {
+    types::typecheck(&e.to_term())
+}
+
+fn run<'prg>(
+    e: Expr<'prg>,
+    strategy: Strategy,
+    limits: Limits,
+    obs: &mut dyn EvalObserver<Expr<'prg>>,
+) -> Result<Outcome<Expr<'prg>, Expr<'prg>>, EvalError> {
+    obs.on_start(&e);
+    let mut machine = Machine::new(e, strategy, &limits);
+    loop {
+        let exhausted = if machine.nodes > limits.max_nodes {
+            Exhausted::Nodes
+        } else {
+            match machine.step(obs).inspect_err(|err| obs.on_stuck(err))? {
+                Progress::Reduced => {
+                    if obs.observes_steps() {
+                        machine.observe_step(obs);
+                    }
+                    continue;
+                }
+                Progress::Finished => break,
+                Progress::Exhausted(exhausted) => exhausted,
+            }
+        };
+        obs.on_out_of_fuel(exhausted);
+        let term = machine.plug();
+        return Ok(Outcome::OutOfFuel { exhausted, term });
     }
-    println!("Result: {e}");
-    *e
+    obs.on_finish(&machine.focus);
+    Ok(Outcome::Value(*machine.focus))
 }
+
 /// ```compile_fail,E0373,E0505
-/// use aptree::heaptree_norc::{Expr,make_lam,make_app,Args};
+/// use aptree::heaptree_norc::{Expr,make_lam,make_app,Args,ZERO};
 /// fn make_lam_cheat<'prg>(args: &'prg Args<'prg>) -> Expr<'prg> {
 ///     make_lam(args, |x| {
-///         let mut cheat = Expr::Bas("0");
+///         let mut cheat = Expr::Bas(ZERO);
 ///         let lam = make_lam(args, |y| {
 ///             cheat = y;
 ///             x
@@ -88,34 +1469,283 @@ where
     'b: 'a,
     F: FnOnce(Expr<'b>) -> Expr<'b> + 'a,
 {
-    let idx = args.1.take();
-    args.1.set(idx + 1);
-    let ptr = Ptr(&args.0[idx]);
-    let body_ptr = Ptr(&args.0[idx]);
-    Expr::Lam(Lam(ptr, Box::new(init(Expr::Ptr(body_ptr)))))
+    let cell = args.cell();
+    let ptr = Ptr(cell);
+    let body_ptr = Ptr(cell);
+    Expr::Lam(Lam(ptr, Box::new(init(Expr::Ptr(body_ptr))), None), None)
+}
+
+/// Like [`make_lam`], with a name for the binder that printing uses.
+pub fn make_named_lam<'a, 'b, 'prg, F>(args: &'prg Args<'a>, hint: &str, init: F) -> Expr<'a>
+where
+    'prg: 'b,
+    'b: 'a,
+    F: FnOnce(Expr<'b>) -> Expr<'b> + 'a,
+{
+    let Expr::Lam(Lam(ptr, body, _), ty) = make_lam(args, init) else {
+        unreachable!("make_lam makes a lambda");
+    };
+    Expr::Lam(Lam(ptr, body, Some(hint.into())), ty)
+}
+
+/// Like [`make_named_lam`], with the binder annotated with its type for [`typecheck`].
+pub fn make_typed_lam<'a, 'b, 'prg, F>(
+    args: &'prg Args<'a>,
+    hint: &str,
+    ty: Type,
+    init: F,
+) -> Expr<'a>
+where
+    'prg: 'b,
+    'b: 'a,
+    F: FnOnce(Expr<'b>) -> Expr<'b> + 'a,
+{
+    let Expr::Lam(lam, _) = make_named_lam(args, hint, init) else {
+        unreachable!("make_named_lam makes a lambda");
+    };
+    Expr::Lam(lam, Some(ty))
+}
+
+/// Makes `let x = v in body`, with `init` building the body from x. It binds x
+/// like applying a lambda to v would, in one step rather than two.
+pub fn make_let<'a, 'b, 'prg, F>(args: &'prg Args<'a>, v: Expr<'a>, init: F) -> Expr<'a>
+where
+    'prg: 'b,
+    'b: 'a,
+    F: FnOnce(Expr<'b>) -> Expr<'b> + 'a,
+{
+    let Expr::Lam(lam, _) = make_lam(args, init) else {
+        unreachable!("make_lam makes a lambda");
+    };
+    Expr::Let(Box::new(v), lam)
+}
+
+/// Like [`make_let`], with a name for the binder that printing uses.
+pub fn make_named_let<'a, 'b, 'prg, F>(
+    args: &'prg Args<'a>,
+    hint: &str,
+    v: Expr<'a>,
+    init: F,
+) -> Expr<'a>
+where
+    'prg: 'b,
+    'b: 'a,
+    F: FnOnce(Expr<'b>) -> Expr<'b> + 'a,
+{
+    let Expr::Lam(lam, _) = make_named_lam(args, hint, init) else {
+        unreachable!("make_named_lam makes a lambda");
+    };
+    Expr::Let(Box::new(v), lam)
 }
 
 pub fn make_app<'prg>(f: Expr<'prg>, v: Expr<'prg>) -> Expr<'prg> {
     Expr::App(Box::new(f), Box::new(v))
 }
 
-pub fn make_bas<'prg>(c: &'static str) -> Expr<'prg> {
+pub fn make_bas<'prg>(c: Const) -> Expr<'prg> {
     Expr::Bas(c)
 }
 
-pub struct Args<'prg>(Vec<Cell<Expr<'prg>>>, std::cell::Cell<usize>);
+/// Makes the primitive `op` on `operands`.
+pub fn make_prim<'prg>(op: PrimOp, operands: Vec<Expr<'prg>>) -> Expr<'prg> {
+    Expr::Prim(op, operands)
+}
+
+/// Makes `if c then t else e`.
+pub fn make_if<'prg>(c: Expr<'prg>, t: Expr<'prg>, e: Expr<'prg>) -> Expr<'prg> {
+    Expr::If(Box::new(c), Box::new(t), Box::new(e))
+}
+
+/// Makes the pair `(a, b)`.
+pub fn make_pair<'prg>(a: Expr<'prg>, b: Expr<'prg>) -> Expr<'prg> {
+    Expr::Pair(Box::new(a), Box::new(b))
+}
+
+/// Makes `let (x, y) = v in body`, with `init` building the body from x and y.
+pub fn make_split<'a, 'b, 'prg, F>(args: &'prg Args<'a>, v: Expr<'a>, init: F) -> Expr<'a>
+where
+    'prg: 'b,
+    'b: 'a,
+    F: FnOnce(Expr<'b>, Expr<'b>) -> Expr<'b> + 'a,
+{
+    let (x, y) = (args.cell(), args.cell());
+    let body = init(Expr::Ptr(Ptr(x)), Expr::Ptr(Ptr(y)));
+    Expr::Split(Box::new(v), Ptr(x), None, Lam(Ptr(y), Box::new(body), None))
+}
+
+/// Makes `inl v`.
+pub fn make_inl<'prg>(v: Expr<'prg>) -> Expr<'prg> {
+    Expr::Inl(Box::new(v))
+}
+
+/// Makes `inr v`.
+pub fn make_inr<'prg>(v: Expr<'prg>) -> Expr<'prg> {
+    Expr::Inr(Box::new(v))
+}
+
+/// Makes `case v of inl x. l | inr y. r`, with `left` building l from x and
+/// `right` building r from y.
+pub fn make_case<'a, 'b, 'prg, F, G>(
+    args: &'prg Args<'a>,
+    v: Expr<'a>,
+    left: F,
+    right: G,
+) -> Expr<'a>
+where
+    'prg: 'b,
+    'b: 'a,
+    F: FnOnce(Expr<'b>) -> Expr<'b> + 'a,
+    G: FnOnce(Expr<'b>) -> Expr<'b> + 'a,
+{
+    let (Expr::Lam(l, _), Expr::Lam(r, _)) = (make_lam(args, left), make_lam(args, right)) else {
+        unreachable!("make_lam makes a lambda");
+    };
+    Expr::Case(Box::new(v), l, r)
+}
+
+/// The cells of the binders, with how many have been handed out and the cells
+/// evaluation freed that are known to be free again.
+pub struct Args<'prg>(
+    Vec<Cell<Expr<'prg>>>,
+    std::cell::Cell<usize>,
+    RefCell<Vec<usize>>,
+);
 impl<'prg> Args<'prg> {
     pub fn with_capacity(cap: usize) -> Self {
         Self(
             Vec::from_iter((0..cap).map(|_| Cell::new(Expr::Invalid))),
             std::cell::Cell::new(0),
+            RefCell::new(Vec::new()),
         )
     }
+    // Hands out the next unused cell, or once there are none left, one that
+    // evaluation freed. Evaluation frees cells without the arena, so they are
+    // found by sweeping it, which only happens again once every cell the last
+    // sweep found is handed out.
+    fn next_cell(&self) -> Option<&Cell<Expr<'prg>>> {
+        let idx = self.1.get();
+        if idx < self.0.len() {
+            self.1.set(idx + 1);
+            return Some(&self.0[idx]);
+        }
+        let mut free = self.2.borrow_mut();
+        if free.is_empty() {
+            let freed = self.0.iter().enumerate().rev().filter(|(_, cell)| {
+                let e = cell.replace(Expr::Invalid);
+                let free = matches!(e, Expr::Free);
+                cell.set(e);
+                free
+            });
+            free.extend(freed.map(|(idx, _)| idx));
+        }
+        let cell = &self.0[free.pop()?];
+        cell.set(Expr::Invalid);
+        Some(cell)
+    }
+    // a cell for the builders, which have no way to report running out
+    fn cell(&self) -> &Cell<Expr<'prg>> {
+        self.next_cell()
+            .expect("the arena has no cell left, counting those evaluation freed")
+    }
+}
+
+/// Builds terms with cells from the arena.
+impl<'prg> AbtBackend for &'prg Args<'prg> {
+    type Term = Expr<'prg>;
+    type Var = Ptr<'prg>;
+    fn constant(&mut self, c: Const) -> Expr<'prg> {
+        make_bas(c)
+    }
+    fn lam(&mut self, body: impl FnOnce(&mut Self, &Ptr<'prg>) -> Expr<'prg>) -> Expr<'prg> {
+        let cell = self.cell();
+        let body = body(self, &Ptr(cell));
+        Expr::Lam(Lam(Ptr(cell), Box::new(body), None), None)
+    }
+    fn typed_lam(
+        &mut self,
+        ty: Type,
+        body: impl FnOnce(&mut Self, &Ptr<'prg>) -> Expr<'prg>,
+    ) -> Expr<'prg> {
+        let Expr::Lam(lam, _) = self.lam(body) else {
+            unreachable!("lam makes a lambda");
+        };
+        Expr::Lam(lam, Some(ty))
+    }
+    fn app(&mut self, f: Expr<'prg>, v: Expr<'prg>) -> Expr<'prg> {
+        make_app(f, v)
+    }
+    fn var(&mut self, x: &Ptr<'prg>) -> Expr<'prg> {
+        Expr::Ptr(Ptr(x.0))
+    }
+    fn let_in(
+        &mut self,
+        v: Expr<'prg>,
+        body: impl FnOnce(&mut Self, &Ptr<'prg>) -> Expr<'prg>,
+    ) -> Expr<'prg> {
+        let Expr::Lam(lam, _) = self.lam(body) else {
+            unreachable!("lam makes a lambda");
+        };
+        Expr::Let(Box::new(v), lam)
+    }
+    fn prim(&mut self, op: PrimOp, operands: Vec<Expr<'prg>>) -> Expr<'prg> {
+        make_prim(op, operands)
+    }
+    fn if_then_else(&mut self, c: Expr<'prg>, t: Expr<'prg>, e: Expr<'prg>) -> Expr<'prg> {
+        make_if(c, t, e)
+    }
+    fn pair(&mut self, a: Expr<'prg>, b: Expr<'prg>) -> Expr<'prg> {
+        make_pair(a, b)
+    }
+    fn split(
+        &mut self,
+        v: Expr<'prg>,
+        body: impl FnOnce(&mut Self, &Ptr<'prg>, &Ptr<'prg>) -> Expr<'prg>,
+    ) -> Expr<'prg> {
+        let (x, y) = (self.cell(), self.cell());
+        let body = body(self, &Ptr(x), &Ptr(y));
+        Expr::Split(Box::new(v), Ptr(x), None, Lam(Ptr(y), Box::new(body), None))
+    }
+    fn inl(&mut self, v: Expr<'prg>) -> Expr<'prg> {
+        make_inl(v)
+    }
+    fn inr(&mut self, v: Expr<'prg>) -> Expr<'prg> {
+        make_inr(v)
+    }
+    fn case(
+        &mut self,
+        v: Expr<'prg>,
+        l: impl FnOnce(&mut Self, &Ptr<'prg>) -> Expr<'prg>,
+        r: impl FnOnce(&mut Self, &Ptr<'prg>) -> Expr<'prg>,
+    ) -> Expr<'prg> {
+        let (Expr::Lam(l, _), Expr::Lam(r, _)) = (self.lam(l), self.lam(r)) else {
+            unreachable!("lam makes a lambda");
+        };
+        Expr::Case(Box::new(v), l, r)
+    }
+    fn typecheck_term(&mut self, t: &Expr<'prg>) -> Result<Type, TypeError> {
+        typecheck(t)
+    }
+    fn eval_term_with(
+        &mut self,
+        t: Expr<'prg>,
+        strategy: Strategy,
+        limits: Limits,
+    ) -> Result<Outcome<Term>, EvalError> {
+        Ok(match eval_with(t, strategy, limits, &mut NoopObserver)? {
+            Outcome::Value(e) => Outcome::Value(e.to_term()),
+            Outcome::OutOfFuel { exhausted, .. } => Outcome::OutOfFuel {
+                exhausted,
+                term: (),
+            },
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dot::{DotDump, ToDot};
+    use crate::observer::{Event, Recorder};
 
     fn make_ident<'a>(args: &'a Args<'a>) -> Expr<'a> {
         make_lam(args, |ptr| ptr)
@@ -131,35 +1761,272 @@ mod tests {
     }
 
     #[test]
-    fn t0() {
+    fn stuck_on_constant() {
         let args = Args::with_capacity(128);
-        let lam_const = make_const_fn(&args);
-        let app = make_app(lam_const, Expr::Bas(ONE));
-        assert!(matches!(eval(app), Expr::Bas(UNIT)));
+        let app = make_app(make_app(make_ident(&args), Expr::Bas(ZERO)), Expr::Bas(ONE));
+        assert!(matches!(
+            eval(app),
+            Err(EvalError::Stuck(Location::Path(path))) if path.is_empty()
+        ));
+    }
+
+    #[test]
+    fn dangling_and_double_deref() {
+        let args = Args::with_capacity(2);
+        // the identity takes cell 0, nothing ever binds cell 1
+        let app = make_app(make_ident(&args), Expr::Ptr(Ptr(&args.0[1])));
+        assert!(matches!(
+            eval(app),
+            Err(EvalError::DanglingPointer(Location::Path(path))) if path == [Branch::Arg]
+        ));
+        // a freed cell stays free for the arena to hand out again
+        args.0[1].set(Expr::Free);
+        assert!(matches!(
+            eval(Expr::Ptr(Ptr(&args.0[1]))),
+            Err(EvalError::DanglingPointer(_))
+        ));
+        assert!(matches!(args.0[1].replace(Expr::Moved), Expr::Free));
+        let app = make_app(Expr::Ptr(Ptr(&args.0[1])), Expr::Bas(ONE));
+        assert!(matches!(
+            eval(app),
+            Err(EvalError::DoubleDeref(Location::Path(path))) if path == [Branch::Fun]
+        ));
+    }
+
+    #[test]
+    fn binding_a_cell_twice() {
+        let args = Args::with_capacity(1);
+        let cell = &args.0[0];
+        let lam = |body| Expr::Lam(Lam(Ptr(cell), Box::new(body), None), None);
+        // (\x. (\x. x) 1) 0, with both lambdas binding the one cell
+        let inner = make_app(lam(Expr::Ptr(Ptr(cell))), Expr::Bas(ONE));
+        let app = make_app(lam(inner), Expr::Bas(ZERO));
+        assert!(matches!(
+            eval(app),
+            Err(EvalError::DoubleDeref(Location::Path(path))) if path.is_empty()
+        ));
+        // a cell freed by an untaken branch is no binder's to bind
+        cell.set(Expr::Free);
+        let app = make_app(lam(Expr::Bas(ONE)), Expr::Bas(ZERO));
+        assert!(matches!(
+            eval(app),
+            Err(EvalError::DanglingPointer(Location::Path(path))) if path.is_empty()
+        ));
+    }
+
+    #[test]
+    fn deep_left_nested_app() {
+        // (((\x. x) (\x. x)) (\x. x)) ... 1, deeper than the native stack could recurse
+        let args = Args::with_capacity(100_001);
+        let mut f = make_ident(&args);
+        for _ in 0..100_000 {
+            f = make_app(f, make_ident(&args));
+        }
+        let mut machine = Machine::new(
+            make_app(f, Expr::Bas(ONE)),
+            Strategy::default(),
+            &Limits::default(),
+        );
+        while let Progress::Reduced = machine.step(&mut NoopObserver).unwrap() {}
+        assert!(matches!(*machine.focus, Expr::Bas(ONE)));
+    }
+
+    #[test]
+    fn untaken_branch_frees_its_cells() {
+        // if true then 0 else \x. x, whose lambda takes the only cell
+        let args = Args::with_capacity(1);
+        let branch = make_if(Expr::Bas(true.into()), Expr::Bas(ZERO), make_ident(&args));
+        assert!(matches!(eval(branch), Ok(Expr::Bas(ZERO))));
+        // which another lambda gets once the else branch is dropped
+        let app = make_app(make_ident(&args), Expr::Bas(ONE));
+        assert!(matches!(eval(app), Ok(Expr::Bas(ONE))));
     }
 
     #[test]
-    fn t1() {
+    fn running_out_of_cells_is_an_error() {
+        let args = Args::with_capacity(1);
+        let term = make_ident(&args).to_term();
+        assert!(matches!(
+            Expr::from_term(&args, &term),
+            Err(BuildError::OutOfCells)
+        ));
+        // until evaluation frees the cell
+        args.0[0].set(Expr::Free);
+        assert!(matches!(
+            eval(Expr::from_term(&args, &term).unwrap()),
+            Ok(Expr::Lam(..))
+        ));
+    }
+
+    #[test]
+    fn untaken_case_branch_frees_its_cells() {
+        // case inr 0 of inl x. \z. z | inr y. y, whose binders and lambda take every cell
+        let args = Args::with_capacity(3);
+        let case = make_case(
+            &args,
+            make_inr(Expr::Bas(ZERO)),
+            |_x| make_ident(&args),
+            |y| y,
+        );
+        assert!(matches!(eval(case), Ok(Expr::Bas(ZERO))));
+        // two of which the binders of a split get once the left branch is dropped
+        let pair = make_pair(Expr::Bas(ZERO), Expr::Bas(ONE));
+        let split = make_split(&args, pair, |_x, y| y);
+        assert!(matches!(eval(split), Ok(Expr::Bas(ONE))));
+    }
+
+    #[test]
+    fn fuel_runs_out_and_resumes() {
+        let args = Args::with_capacity(128);
+        let app = make_app(
+            make_app(make_lam_false(&args), Expr::Bas(ZERO)),
+            Expr::Bas(ONE),
+        );
+        let Ok(Outcome::OutOfFuel { exhausted, term }) = eval_with_fuel(app, 2) else {
+            panic!("two steps are not enough");
+        };
+        assert_eq!(Exhausted::Steps, exhausted);
+        assert!(matches!(
+            eval_with_fuel(term, 1),
+            Ok(Outcome::Value(Expr::Bas(ONE)))
+        ));
+    }
+
+    #[test]
+    fn resuming_counts_the_values_in_cells() {
+        // (\x. x 0) (\y. y)
+        let args = Args::with_capacity(2);
+        let app = make_app(
+            make_lam(&args, |x| make_app(x, Expr::Bas(ZERO))),
+            make_ident(&args),
+        );
+        let Ok(Outcome::OutOfFuel { term, .. }) = eval_with_fuel(app, 1) else {
+            panic!("one step is not enough");
+        };
+        // the identity is in x's cell, out of the term the resumed machine is sized by
+        assert!(matches!(&term, Expr::App(f, _) if matches!(**f, Expr::Ptr(_))));
+        assert!(matches!(eval(term), Ok(Expr::Bas(ZERO))));
+    }
+
+    #[test]
+    fn depth_limit() {
+        let args = Args::with_capacity(16);
+        let mut f = make_ident(&args);
+        for _ in 0..10 {
+            f = make_app(f, make_ident(&args));
+        }
+        let limits = Limits {
+            max_depth: 5,
+            ..Limits::default()
+        };
+        let Ok(Outcome::OutOfFuel { exhausted, term }) =
+            eval_with_limits(make_app(f, Expr::Bas(ONE)), limits)
+        else {
+            panic!("the term is nested deeper than the limit");
+        };
+        assert_eq!(Exhausted::Depth, exhausted);
+        assert!(matches!(eval(term), Ok(Expr::Bas(ONE))));
+    }
+
+    #[test]
+    fn recorder_sees_running_out_of_fuel() {
         let args = Args::with_capacity(128);
         let lam_id = make_ident(&args);
         let lam_const = make_const_fn(&args);
         let app = make_app(make_app(lam_id, lam_const), Expr::Bas(ONE));
-        assert!(matches!(eval(app), Expr::Bas(UNIT)));
+        let mut recorder = Recorder::default();
+        let outcome = eval_with(app, Strategy::default(), Limits::steps(1), &mut recorder);
+        assert!(matches!(outcome, Ok(Outcome::OutOfFuel { .. })));
+        assert!(matches!(
+            recorder.events[..],
+            [
+                Event::Start(_),
+                Event::Beta { .. },
+                Event::OutOfFuel(Exhausted::Steps)
+            ]
+        ));
     }
 
     #[test]
-    fn t2() {
-        let args = Args::with_capacity(128);
-        let lam_true = make_lam_true(&args);
-        let app = make_app(make_app(lam_true, Expr::Bas(ZERO)), Expr::Bas(ONE));
-        assert!(matches!(eval(app), Expr::Bas(ZERO)));
+    fn display_names_binders() {
+        let args = Args::with_capacity(4);
+        let lam_true = make_named_lam(&args, "t", |x| make_lam(&args, |_y| x));
+        let app = make_app(lam_true, Expr::Bas(ZERO));
+        assert_eq!(app.to_string(), r"(\t. \x1. t) 0");
+        // the result's variable is bound to 0 but never dereferenced
+        assert_eq!(eval(app).unwrap().to_string(), r"\x0. 0");
+    }
+
+    #[test]
+    fn dot_hangs_bound_values_off_their_cell() {
+        let args = Args::with_capacity(4);
+        let app = make_app(make_lam_true(&args), Expr::Bas(ZERO));
+        let result = eval(app).unwrap();
+        let dot = result.to_dot();
+        assert!(dot.contains("[label=\"value\"]"));
+        assert!(dot.contains("[label=\"0\"]"));
+        // and draws the same thing every time, though the value leaves its cell meanwhile
+        assert_eq!(dot, result.to_dot());
+    }
+
+    #[test]
+    fn reads_back_a_long_chain_of_bound_cells() {
+        // x0 bound to inl x1, x1 to inl x2, and so on, deeper than the native
+        // stack could recurse
+        let n = 100_000;
+        let args = Args::with_capacity(n + 1);
+        for (cell, next) in args.0.iter().zip(&args.0[1..]) {
+            cell.set(make_inl(Expr::Ptr(Ptr(next))));
+        }
+        args.0[n].set(Expr::Bas(ZERO));
+        let chain = Expr::Ptr(Ptr(&args.0[0]));
+        let mut term = chain.to_term();
+        for _ in 0..n {
+            let Term::Inl(v) = term else {
+                panic!("every bound cell reads back as its value");
+            };
+            term = *v;
+        }
+        assert_eq!(term, Term::Const(ZERO));
+        assert!(chain.to_dot().contains("[label=\"0\"]"));
+    }
+
+    #[test]
+    fn a_cell_bound_to_itself_reads_back_as_a_variable() {
+        let args = Args::with_capacity(1);
+        let cell = &args.0[0];
+        cell.set(make_inl(Expr::Ptr(Ptr(cell))));
+        let e = Expr::Ptr(Ptr(cell));
+        assert_eq!(e.to_string(), "?0");
+        assert!(e.to_dot().contains("[label=\"inl\"]"));
+        // and its value is back in the cell
+        assert!(matches!(cell.replace(Expr::Invalid), Expr::Inl(_)));
     }
 
     #[test]
-    fn t3() {
+    fn dot_dump_writes_every_step() {
+        let dir = std::env::temp_dir().join(format!("aptree-norc-dot-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
         let args = Args::with_capacity(128);
-        let lam_false = make_lam_false(&args);
-        let app = make_app(make_app(lam_false, Expr::Bas(ZERO)), Expr::Bas(ONE));
-        assert!(matches!(eval(app), Expr::Bas(ONE)));
+        let app = make_app(
+            make_app(make_ident(&args), make_const_fn(&args)),
+            Expr::Bas(ONE),
+        );
+        let mut dump = DotDump::new(&dir);
+        assert!(matches!(eval_observed(app, &mut dump), Ok(Expr::Bas(UNIT))));
+        let steps = dump.finish();
+        std::fs::remove_dir_all(&dir).unwrap();
+        // the start, then two betas and a deref
+        assert_eq!(4, steps.unwrap());
+    }
+
+    #[test]
+    fn alpha_eq_across_arenas() {
+        let (a, b) = (Args::with_capacity(2), Args::with_capacity(8));
+        let t = make_lam_true(&a);
+        assert!(alpha_eq(&t, &make_lam_true(&b)));
+        assert!(!alpha_eq(&t, &make_lam(&b, |_x| make_lam(&b, |y| y))));
+        let app = make_app(make_ident(&b), make_lam_true(&b));
+        assert!(alpha_eq(&t, &eval(app).unwrap()));
     }
 }
@@ -1,4 +1,16 @@
 pub mod arraytree;
 pub mod arraytree_lam;
+pub mod backend;
+pub mod constant;
+mod convert;
+pub mod dot;
+pub mod error;
+pub mod fuel;
 pub mod heaptree;
 pub mod heaptree_norc;
+pub mod observer;
+pub mod parse;
+pub mod prim;
+pub mod strategy;
+pub mod term;
+pub mod types;
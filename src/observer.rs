@@ -0,0 +1,142 @@
+use crate::error::EvalError;
+use crate::fuel::Exhausted;
+use std::fmt::Display;
+
+/// Callbacks an evaluator makes as it goes, with `T` the backend's view of a term.
+/// Every callback does nothing by default.
+pub trait EvalObserver<T: ?Sized> {
+    fn on_start(&mut self, _term: &T) {}
+    /// Just before the function `lam` is applied to `arg`
+    fn on_beta(&mut self, _lam: &T, _arg: &T) {}
+    /// Just before the variable `ptr` is replaced by the value it points to
+    fn on_deref(&mut self, _ptr: &T) {}
+    /// Just before the let `binding` binds its variable and is replaced by its body
+    fn on_let(&mut self, _binding: &T) {}
+    /// Just before the primitive `prim`, whose operands are all constants by now,
+    /// is replaced by its result
+    fn on_prim(&mut self, _prim: &T) {}
+    /// Just before the if `branch`, whose condition is a boolean by now, is
+    /// replaced by the branch it picks
+    fn on_if(&mut self, _branch: &T) {}
+    /// Just before the split or case `elim`, whose scrutinee is a pair or an
+    /// injection by now, binds its variables and is replaced by its body or the
+    /// branch the injection picks
+    fn on_match(&mut self, _elim: &T) {}
+    /// Just before the fix `fix` binds its variable to its body and is replaced by it
+    fn on_unfold(&mut self, _fix: &T) {}
+    /// Whether to call `on_step`, which costs the heap backends a walk up and
+    /// down the evaluation context after every reduction
+    fn observes_steps(&self) -> bool {
+        false
+    }
+    /// After every reduction, with the whole term
+    fn on_step(&mut self, _term: &T) {}
+    fn on_stuck(&mut self, _err: &EvalError) {}
+    fn on_out_of_fuel(&mut self, _exhausted: Exhausted) {}
+    fn on_finish(&mut self, _result: &T) {}
+}
+
+/// Observes nothing, which is what plain `eval` uses.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct NoopObserver;
+
+impl<T: ?Sized> EvalObserver<T> for NoopObserver {}
+
+/// Prints every event to stdout, with terms pretty-printed.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct StdoutObserver;
+
+impl<T: Display + ?Sized> EvalObserver<T> for StdoutObserver {
+    fn on_start(&mut self, term: &T) {
+        println!("eval {term}");
+    }
+    fn on_beta(&mut self, lam: &T, arg: &T) {
+        println!("beta {lam} applied to {arg}");
+    }
+    fn on_deref(&mut self, ptr: &T) {
+        println!("deref {ptr}");
+    }
+    fn on_let(&mut self, binding: &T) {
+        println!("{binding}");
+    }
+    fn on_prim(&mut self, prim: &T) {
+        println!("prim {prim}");
+    }
+    fn on_if(&mut self, branch: &T) {
+        println!("{branch}");
+    }
+    fn on_match(&mut self, elim: &T) {
+        println!("{elim}");
+    }
+    fn on_unfold(&mut self, fix: &T) {
+        println!("unfold {fix}");
+    }
+    fn on_stuck(&mut self, err: &EvalError) {
+        println!("stuck: {err}");
+    }
+    fn on_out_of_fuel(&mut self, exhausted: Exhausted) {
+        println!("out of fuel: {exhausted:?}");
+    }
+    fn on_finish(&mut self, result: &T) {
+        println!("result {result}");
+    }
+}
+
+/// One event seen by a [`Recorder`], with terms rendered to strings.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Event {
+    Start(String),
+    Beta { lam: String, arg: String },
+    Deref(String),
+    Let(String),
+    Prim(String),
+    If(String),
+    Match(String),
+    Unfold(String),
+    Stuck(EvalError),
+    OutOfFuel(Exhausted),
+    Finish(String),
+}
+
+/// Collects every event in order.
+#[derive(Clone, Default, Debug)]
+pub struct Recorder {
+    pub events: Vec<Event>,
+}
+
+impl<T: Display + ?Sized> EvalObserver<T> for Recorder {
+    fn on_start(&mut self, term: &T) {
+        self.events.push(Event::Start(format!("{term}")));
+    }
+    fn on_beta(&mut self, lam: &T, arg: &T) {
+        let (lam, arg) = (format!("{lam}"), format!("{arg}"));
+        self.events.push(Event::Beta { lam, arg });
+    }
+    fn on_deref(&mut self, ptr: &T) {
+        self.events.push(Event::Deref(format!("{ptr}")));
+    }
+    fn on_let(&mut self, binding: &T) {
+        self.events.push(Event::Let(format!("{binding}")));
+    }
+    fn on_prim(&mut self, prim: &T) {
+        self.events.push(Event::Prim(format!("{prim}")));
+    }
+    fn on_if(&mut self, branch: &T) {
+        self.events.push(Event::If(format!("{branch}")));
+    }
+    fn on_match(&mut self, elim: &T) {
+        self.events.push(Event::Match(format!("{elim}")));
+    }
+    fn on_unfold(&mut self, fix: &T) {
+        self.events.push(Event::Unfold(format!("{fix}")));
+    }
+    fn on_stuck(&mut self, err: &EvalError) {
+        self.events.push(Event::Stuck(err.clone()));
+    }
+    fn on_out_of_fuel(&mut self, exhausted: Exhausted) {
+        self.events.push(Event::OutOfFuel(exhausted));
+    }
+    fn on_finish(&mut self, result: &T) {
+        self.events.push(Event::Finish(format!("{result}")));
+    }
+}
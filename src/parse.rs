@@ -0,0 +1,1058 @@
+use crate::constant::{Const, UNIT};
+use crate::error::{ParseError, ParseErrorKind};
+use crate::prim::PrimOp;
+use crate::term::{Binder, Term};
+use crate::types::Type;
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::str::Chars;
+
+/// Parses the surface syntax into a [`Term`], with each binder hinted by its name:
+///
+/// ```text
+/// term ::= \x (y: type) …. term (or λ, binding x, y, … in turn)
+///        | fix f. term
+///        | let x = term in term
+///        | let (x, y) = term in term
+///        | if term then term else term
+///        | case term of inl x. term | inr y. term
+///        | atom atom … [term]      (the last argument any of the above)
+/// atom ::= x | () | -12 | true | false | "string" | (term) | (term, term)
+///        | op atom atom | inl atom | inr atom
+/// op   ::= add | sub | mul | eq | lt
+/// type ::= int | bool | str | () | (type) | type * type | type + type | type -> type
+/// ```
+///
+/// A lambda's, fix's or let's body, an if's else branch and a case's right branch
+/// extend as far right as they can, application is by juxtaposition and associates to the
+/// left, and a primitive op takes exactly as many atoms as it has operands, an
+/// injection exactly one. Names are letters, digits, `_` and `'`, starting with a
+/// letter or `_`, other than `fix`, `let`, `in`, `if`, `then`, `else`, `case`,
+/// `of`, `inl`, `inr`, `true`, `false` and the ops.
+/// Integers are decimal and fit in an `i64`; strings take the escapes `\n`, `\r`,
+/// `\t`, `\0`, `\\`, `\'`, `\"` and `\u{…}`, as printing writes them. A lambda's
+/// binder in parentheses is annotated with its type, in which `*` binds tighter
+/// than `+` and `+` than `->`, the first two associating to the left and `->` to
+/// the right.
+pub fn parse(src: &str) -> Result<Term, ParseError> {
+    Parser::new(src, true).run()
+}
+
+// Parses like `parse` for a backend that cannot build `fix`, rejecting it at its
+// keyword.
+pub(crate) fn parse_without_fix(src: &str) -> Result<Term, ParseError> {
+    Parser::new(src, false).run()
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Token {
+    Lambda,
+    Fix,
+    Dot,
+    Colon,
+    Arrow,
+    Star,
+    Plus,
+    Let,
+    Equals,
+    In,
+    If,
+    Then,
+    Else,
+    Case,
+    Of,
+    Bar,
+    Inl,
+    Inr,
+    Open,
+    Comma,
+    Close,
+    Name(String),
+    Const(Const),
+    Prim(PrimOp),
+    End,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Lambda => "`\\`".to_string(),
+            Token::Fix => "`fix`".to_string(),
+            Token::Dot => "`.`".to_string(),
+            Token::Colon => "`:`".to_string(),
+            Token::Arrow => "`->`".to_string(),
+            Token::Star => "`*`".to_string(),
+            Token::Plus => "`+`".to_string(),
+            Token::Let => "`let`".to_string(),
+            Token::Equals => "`=`".to_string(),
+            Token::In => "`in`".to_string(),
+            Token::If => "`if`".to_string(),
+            Token::Then => "`then`".to_string(),
+            Token::Else => "`else`".to_string(),
+            Token::Case => "`case`".to_string(),
+            Token::Of => "`of`".to_string(),
+            Token::Bar => "`|`".to_string(),
+            Token::Inl => "`inl`".to_string(),
+            Token::Inr => "`inr`".to_string(),
+            Token::Open => "`(`".to_string(),
+            Token::Comma => "`,`".to_string(),
+            Token::Close => "`)`".to_string(),
+            Token::Name(name) => format!("`{name}`"),
+            Token::Const(c) => format!("`{c}`"),
+            Token::Prim(op) => format!("`{op}`"),
+            Token::End => "end of input".to_string(),
+        }
+    }
+}
+
+// The groups the parser is in the middle of, innermost last. Each has the
+// application it has built so far; a lambda's body ends where its group does.
+enum Group {
+    Root,
+    // opened by a parenthesis at this line and column
+    Paren(usize, usize),
+    // the binders of a lambda whose body is being parsed
+    Lam(Vec<Binder>),
+    // the binder of a fix whose body is being parsed
+    Fix(Binder),
+    // the name of a let whose value is being parsed
+    LetValue(String),
+    // the binder and value of a let whose body is being parsed
+    LetBody(Binder, Term),
+    // a primitive op and the operands parsed so far, which are atoms
+    Prim(PrimOp, Vec<Term>),
+    // the condition of an if
+    IfCond,
+    // the then branch of an if, after its condition
+    IfThen(Term),
+    // the else branch of an if, after its condition and then branch
+    IfElse(Term, Term),
+    // the second component of a pair, after its first, with the line and column
+    // of the parenthesis that opened it
+    PairSnd(usize, usize, Term),
+    // the names of a split whose pair is being parsed
+    SplitValue(String, String),
+    // the binders and pair of a split whose body is being parsed
+    SplitBody(Binder, Binder, Term),
+    // an injection, which takes one atom
+    Inl,
+    Inr,
+    // the scrutinee of a case
+    CaseScrutinee,
+    // the left branch of a case, after its scrutinee and the binder of the branch
+    CaseLeft(Term, Binder),
+    // the right branch of a case, after its scrutinee, left branch and their binders
+    CaseRight(Term, Binder, Term, Binder),
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+    // binder ids for each name in scope, innermost last
+    scope: HashMap<String, Vec<usize>>,
+    binders: usize,
+    // whether the backend being parsed for can build `fix`
+    fix: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str, fix: bool) -> Self {
+        Self {
+            chars: src.chars().peekable(),
+            line: 1,
+            column: 1,
+            scope: HashMap::new(),
+            binders: 0,
+            fix,
+        }
+    }
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+    // the next token and the line and column it starts at
+    fn token(&mut self) -> Result<(Token, usize, usize), ParseError> {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.bump();
+        }
+        let (line, column) = (self.line, self.column);
+        let Some(c) = self.bump() else {
+            return Ok((Token::End, line, column));
+        };
+        let token = match c {
+            '\\' | 'λ' => Token::Lambda,
+            '.' => Token::Dot,
+            ':' => Token::Colon,
+            '*' => Token::Star,
+            '+' => Token::Plus,
+            '-' if self.chars.peek() == Some(&'>') => {
+                self.bump();
+                Token::Arrow
+            }
+            '=' => Token::Equals,
+            ',' => Token::Comma,
+            '|' => Token::Bar,
+            '(' if self.chars.peek() == Some(&')') => {
+                self.bump();
+                Token::Const(UNIT)
+            }
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '"' => Token::Const(self.string(line, column)?),
+            c if c.is_alphanumeric() || c == '_' || self.starts_negative(c) => {
+                let mut word = String::from(c);
+                while let Some(&c) = self.chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == '\'') {
+                        break;
+                    }
+                    word.push(c);
+                    self.bump();
+                }
+                if c.is_ascii_digit() || c == '-' {
+                    match word.parse() {
+                        Ok(n) => Token::Const(Const::Int(n)),
+                        Err(_) => {
+                            let kind = ParseErrorKind::UnknownConstant(word);
+                            return Err(ParseError { line, column, kind });
+                        }
+                    }
+                } else {
+                    match word.as_str() {
+                        "fix" => Token::Fix,
+                        "let" => Token::Let,
+                        "in" => Token::In,
+                        "if" => Token::If,
+                        "then" => Token::Then,
+                        "else" => Token::Else,
+                        "case" => Token::Case,
+                        "of" => Token::Of,
+                        "inl" => Token::Inl,
+                        "inr" => Token::Inr,
+                        "true" => Token::Const(Const::Bool(true)),
+                        "false" => Token::Const(Const::Bool(false)),
+                        name => match PrimOp::from_name(name) {
+                            Some(op) => Token::Prim(op),
+                            None => Token::Name(word),
+                        },
+                    }
+                }
+            }
+            c => {
+                let kind = ParseErrorKind::UnexpectedChar(c);
+                return Err(ParseError { line, column, kind });
+            }
+        };
+        Ok((token, line, column))
+    }
+    fn run(mut self) -> Result<Term, ParseError> {
+        let mut groups = vec![(Group::Root, None)];
+        loop {
+            let (token, line, column) = self.token()?;
+            let unexpected = |token: &Token, expected| ParseError {
+                line,
+                column,
+                kind: ParseErrorKind::Unexpected {
+                    found: token.describe(),
+                    expected,
+                },
+            };
+            let operand = matches!(
+                token,
+                Token::Name(_)
+                    | Token::Const(_)
+                    | Token::Prim(_)
+                    | Token::Inl
+                    | Token::Inr
+                    | Token::Open
+            );
+            let needs_operand = matches!(
+                groups.last(),
+                Some((Group::Prim(..) | Group::Inl | Group::Inr, _))
+            );
+            if !operand && needs_operand {
+                return Err(unexpected(&token, "an operand"));
+            }
+            let mut atom = match token {
+                Token::Name(name) => match self.scope.get(&name).and_then(|ids| ids.last()) {
+                    Some(&id) => Term::Var(id),
+                    None => {
+                        let kind = ParseErrorKind::UnboundVariable(name);
+                        return Err(ParseError { line, column, kind });
+                    }
+                },
+                Token::Const(c) => Term::Const(c),
+                Token::Prim(op) => {
+                    groups.push((Group::Prim(op, Vec::new()), None));
+                    continue;
+                }
+                Token::Inl => {
+                    groups.push((Group::Inl, None));
+                    continue;
+                }
+                Token::Inr => {
+                    groups.push((Group::Inr, None));
+                    continue;
+                }
+                Token::Open => {
+                    groups.push((Group::Paren(line, column), None));
+                    continue;
+                }
+                Token::Lambda => {
+                    let binders = self.binders_up_to_dot()?;
+                    groups.push((Group::Lam(binders), None));
+                    continue;
+                }
+                Token::Fix if !self.fix => {
+                    let kind = ParseErrorKind::UnsupportedFix;
+                    return Err(ParseError { line, column, kind });
+                }
+                Token::Fix => {
+                    let name = self.name()?;
+                    self.expect(Token::Dot, "`.`")?;
+                    let binder = self.bind(name);
+                    groups.push((Group::Fix(binder), None));
+                    continue;
+                }
+                Token::Let => {
+                    let group = self.let_binders()?;
+                    groups.push((group, None));
+                    continue;
+                }
+                Token::If => {
+                    groups.push((Group::IfCond, None));
+                    continue;
+                }
+                Token::Case => {
+                    groups.push((Group::CaseScrutinee, None));
+                    continue;
+                }
+                Token::Dot
+                | Token::Equals
+                | Token::Colon
+                | Token::Arrow
+                | Token::Star
+                | Token::Plus => return Err(unexpected(&token, "a term")),
+                Token::Close
+                | Token::End
+                | Token::In
+                | Token::Then
+                | Token::Else
+                | Token::Comma
+                | Token::Of
+                | Token::Bar => {
+                    // finish the lambdas, fixes, lets, ifs and cases the group
+                    // ends, innermost first
+                    let mut term = None;
+                    let (group, app) = loop {
+                        let (group, app) = groups.pop().expect("the root group is never closed");
+                        let app = match (app, term.take()) {
+                            (app, None) => app,
+                            (None, Some(lam)) => Some(lam),
+                            (Some(f), Some(lam)) => Some(Term::App(Box::new(f), Box::new(lam))),
+                        };
+                        if !matches!(
+                            group,
+                            Group::Lam(_)
+                                | Group::Fix(_)
+                                | Group::LetBody(..)
+                                | Group::IfElse(..)
+                                | Group::SplitBody(..)
+                                | Group::CaseRight(..)
+                        ) {
+                            break (group, app);
+                        }
+                        let Some(body) = app else {
+                            let expected = match group {
+                                Group::Lam(_) => "a lambda body",
+                                Group::Fix(_) => "a fix body",
+                                Group::LetBody(..) | Group::SplitBody(..) => "a let body",
+                                Group::IfElse(..) => "an else branch",
+                                _ => "a case branch",
+                            };
+                            return Err(unexpected(&token, expected));
+                        };
+                        term = Some(match group {
+                            Group::Lam(binders) => self.close_lam(binders, body),
+                            Group::Fix(binder) => {
+                                self.unbind(&binder);
+                                Term::Fix(binder, Box::new(body))
+                            }
+                            Group::LetBody(binder, v) => self.close_let(binder, v, body),
+                            Group::IfElse(c, t) => {
+                                Term::If(Box::new(c), Box::new(t), Box::new(body))
+                            }
+                            Group::SplitBody(x, y, v) => {
+                                self.unbind(&x);
+                                self.unbind(&y);
+                                Term::Split(x, y, Box::new(v), Box::new(body))
+                            }
+                            Group::CaseRight(v, x, l, y) => {
+                                self.unbind(&y);
+                                Term::Case(Box::new(v), x, Box::new(l), y, Box::new(body))
+                            }
+                            _ => unreachable!("only binders, ifs and cases are closed here"),
+                        });
+                    };
+                    let Some(app) = app else {
+                        return Err(unexpected(&token, "a term"));
+                    };
+                    match (group, token) {
+                        (Group::Root, Token::End) => return Ok(app),
+                        (Group::Paren(..), Token::Close) => app,
+                        (Group::Paren(line, column), Token::Comma) => {
+                            groups.push((Group::PairSnd(line, column, app), None));
+                            continue;
+                        }
+                        (Group::PairSnd(_, _, a), Token::Close) => {
+                            Term::Pair(Box::new(a), Box::new(app))
+                        }
+                        (Group::LetValue(name), Token::In) => {
+                            let binder = self.bind(name);
+                            groups.push((Group::LetBody(binder, app), None));
+                            continue;
+                        }
+                        (Group::IfCond, Token::Then) => {
+                            groups.push((Group::IfThen(app), None));
+                            continue;
+                        }
+                        (Group::IfThen(c), Token::Else) => {
+                            groups.push((Group::IfElse(c, app), None));
+                            continue;
+                        }
+                        (Group::SplitValue(x, y), Token::In) => {
+                            let (x, y) = (self.bind(x), self.bind(y));
+                            groups.push((Group::SplitBody(x, y, app), None));
+                            continue;
+                        }
+                        (Group::CaseScrutinee, Token::Of) => {
+                            let x = self.case_binder(Token::Inl, "`inl`")?;
+                            groups.push((Group::CaseLeft(app, x), None));
+                            continue;
+                        }
+                        (Group::CaseLeft(v, x), Token::Bar) => {
+                            self.unbind(&x);
+                            let y = self.case_binder(Token::Inr, "`inr`")?;
+                            groups.push((Group::CaseRight(v, x, app, y), None));
+                            continue;
+                        }
+                        (Group::Root, token) => return Err(unexpected(&token, "end of input")),
+                        (
+                            Group::Paren(line, column) | Group::PairSnd(line, column, _),
+                            Token::End,
+                        ) => {
+                            let kind = ParseErrorKind::Unclosed;
+                            return Err(ParseError { line, column, kind });
+                        }
+                        (Group::Paren(..) | Group::PairSnd(..), token) => {
+                            return Err(unexpected(&token, "`)`"))
+                        }
+                        (Group::LetValue(_) | Group::SplitValue(..), token) => {
+                            return Err(unexpected(&token, "`in`"))
+                        }
+                        (Group::IfCond, token) => return Err(unexpected(&token, "`then`")),
+                        (Group::IfThen(_), token) => return Err(unexpected(&token, "`else`")),
+                        (Group::CaseScrutinee, token) => return Err(unexpected(&token, "`of`")),
+                        (Group::CaseLeft(..), token) => return Err(unexpected(&token, "`|`")),
+                        (
+                            Group::Lam(_)
+                            | Group::Fix(_)
+                            | Group::LetBody(..)
+                            | Group::IfElse(..)
+                            | Group::SplitBody(..)
+                            | Group::CaseRight(..),
+                            _,
+                        ) => unreachable!("binders, ifs and cases were closed above"),
+                        (Group::Prim(..) | Group::Inl | Group::Inr, _) => {
+                            unreachable!("primitives and injections only take operands")
+                        }
+                    }
+                }
+            };
+            // the atom is the last operand a primitive or injection needs, making it
+            // an atom in turn, or an operand a primitive still needs, or the next
+            // argument
+            loop {
+                match groups.last_mut().expect("the root group is never closed") {
+                    (Group::Inl, _) => {
+                        groups.pop();
+                        atom = Term::Inl(Box::new(atom));
+                    }
+                    (Group::Inr, _) => {
+                        groups.pop();
+                        atom = Term::Inr(Box::new(atom));
+                    }
+                    (Group::Prim(op, operands), _) => {
+                        operands.push(atom);
+                        if operands.len() < op.arity() {
+                            break;
+                        }
+                        let Some((Group::Prim(op, operands), _)) = groups.pop() else {
+                            unreachable!("just matched");
+                        };
+                        atom = Term::Prim(op, operands);
+                    }
+                    (_, app) => {
+                        *app = Some(match app.take() {
+                            None => atom,
+                            Some(f) => Term::App(Box::new(f), Box::new(atom)),
+                        });
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    // whether c, just bumped, is the minus sign of a negative integer
+    fn starts_negative(&mut self, c: char) -> bool {
+        c == '-' && self.chars.peek().is_some_and(|c| c.is_ascii_digit())
+    }
+    // the rest of a string literal whose opening quote is at line and column
+    fn string(&mut self, line: usize, column: usize) -> Result<Const, ParseError> {
+        let mut s = String::new();
+        loop {
+            let (esc_line, esc_column) = (self.line, self.column);
+            let bad_escape = |c| ParseError {
+                line: esc_line,
+                column: esc_column,
+                kind: ParseErrorKind::UnknownEscape(c),
+            };
+            match self.bump() {
+                Some('"') => return Ok(Const::Str(s.into())),
+                Some('\\') => s.push(match self.bump() {
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('0') => '\0',
+                    Some(c @ ('\\' | '\'' | '"')) => c,
+                    Some('u') => self.unicode_escape().ok_or(bad_escape('u'))?,
+                    Some(c) => return Err(bad_escape(c)),
+                    None => break,
+                }),
+                Some(c) => s.push(c),
+                None => break,
+            }
+        }
+        let kind = ParseErrorKind::UnclosedString;
+        Err(ParseError { line, column, kind })
+    }
+    // the `{…}` after `\u`, as the character its hex digits name
+    fn unicode_escape(&mut self) -> Option<char> {
+        if self.bump()? != '{' {
+            return None;
+        }
+        let mut hex = String::new();
+        loop {
+            match self.bump()? {
+                '}' => break,
+                c => hex.push(c),
+            }
+        }
+        char::from_u32(u32::from_str_radix(&hex, 16).ok()?)
+    }
+    // the names after a lambda, each bound in turn, and annotated if in parentheses
+    fn binders_up_to_dot(&mut self) -> Result<Vec<Binder>, ParseError> {
+        let mut binders = Vec::new();
+        loop {
+            match self.token()? {
+                (Token::Name(name), _, _) => binders.push(self.bind(name)),
+                (Token::Open, _, _) => {
+                    let name = self.name()?;
+                    self.expect(Token::Colon, "`:`")?;
+                    let ty = Some(self.annotation()?);
+                    binders.push(Binder {
+                        ty,
+                        ..self.bind(name)
+                    });
+                }
+                (Token::Dot, line, column) if binders.is_empty() => {
+                    let found = Token::Dot.describe();
+                    let kind = ParseErrorKind::Unexpected {
+                        found,
+                        expected: "a name",
+                    };
+                    return Err(ParseError { line, column, kind });
+                }
+                (Token::Dot, _, _) => return Ok(binders),
+                (token, line, column) => {
+                    let kind = ParseErrorKind::Unexpected {
+                        found: token.describe(),
+                        expected: "a name, `(` or `.`",
+                    };
+                    return Err(ParseError { line, column, kind });
+                }
+            }
+        }
+    }
+    // the type after a binder's `:`, up to and including the `)` that closes the
+    // binder, with the operators each waiting for their right operand reduced as
+    // soon as one that binds less tightly, or as tightly to the left, follows them
+    fn annotation(&mut self) -> Result<Type, ParseError> {
+        // the operators and open parentheses not reduced yet, innermost last
+        let mut ops: Vec<Token> = Vec::new();
+        let mut operands: Vec<Type> = Vec::new();
+        let prec = |op: &Token| match op {
+            Token::Arrow => 0,
+            Token::Plus => 1,
+            Token::Star => 2,
+            _ => unreachable!("only operators are reduced"),
+        };
+        // reduces the innermost operators up to an open parenthesis, or up to one
+        // that binds less tightly than `min`
+        let reduce = |ops: &mut Vec<Token>, operands: &mut Vec<Type>, min: Option<u8>| {
+            while let Some(op) = ops.last() {
+                if *op == Token::Open || min.is_some_and(|min| prec(op) < min) {
+                    break;
+                }
+                let op = ops.pop().expect("just looked at it");
+                let b = operands.pop().expect("an operator's right operand");
+                let a = operands.pop().expect("an operator's left operand");
+                operands.push(match op {
+                    Token::Arrow => Type::fun(a, b),
+                    Token::Plus => Type::sum(a, b),
+                    _ => Type::prod(a, b),
+                });
+            }
+        };
+        // whether an operand is next, rather than an operator or `)`
+        let mut operand = true;
+        loop {
+            let (token, line, column) = self.token()?;
+            let next = matches!(
+                token,
+                Token::Open | Token::Arrow | Token::Plus | Token::Star
+            );
+            match (operand, &token) {
+                (true, Token::Open) => ops.push(token),
+                (true, Token::Const(UNIT)) => operands.push(Type::Unit),
+                (true, Token::Name(name)) if name == "int" => operands.push(Type::Int),
+                (true, Token::Name(name)) if name == "bool" => operands.push(Type::Bool),
+                (true, Token::Name(name)) if name == "str" => operands.push(Type::Str),
+                (false, Token::Arrow | Token::Plus | Token::Star) => {
+                    // `->` associates to the right, so it leaves another `->` waiting
+                    let min = prec(&token) + u8::from(token == Token::Arrow);
+                    reduce(&mut ops, &mut operands, Some(min));
+                    ops.push(token);
+                }
+                (false, Token::Close) => {
+                    reduce(&mut ops, &mut operands, None);
+                    if ops.pop().is_none() {
+                        return Ok(operands.pop().expect("the whole type"));
+                    }
+                }
+                _ => {
+                    let expected = if operand {
+                        "a type"
+                    } else {
+                        "`->`, `+`, `*` or `)`"
+                    };
+                    let kind = ParseErrorKind::Unexpected {
+                        found: token.describe(),
+                        expected,
+                    };
+                    return Err(ParseError { line, column, kind });
+                }
+            }
+            operand = next;
+        }
+    }
+    // what comes after `let`, up to and including the `=`: the name of a let, or
+    // the two names of a split in parentheses, as the group its value starts
+    fn let_binders(&mut self) -> Result<Group, ParseError> {
+        let group = match self.token()? {
+            (Token::Name(name), _, _) => Group::LetValue(name),
+            (Token::Open, _, _) => {
+                let x = self.name()?;
+                self.expect(Token::Comma, "`,`")?;
+                let y = self.name()?;
+                self.expect(Token::Close, "`)`")?;
+                Group::SplitValue(x, y)
+            }
+            (token, line, column) => {
+                let kind = ParseErrorKind::Unexpected {
+                    found: token.describe(),
+                    expected: "a name or `(`",
+                };
+                return Err(ParseError { line, column, kind });
+            }
+        };
+        self.expect(Token::Equals, "`=`")?;
+        Ok(group)
+    }
+    // `inl x.` or `inr y.` starting a branch of a case, binding its name
+    fn case_binder(&mut self, side: Token, expected: &'static str) -> Result<Binder, ParseError> {
+        self.expect(side, expected)?;
+        let name = self.name()?;
+        self.expect(Token::Dot, "`.`")?;
+        Ok(self.bind(name))
+    }
+    fn name(&mut self) -> Result<String, ParseError> {
+        match self.token()? {
+            (Token::Name(name), _, _) => Ok(name),
+            (token, line, column) => {
+                let kind = ParseErrorKind::Unexpected {
+                    found: token.describe(),
+                    expected: "a name",
+                };
+                Err(ParseError { line, column, kind })
+            }
+        }
+    }
+    // the next token, which must be `token`
+    fn expect(&mut self, token: Token, expected: &'static str) -> Result<(), ParseError> {
+        match self.token()? {
+            (next, _, _) if next == token => Ok(()),
+            (next, line, column) => {
+                let kind = ParseErrorKind::Unexpected {
+                    found: next.describe(),
+                    expected,
+                };
+                Err(ParseError { line, column, kind })
+            }
+        }
+    }
+    // a fresh binder for `name`, which is in scope until `unbind` takes it out
+    fn bind(&mut self, name: String) -> Binder {
+        self.scope
+            .entry(name.clone())
+            .or_default()
+            .push(self.binders);
+        self.binders += 1;
+        Binder::new(self.binders - 1, Some(Rc::from(name)))
+    }
+    fn unbind(&mut self, binder: &Binder) {
+        let name = binder.hint.as_deref().expect("parsed binders have names");
+        self.scope.get_mut(name).map(Vec::pop);
+    }
+    fn close_let(&mut self, binder: Binder, v: Term, body: Term) -> Term {
+        self.unbind(&binder);
+        Term::Let(binder, Box::new(v), Box::new(body))
+    }
+    fn close_lam(&mut self, binders: Vec<Binder>, body: Term) -> Term {
+        binders.into_iter().rev().fold(body, |body, binder| {
+            self.unbind(&binder);
+            Term::Lam(binder, Box::new(body))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_display() {
+        for src in [
+            r"\x. \y. x",
+            r"(\x. x) (\y. y) 1",
+            r"\f. f (f 0)",
+            r"\x. x (\x'. x') ()",
+            r"(\x. x x) (\x. x x)",
+            r"let x = 0 in \y. x y",
+            r"(\f. let x = f 0 in f x) (\y. y)",
+            r"let x = let y = 0 in y in (let x' = x in x') x",
+            r#"(\x. x) -12 true ("a \"b\"\n\u{7f}" false) 9223372036854775807"#,
+            r"\x. add x (mul 2 x)",
+            r"lt -1 0 (eq () ())",
+            r"\f. f (sub (f 1) 2) 3",
+            r"\x. if x then 0 else 1",
+            r"(if true then \y. y else \y. y) 0",
+            r"\f. f (if eq (f 0) 1 then 2 else 3) 4",
+            r"if true then if false then 0 else 1 else let x = 2 in x",
+            r"\p. let (x, y) = p in (y, x)",
+            r"((inl 0, inr ()), (\x. x, 1))",
+            r"\s. case s of inl x. inl (x, 0) | inr y. inr y",
+            r"\f. f (case f 0 of inl x. x | inr y. let (a, b) = y in a) 2",
+            r"case inl 0 of inl x. case x of inl y. y | inr z. z | inr w. w",
+            r"fix f. \n. if eq n 0 then 0 else add n (f (sub n 1))",
+            r"(fix f. f) (fix f. \x. f x)",
+            r"\(f: int -> int). \x. f (f x)",
+            r"(\(p: (int -> int) -> str + bool * ()). p) (\f. f 0)",
+            r"\(s: int + bool + () * (str * ())). s",
+        ] {
+            assert_eq!(src, parse(src).unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn sugar_and_scoping() {
+        // several binders per lambda, λ, and a lambda as the last argument
+        let t = parse("λx y. f' x y").unwrap_err();
+        assert_eq!(t.kind, ParseErrorKind::UnboundVariable("f'".to_string()));
+        assert_eq!((t.line, t.column), (1, 7));
+        let t = parse(r"\x y. x (\y. y) \z. y").unwrap();
+        assert_eq!(t.to_string(), r"\x. \y. x (\y'. y') (\z. y)");
+        // a name is only in scope in its lambda's body
+        let err = parse(r"(\x. x) x").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::UnboundVariable("x".to_string()));
+        assert_eq!((err.line, err.column), (1, 9));
+    }
+
+    #[test]
+    fn errors_have_line_and_column() {
+        let err = |src| {
+            let err: ParseError = parse(src).unwrap_err();
+            (err.line, err.column, err.kind)
+        };
+        assert_eq!(err("\\x.\n  (x\n 1"), (2, 3, ParseErrorKind::Unclosed));
+        assert_eq!(
+            err("(\\x. x))"),
+            (
+                1,
+                8,
+                ParseErrorKind::Unexpected {
+                    found: "`)`".to_string(),
+                    expected: "end of input"
+                }
+            )
+        );
+        assert_eq!(
+            err("add 1 )"),
+            (
+                1,
+                7,
+                ParseErrorKind::Unexpected {
+                    found: "`)`".to_string(),
+                    expected: "an operand"
+                }
+            )
+        );
+        assert_eq!(
+            err("\\x. x 2y"),
+            (1, 7, ParseErrorKind::UnknownConstant("2y".to_string()))
+        );
+        assert_eq!(
+            err("-9223372036854775809"),
+            (
+                1,
+                1,
+                ParseErrorKind::UnknownConstant("-9223372036854775809".to_string())
+            )
+        );
+        assert_eq!(err("0 - 1"), (1, 3, ParseErrorKind::UnexpectedChar('-')));
+        assert_eq!(err("0 \"a\nb"), (1, 3, ParseErrorKind::UnclosedString));
+        assert_eq!(err(r#""a\qb""#), (1, 3, ParseErrorKind::UnknownEscape('q')));
+        assert_eq!(
+            err(r#""\u{110000}""#),
+            (1, 2, ParseErrorKind::UnknownEscape('u'))
+        );
+        assert_eq!(err("\\x. #"), (1, 5, ParseErrorKind::UnexpectedChar('#')));
+        assert_eq!(
+            err("\\. x"),
+            (
+                1,
+                2,
+                ParseErrorKind::Unexpected {
+                    found: "`.`".to_string(),
+                    expected: "a name"
+                }
+            )
+        );
+        assert_eq!(
+            err("\\x."),
+            (
+                1,
+                4,
+                ParseErrorKind::Unexpected {
+                    found: "end of input".to_string(),
+                    expected: "a lambda body"
+                }
+            )
+        );
+        assert_eq!(
+            err("let x = 0 ()"),
+            (
+                1,
+                13,
+                ParseErrorKind::Unexpected {
+                    found: "end of input".to_string(),
+                    expected: "`in`"
+                }
+            )
+        );
+        assert_eq!(
+            err("(let x = 0) in x"),
+            (
+                1,
+                11,
+                ParseErrorKind::Unexpected {
+                    found: "`)`".to_string(),
+                    expected: "`in`"
+                }
+            )
+        );
+        assert_eq!(
+            err("if true 0 else 1"),
+            (
+                1,
+                11,
+                ParseErrorKind::Unexpected {
+                    found: "`else`".to_string(),
+                    expected: "`then`"
+                }
+            )
+        );
+        assert_eq!(
+            err("\\x. if x then 0"),
+            (
+                1,
+                16,
+                ParseErrorKind::Unexpected {
+                    found: "end of input".to_string(),
+                    expected: "`else`"
+                }
+            )
+        );
+        assert_eq!(
+            err("(if true then 0) else 1"),
+            (
+                1,
+                16,
+                ParseErrorKind::Unexpected {
+                    found: "`)`".to_string(),
+                    expected: "`else`"
+                }
+            )
+        );
+        assert_eq!(
+            err("let (x y) = 0 in x"),
+            (
+                1,
+                8,
+                ParseErrorKind::Unexpected {
+                    found: "`y`".to_string(),
+                    expected: "`,`"
+                }
+            )
+        );
+        assert_eq!(
+            err("let x, y = (0, 1) in x"),
+            (
+                1,
+                6,
+                ParseErrorKind::Unexpected {
+                    found: "`,`".to_string(),
+                    expected: "`=`"
+                }
+            )
+        );
+        assert_eq!(err("(0, 1"), (1, 1, ParseErrorKind::Unclosed));
+        assert_eq!(
+            err(r"\(x int). x"),
+            (
+                1,
+                5,
+                ParseErrorKind::Unexpected {
+                    found: "`int`".to_string(),
+                    expected: "`:`"
+                }
+            )
+        );
+        assert_eq!(
+            err(r"\(x: int x). x"),
+            (
+                1,
+                10,
+                ParseErrorKind::Unexpected {
+                    found: "`x`".to_string(),
+                    expected: "`->`, `+`, `*` or `)`"
+                }
+            )
+        );
+        assert_eq!(
+            err(r"\(x: int -> foo). x"),
+            (
+                1,
+                13,
+                ParseErrorKind::Unexpected {
+                    found: "`foo`".to_string(),
+                    expected: "a type"
+                }
+            )
+        );
+        assert_eq!(
+            err(r"\x: int. x"),
+            (
+                1,
+                3,
+                ParseErrorKind::Unexpected {
+                    found: "`:`".to_string(),
+                    expected: "a name, `(` or `.`"
+                }
+            )
+        );
+        assert_eq!(
+            err("fix . f"),
+            (
+                1,
+                5,
+                ParseErrorKind::Unexpected {
+                    found: "`.`".to_string(),
+                    expected: "a name"
+                }
+            )
+        );
+        assert_eq!(
+            err("fix f x. f"),
+            (
+                1,
+                7,
+                ParseErrorKind::Unexpected {
+                    found: "`x`".to_string(),
+                    expected: "`.`"
+                }
+            )
+        );
+        assert_eq!(
+            err("case inl 0 then 1"),
+            (
+                1,
+                12,
+                ParseErrorKind::Unexpected {
+                    found: "`then`".to_string(),
+                    expected: "`of`"
+                }
+            )
+        );
+        assert_eq!(
+            err("case inl 0 of inl x. x | inl y. y"),
+            (
+                1,
+                26,
+                ParseErrorKind::Unexpected {
+                    found: "`inl`".to_string(),
+                    expected: "`inr`"
+                }
+            )
+        );
+        assert_eq!(
+            err("(case inl 0 of inl x. x) | inr y. y"),
+            (
+                1,
+                24,
+                ParseErrorKind::Unexpected {
+                    found: "`)`".to_string(),
+                    expected: "`|`"
+                }
+            )
+        );
+        assert_eq!(
+            err("f ()\n\n"),
+            (1, 1, ParseErrorKind::UnboundVariable("f".to_string()))
+        );
+    }
+
+    #[test]
+    fn deeply_nested_input() {
+        let depth = 100_000;
+        let src = format!("{}0{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(Term::Const(Const::Int(0)), parse(&src).unwrap());
+    }
+}
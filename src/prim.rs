@@ -0,0 +1,89 @@
+use crate::constant::Const;
+use std::fmt;
+
+/// A primitive operation on constants. A primitive node evaluates its operands
+/// left to right under every strategy, then reduces in one step to the result.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PrimOp {
+    Add,
+    Sub,
+    Mul,
+    /// Whether two constants are the same, of any kind
+    Eq,
+    Lt,
+}
+
+impl PrimOp {
+    pub const ALL: [PrimOp; 5] = [
+        PrimOp::Add,
+        PrimOp::Sub,
+        PrimOp::Mul,
+        PrimOp::Eq,
+        PrimOp::Lt,
+    ];
+
+    /// The name the surface syntax and printing use
+    pub fn name(self) -> &'static str {
+        match self {
+            PrimOp::Add => "add",
+            PrimOp::Sub => "sub",
+            PrimOp::Mul => "mul",
+            PrimOp::Eq => "eq",
+            PrimOp::Lt => "lt",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|op| op.name() == name)
+    }
+    /// How many operands the op takes
+    pub fn arity(self) -> usize {
+        2
+    }
+    /// The result of the op on `operands`, or `None` if it is not defined on them:
+    /// the wrong number of operands, an integer op on other constants, or arithmetic
+    /// that overflows an `i64`.
+    pub fn apply(self, operands: &[&Const]) -> Option<Const> {
+        match (self, operands) {
+            (PrimOp::Eq, [a, b]) => Some(Const::Bool(a == b)),
+            (_, [Const::Int(a), Const::Int(b)]) => match self {
+                PrimOp::Add => a.checked_add(*b).map(Const::Int),
+                PrimOp::Sub => a.checked_sub(*b).map(Const::Int),
+                PrimOp::Mul => a.checked_mul(*b).map(Const::Int),
+                PrimOp::Lt => Some(Const::Bool(a < b)),
+                PrimOp::Eq => unreachable!("matched above"),
+            },
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PrimOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::{ONE, UNIT, ZERO};
+
+    #[test]
+    fn ops_on_constants() {
+        let apply = |op: PrimOp, a: &Const, b: &Const| op.apply(&[a, b]);
+        let (two, max) = (Const::Int(2), Const::Int(i64::MAX));
+        assert_eq!(apply(PrimOp::Add, &ONE, &two), Some(Const::Int(3)));
+        assert_eq!(apply(PrimOp::Sub, &ONE, &two), Some(Const::Int(-1)));
+        assert_eq!(apply(PrimOp::Mul, &two, &two), Some(Const::Int(4)));
+        assert_eq!(apply(PrimOp::Lt, &ZERO, &ONE), Some(Const::Bool(true)));
+        assert_eq!(apply(PrimOp::Eq, &UNIT, &UNIT), Some(Const::Bool(true)));
+        assert_eq!(apply(PrimOp::Eq, &UNIT, &ZERO), Some(Const::Bool(false)));
+        // undefined: overflow, the wrong kind of constant, the wrong arity
+        assert_eq!(apply(PrimOp::Add, &max, &ONE), None);
+        assert_eq!(apply(PrimOp::Lt, &UNIT, &ONE), None);
+        assert_eq!(PrimOp::Add.apply(&[&ONE]), None);
+        for op in PrimOp::ALL {
+            assert_eq!(PrimOp::from_name(op.name()), Some(op));
+        }
+    }
+}
@@ -0,0 +1,43 @@
+/// The order evaluation reduces a term in.
+///
+/// The weak strategies stop at a lambda; the strong ones go on under it,
+/// with variables that nothing binds as values. On the linear backends every
+/// variable is used at most once, so call-by-need is the same as call-by-name.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Strategy {
+    /// Arguments are evaluated before they are bound
+    #[default]
+    CallByValue,
+    /// Arguments are bound unevaluated, and every use evaluates its own copy
+    CallByName,
+    /// Arguments are bound unevaluated, and the first use evaluates the argument
+    /// for all of them
+    CallByNeed,
+    /// Normalizes, evaluating arguments to their normal form before they are bound
+    ApplicativeOrder,
+    /// Normalizes, reducing the leftmost outermost redex first, which finds the
+    /// normal form whenever there is one
+    NormalOrder,
+}
+
+impl Strategy {
+    // whether arguments are bound without evaluating them first
+    pub(crate) fn by_name(self) -> bool {
+        matches!(
+            self,
+            Strategy::CallByName | Strategy::CallByNeed | Strategy::NormalOrder
+        )
+    }
+    // whether the first use of an argument evaluates it for every other use
+    pub(crate) fn shares(self) -> bool {
+        self == Strategy::CallByNeed
+    }
+    // whether evaluation goes on under lambdas
+    pub(crate) fn strong(self) -> bool {
+        matches!(self, Strategy::ApplicativeOrder | Strategy::NormalOrder)
+    }
+    // whether arguments are normalized, not just evaluated, before they are bound
+    pub(crate) fn normalizes_args(self) -> bool {
+        self == Strategy::ApplicativeOrder
+    }
+}
@@ -0,0 +1,726 @@
+use crate::constant::Const;
+use crate::error::{Branch, Location};
+use crate::prim::PrimOp;
+use crate::types::Type;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+use std::rc::Rc;
+
+/// A backend-independent view of a term, read back from any of the backends.
+///
+/// Binders are told apart by their `id`, which is unique within a term; a `Var`
+/// refers to the binder with the same id, or is free if no enclosing binder has it.
+/// Variables that evaluation already substituted are read back as their value.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Term {
+    Var(usize),
+    Const(Const),
+    Lam(Binder, Box<Term>),
+    App(Box<Term>, Box<Term>),
+    /// `let x = v in body`, which binds like `(\x. body) v`
+    Let(Binder, Box<Term>, Box<Term>),
+    /// A primitive op on its operands, such as `add x 1`
+    Prim(PrimOp, Vec<Term>),
+    /// `if c then t else e`, which only evaluates the branch the boolean c picks
+    If(Box<Term>, Box<Term>, Box<Term>),
+    /// The pair `(a, b)`
+    Pair(Box<Term>, Box<Term>),
+    /// `let (x, y) = v in body`, which binds x and y to the components of the pair v
+    Split(Binder, Binder, Box<Term>, Box<Term>),
+    /// `inl v`, injecting v into the left of a sum
+    Inl(Box<Term>),
+    /// `inr v`, injecting v into the right of a sum
+    Inr(Box<Term>),
+    /// `case v of inl x. l | inr y. r`, which binds x or y to what v injects and
+    /// only evaluates the branch that binds it
+    Case(Box<Term>, Binder, Box<Term>, Binder, Box<Term>),
+    /// `fix f. body`, which binds f to the whole fix so body can refer to itself.
+    /// Only heaptree builds it; the other backends reject it with a
+    /// [`BuildError`](crate::error::BuildError).
+    Fix(Binder, Box<Term>),
+    /// A part of the term that was never filled in
+    Invalid,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Binder {
+    pub id: usize,
+    /// The name the binder was built with, if any, which printing prefers over `x0, x1, …`
+    pub hint: Option<Rc<str>>,
+    /// The type the binder was annotated with, if any, which type checking takes
+    /// as given instead of inferring it. Only lambdas' binders carry one through
+    /// the parser and the backends.
+    pub ty: Option<Type>,
+}
+
+impl Binder {
+    pub fn new(id: usize, hint: Option<Rc<str>>) -> Self {
+        Self { id, hint, ty: None }
+    }
+}
+
+impl Term {
+    /// Whether the two terms are the same up to renaming binders: their binders
+    /// correspond one-to-one, each variable refers to corresponding binders, and
+    /// free variables correspond one-to-one too. Hints are ignored.
+    pub fn alpha_eq(&self, other: &Term) -> bool {
+        enum Task<'a> {
+            Compare(&'a Term, &'a Term),
+            // a pair of binders coming into scope for comparing two bodies
+            Bind(&'a Binder, &'a Binder, &'a Term, &'a Term),
+            // a pair of binders coming into scope ahead of another pair that `Bind`s
+            Enter(&'a Binder, &'a Binder),
+            // a pair of binders going out of scope
+            Unbind(usize, usize),
+        }
+        // the depth of each binder in scope, innermost last, on either side
+        let mut scopes: [HashMap<usize, Vec<usize>>; 2] = Default::default();
+        let mut depth = 0;
+        // free variables paired up so far, both ways
+        let mut free: [HashMap<usize, usize>; 2] = Default::default();
+        let mut todo = vec![Task::Compare(self, other)];
+        while let Some(task) = todo.pop() {
+            match task {
+                Task::Compare(Term::Var(a), Term::Var(b)) => {
+                    let level = |side: usize, id| scopes[side].get(id).and_then(|s| s.last());
+                    match (level(0, a), level(1, b)) {
+                        (Some(x), Some(y)) if x == y => {}
+                        (None, None) => {
+                            if *free[0].entry(*a).or_insert(*b) != *b
+                                || *free[1].entry(*b).or_insert(*a) != *a
+                            {
+                                return false;
+                            }
+                        }
+                        _ => return false,
+                    }
+                }
+                Task::Compare(Term::Const(a), Term::Const(b)) if a == b => {}
+                Task::Compare(Term::Invalid, Term::Invalid) => {}
+                Task::Compare(Term::Lam(x, a), Term::Lam(y, b))
+                | Task::Compare(Term::Fix(x, a), Term::Fix(y, b)) => {
+                    todo.push(Task::Bind(x, y, a, b))
+                }
+                // the values are compared before their binders come into scope
+                Task::Compare(Term::Let(x, v, a), Term::Let(y, w, b)) => {
+                    todo.push(Task::Bind(x, y, a, b));
+                    todo.push(Task::Compare(v, w));
+                }
+                Task::Compare(Term::App(f, v), Term::App(g, w)) => {
+                    todo.push(Task::Compare(v, w));
+                    todo.push(Task::Compare(f, g));
+                }
+                Task::Compare(Term::If(c, t, e), Term::If(d, u, f)) => {
+                    todo.push(Task::Compare(e, f));
+                    todo.push(Task::Compare(t, u));
+                    todo.push(Task::Compare(c, d));
+                }
+                Task::Compare(Term::Pair(a, b), Term::Pair(c, d)) => {
+                    todo.push(Task::Compare(b, d));
+                    todo.push(Task::Compare(a, c));
+                }
+                Task::Compare(Term::Inl(a), Term::Inl(b))
+                | Task::Compare(Term::Inr(a), Term::Inr(b)) => todo.push(Task::Compare(a, b)),
+                // both binders of a split are in scope in its body
+                Task::Compare(Term::Split(x, x2, v, a), Term::Split(y, y2, w, b)) => {
+                    todo.push(Task::Unbind(x.id, y.id));
+                    todo.push(Task::Bind(x2, y2, a, b));
+                    todo.push(Task::Enter(x, y));
+                    todo.push(Task::Compare(v, w));
+                }
+                Task::Compare(Term::Case(v, x, l, x2, r), Term::Case(w, y, m, y2, s)) => {
+                    todo.push(Task::Bind(x2, y2, r, s));
+                    todo.push(Task::Bind(x, y, l, m));
+                    todo.push(Task::Compare(v, w));
+                }
+                Task::Compare(Term::Prim(o, a), Term::Prim(p, b))
+                    if o == p && a.len() == b.len() =>
+                {
+                    todo.extend(a.iter().zip(b).rev().map(|(a, b)| Task::Compare(a, b)));
+                }
+                Task::Compare(..) => return false,
+                Task::Bind(x, y, _, _) | Task::Enter(x, y) if x.ty != y.ty => return false,
+                Task::Bind(x, y, a, b) => {
+                    todo.push(Task::Unbind(x.id, y.id));
+                    todo.push(Task::Compare(a, b));
+                    todo.push(Task::Enter(x, y));
+                }
+                Task::Enter(x, y) => {
+                    scopes[0].entry(x.id).or_default().push(depth);
+                    scopes[1].entry(y.id).or_default().push(depth);
+                    depth += 1;
+                }
+                Task::Unbind(x, y) => {
+                    scopes[0].get_mut(&x).map(Vec::pop);
+                    scopes[1].get_mut(&y).map(Vec::pop);
+                    depth -= 1;
+                }
+            }
+        }
+        true
+    }
+    /// Where the first `fix` in the term is, for the backends that have no way to
+    /// build the cycle it needs, or `None` if there is none.
+    pub fn find_fix(&self) -> Option<Location> {
+        // each subterm with the length of the path to its parent
+        let mut path = Vec::new();
+        let mut todo = vec![(self, 0, None)];
+        while let Some((term, depth, branch)) = todo.pop() {
+            path.truncate(depth);
+            path.extend(branch);
+            let depth = path.len();
+            let mut visit = |branch, child| todo.push((child, depth, Some(branch)));
+            match term {
+                Term::Fix(..) => return Some(Location::Path(path)),
+                Term::Var(_) | Term::Const(_) | Term::Invalid => {}
+                Term::Lam(_, body) => visit(Branch::Body, body),
+                Term::App(f, v) => {
+                    visit(Branch::Arg, v);
+                    visit(Branch::Fun, f);
+                }
+                Term::Let(_, v, body) => {
+                    visit(Branch::Body, body);
+                    visit(Branch::Bound, v);
+                }
+                Term::Prim(_, operands) => {
+                    for (i, operand) in operands.iter().enumerate().rev() {
+                        visit(Branch::Operand(i), operand);
+                    }
+                }
+                Term::If(c, t, e) => {
+                    visit(Branch::Else, e);
+                    visit(Branch::Then, t);
+                    visit(Branch::Cond, c);
+                }
+                Term::Pair(a, b) => {
+                    visit(Branch::Snd, b);
+                    visit(Branch::Fst, a);
+                }
+                Term::Split(_, _, v, body) => {
+                    visit(Branch::Body, body);
+                    visit(Branch::Scrutinee, v);
+                }
+                Term::Inl(v) | Term::Inr(v) => visit(Branch::Inj, v),
+                Term::Case(v, _, l, _, r) => {
+                    visit(Branch::Right, r);
+                    visit(Branch::Left, l);
+                    visit(Branch::Scrutinee, v);
+                }
+            }
+        }
+        None
+    }
+}
+
+// What a backend node looks like to `ReadBack`, with `N` the backend's handle on a node
+pub(crate) enum Shape<N> {
+    Leaf(Term),
+    Lam(Binder, N),
+    App(N, N),
+    // a let's binder, value and body
+    Let(Binder, N, N),
+    Prim(PrimOp, Vec<N>),
+    // an if's condition and branches
+    If(N, N, N),
+    Pair(N, N),
+    // a split's binders, the pair and the body
+    Split(Binder, Binder, N, N),
+    Inl(N),
+    Inr(N),
+    // a case's scrutinee, and the binder and branch for either side
+    Case(N, Binder, N, Binder, N),
+    Fix(Binder, N),
+}
+
+// Reads a term back out of a backend, numbering binders in pre-order. `K` is what
+// the backend identifies a binder by. A substituted value that is read back twice
+// gets fresh binder ids the second time, so ids stay unique within the term.
+pub(crate) struct ReadBack<K> {
+    ids: HashMap<K, usize>,
+    next: usize,
+}
+
+impl<K: Hash + Eq> ReadBack<K> {
+    pub(crate) fn new() -> Self {
+        Self {
+            ids: HashMap::new(),
+            next: 0,
+        }
+    }
+    pub(crate) fn binder(&mut self, key: K, hint: Option<Rc<str>>) -> Binder {
+        self.ids.insert(key, self.next);
+        self.next += 1;
+        Binder::new(self.next - 1, hint)
+    }
+    // a variable whose binder is not in the term gets an id of its own
+    pub(crate) fn var(&mut self, key: K) -> Term {
+        let next = &mut self.next;
+        Term::Var(*self.ids.entry(key).or_insert_with(|| {
+            *next += 1;
+            *next - 1
+        }))
+    }
+    pub(crate) fn run<N>(&mut self, root: N, shape: impl Fn(&mut Self, N) -> Shape<N>) -> Term {
+        enum Task<N> {
+            Read(N),
+            Lam(Binder),
+            App,
+            Let(Binder),
+            // a primitive with this many operands
+            Prim(PrimOp, usize),
+            If,
+            Pair,
+            Split(Binder, Binder),
+            Inl,
+            Inr,
+            Case(Binder, Binder),
+            Fix(Binder),
+        }
+        let mut todo = vec![Task::Read(root)];
+        let mut done = Vec::new();
+        while let Some(task) = todo.pop() {
+            match task {
+                Task::Read(node) => match shape(self, node) {
+                    Shape::Leaf(term) => done.push(term),
+                    Shape::Lam(binder, body) => {
+                        todo.push(Task::Lam(binder));
+                        todo.push(Task::Read(body));
+                    }
+                    Shape::App(f, v) => {
+                        todo.push(Task::App);
+                        todo.push(Task::Read(v));
+                        todo.push(Task::Read(f));
+                    }
+                    Shape::Let(binder, v, body) => {
+                        todo.push(Task::Let(binder));
+                        todo.push(Task::Read(body));
+                        todo.push(Task::Read(v));
+                    }
+                    Shape::Prim(op, operands) => {
+                        todo.push(Task::Prim(op, operands.len()));
+                        todo.extend(operands.into_iter().rev().map(Task::Read));
+                    }
+                    Shape::If(c, t, e) => {
+                        todo.push(Task::If);
+                        todo.push(Task::Read(e));
+                        todo.push(Task::Read(t));
+                        todo.push(Task::Read(c));
+                    }
+                    Shape::Pair(a, b) => {
+                        todo.push(Task::Pair);
+                        todo.push(Task::Read(b));
+                        todo.push(Task::Read(a));
+                    }
+                    Shape::Split(x, y, v, body) => {
+                        todo.push(Task::Split(x, y));
+                        todo.push(Task::Read(body));
+                        todo.push(Task::Read(v));
+                    }
+                    Shape::Inl(v) => {
+                        todo.push(Task::Inl);
+                        todo.push(Task::Read(v));
+                    }
+                    Shape::Inr(v) => {
+                        todo.push(Task::Inr);
+                        todo.push(Task::Read(v));
+                    }
+                    Shape::Case(v, x, l, y, r) => {
+                        todo.push(Task::Case(x, y));
+                        todo.push(Task::Read(r));
+                        todo.push(Task::Read(l));
+                        todo.push(Task::Read(v));
+                    }
+                    Shape::Fix(binder, body) => {
+                        todo.push(Task::Fix(binder));
+                        todo.push(Task::Read(body));
+                    }
+                },
+                Task::Lam(binder) => {
+                    let body = done.pop().expect("read back body");
+                    done.push(Term::Lam(binder, Box::new(body)));
+                }
+                Task::App => {
+                    let v = done.pop().expect("read back argument");
+                    let f = done.pop().expect("read back function");
+                    done.push(Term::App(Box::new(f), Box::new(v)));
+                }
+                Task::Let(binder) => {
+                    let body = done.pop().expect("read back body");
+                    let v = done.pop().expect("read back value");
+                    done.push(Term::Let(binder, Box::new(v), Box::new(body)));
+                }
+                Task::Prim(op, n) => {
+                    let operands = done.split_off(done.len() - n);
+                    done.push(Term::Prim(op, operands));
+                }
+                Task::If => {
+                    let e = done.pop().expect("read back else branch");
+                    let t = done.pop().expect("read back then branch");
+                    let c = done.pop().expect("read back condition");
+                    done.push(Term::If(Box::new(c), Box::new(t), Box::new(e)));
+                }
+                Task::Pair => {
+                    let b = done.pop().expect("read back second component");
+                    let a = done.pop().expect("read back first component");
+                    done.push(Term::Pair(Box::new(a), Box::new(b)));
+                }
+                Task::Split(x, y) => {
+                    let body = done.pop().expect("read back body");
+                    let v = done.pop().expect("read back pair");
+                    done.push(Term::Split(x, y, Box::new(v), Box::new(body)));
+                }
+                Task::Inl => {
+                    let v = done.pop().expect("read back injected term");
+                    done.push(Term::Inl(Box::new(v)));
+                }
+                Task::Inr => {
+                    let v = done.pop().expect("read back injected term");
+                    done.push(Term::Inr(Box::new(v)));
+                }
+                Task::Case(x, y) => {
+                    let r = done.pop().expect("read back right branch");
+                    let l = done.pop().expect("read back left branch");
+                    let v = done.pop().expect("read back scrutinee");
+                    done.push(Term::Case(Box::new(v), x, Box::new(l), y, Box::new(r)));
+                }
+                Task::Fix(binder) => {
+                    let body = done.pop().expect("read back body");
+                    done.push(Term::Fix(binder, Box::new(body)));
+                }
+            }
+        }
+        done.pop().expect("read back term")
+    }
+}
+
+// How tightly the surrounding syntax binds the term being printed
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Prec {
+    // a lambda or let body, a let's value, any part of an if or a case, a component
+    // of a pair or the whole term: anything goes
+    Top,
+    // the function of an application: lambdas, fixes, lets, ifs, splits and cases
+    // need parentheses
+    Fun,
+    // the argument of an application or an operand: lambdas, fixes, applications,
+    // primitives and injections need parentheses
+    Arg,
+}
+
+/// Binders are named in the order they appear, `x0, x1, …` or their hint,
+/// with primes added until the name is not already in scope, so alpha-equivalent
+/// terms without hints print the same. Free variables print as `?0, ?1, …`.
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        enum Task<'a> {
+            Print(&'a Term, Prec),
+            Str(&'static str),
+            // text built while printing, like a binder's name with the syntax around it
+            Text(String),
+            // a let's name coming into scope once its value is printed
+            Bind(String),
+            // a binder's name going out of scope
+            Unbind(String),
+        }
+        // a name for `binder` that is not in `used`, counting it as the next binder
+        fn fresh(binder: &Binder, binders: &mut usize, used: &HashSet<String>) -> String {
+            let mut name = match &binder.hint {
+                Some(hint) => hint.to_string(),
+                None => format!("x{binders}"),
+            };
+            while used.contains(&name) {
+                name.push('\'');
+            }
+            *binders += 1;
+            name
+        }
+        let mut names: HashMap<usize, String> = HashMap::new();
+        let mut used = HashSet::new();
+        let (mut binders, mut free) = (0, 0);
+        let mut todo = vec![Task::Print(self, Prec::Top)];
+        while let Some(task) = todo.pop() {
+            match task {
+                Task::Str(s) => f.write_str(s)?,
+                Task::Text(s) => f.write_str(&s)?,
+                Task::Bind(name) => {
+                    used.insert(name);
+                }
+                Task::Unbind(name) => {
+                    used.remove(&name);
+                }
+                Task::Print(Term::Var(id), _) => {
+                    let name = names.entry(*id).or_insert_with(|| {
+                        free += 1;
+                        format!("?{}", free - 1)
+                    });
+                    f.write_str(name)?;
+                }
+                Task::Print(Term::Const(c), _) => write!(f, "{c}")?,
+                Task::Print(Term::Invalid, _) => f.write_str("<invalid>")?,
+                Task::Print(term @ (Term::Lam(binder, body) | Term::Fix(binder, body)), prec) => {
+                    let name = fresh(binder, &mut binders, &used);
+                    let parens = prec > Prec::Top;
+                    let open = if parens { "(" } else { "" };
+                    match (term, &binder.ty) {
+                        (Term::Fix(..), _) => write!(f, "{open}fix {name}. ")?,
+                        (_, Some(ty)) => write!(f, "{open}\\({name}: {ty}). ")?,
+                        (_, None) => write!(f, "{open}\\{name}. ")?,
+                    }
+                    used.insert(name.clone());
+                    names.insert(binder.id, name.clone());
+                    if parens {
+                        todo.push(Task::Str(")"));
+                    }
+                    todo.push(Task::Unbind(name));
+                    todo.push(Task::Print(body, Prec::Top));
+                }
+                Task::Print(Term::Let(binder, v, body), prec) => {
+                    let name = fresh(binder, &mut binders, &used);
+                    let parens = prec > Prec::Top;
+                    write!(f, "{}let {name} = ", if parens { "(" } else { "" })?;
+                    names.insert(binder.id, name.clone());
+                    if parens {
+                        todo.push(Task::Str(")"));
+                    }
+                    todo.push(Task::Unbind(name.clone()));
+                    todo.push(Task::Print(body, Prec::Top));
+                    todo.push(Task::Bind(name));
+                    todo.push(Task::Str(" in "));
+                    todo.push(Task::Print(v, Prec::Top));
+                }
+                Task::Print(Term::App(fun, arg), prec) => {
+                    let parens = prec == Prec::Arg;
+                    if parens {
+                        f.write_str("(")?;
+                        todo.push(Task::Str(")"));
+                    }
+                    todo.push(Task::Print(arg, Prec::Arg));
+                    todo.push(Task::Str(" "));
+                    todo.push(Task::Print(fun, Prec::Fun));
+                }
+                Task::Print(Term::If(c, t, e), prec) => {
+                    let parens = prec > Prec::Top;
+                    write!(f, "{}if ", if parens { "(" } else { "" })?;
+                    if parens {
+                        todo.push(Task::Str(")"));
+                    }
+                    todo.push(Task::Print(e, Prec::Top));
+                    todo.push(Task::Str(" else "));
+                    todo.push(Task::Print(t, Prec::Top));
+                    todo.push(Task::Str(" then "));
+                    todo.push(Task::Print(c, Prec::Top));
+                }
+                // the second name must differ from the first, which is in scope
+                // alongside it
+                Task::Print(Term::Split(bx, by, v, body), prec) => {
+                    let x = fresh(bx, &mut binders, &used);
+                    used.insert(x.clone());
+                    let y = fresh(by, &mut binders, &used);
+                    used.remove(&x);
+                    let parens = prec > Prec::Top;
+                    write!(f, "{}let ({x}, {y}) = ", if parens { "(" } else { "" })?;
+                    names.insert(bx.id, x.clone());
+                    names.insert(by.id, y.clone());
+                    if parens {
+                        todo.push(Task::Str(")"));
+                    }
+                    todo.push(Task::Unbind(y.clone()));
+                    todo.push(Task::Unbind(x.clone()));
+                    todo.push(Task::Print(body, Prec::Top));
+                    todo.push(Task::Bind(y));
+                    todo.push(Task::Bind(x));
+                    todo.push(Task::Str(" in "));
+                    todo.push(Task::Print(v, Prec::Top));
+                }
+                Task::Print(Term::Case(v, bx, l, by, r), prec) => {
+                    let x = fresh(bx, &mut binders, &used);
+                    let y = fresh(by, &mut binders, &used);
+                    let parens = prec > Prec::Top;
+                    write!(f, "{}case ", if parens { "(" } else { "" })?;
+                    names.insert(bx.id, x.clone());
+                    names.insert(by.id, y.clone());
+                    if parens {
+                        todo.push(Task::Str(")"));
+                    }
+                    todo.push(Task::Unbind(y.clone()));
+                    todo.push(Task::Print(r, Prec::Top));
+                    todo.push(Task::Bind(y.clone()));
+                    todo.push(Task::Text(format!(" | inr {y}. ")));
+                    todo.push(Task::Unbind(x.clone()));
+                    todo.push(Task::Print(l, Prec::Top));
+                    todo.push(Task::Bind(x.clone()));
+                    todo.push(Task::Text(format!(" of inl {x}. ")));
+                    todo.push(Task::Print(v, Prec::Top));
+                }
+                // a pair is in parentheses of its own, so its components need none
+                Task::Print(Term::Pair(a, b), _) => {
+                    f.write_str("(")?;
+                    todo.push(Task::Str(")"));
+                    todo.push(Task::Print(b, Prec::Top));
+                    todo.push(Task::Str(", "));
+                    todo.push(Task::Print(a, Prec::Top));
+                }
+                // an injection takes exactly one operand, like a primitive
+                Task::Print(inj @ (Term::Inl(v) | Term::Inr(v)), prec) => {
+                    let parens = prec == Prec::Arg;
+                    if parens {
+                        f.write_str("(")?;
+                        todo.push(Task::Str(")"));
+                    }
+                    f.write_str(if matches!(inj, Term::Inl(_)) {
+                        "inl "
+                    } else {
+                        "inr "
+                    })?;
+                    todo.push(Task::Print(v, Prec::Arg));
+                }
+                // a primitive takes exactly its operands, so it needs no parentheses
+                // as a function
+                Task::Print(Term::Prim(op, operands), prec) => {
+                    let parens = prec == Prec::Arg;
+                    if parens {
+                        f.write_str("(")?;
+                        todo.push(Task::Str(")"));
+                    }
+                    f.write_str(op.name())?;
+                    for operand in operands.iter().rev() {
+                        todo.push(Task::Print(operand, Prec::Arg));
+                        todo.push(Task::Str(" "));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::{ONE, ZERO};
+
+    fn lam(id: usize, body: Term) -> Term {
+        Term::Lam(Binder::new(id, None), Box::new(body))
+    }
+    fn app(f: Term, v: Term) -> Term {
+        Term::App(Box::new(f), Box::new(v))
+    }
+
+    #[test]
+    fn minimal_parentheses() {
+        let id = |n| lam(n, Term::Var(n));
+        let c = |c: Const| Term::Const(c);
+        let t = app(app(id(7), app(c("f".into()), c(Const::Int(-1)))), id(8));
+        assert_eq!(t.to_string(), r#"(\x0. x0) ("f" -1) (\x1. x1)"#);
+        let t = lam(
+            3,
+            lam(4, app(app(Term::Var(4), Term::Var(3)), Term::Var(9))),
+        );
+        assert_eq!(t.to_string(), r"\x0. \x1. x1 x0 ?0");
+        // a primitive is an argument in parentheses, and its operands are arguments
+        let t = app(
+            Term::Prim(PrimOp::Add, vec![app(Term::Var(5), c(ONE)), c(ZERO)]),
+            Term::Prim(PrimOp::Lt, vec![c(ZERO), c(ONE)]),
+        );
+        assert_eq!(t.to_string(), "add (?0 1) 0 (lt 0 1)");
+        // an if extends as far right as it can, so it is in parentheses anywhere else
+        let branch = |n| Term::If(Box::new(Term::Var(n)), Box::new(id(n)), Box::new(c(ZERO)));
+        let t = app(branch(5), lam(6, branch(6)));
+        assert_eq!(
+            t.to_string(),
+            r"(if ?0 then \x0. x0 else 0) (\x1. if x1 then \x2. x2 else 0)"
+        );
+        // a pair brings its own parentheses, an injection is an argument in them, and
+        // a split or a case extends as far right as it can
+        let b = |n| Binder::new(n, None);
+        let t = app(
+            Term::Inl(Box::new(Term::Pair(Box::new(id(1)), Box::new(c(ONE))))),
+            Term::Inr(Box::new(c(ZERO))),
+        );
+        assert_eq!(t.to_string(), r"inl (\x0. x0, 1) (inr 0)");
+        let t = Term::Split(
+            b(1),
+            b(2),
+            Box::new(Term::Var(3)),
+            Box::new(Term::Case(
+                Box::new(Term::Var(1)),
+                b(4),
+                Box::new(Term::Var(2)),
+                b(5),
+                Box::new(app(Term::Var(5), Term::Var(2))),
+            )),
+        );
+        assert_eq!(
+            t.to_string(),
+            "let (x0, x1) = ?0 in case x0 of inl x2. x1 | inr x3. x3 x1"
+        );
+        let t = app(t, c(ZERO));
+        assert!(t.to_string().starts_with("(let (x0, x1) = ?0 in case"));
+        // a fix extends as far right as a lambda does
+        let t = app(
+            Term::Fix(b(1), Box::new(id(2))),
+            Term::Fix(b(3), Box::new(Term::Var(3))),
+        );
+        assert_eq!(t.to_string(), r"(fix x0. \x1. x1) (fix x2. x2)");
+    }
+
+    #[test]
+    fn alpha_equivalent_terms_print_the_same() {
+        let t1 = lam(1, app(Term::Var(1), lam(2, Term::Var(1))));
+        let t2 = lam(20, app(Term::Var(20), lam(10, Term::Var(20))));
+        assert_eq!(t1.to_string(), t2.to_string());
+    }
+
+    #[test]
+    fn find_fix_gives_the_path_to_the_first_fix() {
+        let parse = |src| crate::parse::parse(src).unwrap();
+        assert_eq!(None, parse(r"\x. (x, let y = x in y)").find_fix());
+        let t = parse(r"\x. (x, if x then fix f. f else fix g. g)");
+        let path = vec![Branch::Body, Branch::Snd, Branch::Then];
+        assert_eq!(Some(Location::Path(path)), t.find_fix());
+    }
+
+    #[test]
+    fn alpha_eq_needs_binders_to_correspond() {
+        // \x. \y. x against \a. \b. a and \a. \b. b
+        let t = lam(0, lam(1, Term::Var(0)));
+        assert!(t.alpha_eq(&lam(5, lam(3, Term::Var(5)))));
+        assert!(!t.alpha_eq(&lam(5, lam(3, Term::Var(3)))));
+        // the same id reused once its binder is out of scope is free
+        let shadowed = app(lam(0, Term::Var(0)), Term::Var(0));
+        assert!(shadowed.alpha_eq(&app(lam(1, Term::Var(1)), Term::Var(7))));
+        assert!(!shadowed.alpha_eq(&app(lam(1, Term::Var(7)), Term::Var(7))));
+        // free variables pair up one-to-one
+        let free = app(Term::Var(0), Term::Var(1));
+        assert!(free.alpha_eq(&app(Term::Var(4), Term::Var(2))));
+        assert!(!free.alpha_eq(&app(Term::Var(4), Term::Var(4))));
+        assert!(!app(Term::Var(4), Term::Var(4)).alpha_eq(&free));
+        assert!(!lam(0, Term::Const(ZERO)).alpha_eq(&Term::Const(ZERO)));
+        // annotations have to agree, hints don't
+        let typed = |ty| {
+            let binder = Binder {
+                ty,
+                ..Binder::new(0, Some(Rc::from("x")))
+            };
+            Term::Lam(binder, Box::new(Term::Var(0)))
+        };
+        assert!(!typed(Some(Type::Int)).alpha_eq(&lam(2, Term::Var(2))));
+        assert!(typed(Some(Type::Int)).alpha_eq(&typed(Some(Type::Int))));
+        assert!(typed(None).alpha_eq(&lam(2, Term::Var(2))));
+        assert_eq!(typed(Some(Type::Int)).to_string(), r"\(x: int). x");
+    }
+
+    #[test]
+    fn hints_are_deduplicated() {
+        let x = || Some(Rc::from("x"));
+        let t = Term::Lam(
+            Binder::new(0, x()),
+            Box::new(Term::Lam(
+                Binder::new(1, x()),
+                Box::new(app(Term::Var(0), Term::Var(1))),
+            )),
+        );
+        assert_eq!(t.to_string(), r"\x. \x'. x x'");
+    }
+}
@@ -0,0 +1,614 @@
+use crate::error::{Branch, Location, TypeError};
+use crate::prim::PrimOp;
+use crate::term::{Binder, Term};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A simple type: a constant's, or one built from smaller types.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Type {
+    Unit,
+    Int,
+    Bool,
+    Str,
+    /// `a -> b`, the type of a lambda from a to b
+    Fun(Box<Type>, Box<Type>),
+    /// `a * b`, the type of a pair
+    Prod(Box<Type>, Box<Type>),
+    /// `a + b`, the type of `inl` of an a or `inr` of a b
+    Sum(Box<Type>, Box<Type>),
+    /// A type the term does not pin down, which it could be used at any type in place
+    /// of. Only [`typecheck`] makes these, numbered from 0 in the order they appear,
+    /// and it rejects a binder annotated with one.
+    Var(usize),
+}
+
+impl Type {
+    pub fn fun(a: Type, b: Type) -> Self {
+        Type::Fun(Box::new(a), Box::new(b))
+    }
+    pub fn prod(a: Type, b: Type) -> Self {
+        Type::Prod(Box::new(a), Box::new(b))
+    }
+    pub fn sum(a: Type, b: Type) -> Self {
+        Type::Sum(Box::new(a), Box::new(b))
+    }
+}
+
+/// Prints the type the way a lambda's annotation is parsed: `()`, `int`, `bool`,
+/// `str`, then `*`, `+` and `->` binding ever more loosely, the first two to the
+/// left and `->` to the right. Variables print as `?0, ?1, …`.
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // how tightly each operator binds; an operand of a looser one needs parentheses
+        fn prec(t: &Type) -> u8 {
+            match t {
+                Type::Fun(..) => 0,
+                Type::Sum(..) => 1,
+                Type::Prod(..) => 2,
+                _ => 3,
+            }
+        }
+        fn operand(f: &mut fmt::Formatter<'_>, t: &Type, min: u8) -> fmt::Result {
+            if prec(t) < min {
+                write!(f, "({t})")
+            } else {
+                write!(f, "{t}")
+            }
+        }
+        match self {
+            Type::Unit => f.write_str("()"),
+            Type::Int => f.write_str("int"),
+            Type::Bool => f.write_str("bool"),
+            Type::Str => f.write_str("str"),
+            Type::Fun(a, b) => {
+                operand(f, a, 1)?;
+                f.write_str(" -> ")?;
+                operand(f, b, 0)
+            }
+            Type::Sum(a, b) => {
+                operand(f, a, 1)?;
+                f.write_str(" + ")?;
+                operand(f, b, 2)
+            }
+            Type::Prod(a, b) => {
+                operand(f, a, 2)?;
+                f.write_str(" * ")?;
+                operand(f, b, 3)
+            }
+            Type::Var(n) => write!(f, "?{n}"),
+        }
+    }
+}
+
+/// The simple type of `term`, or why it has none.
+///
+/// Binders annotated with a type have that type; every other binder's type is
+/// inferred from how the term uses it. A variable has one type wherever it is
+/// used, so unlike in ML a let-bound lambda can't be used at two different types.
+/// `eq` compares two operands of the same type, which must be a constant's. A
+/// free variable or a part that was never filled in can have any type.
+///
+/// A term that checks does not get stuck evaluating: it does not apply a
+/// non-function, add a non-integer, branch on a non-boolean, or take apart
+/// something that is not a pair or an injection. It can still overflow or, with
+/// `fix`, run forever.
+pub fn typecheck(term: &Term) -> Result<Type, TypeError> {
+    enum Task<'a> {
+        Check(&'a Term),
+        // going into a child of the term being checked, and back out
+        Enter(Branch),
+        Leave,
+        // the types of the term's children are on `done`, last child last; the ones
+        // that bind put their type on `done` as they go
+        Lam(Type),
+        App,
+        // binds a let's binder once its value is checked
+        Let(&'a Binder),
+        Prim(PrimOp, usize),
+        If,
+        Pair,
+        // an injection, left or right
+        Inj(bool),
+        // binds a split's binders once its pair is checked
+        Split(&'a Binder, &'a Binder),
+        // binds a case's binders once its scrutinee is checked, then checks its
+        // branches have the same type
+        Case(&'a Binder, &'a Binder),
+        Branches,
+        Fix(Type),
+    }
+    // pushes the tasks that check `child` along `branch`
+    fn visit<'a>(todo: &mut Vec<Task<'a>>, branch: Branch, child: &'a Term) {
+        todo.extend([Task::Leave, Task::Check(child), Task::Enter(branch)]);
+    }
+    let mut checker = Checker::default();
+    let mut todo = vec![Task::Check(term)];
+    let mut done = Vec::new();
+    while let Some(task) = todo.pop() {
+        match task {
+            Task::Enter(branch) => checker.path.push(branch),
+            Task::Leave => {
+                checker.path.pop();
+            }
+            Task::Check(Term::Var(id)) => done.push(checker.var(*id)),
+            Task::Check(Term::Const(c)) => done.push(c.ty()),
+            Task::Check(Term::Invalid) => done.push(checker.fresh()),
+            Task::Check(Term::Lam(binder, body)) => {
+                let t = checker.bind(binder)?;
+                todo.push(Task::Lam(t));
+                visit(&mut todo, Branch::Body, body);
+            }
+            Task::Check(Term::App(f, v)) => {
+                todo.push(Task::App);
+                visit(&mut todo, Branch::Arg, v);
+                visit(&mut todo, Branch::Fun, f);
+            }
+            Task::Check(Term::Let(binder, v, body)) => {
+                visit(&mut todo, Branch::Body, body);
+                todo.push(Task::Let(binder));
+                visit(&mut todo, Branch::Bound, v);
+            }
+            Task::Check(Term::Prim(op, operands)) => {
+                todo.push(Task::Prim(*op, operands.len()));
+                for (i, operand) in operands.iter().enumerate().rev() {
+                    visit(&mut todo, Branch::Operand(i), operand);
+                }
+            }
+            Task::Check(Term::If(c, t, e)) => {
+                todo.push(Task::If);
+                visit(&mut todo, Branch::Else, e);
+                visit(&mut todo, Branch::Then, t);
+                visit(&mut todo, Branch::Cond, c);
+            }
+            Task::Check(Term::Pair(a, b)) => {
+                todo.push(Task::Pair);
+                visit(&mut todo, Branch::Snd, b);
+                visit(&mut todo, Branch::Fst, a);
+            }
+            Task::Check(Term::Inl(v)) => {
+                todo.push(Task::Inj(true));
+                visit(&mut todo, Branch::Inj, v);
+            }
+            Task::Check(Term::Inr(v)) => {
+                todo.push(Task::Inj(false));
+                visit(&mut todo, Branch::Inj, v);
+            }
+            Task::Check(Term::Split(x, y, v, body)) => {
+                visit(&mut todo, Branch::Body, body);
+                todo.push(Task::Split(x, y));
+                visit(&mut todo, Branch::Scrutinee, v);
+            }
+            Task::Check(Term::Case(v, x, l, y, r)) => {
+                todo.push(Task::Branches);
+                visit(&mut todo, Branch::Right, r);
+                visit(&mut todo, Branch::Left, l);
+                todo.push(Task::Case(x, y));
+                visit(&mut todo, Branch::Scrutinee, v);
+            }
+            Task::Check(Term::Fix(binder, body)) => {
+                let t = checker.bind(binder)?;
+                todo.push(Task::Fix(t));
+                visit(&mut todo, Branch::Body, body);
+            }
+            Task::Lam(t) => {
+                let body = done.pop().expect("checked body");
+                done.push(Type::fun(t, body));
+            }
+            // an application of what is already known to be a function checks the
+            // argument against it, so a mismatch is blamed on the argument
+            Task::App => {
+                let v = done.pop().expect("checked argument");
+                let f = done.pop().expect("checked function");
+                let (a, b) = match checker.head(&f) {
+                    Type::Fun(a, b) => (*a, *b),
+                    _ => {
+                        let (a, b) = (checker.fresh(), checker.fresh());
+                        let fun = Type::fun(a.clone(), b.clone());
+                        checker.unify(&fun, &f, Branch::Fun)?;
+                        (a, b)
+                    }
+                };
+                checker.unify(&a, &v, Branch::Arg)?;
+                done.push(b);
+            }
+            Task::Let(binder) => {
+                let v = done.pop().expect("checked value");
+                let t = checker.bind(binder)?;
+                checker.unify(&t, &v, Branch::Bound)?;
+            }
+            Task::Prim(op, n) => {
+                let operands = done.split_off(done.len() - n);
+                if n != op.arity() {
+                    let at = checker.at(None);
+                    return Err(TypeError::Arity { at, op, found: n });
+                }
+                done.push(match op {
+                    PrimOp::Add | PrimOp::Sub | PrimOp::Mul | PrimOp::Lt => {
+                        for (i, operand) in operands.iter().enumerate() {
+                            checker.unify(&Type::Int, operand, Branch::Operand(i))?;
+                        }
+                        if op == PrimOp::Lt {
+                            Type::Bool
+                        } else {
+                            Type::Int
+                        }
+                    }
+                    PrimOp::Eq => {
+                        checker.unify(&operands[0], &operands[1], Branch::Operand(1))?;
+                        let mut path = checker.path.clone();
+                        path.push(Branch::Operand(0));
+                        checker.compared.push((operands[0].clone(), path));
+                        Type::Bool
+                    }
+                });
+            }
+            Task::If => {
+                let e = done.pop().expect("checked else branch");
+                let t = done.pop().expect("checked then branch");
+                let c = done.pop().expect("checked condition");
+                checker.unify(&Type::Bool, &c, Branch::Cond)?;
+                checker.unify(&t, &e, Branch::Else)?;
+                done.push(t);
+            }
+            Task::Pair => {
+                let b = done.pop().expect("checked second component");
+                let a = done.pop().expect("checked first component");
+                done.push(Type::prod(a, b));
+            }
+            Task::Inj(left) => {
+                let v = done.pop().expect("checked injected term");
+                let other = checker.fresh();
+                done.push(if left {
+                    Type::sum(v, other)
+                } else {
+                    Type::sum(other, v)
+                });
+            }
+            Task::Split(x, y) => {
+                let v = done.pop().expect("checked pair");
+                let pair = Type::prod(checker.bind(x)?, checker.bind(y)?);
+                checker.unify(&pair, &v, Branch::Scrutinee)?;
+            }
+            Task::Case(x, y) => {
+                let v = done.pop().expect("checked scrutinee");
+                let sum = Type::sum(checker.bind(x)?, checker.bind(y)?);
+                checker.unify(&sum, &v, Branch::Scrutinee)?;
+            }
+            Task::Branches => {
+                let r = done.pop().expect("checked right branch");
+                let l = done.pop().expect("checked left branch");
+                checker.unify(&l, &r, Branch::Right)?;
+                done.push(l);
+            }
+            Task::Fix(t) => {
+                let body = done.pop().expect("checked body");
+                checker.unify(&t, &body, Branch::Body)?;
+                done.push(t);
+            }
+        }
+    }
+    for (t, path) in std::mem::take(&mut checker.compared) {
+        if matches!(
+            checker.head(&t),
+            Type::Fun(..) | Type::Prod(..) | Type::Sum(..)
+        ) {
+            let [ty] = checker.canonical([&t]);
+            let at = Location::Path(path);
+            return Err(TypeError::NotComparable { at, ty });
+        }
+    }
+    let t = done.pop().expect("checked term");
+    let [t] = checker.canonical([&t]);
+    Ok(t)
+}
+
+#[derive(Default)]
+struct Checker {
+    // what each type variable turned out to be, once unifying decided it
+    solved: Vec<Option<Type>>,
+    // the type of each variable, by the id of its binder
+    vars: HashMap<usize, Type>,
+    // the operands `eq` compares and where they are, which must turn out to be of
+    // a constant's type once every type is known
+    compared: Vec<(Type, Vec<Branch>)>,
+    // the path from the root to the term being checked
+    path: Vec<Branch>,
+}
+
+impl Checker {
+    fn fresh(&mut self) -> Type {
+        self.solved.push(None);
+        Type::Var(self.solved.len() - 1)
+    }
+    // the type of a binder's variable: its annotation, or a fresh variable. The
+    // variables of this checker are its own, so an annotation can't name them.
+    fn bind(&mut self, binder: &Binder) -> Result<Type, TypeError> {
+        let t = match &binder.ty {
+            Some(ty) if has_var(ty) => {
+                let at = self.at(None);
+                return Err(TypeError::Annotation { at, ty: ty.clone() });
+            }
+            Some(ty) => ty.clone(),
+            None => self.fresh(),
+        };
+        self.vars.insert(binder.id, t.clone());
+        Ok(t)
+    }
+    // a variable whose binder is not in the term gets a type of its own
+    fn var(&mut self, id: usize) -> Type {
+        if let Some(t) = self.vars.get(&id) {
+            return t.clone();
+        }
+        let t = self.fresh();
+        self.vars.insert(id, t.clone());
+        t
+    }
+    // where the term being checked is, or its child along `branch`
+    fn at(&self, branch: Option<Branch>) -> Location {
+        Location::Path(self.path.iter().copied().chain(branch).collect())
+    }
+    // `t` with the variables it is solved as followed, until either one that is
+    // not solved or a type that is not a variable
+    fn head(&self, t: &Type) -> Type {
+        let mut t = t;
+        while let Type::Var(n) = t {
+            match &self.solved[*n] {
+                Some(solved) => t = solved,
+                None => break,
+            }
+        }
+        t.clone()
+    }
+    // `t` with every variable that is solved replaced by what it is solved as
+    fn resolve(&self, t: &Type) -> Type {
+        match self.head(t) {
+            Type::Fun(a, b) => Type::fun(self.resolve(&a), self.resolve(&b)),
+            Type::Prod(a, b) => Type::prod(self.resolve(&a), self.resolve(&b)),
+            Type::Sum(a, b) => Type::sum(self.resolve(&a), self.resolve(&b)),
+            t => t,
+        }
+    }
+    // the types resolved, with the variables left numbered from 0 in the order
+    // they appear, so what is reported does not depend on how many there were
+    fn canonical<const N: usize>(&self, types: [&Type; N]) -> [Type; N] {
+        fn rename(t: &Type, names: &mut HashMap<usize, usize>) -> Type {
+            match t {
+                Type::Var(n) => {
+                    let next = names.len();
+                    Type::Var(*names.entry(*n).or_insert(next))
+                }
+                Type::Fun(a, b) => Type::fun(rename(a, names), rename(b, names)),
+                Type::Prod(a, b) => Type::prod(rename(a, names), rename(b, names)),
+                Type::Sum(a, b) => Type::sum(rename(a, names), rename(b, names)),
+                t => t.clone(),
+            }
+        }
+        let mut names = HashMap::new();
+        types.map(|t| rename(&self.resolve(t), &mut names))
+    }
+    // whether the variable `n` appears in `t`
+    fn occurs(&self, n: usize, t: &Type) -> bool {
+        let mut todo = vec![t.clone()];
+        while let Some(t) = todo.pop() {
+            match self.head(&t) {
+                Type::Var(m) if m == n => return true,
+                Type::Fun(a, b) | Type::Prod(a, b) | Type::Sum(a, b) => {
+                    todo.extend([*a, *b]);
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+    // solves variables so that `found`, the type of the child along `branch`, is
+    // the type `expected` there
+    fn unify(&mut self, expected: &Type, found: &Type, branch: Branch) -> Result<(), TypeError> {
+        let mut todo = vec![(expected.clone(), found.clone())];
+        while let Some((a, b)) = todo.pop() {
+            match (self.head(&a), self.head(&b)) {
+                (Type::Var(n), Type::Var(m)) if n == m => {}
+                (Type::Var(n), t) | (t, Type::Var(n)) => {
+                    if self.occurs(n, &t) {
+                        let [var, ty] = self.canonical([&Type::Var(n), &t]);
+                        let at = self.at(Some(branch));
+                        return Err(TypeError::Cyclic { at, var, ty });
+                    }
+                    self.solved[n] = Some(t);
+                }
+                (Type::Fun(a, b), Type::Fun(c, d))
+                | (Type::Prod(a, b), Type::Prod(c, d))
+                | (Type::Sum(a, b), Type::Sum(c, d)) => {
+                    todo.extend([(*b, *d), (*a, *c)]);
+                }
+                (a, b) if a == b => {}
+                _ => {
+                    let [expected, found] = self.canonical([expected, found]);
+                    let at = self.at(Some(branch));
+                    return Err(TypeError::Mismatch {
+                        at,
+                        expected,
+                        found,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// whether a type variable appears in `t`
+fn has_var(t: &Type) -> bool {
+    let mut todo = vec![t];
+    while let Some(t) = todo.pop() {
+        match t {
+            Type::Var(_) => return true,
+            Type::Fun(a, b) | Type::Prod(a, b) | Type::Sum(a, b) => todo.extend([&**a, &**b]),
+            Type::Unit | Type::Int | Type::Bool | Type::Str => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse;
+
+    fn check(src: &str) -> Result<String, TypeError> {
+        typecheck(&parse(src).unwrap()).map(|t| t.to_string())
+    }
+    fn path(branches: &[Branch]) -> Location {
+        Location::Path(branches.to_vec())
+    }
+
+    #[test]
+    fn infers_simple_types() {
+        for (src, ty) in [
+            (r#"((), (1, (true, "s")))"#, "() * (int * (bool * str))"),
+            (r"\x. x", "?0 -> ?0"),
+            (r"\f x. f (f x)", "(?0 -> ?0) -> ?0 -> ?0"),
+            (r"\x. add x 1", "int -> int"),
+            (
+                r"\x y. if eq x y then inl x else inr ()",
+                "?0 -> ?0 -> ?0 + ()",
+            ),
+            (r"\p. let (x, y) = p in (y, x)", "?0 * ?1 -> ?1 * ?0"),
+            (
+                r"\s. case s of inl x. lt x 0 | inr b. b",
+                "int + bool -> bool",
+            ),
+            (r"let f = \x. x in f 0", "int"),
+            (
+                r"fix f. \n. if eq n 0 then 0 else add n (f (sub n 1))",
+                "int -> int",
+            ),
+            // an annotation pins down what would otherwise be left open
+            (r"\(x: int -> bool) y. y", "(int -> bool) -> ?0 -> ?0"),
+            (
+                r"\(x: (int + bool) * ()). x",
+                "(int + bool) * () -> (int + bool) * ()",
+            ),
+        ] {
+            assert_eq!(Ok(ty.to_string()), check(src), "{src}");
+        }
+    }
+
+    #[test]
+    fn rejects_what_would_get_stuck() {
+        let mismatch = |at: &[Branch], expected: Type, found: Type| {
+            Err(TypeError::Mismatch {
+                at: path(at),
+                expected,
+                found,
+            })
+        };
+        let v = Type::Var;
+        // applying a constant
+        assert_eq!(
+            typecheck(&parse("1 0").unwrap()),
+            mismatch(&[Branch::Fun], Type::fun(v(0), v(1)), Type::Int)
+        );
+        // an argument of the wrong type, blamed on the argument
+        assert_eq!(
+            typecheck(&parse(r"(\(x: int). x) true").unwrap()),
+            mismatch(&[Branch::Arg], Type::Int, Type::Bool)
+        );
+        assert_eq!(
+            typecheck(&parse(r#"\x. add x "1""#).unwrap()),
+            mismatch(&[Branch::Body, Branch::Operand(1)], Type::Int, Type::Str)
+        );
+        assert_eq!(
+            typecheck(&parse("if 0 then 1 else 2").unwrap()),
+            mismatch(&[Branch::Cond], Type::Bool, Type::Int)
+        );
+        assert_eq!(
+            typecheck(&parse("if true then 1 else ()").unwrap()),
+            mismatch(&[Branch::Else], Type::Int, Type::Unit)
+        );
+        assert_eq!(
+            typecheck(&parse("let (x, y) = inl 0 in x").unwrap()),
+            mismatch(
+                &[Branch::Scrutinee],
+                Type::prod(v(0), v(1)),
+                Type::sum(Type::Int, v(2))
+            )
+        );
+        // a let-bound lambda has one type, as in the simply-typed lambda calculus
+        assert_eq!(
+            typecheck(&parse(r"let id = \x. x in (id 0, id true)").unwrap()),
+            mismatch(
+                &[Branch::Body, Branch::Snd, Branch::Arg],
+                Type::Int,
+                Type::Bool
+            )
+        );
+        assert_eq!(
+            typecheck(&parse(r"\x. x x").unwrap()),
+            Err(TypeError::Cyclic {
+                at: path(&[Branch::Body, Branch::Arg]),
+                var: v(0),
+                ty: Type::fun(v(0), v(1)),
+            })
+        );
+        assert_eq!(
+            typecheck(&parse(r"\f. eq f (\x. add x 1)").unwrap()),
+            Err(TypeError::NotComparable {
+                at: path(&[Branch::Body, Branch::Operand(0)]),
+                ty: Type::fun(Type::Int, Type::Int),
+            })
+        );
+        // a type variable in an annotation, which the parser can't make but a
+        // term built by hand can
+        let mut lam = parse(r"\(x: int). \y. y").unwrap();
+        let Term::Lam(_, body) = &mut lam else {
+            unreachable!("parsed a lambda");
+        };
+        let Term::Lam(y, _) = &mut **body else {
+            unreachable!("parsed a lambda");
+        };
+        y.ty = Some(Type::fun(Type::Int, v(7)));
+        assert_eq!(
+            typecheck(&lam),
+            Err(TypeError::Annotation {
+                at: path(&[Branch::Body]),
+                ty: Type::fun(Type::Int, v(7)),
+            })
+        );
+        let e = typecheck(&parse("case inl 0 of inl x. x | inr y. true").unwrap()).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "type mismatch at root.right: expected int, found bool"
+        );
+    }
+
+    #[test]
+    fn prints_with_minimal_parentheses() {
+        let (i, b) = (Type::Int, Type::Bool);
+        let f = Type::fun(i.clone(), b.clone());
+        for (t, printed) in [
+            (
+                Type::fun(f.clone(), f.clone()),
+                "(int -> bool) -> int -> bool",
+            ),
+            (
+                Type::sum(Type::sum(i.clone(), b.clone()), Type::Unit),
+                "int + bool + ()",
+            ),
+            (
+                Type::sum(i.clone(), Type::sum(b.clone(), Type::Unit)),
+                "int + (bool + ())",
+            ),
+            (
+                Type::prod(Type::sum(i.clone(), b.clone()), f.clone()),
+                "(int + bool) * (int -> bool)",
+            ),
+            (
+                Type::sum(Type::prod(i.clone(), b.clone()), Type::Var(0)),
+                "int * bool + ?0",
+            ),
+        ] {
+            assert_eq!(printed, t.to_string());
+        }
+    }
+}